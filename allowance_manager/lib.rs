@@ -0,0 +1,345 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// One place to manage token allowances, in the style of Permit2.
+///
+/// A holder approves this contract once per PSP22 token, for as much as it
+/// likes, and from then on grants integrations limited permissions here:
+/// per token and spender, up to an amount and until an expiration. Spenders
+/// move tokens with `transfer_from`, which spends their permission. Holders
+/// can grant permissions in a transaction of their own with `approve`, or sign
+/// a `Permit` off-chain that anyone, usually the spender, submits with
+/// `permit`. Signed permits need the runtime's signature checks.
+#[ink::contract(env = chain_extensions::signatures::SignaturesEnvironment)]
+mod allowance_manager {
+    use chain_extensions::signatures::{Signature, SignaturesError};
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+    use scale::Encode;
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Permission {
+        pub amount: Balance,
+        /// The permission can't be used after this timestamp.
+        pub expiration: Timestamp,
+    }
+
+    /// A permission signed off-chain by the holder.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Permit {
+        pub token: AccountId,
+        pub spender: AccountId,
+        pub amount: Balance,
+        pub expiration: Timestamp,
+        /// Has to be the holder's current `nonce`, so a permit can be used
+        /// only once.
+        pub nonce: u64,
+        /// The permit can't be submitted after this timestamp.
+        pub deadline: Timestamp,
+    }
+
+    #[ink(storage)]
+    pub struct AllowanceManager {
+        /// Keyed by `(owner, token, spender)`.
+        permissions: Mapping<(AccountId, AccountId, AccountId), Permission>,
+        nonces: Mapping<AccountId, u64>,
+    }
+
+    #[ink(event)]
+    pub struct Permitted {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        amount: Balance,
+        expiration: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Revoked {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct NonceInvalidated {
+        #[ink(topic)]
+        owner: AccountId,
+        nonce: u64,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        InsufficientAllowance,
+        PermissionExpired,
+        PermitExpired,
+        /// The permit's nonce is not the holder's current one.
+        InvalidNonce,
+        InvalidSignature,
+        SignaturesError(SignaturesError),
+        TokenError(PSP22Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<SignaturesError> for Error {
+        fn from(error: SignaturesError) -> Self {
+            Error::SignaturesError(error)
+        }
+    }
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl AllowanceManager {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                permissions: Mapping::default(),
+                nonces: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn permission(
+            &self,
+            owner: AccountId,
+            token: AccountId,
+            spender: AccountId,
+        ) -> Option<Permission> {
+            self.permissions.get((owner, token, spender))
+        }
+
+        /// What `spender` may still move of `owner`'s `token` now.
+        #[ink(message)]
+        pub fn allowance(&self, owner: AccountId, token: AccountId, spender: AccountId) -> Balance {
+            self.permission(owner, token, spender)
+                .filter(|permission| self.env().block_timestamp() <= permission.expiration)
+                .map_or(0, |permission| permission.amount)
+        }
+
+        /// The nonce the next permit of `owner` has to carry.
+        #[ink(message)]
+        pub fn nonce(&self, owner: AccountId) -> u64 {
+            self.nonces.get(owner).unwrap_or(0)
+        }
+
+        /// Lets `spender` move up to `amount` of the caller's `token` until
+        /// `expiration`, replacing any permission it had.
+        #[ink(message)]
+        pub fn approve(
+            &mut self,
+            token: AccountId,
+            spender: AccountId,
+            amount: Balance,
+            expiration: Timestamp,
+        ) -> Result<()> {
+            let owner = self.env().caller();
+            self.set_permission(owner, token, spender, amount, expiration);
+            Ok(())
+        }
+
+        /// Grants the permission `owner` signed in `permit`. The signature is
+        /// over the SCALE encoding of `(manager, owner, permit)`, where
+        /// `manager` is this contract.
+        #[ink(message)]
+        pub fn permit(
+            &mut self,
+            owner: AccountId,
+            permit: Permit,
+            signature: Signature,
+        ) -> Result<()> {
+            if self.env().block_timestamp() > permit.deadline {
+                return Err(Error::PermitExpired);
+            }
+            let nonce = self.nonce(owner);
+            if permit.nonce != nonce {
+                return Err(Error::InvalidNonce);
+            }
+            let message = (self.env().account_id(), owner, &permit).encode();
+            if !self.env().extension().verify(signature, message, owner)? {
+                return Err(Error::InvalidSignature);
+            }
+            self.nonces.insert(owner, &(nonce + 1));
+            self.set_permission(
+                owner,
+                permit.token,
+                permit.spender,
+                permit.amount,
+                permit.expiration,
+            );
+            Ok(())
+        }
+
+        /// Makes permits signed with the caller's current nonce unusable.
+        #[ink(message)]
+        pub fn invalidate_nonce(&mut self) -> Result<()> {
+            let owner = self.env().caller();
+            let nonce = self.nonce(owner) + 1;
+            self.nonces.insert(owner, &nonce);
+            self.env().emit_event(NonceInvalidated { owner, nonce });
+            Ok(())
+        }
+
+        /// Removes the caller's permissions for each `(token, spender)` pair.
+        #[ink(message)]
+        pub fn revoke(&mut self, pairs: Vec<(AccountId, AccountId)>) -> Result<()> {
+            let owner = self.env().caller();
+            for (token, spender) in pairs {
+                if self.permissions.take((owner, token, spender)).is_some() {
+                    self.env().emit_event(Revoked {
+                        owner,
+                        token,
+                        spender,
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        /// Moves `amount` of `owner`'s `token` to `to`, spending the caller's
+        /// permission. `owner` has to have approved this contract on `token`.
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            owner: AccountId,
+            token: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<()> {
+            let spender = self.env().caller();
+            let key = (owner, token, spender);
+            let now = self.env().block_timestamp();
+            let mut permission = self
+                .permissions
+                .get(key)
+                .ok_or(Error::InsufficientAllowance)?;
+            if now > permission.expiration {
+                return Err(Error::PermissionExpired);
+            }
+            if permission.amount < amount {
+                return Err(Error::InsufficientAllowance);
+            }
+            permission.amount -= amount;
+            if permission.amount == 0 {
+                self.permissions.remove(key);
+            } else {
+                self.permissions.insert(key, &permission);
+            }
+            psp22::transfer_from(token, owner, to, amount)?;
+            Ok(())
+        }
+
+        fn set_permission(
+            &mut self,
+            owner: AccountId,
+            token: AccountId,
+            spender: AccountId,
+            amount: Balance,
+            expiration: Timestamp,
+        ) {
+            self.permissions
+                .insert((owner, token, spender), &Permission { amount, expiration });
+            self.env().emit_event(Permitted {
+                owner,
+                token,
+                spender,
+                amount,
+                expiration,
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const TOKEN: [u8; 32] = [0x9; 32];
+
+        fn now() -> Timestamp {
+            ink::env::block_timestamp::<ink::env::DefaultEnvironment>()
+        }
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn permit(spender: AccountId, nonce: u64, deadline: Timestamp) -> Permit {
+            Permit {
+                token: AccountId::from(TOKEN),
+                spender,
+                amount: 100,
+                expiration: Timestamp::MAX,
+                nonce,
+                deadline,
+            }
+        }
+
+        #[ink::test]
+        fn approve_and_revoke_work() {
+            let accounts = accounts();
+            let token = AccountId::from(TOKEN);
+            let mut manager = AllowanceManager::new();
+            assert_eq!(manager.approve(token, accounts.bob, 100, now()), Ok(()));
+            assert_eq!(manager.allowance(accounts.alice, token, accounts.bob), 100);
+            assert_eq!(manager.revoke(vec![(token, accounts.bob)]), Ok(()));
+            assert_eq!(
+                manager.permission(accounts.alice, token, accounts.bob),
+                None
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_checks_permission() {
+            let accounts = accounts();
+            let token = AccountId::from(TOKEN);
+            let mut manager = AllowanceManager::new();
+            manager.approve(token, accounts.alice, 100, now()).unwrap();
+            assert_eq!(
+                manager.transfer_from(accounts.alice, token, accounts.bob, 101),
+                Err(Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                manager.transfer_from(accounts.bob, token, accounts.bob, 1),
+                Err(Error::InsufficientAllowance)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(manager.allowance(accounts.alice, token, accounts.alice), 0);
+            assert_eq!(
+                manager.transfer_from(accounts.alice, token, accounts.bob, 1),
+                Err(Error::PermissionExpired)
+            );
+        }
+
+        #[ink::test]
+        fn permit_checks_deadline_and_nonce() {
+            let accounts = accounts();
+            let mut manager = AllowanceManager::new();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                manager.permit(accounts.alice, permit(accounts.bob, 0, now() - 1), [0; 64]),
+                Err(Error::PermitExpired)
+            );
+            assert_eq!(manager.invalidate_nonce(), Ok(()));
+            assert_eq!(manager.nonce(accounts.alice), 1);
+            assert_eq!(
+                manager.permit(accounts.alice, permit(accounts.bob, 0, now()), [0; 64]),
+                Err(Error::InvalidNonce)
+            );
+        }
+    }
+}
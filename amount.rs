@@ -0,0 +1,161 @@
+//! Conversions between raw token amounts and amounts in whole tokens.
+//!
+//! A raw amount of `1_500` with 3 decimals is 1.5 tokens. The integer helpers
+//! back the `to_display_amount` and `from_display_amount` messages; the string
+//! helpers are for frontends and tests and only exist with `std`.
+
+type Balance = <ink::env::DefaultEnvironment as ink::env::Environment>::Balance;
+
+/// Raw units per whole token, or `None` if that does not fit a `Balance`.
+pub fn unit(decimals: u8) -> Option<Balance> {
+    Balance::from(10u8).checked_pow(u32::from(decimals))
+}
+
+/// Splits `raw` into whole tokens and the raw units left over.
+pub fn split_amount(raw: Balance, decimals: u8) -> (Balance, Balance) {
+    match unit(decimals) {
+        Some(unit) => (raw / unit, raw % unit),
+        // More decimals than a `Balance` has digits: always less than a token.
+        None => (0, raw),
+    }
+}
+
+/// The raw amount of `whole` tokens plus `frac` raw units. `None` if `frac` is
+/// a whole token or more, or the result overflows.
+pub fn join_amount(whole: Balance, frac: Balance, decimals: u8) -> Option<Balance> {
+    match unit(decimals) {
+        Some(unit) if frac < unit => whole.checked_mul(unit)?.checked_add(frac),
+        Some(_) => None,
+        None if whole == 0 => Some(frac),
+        None => None,
+    }
+}
+
+/// What to do with digits beyond `decimals` when parsing.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Drop them, never giving out more than was written.
+    Down,
+    /// Round up to the next raw unit if any of them is nonzero.
+    Up,
+    /// Fail with `AmountError::TooPrecise` if any of them is nonzero.
+    Exact,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountError {
+    /// Not a decimal number like `12`, `12.` or `12.345`.
+    Invalid,
+    /// The raw amount does not fit a `Balance`.
+    Overflow,
+    /// More fractional digits than `decimals` with `Rounding::Exact`.
+    TooPrecise,
+}
+
+/// Formats `raw` in whole tokens, without trailing zeros: `1.5`, `2`, `0.001`.
+#[cfg(feature = "std")]
+pub fn format_amount(raw: Balance, decimals: u8) -> String {
+    let (whole, frac) = split_amount(raw, decimals);
+    if frac == 0 {
+        return whole.to_string();
+    }
+    let frac = format!("{:0>width$}", frac, width = usize::from(decimals));
+    format!("{}.{}", whole, frac.trim_end_matches('0'))
+}
+
+/// Parses an amount in whole tokens, like `1.5`, into a raw amount.
+#[cfg(feature = "std")]
+pub fn parse_amount(
+    display: &str,
+    decimals: u8,
+    rounding: Rounding,
+) -> Result<Balance, AmountError> {
+    let (whole, frac) = match display.find('.') {
+        Some(dot) => (&display[..dot], &display[dot + 1..]),
+        None => (display, ""),
+    };
+    let is_digits = |digits: &str| digits.bytes().all(|digit| digit.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(frac) {
+        return Err(AmountError::Invalid);
+    }
+    let decimals_len = usize::from(decimals);
+    let (kept, dropped) = frac.split_at(frac.len().min(decimals_len));
+    let mut raw = Balance::from(0u8);
+    for digit in whole.bytes().chain(kept.bytes()) {
+        raw = raw
+            .checked_mul(10)
+            .and_then(|raw| raw.checked_add(Balance::from(digit - b'0')))
+            .ok_or(AmountError::Overflow)?;
+    }
+    let scale = unit((decimals_len - kept.len()) as u8).ok_or(AmountError::Overflow)?;
+    raw = raw.checked_mul(scale).ok_or(AmountError::Overflow)?;
+    if dropped.bytes().any(|digit| digit != b'0') {
+        match rounding {
+            Rounding::Down => {}
+            Rounding::Up => raw = raw.checked_add(1).ok_or(AmountError::Overflow)?,
+            Rounding::Exact => return Err(AmountError::TooPrecise),
+        }
+    }
+    Ok(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_join_are_inverse() {
+        assert_eq!(split_amount(1_500, 3), (1, 500));
+        assert_eq!(join_amount(1, 500, 3), Some(1_500));
+        assert_eq!(join_amount(1, 1_000, 3), None);
+        assert_eq!(join_amount(Balance::MAX, 0, 3), None);
+        assert_eq!(split_amount(Balance::MAX, 39), (0, Balance::MAX));
+        assert_eq!(join_amount(0, Balance::MAX, 39), Some(Balance::MAX));
+        assert_eq!(join_amount(1, 0, 39), None);
+    }
+
+    #[test]
+    fn format_amount_trims_zeros() {
+        assert_eq!(format_amount(1_500, 3), "1.5");
+        assert_eq!(format_amount(2_000, 3), "2");
+        assert_eq!(format_amount(1, 3), "0.001");
+        assert_eq!(format_amount(42, 0), "42");
+    }
+
+    #[test]
+    fn parse_amount_works() {
+        assert_eq!(parse_amount("1.5", 3, Rounding::Exact), Ok(1_500));
+        assert_eq!(parse_amount("2", 3, Rounding::Exact), Ok(2_000));
+        assert_eq!(parse_amount("2.", 3, Rounding::Exact), Ok(2_000));
+        assert_eq!(parse_amount("0.0010", 3, Rounding::Exact), Ok(1));
+        assert_eq!(
+            parse_amount(".5", 3, Rounding::Exact),
+            Err(AmountError::Invalid)
+        );
+        assert_eq!(
+            parse_amount("1,5", 3, Rounding::Exact),
+            Err(AmountError::Invalid)
+        );
+        assert_eq!(
+            parse_amount("-1", 3, Rounding::Exact),
+            Err(AmountError::Invalid)
+        );
+        assert_eq!(
+            parse_amount(&Balance::MAX.to_string(), 1, Rounding::Exact),
+            Err(AmountError::Overflow)
+        );
+    }
+
+    #[test]
+    fn parse_amount_rounds_extra_digits() {
+        assert_eq!(parse_amount("1.2345", 3, Rounding::Down), Ok(1_234));
+        assert_eq!(parse_amount("1.2341", 3, Rounding::Up), Ok(1_235));
+        assert_eq!(parse_amount("1.2340", 3, Rounding::Up), Ok(1_234));
+        assert_eq!(
+            parse_amount("1.2345", 3, Rounding::Exact),
+            Err(AmountError::TooPrecise)
+        );
+    }
+}
@@ -0,0 +1,270 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Secret ballot weighted by holdings of the course `Erc20`.
+///
+/// Until `commit_end` voters only commit to the hash of their choice, see
+/// `vote_hash`, so nobody can see how the vote is going and copy or bandwagon.
+/// Until `reveal_end` they reveal the choice and the salt behind the hash, and
+/// the choice counts with the voter's balance at `snapshot_block`, which is
+/// in the past so buying tokens during the vote doesn't help. The hash covers
+/// the voter, so a copied commitment can't be revealed by anyone else. Votes
+/// that aren't revealed don't count.
+#[ink::contract]
+mod ballot {
+    use erc20::Erc20Ref;
+    use ink::env::hash::Blake2x256;
+    use ink::storage::Mapping;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Phase {
+        Commit,
+        Reveal,
+        Ended,
+    }
+
+    #[ink(storage)]
+    pub struct Ballot {
+        token: AccountId,
+        /// Choices are numbered from 0 to `choice_count - 1`.
+        choice_count: u32,
+        snapshot_block: BlockNumber,
+        commit_end: Timestamp,
+        reveal_end: Timestamp,
+        commitments: Mapping<AccountId, Hash>,
+        tallies: Mapping<u32, Balance>,
+    }
+
+    #[ink(event)]
+    pub struct Committed {
+        #[ink(topic)]
+        voter: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Revealed {
+        #[ink(topic)]
+        voter: AccountId,
+        choice: u32,
+        weight: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        WrongPhase,
+        InvalidChoice,
+        NoCommitment,
+        /// The choice and salt don't hash to the caller's commitment.
+        CommitmentMismatch,
+        /// The token no longer has the voter's balance at the snapshot.
+        SnapshotUnavailable,
+        NoVotingPower,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl Ballot {
+        /// `snapshot_block` has to be in the past, so that the weights are
+        /// settled before anyone votes.
+        #[ink(constructor)]
+        pub fn new(
+            token: AccountId,
+            choice_count: u32,
+            snapshot_block: BlockNumber,
+            commit_end: Timestamp,
+            reveal_end: Timestamp,
+        ) -> Self {
+            assert!(choice_count > 1, "need at least two choices");
+            assert!(
+                snapshot_block < Self::env().block_number(),
+                "snapshot is not in the past"
+            );
+            assert!(commit_end < reveal_end, "reveal phase is empty");
+            Self {
+                token,
+                choice_count,
+                snapshot_block,
+                commit_end,
+                reveal_end,
+                commitments: Mapping::default(),
+                tallies: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn phase(&self) -> Phase {
+            let now = self.env().block_timestamp();
+            if now < self.commit_end {
+                Phase::Commit
+            } else if now < self.reveal_end {
+                Phase::Reveal
+            } else {
+                Phase::Ended
+            }
+        }
+
+        #[ink(message)]
+        pub fn snapshot_block(&self) -> BlockNumber {
+            self.snapshot_block
+        }
+
+        /// Weight revealed for `choice` so far.
+        #[ink(message)]
+        pub fn tally(&self, choice: u32) -> Balance {
+            self.tallies.get(choice).unwrap_or(0)
+        }
+
+        /// The choice with the most weight once the vote has ended, or `None`
+        /// on a tie or before then.
+        #[ink(message)]
+        pub fn winner(&self) -> Option<u32> {
+            if self.phase() != Phase::Ended {
+                return None;
+            }
+            let mut winner = None;
+            let mut highest = 0;
+            for choice in 0..self.choice_count {
+                let tally = self.tally(choice);
+                if tally > highest {
+                    winner = Some(choice);
+                    highest = tally;
+                } else if tally == highest {
+                    winner = None;
+                }
+            }
+            winner
+        }
+
+        /// What `voter` has to commit to vote for `choice`.
+        #[ink(message)]
+        pub fn vote_hash(&self, voter: AccountId, choice: u32, salt: [u8; 32]) -> Hash {
+            vote_hash(voter, choice, salt)
+        }
+
+        /// Commits the caller to a vote, replacing an earlier commitment.
+        #[ink(message)]
+        pub fn commit(&mut self, commitment: Hash) -> Result<()> {
+            if self.phase() != Phase::Commit {
+                return Err(Error::WrongPhase);
+            }
+            let voter = self.env().caller();
+            self.commitments.insert(voter, &commitment);
+            ink::codegen::EmitEvent::<Ballot>::emit_event(self.env(), Committed { voter });
+            Ok(())
+        }
+
+        /// Counts the caller's committed vote for `choice`.
+        #[ink(message)]
+        pub fn reveal(&mut self, choice: u32, salt: [u8; 32]) -> Result<()> {
+            if self.phase() != Phase::Reveal {
+                return Err(Error::WrongPhase);
+            }
+            if choice >= self.choice_count {
+                return Err(Error::InvalidChoice);
+            }
+            let voter = self.env().caller();
+            let commitment = self.commitments.get(voter).ok_or(Error::NoCommitment)?;
+            if commitment != vote_hash(voter, choice, salt) {
+                return Err(Error::CommitmentMismatch);
+            }
+            let token: Erc20Ref = ink::env::call::FromAccountId::from_account_id(self.token);
+            let weight = token
+                .balance_of_at(voter, self.snapshot_block)
+                .ok_or(Error::SnapshotUnavailable)?;
+            if weight == 0 {
+                return Err(Error::NoVotingPower);
+            }
+            self.commitments.remove(voter);
+            self.tallies.insert(choice, &(self.tally(choice) + weight));
+            ink::codegen::EmitEvent::<Ballot>::emit_event(
+                self.env(),
+                Revealed {
+                    voter,
+                    choice,
+                    weight,
+                },
+            );
+            Ok(())
+        }
+    }
+
+    /// Blake2x256 hash of the SCALE encoded `(voter, choice, salt)`.
+    fn vote_hash(voter: AccountId, choice: u32, salt: [u8; 32]) -> Hash {
+        let mut output = [0u8; 32];
+        ink::env::hash_encoded::<Blake2x256, _>(&(voter, choice, salt), &mut output);
+        Hash::from(output)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const SALT: [u8; 32] = [0x5; 32];
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn set_block_timestamp(timestamp: Timestamp) {
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+        }
+
+        /// Three choices, committing until 100 and revealing until 200.
+        fn ballot() -> Ballot {
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            set_block_timestamp(0);
+            Ballot::new(AccountId::from([0x9; 32]), 3, 0, 100, 200)
+        }
+
+        #[ink::test]
+        fn phases_follow_the_clock() {
+            let mut ballot = ballot();
+            let commitment = vote_hash(accounts().alice, 1, SALT);
+            assert_eq!(ballot.phase(), Phase::Commit);
+            assert_eq!(ballot.reveal(1, SALT), Err(Error::WrongPhase));
+            assert_eq!(ballot.commit(commitment), Ok(()));
+            set_block_timestamp(100);
+            assert_eq!(ballot.phase(), Phase::Reveal);
+            assert_eq!(ballot.commit(commitment), Err(Error::WrongPhase));
+            set_block_timestamp(200);
+            assert_eq!(ballot.phase(), Phase::Ended);
+            assert_eq!(ballot.reveal(1, SALT), Err(Error::WrongPhase));
+        }
+
+        #[ink::test]
+        fn reveal_must_match_commitment() {
+            let accounts = accounts();
+            let mut ballot = ballot();
+            ballot.commit(vote_hash(accounts.alice, 1, SALT)).unwrap();
+            // Bob copies alice's commitment.
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ballot.commit(vote_hash(accounts.alice, 1, SALT)).unwrap();
+            set_block_timestamp(100);
+            assert_eq!(ballot.reveal(1, SALT), Err(Error::CommitmentMismatch));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ballot.reveal(3, SALT), Err(Error::InvalidChoice));
+            assert_eq!(ballot.reveal(2, SALT), Err(Error::CommitmentMismatch));
+            assert_eq!(ballot.reveal(1, [0x6; 32]), Err(Error::CommitmentMismatch));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ballot.reveal(1, SALT), Err(Error::NoCommitment));
+        }
+
+        #[ink::test]
+        fn winner_needs_the_most_weight() {
+            let mut ballot = ballot();
+            ballot.tallies.insert(0, &300);
+            ballot.tallies.insert(2, &500);
+            assert_eq!(ballot.winner(), None);
+            set_block_timestamp(200);
+            assert_eq!(ballot.winner(), Some(2));
+            ballot.tallies.insert(0, &500);
+            assert_eq!(ballot.winner(), None);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "snapshot is not in the past")]
+        fn snapshot_must_be_in_the_past() {
+            Ballot::new(AccountId::from([0x9; 32]), 3, 0, 100, 200);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Lock-and-mint bridge for the course `Erc20`.
+///
+/// Outbound, `lock` takes tokens from the caller and emits `Locked` for
+/// off-chain relayers, which mint the same amount on the destination chain.
+/// Inbound, a relayer calls `mint_wrapped` for a lock seen on a source chain.
+/// The bridge needs a minter allowance on `token` for that, and every source
+/// transaction is minted for at most once.
+#[ink::contract]
+mod bridge {
+    use erc20::Erc20Ref;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    pub type ChainId = u32;
+
+    #[ink(storage)]
+    pub struct Bridge {
+        token: AccountId,
+        admin: AccountId,
+        relayers: Mapping<AccountId, ()>,
+        /// Source chain transactions already minted for.
+        processed: Mapping<Hash, ()>,
+        /// Counts locks, so relayers can tell them apart and spot gaps.
+        nonce: u64,
+    }
+
+    #[ink(event)]
+    pub struct Locked {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        dest_chain: ChainId,
+        /// Recipient on `dest_chain`, in that chain's address format.
+        dest_address: Vec<u8>,
+        amount: Balance,
+        nonce: u64,
+    }
+
+    #[ink(event)]
+    pub struct Minted {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        source_tx_id: Hash,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct RelayerUpdated {
+        #[ink(topic)]
+        relayer: AccountId,
+        allowed: bool,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotAdmin,
+        NotRelayer,
+        ZeroAmount,
+        AlreadyProcessed,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Bridge {
+        #[ink(constructor)]
+        pub fn new(token: AccountId) -> Self {
+            Self {
+                token,
+                admin: Self::env().caller(),
+                relayers: Mapping::default(),
+                processed: Mapping::default(),
+                nonce: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn token(&self) -> AccountId {
+            self.token
+        }
+
+        #[ink(message)]
+        pub fn admin(&self) -> AccountId {
+            self.admin
+        }
+
+        #[ink(message)]
+        pub fn is_relayer(&self, account: AccountId) -> bool {
+            self.relayers.contains(account)
+        }
+
+        #[ink(message)]
+        pub fn is_processed(&self, source_tx_id: Hash) -> bool {
+            self.processed.contains(source_tx_id)
+        }
+
+        #[ink(message)]
+        pub fn set_relayer(&mut self, relayer: AccountId, allowed: bool) -> Result<()> {
+            if self.env().caller() != self.admin {
+                return Err(Error::NotAdmin);
+            }
+            if allowed {
+                self.relayers.insert(relayer, &());
+            } else {
+                self.relayers.remove(relayer);
+            }
+            ink::codegen::EmitEvent::<Bridge>::emit_event(
+                self.env(),
+                RelayerUpdated { relayer, allowed },
+            );
+            Ok(())
+        }
+
+        /// Locks `amount` tokens of the caller to be minted to `dest_address`
+        /// on `dest_chain`. The caller must have approved the bridge first.
+        #[ink(message)]
+        pub fn lock(
+            &mut self,
+            amount: Balance,
+            dest_chain: ChainId,
+            dest_address: Vec<u8>,
+        ) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let sender = self.env().caller();
+            self.token_contract()
+                .transfer_from(sender, self.env().account_id(), amount)?;
+            self.nonce += 1;
+            ink::codegen::EmitEvent::<Bridge>::emit_event(
+                self.env(),
+                Locked {
+                    sender,
+                    dest_chain,
+                    dest_address,
+                    amount,
+                    nonce: self.nonce,
+                },
+            );
+            Ok(())
+        }
+
+        /// Mints `amount` to `account` for the lock made in `source_tx_id` on
+        /// the source chain.
+        #[ink(message)]
+        pub fn mint_wrapped(
+            &mut self,
+            account: AccountId,
+            amount: Balance,
+            source_tx_id: Hash,
+        ) -> Result<()> {
+            if !self.is_relayer(self.env().caller()) {
+                return Err(Error::NotRelayer);
+            }
+            if self.processed.insert(source_tx_id, &()).is_some() {
+                return Err(Error::AlreadyProcessed);
+            }
+            self.token_contract().mint(account, amount)?;
+            ink::codegen::EmitEvent::<Bridge>::emit_event(
+                self.env(),
+                Minted {
+                    account,
+                    source_tx_id,
+                    amount,
+                },
+            );
+            Ok(())
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_works() {
+            let bridge = Bridge::new(AccountId::from([0x9; 32]));
+            assert_eq!(bridge.token(), AccountId::from([0x9; 32]));
+            assert_eq!(bridge.admin(), AccountId::from([0x1; 32]));
+            assert!(!bridge.is_relayer(AccountId::from([0x1; 32])));
+        }
+
+        #[ink::test]
+        fn only_admin_sets_relayers() {
+            let mut bridge = Bridge::new(AccountId::from([0x9; 32]));
+            let relayer = AccountId::from([0x2; 32]);
+            assert_eq!(bridge.set_relayer(relayer, true), Ok(()));
+            assert!(bridge.is_relayer(relayer));
+            bridge.admin = relayer;
+            assert_eq!(bridge.set_relayer(relayer, false), Err(Error::NotAdmin));
+        }
+
+        #[ink::test]
+        fn mint_wrapped_needs_relayer() {
+            let mut bridge = Bridge::new(AccountId::from([0x9; 32]));
+            assert_eq!(
+                bridge.mint_wrapped(AccountId::from([0x2; 32]), 100, Hash::from([0x7; 32])),
+                Err(Error::NotRelayer)
+            );
+            assert!(!bridge.is_processed(Hash::from([0x7; 32])));
+        }
+
+        #[ink::test]
+        fn mint_wrapped_rejects_replay() {
+            let mut bridge = Bridge::new(AccountId::from([0x9; 32]));
+            bridge
+                .set_relayer(AccountId::from([0x1; 32]), true)
+                .unwrap();
+            bridge.processed.insert(Hash::from([0x7; 32]), &());
+            assert_eq!(
+                bridge.mint_wrapped(AccountId::from([0x2; 32]), 100, Hash::from([0x7; 32])),
+                Err(Error::AlreadyProcessed)
+            );
+        }
+
+        #[ink::test]
+        fn lock_rejects_zero_amount() {
+            let mut bridge = Bridge::new(AccountId::from([0x9; 32]));
+            assert_eq!(bridge.lock(0, 1, Vec::new()), Err(Error::ZeroAmount));
+        }
+    }
+}
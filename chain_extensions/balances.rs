@@ -0,0 +1,81 @@
+//! Native balance operations of the balances pallet, beyond the plain
+//! transfers the contracts pallet offers.
+//!
+//! The runtime has to implement these functions of its chain extension, all
+//! acting on the calling contract's own account. They return `0` on success or
+//! one of the codes of `BalancesError`.
+//!
+//! | Function | Input                  | Output    |
+//! |----------|------------------------|-----------|
+//! | `0x1201` | `(AccountId, Balance)` | nothing   |
+//! | `0x1202` | `Balance`              | nothing   |
+//! | `0x1203` | `Balance`              | `Balance` |
+//! | `0x1204` | nothing                | `Balance` |
+
+use ink::env::{DefaultEnvironment, Environment};
+
+pub type AccountId = <DefaultEnvironment as Environment>::AccountId;
+pub type Balance = <DefaultEnvironment as Environment>::Balance;
+
+#[ink::chain_extension]
+pub trait Balances {
+    type ErrorCode = BalancesError;
+
+    /// Transfers `value` to `to`, keeping the contract's account alive.
+    #[ink(extension = 0x1201)]
+    fn transfer(to: AccountId, value: Balance);
+
+    /// Moves `value` of the free balance to the reserved balance, where it
+    /// can't be spent until unreserved.
+    #[ink(extension = 0x1202)]
+    fn reserve(value: Balance);
+
+    /// Moves up to `value` back to the free balance and returns what could
+    /// not be unreserved.
+    #[ink(extension = 0x1203)]
+    fn unreserve(value: Balance) -> Balance;
+
+    #[ink(extension = 0x1204)]
+    fn reserved_balance() -> Balance;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum BalancesError {
+    InsufficientBalance,
+    /// The operation would take the account below the existential deposit.
+    ExistentialDeposit,
+    /// Locks or other restrictions forbid the operation.
+    LiquidityRestrictions,
+    /// Any other status code.
+    Unknown,
+}
+
+impl ink::env::chain_extension::FromStatusCode for BalancesError {
+    fn from_status_code(status_code: u32) -> Result<(), Self> {
+        match status_code {
+            0 => Ok(()),
+            1 => Err(BalancesError::InsufficientBalance),
+            2 => Err(BalancesError::ExistentialDeposit),
+            3 => Err(BalancesError::LiquidityRestrictions),
+            _ => Err(BalancesError::Unknown),
+        }
+    }
+}
+
+/// The default environment with the balances pallet as its chain extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum BalancesEnvironment {}
+
+impl Environment for BalancesEnvironment {
+    const MAX_EVENT_TOPICS: usize = <DefaultEnvironment as Environment>::MAX_EVENT_TOPICS;
+
+    type AccountId = <DefaultEnvironment as Environment>::AccountId;
+    type Balance = <DefaultEnvironment as Environment>::Balance;
+    type Hash = <DefaultEnvironment as Environment>::Hash;
+    type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+    type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+
+    type ChainExtension = Balances;
+}
@@ -0,0 +1,14 @@
+//! Clients of runtime pallets that contracts reach through chain extensions.
+//!
+//! A chain extension only works on a runtime that implements it, so every
+//! client sits behind its own feature. Each one comes with an environment
+//! type to pass to `#[ink::contract(env = ...)]`.
+
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+#[cfg(feature = "balances")]
+pub mod balances;
+#[cfg(feature = "oracle")]
+pub mod oracle;
+#[cfg(feature = "signatures")]
+pub mod signatures;
@@ -0,0 +1,66 @@
+//! Price feeds of an oracle pallet, filled by its off-chain workers.
+//!
+//! The runtime has to implement function `0x1101` of its chain extension:
+//! given a SCALE encoded `PairId` it writes the encoded `(Price, Timestamp)`
+//! of the latest report to the output buffer and returns `0`, or returns one
+//! of the codes of `OracleError`.
+
+use ink::env::{DefaultEnvironment, Environment};
+
+/// Identifies a price feed, e.g. `*b"DOT/USD\0"`.
+pub type PairId = [u8; 8];
+
+/// Price of one whole unit of the base asset, with `PRICE_DECIMALS` decimals.
+pub type Price = u128;
+
+pub type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+
+pub const PRICE_DECIMALS: u32 = 8;
+
+#[ink::chain_extension]
+pub trait PriceOracle {
+    type ErrorCode = OracleError;
+
+    /// Latest price of `pair` and when it was reported.
+    #[ink(extension = 0x1101)]
+    fn latest_price(pair: PairId) -> (Price, Timestamp);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum OracleError {
+    /// The oracle has no feed for the pair.
+    UnknownPair,
+    /// The feed exists but has no report yet.
+    NoPrice,
+    /// Any other status code.
+    Unavailable,
+}
+
+impl ink::env::chain_extension::FromStatusCode for OracleError {
+    fn from_status_code(status_code: u32) -> Result<(), Self> {
+        match status_code {
+            0 => Ok(()),
+            1 => Err(OracleError::UnknownPair),
+            2 => Err(OracleError::NoPrice),
+            _ => Err(OracleError::Unavailable),
+        }
+    }
+}
+
+/// The default environment with the oracle as its chain extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum OracleEnvironment {}
+
+impl Environment for OracleEnvironment {
+    const MAX_EVENT_TOPICS: usize = <DefaultEnvironment as Environment>::MAX_EVENT_TOPICS;
+
+    type AccountId = <DefaultEnvironment as Environment>::AccountId;
+    type Balance = <DefaultEnvironment as Environment>::Balance;
+    type Hash = <DefaultEnvironment as Environment>::Hash;
+    type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+    type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+
+    type ChainExtension = PriceOracle;
+}
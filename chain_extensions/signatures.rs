@@ -0,0 +1,57 @@
+//! Signature checks of the runtime, for contracts acting on messages that
+//! accounts signed off-chain.
+//!
+//! The runtime has to implement function `0x1301` of its chain extension:
+//! given a SCALE encoded `(Signature, Vec<u8>, AccountId)` it writes whether
+//! the signature is a valid sr25519 signature of the message by the account
+//! to the output buffer and returns `0`, or returns `1` if it can't check it.
+
+use ink::env::{DefaultEnvironment, Environment};
+use ink::prelude::vec::Vec;
+
+pub type AccountId = <DefaultEnvironment as Environment>::AccountId;
+
+/// An sr25519 signature.
+pub type Signature = [u8; 64];
+
+#[ink::chain_extension]
+pub trait Signatures {
+    type ErrorCode = SignaturesError;
+
+    /// Whether `signature` is `signer`'s signature of `message`.
+    #[ink(extension = 0x1301)]
+    fn verify(signature: Signature, message: Vec<u8>, signer: AccountId) -> bool;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum SignaturesError {
+    /// The runtime could not check the signature.
+    Unavailable,
+}
+
+impl ink::env::chain_extension::FromStatusCode for SignaturesError {
+    fn from_status_code(status_code: u32) -> Result<(), Self> {
+        match status_code {
+            0 => Ok(()),
+            _ => Err(SignaturesError::Unavailable),
+        }
+    }
+}
+
+/// The default environment with signature checks as its chain extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum SignaturesEnvironment {}
+
+impl Environment for SignaturesEnvironment {
+    const MAX_EVENT_TOPICS: usize = <DefaultEnvironment as Environment>::MAX_EVENT_TOPICS;
+
+    type AccountId = <DefaultEnvironment as Environment>::AccountId;
+    type Balance = <DefaultEnvironment as Environment>::Balance;
+    type Hash = <DefaultEnvironment as Environment>::Hash;
+    type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+    type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+
+    type ChainExtension = Signatures;
+}
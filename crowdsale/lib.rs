@@ -0,0 +1,268 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Sells the course `Erc20` for native currency at a fixed rate until
+/// `closing_time`, minting the tokens bought. The crowdsale needs a minter
+/// allowance on the token.
+///
+/// A buyer can name a referrer with `buy_with_referrer`; the referrer is
+/// minted a bonus of `referral_bonus_bps` of the tokens bought. To limit abuse,
+/// buyers can't refer themselves, each account can buy at most
+/// `max_purchase_per_account` tokens in total and each referrer can earn at
+/// most `max_referral_bonus` tokens in bonuses.
+#[ink::contract]
+mod crowdsale {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    #[ink(storage)]
+    pub struct Crowdsale {
+        token: AccountId,
+        owner: AccountId,
+        /// Tokens sold per unit of native currency.
+        rate: Balance,
+        closing_time: Timestamp,
+        max_purchase_per_account: Balance,
+        referral_bonus_bps: u16,
+        max_referral_bonus: Balance,
+        purchased: Mapping<AccountId, Balance>,
+        /// Bonus tokens earned per referrer.
+        referral_totals: Mapping<AccountId, Balance>,
+    }
+
+    #[ink(event)]
+    pub struct Purchased {
+        #[ink(topic)]
+        buyer: AccountId,
+        paid: Balance,
+        tokens: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ReferralPaid {
+        #[ink(topic)]
+        referrer: AccountId,
+        #[ink(topic)]
+        buyer: AccountId,
+        bonus: Balance,
+        total: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        SaleClosed,
+        ZeroPurchase,
+        PurchaseLimitExceeded,
+        SelfReferral,
+        InvalidBonus,
+        Overflow,
+        WithdrawFailed,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Crowdsale {
+        #[ink(constructor)]
+        pub fn new(
+            token: AccountId,
+            rate: Balance,
+            closing_time: Timestamp,
+            max_purchase_per_account: Balance,
+        ) -> Self {
+            Self {
+                token,
+                owner: Self::env().caller(),
+                rate,
+                closing_time,
+                max_purchase_per_account,
+                referral_bonus_bps: 0,
+                max_referral_bonus: 0,
+                purchased: Mapping::default(),
+                referral_totals: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn purchased(&self, account: AccountId) -> Balance {
+            self.purchased.get(account).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn referral_total(&self, referrer: AccountId) -> Balance {
+            self.referral_totals.get(referrer).unwrap_or(0)
+        }
+
+        /// Buys tokens for the native currency sent along.
+        #[ink(message, payable)]
+        pub fn buy(&mut self) -> Result<Balance> {
+            let buyer = self.env().caller();
+            let tokens = self.purchase(buyer)?;
+            self.token_contract().mint(buyer, tokens)?;
+            Ok(tokens)
+        }
+
+        /// Like `buy`, and pays `referrer` the referral bonus on top. Once the
+        /// referrer has reached `max_referral_bonus` the purchase still goes
+        /// through without a bonus.
+        #[ink(message, payable)]
+        pub fn buy_with_referrer(&mut self, referrer: AccountId) -> Result<Balance> {
+            let buyer = self.env().caller();
+            if referrer == buyer {
+                return Err(Error::SelfReferral);
+            }
+            let tokens = self.purchase(buyer)?;
+            self.token_contract().mint(buyer, tokens)?;
+
+            let earned = self.referral_total(referrer);
+            let bonus = self
+                .referral_bonus(tokens)
+                .min(self.max_referral_bonus.saturating_sub(earned));
+            if bonus > 0 {
+                let total = earned + bonus;
+                self.referral_totals.insert(referrer, &total);
+                self.token_contract().mint(referrer, bonus)?;
+                ink::codegen::EmitEvent::<Crowdsale>::emit_event(
+                    self.env(),
+                    ReferralPaid {
+                        referrer,
+                        buyer,
+                        bonus,
+                        total,
+                    },
+                );
+            }
+            Ok(tokens)
+        }
+
+        /// Sets the referral bonus to `bonus_bps` basis points of a purchase,
+        /// up to `max_bonus` tokens per referrer.
+        #[ink(message)]
+        pub fn set_referral_bonus(&mut self, bonus_bps: u16, max_bonus: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            if bonus_bps > 10_000 {
+                return Err(Error::InvalidBonus);
+            }
+            self.referral_bonus_bps = bonus_bps;
+            self.max_referral_bonus = max_bonus;
+            Ok(())
+        }
+
+        /// Sends the proceeds to the owner.
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            let amount = self
+                .env()
+                .balance()
+                .saturating_sub(self.env().minimum_balance());
+            self.env()
+                .transfer(self.owner, amount)
+                .map_err(|_| Error::WithdrawFailed)
+        }
+
+        /// Books a purchase for the value sent and returns the tokens bought.
+        fn purchase(&mut self, buyer: AccountId) -> Result<Balance> {
+            if self.env().block_timestamp() >= self.closing_time {
+                return Err(Error::SaleClosed);
+            }
+            let paid = self.env().transferred_value();
+            let tokens = paid.checked_mul(self.rate).ok_or(Error::Overflow)?;
+            if tokens == 0 {
+                return Err(Error::ZeroPurchase);
+            }
+            let purchased = self
+                .purchased(buyer)
+                .checked_add(tokens)
+                .ok_or(Error::Overflow)?;
+            if purchased > self.max_purchase_per_account {
+                return Err(Error::PurchaseLimitExceeded);
+            }
+            self.purchased.insert(buyer, &purchased);
+            ink::codegen::EmitEvent::<Crowdsale>::emit_event(
+                self.env(),
+                Purchased {
+                    buyer,
+                    paid,
+                    tokens,
+                },
+            );
+            Ok(tokens)
+        }
+
+        fn referral_bonus(&self, tokens: Balance) -> Balance {
+            // Split so that large purchases can't overflow.
+            tokens / 10_000 * Balance::from(self.referral_bonus_bps)
+                + tokens % 10_000 * Balance::from(self.referral_bonus_bps) / 10_000
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn crowdsale() -> Crowdsale {
+            Crowdsale::new(AccountId::from([0x9; 32]), 10, Timestamp::MAX, 1_000)
+        }
+
+        #[ink::test]
+        fn purchase_is_limited_per_account() {
+            let mut crowdsale = crowdsale();
+            let buyer = AccountId::from([0x1; 32]);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(60);
+            assert_eq!(crowdsale.purchase(buyer), Ok(600));
+            assert_eq!(crowdsale.purchase(buyer), Err(Error::PurchaseLimitExceeded));
+            assert_eq!(crowdsale.purchased(buyer), 600);
+        }
+
+        #[ink::test]
+        fn purchase_needs_value_and_open_sale() {
+            let mut crowdsale = crowdsale();
+            let buyer = AccountId::from([0x1; 32]);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(crowdsale.purchase(buyer), Err(Error::ZeroPurchase));
+            crowdsale.closing_time = 0;
+            assert_eq!(crowdsale.purchase(buyer), Err(Error::SaleClosed));
+        }
+
+        #[ink::test]
+        fn self_referral_is_rejected() {
+            let mut crowdsale = crowdsale();
+            assert_eq!(
+                crowdsale.buy_with_referrer(AccountId::from([0x1; 32])),
+                Err(Error::SelfReferral)
+            );
+        }
+
+        #[ink::test]
+        fn referral_bonus_works() {
+            let mut crowdsale = crowdsale();
+            assert_eq!(crowdsale.set_referral_bonus(500, 100), Ok(()));
+            assert_eq!(crowdsale.referral_bonus(600), 30);
+            assert_eq!(crowdsale.referral_bonus(Balance::MAX), Balance::MAX / 20);
+            assert_eq!(
+                crowdsale.set_referral_bonus(10_001, 0),
+                Err(Error::InvalidBonus)
+            );
+            crowdsale.owner = AccountId::from([0x2; 32]);
+            assert_eq!(crowdsale.set_referral_bonus(0, 0), Err(Error::NotOwner));
+        }
+    }
+}
@@ -0,0 +1,297 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// A name service like the classic ink! `dns` example, paid in the course
+/// `Erc20`.
+///
+/// Names are registered by their hash for whole periods, paying
+/// `fee_per_period` tokens per period to the operator with `transfer_from`, so
+/// callers have to approve this contract first. A name resolves to an account
+/// its owner sets. Once a registration expires the name resolves to nothing
+/// and anyone can register it again; until then anyone can renew it.
+#[ink::contract]
+mod dns {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Registration {
+        pub owner: AccountId,
+        /// The account the name resolves to.
+        pub resolver: Option<AccountId>,
+        pub expires_at: Timestamp,
+    }
+
+    #[ink(storage)]
+    pub struct Dns {
+        token: AccountId,
+        /// Receives the registration fees.
+        operator: AccountId,
+        fee_per_period: Balance,
+        period: Timestamp,
+        registrations: Mapping<Hash, Registration>,
+    }
+
+    #[ink(event)]
+    pub struct Registered {
+        #[ink(topic)]
+        name: Hash,
+        #[ink(topic)]
+        owner: AccountId,
+        expires_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Renewed {
+        #[ink(topic)]
+        name: Hash,
+        expires_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct NameTransferred {
+        #[ink(topic)]
+        name: Hash,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ResolverSet {
+        #[ink(topic)]
+        name: Hash,
+        resolver: Option<AccountId>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NameTaken,
+        /// The name is not registered or its registration expired.
+        NameNotFound,
+        NotNameOwner,
+        ZeroPeriods,
+        Overflow,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Dns {
+        /// The caller becomes the operator. A registration lasts `period`
+        /// milliseconds per `fee_per_period` paid.
+        #[ink(constructor)]
+        pub fn new(token: AccountId, fee_per_period: Balance, period: Timestamp) -> Self {
+            Self {
+                token,
+                operator: Self::env().caller(),
+                fee_per_period,
+                period,
+                registrations: Mapping::default(),
+            }
+        }
+
+        /// The registration of `name`, while it is active.
+        #[ink(message)]
+        pub fn registration(&self, name: Hash) -> Option<Registration> {
+            self.registrations
+                .get(name)
+                .filter(|registration| self.env().block_timestamp() < registration.expires_at)
+        }
+
+        #[ink(message)]
+        pub fn resolve(&self, name: Hash) -> Option<AccountId> {
+            self.registration(name)
+                .and_then(|registration| registration.resolver)
+        }
+
+        /// Registers `name` to the caller for `periods` periods.
+        #[ink(message)]
+        pub fn register(&mut self, name: Hash, periods: u32) -> Result<()> {
+            let owner = self.env().caller();
+            if self.registration(name).is_some() {
+                return Err(Error::NameTaken);
+            }
+            let expires_at = self
+                .env()
+                .block_timestamp()
+                .checked_add(self.duration(periods)?)
+                .ok_or(Error::Overflow)?;
+            self.charge(owner, periods)?;
+            self.registrations.insert(
+                name,
+                &Registration {
+                    owner,
+                    resolver: None,
+                    expires_at,
+                },
+            );
+            ink::codegen::EmitEvent::<Dns>::emit_event(
+                self.env(),
+                Registered {
+                    name,
+                    owner,
+                    expires_at,
+                },
+            );
+            Ok(())
+        }
+
+        /// Extends the registration of `name` by `periods` periods, paid by
+        /// the caller.
+        #[ink(message)]
+        pub fn renew(&mut self, name: Hash, periods: u32) -> Result<()> {
+            let payer = self.env().caller();
+            let mut registration = self.registration(name).ok_or(Error::NameNotFound)?;
+            let expires_at = registration
+                .expires_at
+                .checked_add(self.duration(periods)?)
+                .ok_or(Error::Overflow)?;
+            self.charge(payer, periods)?;
+            registration.expires_at = expires_at;
+            self.registrations.insert(name, &registration);
+            ink::codegen::EmitEvent::<Dns>::emit_event(self.env(), Renewed { name, expires_at });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer(&mut self, name: Hash, to: AccountId) -> Result<()> {
+            let from = self.env().caller();
+            let mut registration = self.owned_registration(name)?;
+            registration.owner = to;
+            self.registrations.insert(name, &registration);
+            ink::codegen::EmitEvent::<Dns>::emit_event(
+                self.env(),
+                NameTransferred { name, from, to },
+            );
+            Ok(())
+        }
+
+        /// Makes `name` resolve to `resolver`, or with `None` to nothing.
+        #[ink(message)]
+        pub fn set_resolver(&mut self, name: Hash, resolver: Option<AccountId>) -> Result<()> {
+            let mut registration = self.owned_registration(name)?;
+            registration.resolver = resolver;
+            self.registrations.insert(name, &registration);
+            ink::codegen::EmitEvent::<Dns>::emit_event(self.env(), ResolverSet { name, resolver });
+            Ok(())
+        }
+
+        /// The active registration of `name` if the caller owns it.
+        fn owned_registration(&self, name: Hash) -> Result<Registration> {
+            let registration = self.registration(name).ok_or(Error::NameNotFound)?;
+            if registration.owner != self.env().caller() {
+                return Err(Error::NotNameOwner);
+            }
+            Ok(registration)
+        }
+
+        fn duration(&self, periods: u32) -> Result<Timestamp> {
+            if periods == 0 {
+                return Err(Error::ZeroPeriods);
+            }
+            self.period
+                .checked_mul(Timestamp::from(periods))
+                .ok_or(Error::Overflow)
+        }
+
+        /// Takes the fee for `periods` periods from `payer`.
+        fn charge(&self, payer: AccountId, periods: u32) -> Result<()> {
+            let fee = self
+                .fee_per_period
+                .checked_mul(Balance::from(periods))
+                .ok_or(Error::Overflow)?;
+            if fee > 0 {
+                self.token_contract()
+                    .transfer_from(payer, self.operator, fee)?;
+            }
+            Ok(())
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        /// A free name service, so tests don't need the token.
+        fn dns() -> Dns {
+            Dns::new(AccountId::from([0x9; 32]), 0, 1000)
+        }
+
+        #[ink::test]
+        fn register_and_resolve_work() {
+            let accounts = accounts();
+            let name = Hash::from([0x7; 32]);
+            let mut dns = dns();
+            assert_eq!(dns.register(name, 0), Err(Error::ZeroPeriods));
+            assert_eq!(dns.register(name, 2), Ok(()));
+            assert_eq!(dns.register(name, 1), Err(Error::NameTaken));
+            assert_eq!(dns.resolve(name), None);
+            assert_eq!(dns.set_resolver(name, Some(accounts.eve)), Ok(()));
+            assert_eq!(dns.resolve(name), Some(accounts.eve));
+            let expires_at = dns.registration(name).unwrap().expires_at;
+            assert_eq!(dns.renew(name, 1), Ok(()));
+            assert_eq!(
+                dns.registration(name).unwrap().expires_at,
+                expires_at + 1000
+            );
+        }
+
+        #[ink::test]
+        fn only_the_owner_manages_a_name() {
+            let accounts = accounts();
+            let name = Hash::from([0x7; 32]);
+            let mut dns = dns();
+            dns.register(name, 1).unwrap();
+            assert_eq!(dns.transfer(name, accounts.bob), Ok(()));
+            assert_eq!(
+                dns.set_resolver(name, Some(accounts.alice)),
+                Err(Error::NotNameOwner)
+            );
+            set_caller(accounts.bob);
+            assert_eq!(dns.set_resolver(name, Some(accounts.bob)), Ok(()));
+            assert_eq!(
+                dns.transfer(Hash::from([0x8; 32]), accounts.alice),
+                Err(Error::NameNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn expired_names_can_be_registered_again() {
+            let accounts = accounts();
+            let name = Hash::from([0x7; 32]);
+            let mut dns = dns();
+            dns.register(name, 1).unwrap();
+            let mut registration = dns.registration(name).unwrap();
+            registration.expires_at = 0;
+            dns.registrations.insert(name, &registration);
+            assert_eq!(dns.registration(name), None);
+            assert_eq!(dns.renew(name, 1), Err(Error::NameNotFound));
+            set_caller(accounts.bob);
+            assert_eq!(dns.register(name, 1), Ok(()));
+            assert_eq!(dns.registration(name).unwrap().owner, accounts.bob);
+        }
+    }
+}
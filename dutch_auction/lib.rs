@@ -0,0 +1,355 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Sells `supply` of the course `Erc20` in a Dutch auction: the price starts at
+/// `start_price` and falls every block until it reaches `floor_price`.
+///
+/// Buyers `bid` native currency at any time. The auction is over once the
+/// bids buy the whole supply at the current price, or at `end_block`. Everyone
+/// then pays the same clearing price, the lowest one at which the bids still
+/// bought the supply, however early they bid. `claim` mints a buyer its tokens
+/// and refunds what it paid above the clearing price. The auction needs a
+/// minter allowance on the token.
+#[ink::contract]
+mod dutch_auction {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    /// Fixed point scale of the exponential decay factor.
+    const SCALE: Balance = 1_000_000_000_000;
+
+    /// How the price falls per block.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Decay {
+        /// By a fixed amount.
+        Linear(Balance),
+        /// By a fixed share of the previous block's price, in basis points.
+        Exponential(u16),
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Bid {
+        pub paid: Balance,
+        /// The part of `paid` that went towards the supply. Only the bid that
+        /// sold out the auction has less accepted than paid.
+        pub accepted: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct DutchAuction {
+        token: AccountId,
+        owner: AccountId,
+        supply: Balance,
+        start_price: Balance,
+        floor_price: Balance,
+        decay: Decay,
+        start_block: BlockNumber,
+        end_block: BlockNumber,
+        bids: Mapping<AccountId, Bid>,
+        /// Sum of the accepted bids.
+        total_accepted: Balance,
+        /// Proceeds of the claims so far, not yet withdrawn by the owner.
+        proceeds: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BidPlaced {
+        #[ink(topic)]
+        bidder: AccountId,
+        paid: Balance,
+        accepted: Balance,
+        price: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Claimed {
+        #[ink(topic)]
+        bidder: AccountId,
+        tokens: Balance,
+        refund: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        AuctionNotStarted,
+        AuctionOver,
+        AuctionNotOver,
+        ZeroBid,
+        NoBid,
+        Overflow,
+        TransferFailed,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl DutchAuction {
+        #[ink(constructor)]
+        pub fn new(
+            token: AccountId,
+            supply: Balance,
+            start_price: Balance,
+            floor_price: Balance,
+            decay: Decay,
+            start_block: BlockNumber,
+            end_block: BlockNumber,
+        ) -> Self {
+            Self {
+                token,
+                owner: Self::env().caller(),
+                supply,
+                start_price,
+                floor_price,
+                decay,
+                start_block,
+                end_block,
+                bids: Mapping::default(),
+                total_accepted: 0,
+                proceeds: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn bid_of(&self, bidder: AccountId) -> Bid {
+            self.bids.get(bidder).unwrap_or_default()
+        }
+
+        /// The price of a token in the current block.
+        #[ink(message)]
+        pub fn current_price(&self) -> Balance {
+            self.price_at(self.env().block_number())
+        }
+
+        /// The price every buyer pays, once the auction is over.
+        #[ink(message)]
+        pub fn clearing_price(&self) -> Option<Balance> {
+            let price = self.current_price();
+            let sold_out_value = self.supply.saturating_mul(price);
+            if self.total_accepted >= sold_out_value && self.supply > 0 {
+                // The price at which the bids bought the supply, rounded up so
+                // that no more than the supply is sold.
+                Some(self.total_accepted.div_ceil(self.supply))
+            } else if self.env().block_number() >= self.end_block {
+                Some(price)
+            } else {
+                None
+            }
+        }
+
+        /// Bids the native currency sent along. The part that would buy more
+        /// than the remaining supply at the current price is not accepted and
+        /// refunded by `claim`.
+        #[ink(message, payable)]
+        pub fn bid(&mut self) -> Result<()> {
+            if self.env().block_number() < self.start_block {
+                return Err(Error::AuctionNotStarted);
+            }
+            if self.clearing_price().is_some() {
+                return Err(Error::AuctionOver);
+            }
+            let paid = self.env().transferred_value();
+            if paid == 0 {
+                return Err(Error::ZeroBid);
+            }
+            let price = self.current_price();
+            let sold_out_value = self.supply.checked_mul(price).ok_or(Error::Overflow)?;
+            let accepted = paid.min(sold_out_value - self.total_accepted);
+            self.total_accepted += accepted;
+
+            let bidder = self.env().caller();
+            let mut bid = self.bid_of(bidder);
+            bid.paid = bid.paid.checked_add(paid).ok_or(Error::Overflow)?;
+            bid.accepted += accepted;
+            self.bids.insert(bidder, &bid);
+            ink::codegen::EmitEvent::<DutchAuction>::emit_event(
+                self.env(),
+                BidPlaced {
+                    bidder,
+                    paid,
+                    accepted,
+                    price,
+                },
+            );
+            Ok(())
+        }
+
+        /// Mints the caller the tokens its bid bought at the clearing price and
+        /// refunds the rest of what it paid. Returns the tokens bought.
+        #[ink(message)]
+        pub fn claim(&mut self) -> Result<Balance> {
+            let clearing_price = self.clearing_price().ok_or(Error::AuctionNotOver)?;
+            let bidder = self.env().caller();
+            let bid = self.bids.take(bidder).ok_or(Error::NoBid)?;
+            let (tokens, refund) = settle(&bid, clearing_price);
+            self.proceeds += bid.paid - refund;
+            if tokens > 0 {
+                self.token_contract().mint(bidder, tokens)?;
+            }
+            if refund > 0 {
+                self.env()
+                    .transfer(bidder, refund)
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+            ink::codegen::EmitEvent::<DutchAuction>::emit_event(
+                self.env(),
+                Claimed {
+                    bidder,
+                    tokens,
+                    refund,
+                },
+            );
+            Ok(tokens)
+        }
+
+        /// Sends the proceeds of the claims so far to the owner.
+        #[ink(message)]
+        pub fn withdraw_proceeds(&mut self) -> Result<Balance> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            let amount = core::mem::take(&mut self.proceeds);
+            self.env()
+                .transfer(self.owner, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            Ok(amount)
+        }
+
+        fn price_at(&self, block: BlockNumber) -> Balance {
+            let blocks = block.min(self.end_block).saturating_sub(self.start_block);
+            let price = match self.decay {
+                Decay::Linear(per_block) => self
+                    .start_price
+                    .saturating_sub(per_block.saturating_mul(Balance::from(blocks))),
+                Decay::Exponential(bps) => {
+                    let factor = decay_factor(bps, blocks);
+                    // Split so that large prices can't overflow.
+                    self.start_price / SCALE * factor + self.start_price % SCALE * factor / SCALE
+                }
+            };
+            price.max(self.floor_price)
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    /// `(1 - bps / 10_000) ^ blocks`, scaled by `SCALE`.
+    fn decay_factor(bps: u16, mut blocks: BlockNumber) -> Balance {
+        let mut base = Balance::from(10_000u16.saturating_sub(bps)) * SCALE / 10_000;
+        let mut factor = SCALE;
+        while blocks > 0 && factor > 0 {
+            if blocks & 1 == 1 {
+                factor = factor * base / SCALE;
+            }
+            base = base * base / SCALE;
+            blocks >>= 1;
+        }
+        factor
+    }
+
+    /// The tokens `bid` bought at `clearing_price` and the refund it is owed.
+    fn settle(bid: &Bid, clearing_price: Balance) -> (Balance, Balance) {
+        let tokens = bid.accepted / clearing_price.max(1);
+        (tokens, bid.paid - tokens * clearing_price)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn auction(decay: Decay) -> DutchAuction {
+            DutchAuction::new(AccountId::from([0x9; 32]), 10, 100, 40, decay, 0, 100)
+        }
+
+        fn bid_as(auction: &mut DutchAuction, bidder: AccountId, value: Balance) -> Result<()> {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bidder);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(value);
+            auction.bid()
+        }
+
+        fn advance_blocks(blocks: u32) {
+            for _ in 0..blocks {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+        }
+
+        #[ink::test]
+        fn linear_price_stops_at_floor() {
+            let auction = auction(Decay::Linear(10));
+            assert_eq!(auction.price_at(0), 100);
+            assert_eq!(auction.price_at(3), 70);
+            assert_eq!(auction.price_at(6), 40);
+            assert_eq!(auction.price_at(50), 40);
+        }
+
+        #[ink::test]
+        fn exponential_price_halves() {
+            let auction = auction(Decay::Exponential(5_000));
+            assert_eq!(auction.price_at(0), 100);
+            assert_eq!(auction.price_at(1), 50);
+            assert_eq!(auction.price_at(2), 40);
+            assert_eq!(decay_factor(5_000, 3), SCALE / 8);
+            assert_eq!(decay_factor(10_000, 1), 0);
+        }
+
+        #[ink::test]
+        fn sell_out_ends_auction() {
+            let mut auction = auction(Decay::Linear(10));
+            let alice = AccountId::from([0x1; 32]);
+            let bob = AccountId::from([0x2; 32]);
+            assert_eq!(bid_as(&mut auction, alice, 0), Err(Error::ZeroBid));
+            assert_eq!(bid_as(&mut auction, alice, 600), Ok(()));
+            assert_eq!(auction.clearing_price(), None);
+            assert_eq!(bid_as(&mut auction, bob, 600), Ok(()));
+            assert_eq!(
+                auction.bid_of(bob),
+                Bid {
+                    paid: 600,
+                    accepted: 400
+                }
+            );
+            assert_eq!(auction.clearing_price(), Some(100));
+            assert_eq!(bid_as(&mut auction, bob, 1), Err(Error::AuctionOver));
+            assert_eq!(settle(&auction.bid_of(alice), 100), (6, 0));
+            assert_eq!(settle(&auction.bid_of(bob), 100), (4, 200));
+        }
+
+        #[ink::test]
+        fn early_bids_pay_clearing_price() {
+            let mut auction = auction(Decay::Linear(10));
+            let alice = AccountId::from([0x1; 32]);
+            assert_eq!(bid_as(&mut auction, alice, 590), Ok(()));
+            advance_blocks(5);
+            assert_eq!(auction.current_price(), 50);
+            assert_eq!(auction.clearing_price(), Some(59));
+            assert_eq!(settle(&auction.bid_of(alice), 59), (10, 0));
+            assert_eq!(bid_as(&mut auction, alice, 1), Err(Error::AuctionOver));
+        }
+
+        #[ink::test]
+        fn unsold_auction_clears_at_end_price() {
+            let mut auction = auction(Decay::Linear(10));
+            let alice = AccountId::from([0x1; 32]);
+            assert_eq!(bid_as(&mut auction, alice, 250), Ok(()));
+            assert_eq!(auction.claim(), Err(Error::AuctionNotOver));
+            auction.end_block = 0;
+            assert_eq!(auction.clearing_price(), Some(100));
+            assert_eq!(settle(&auction.bid_of(alice), 100), (2, 50));
+        }
+    }
+}
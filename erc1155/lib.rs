@@ -0,0 +1,425 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Implemented by contracts that accept tokens from `safe_transfer_from` and
+/// `safe_batch_transfer_from`. Returning `false` reverts the transfer.
+#[ink::trait_definition]
+pub trait Erc1155TokenReceiver {
+    #[ink(message)]
+    fn on_received(
+        &mut self,
+        operator: ink::primitives::AccountId,
+        from: ink::primitives::AccountId,
+        id: u128,
+        value: u128,
+        data: ink::prelude::vec::Vec<u8>,
+    ) -> bool;
+
+    #[ink(message)]
+    fn on_batch_received(
+        &mut self,
+        operator: ink::primitives::AccountId,
+        from: ink::primitives::AccountId,
+        ids: ink::prelude::vec::Vec<u128>,
+        values: ink::prelude::vec::Vec<u128>,
+        data: ink::prelude::vec::Vec<u8>,
+    ) -> bool;
+}
+
+/// Many fungible and non-fungible tokens in one contract, each identified by
+/// a `TokenId`. Batched messages move several ids in one call and emit a
+/// single `TransferBatch` event.
+#[ink::contract]
+mod erc1155 {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    pub type TokenId = u128;
+
+    const ON_RECEIVED: [u8; 4] = [0xe6, 0xd4, 0xd1, 0x2f];
+    const ON_BATCH_RECEIVED: [u8; 4] = [0xa5, 0xc0, 0x5d, 0x47];
+
+    #[ink(storage)]
+    pub struct Erc1155 {
+        balances: Mapping<(AccountId, TokenId), Balance>,
+        approvals: Mapping<(AccountId, AccountId), ()>,
+        /// The highest token id created so far.
+        token_id_nonce: TokenId,
+    }
+
+    /// Mints have no `from`, burns have no `to`.
+    #[ink(event)]
+    pub struct TransferSingle {
+        #[ink(topic)]
+        operator: AccountId,
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        id: TokenId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TransferBatch {
+        #[ink(topic)]
+        operator: AccountId,
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        ids: Vec<TokenId>,
+        values: Vec<Balance>,
+    }
+
+    #[ink(event)]
+    pub struct ApprovalForAll {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        operator: AccountId,
+        approved: bool,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        UnexistentToken,
+        ZeroAddressTransfer,
+        NotApproved,
+        InsufficientBalance,
+        SelfApproval,
+        BatchTransferMismatch,
+        TransferRejected,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl Erc1155 {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                balances: Mapping::default(),
+                approvals: Mapping::default(),
+                token_id_nonce: 0,
+            }
+        }
+
+        /// Creates a new token id and mints `value` of it to the caller.
+        #[ink(message)]
+        pub fn create(&mut self, value: Balance) -> TokenId {
+            let caller = self.env().caller();
+            self.token_id_nonce += 1;
+            let id = self.token_id_nonce;
+            self.balances.insert((caller, id), &value);
+            self.env().emit_event(TransferSingle {
+                operator: caller,
+                from: None,
+                to: Some(caller),
+                id,
+                value,
+            });
+            id
+        }
+
+        /// Mints more of an existing token id to the caller.
+        #[ink(message)]
+        pub fn mint(&mut self, id: TokenId, value: Balance) -> Result<()> {
+            if id == 0 || id > self.token_id_nonce {
+                return Err(Error::UnexistentToken);
+            }
+            let caller = self.env().caller();
+            let balance = self.balance_of(caller, id);
+            self.balances.insert((caller, id), &(balance + value));
+            self.env().emit_event(TransferSingle {
+                operator: caller,
+                from: None,
+                to: Some(caller),
+                id,
+                value,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId, id: TokenId) -> Balance {
+            self.balances.get((owner, id)).unwrap_or(0)
+        }
+
+        /// Balances of every owner for every id, owner by owner.
+        #[ink(message)]
+        pub fn balance_of_batch(&self, owners: Vec<AccountId>, ids: Vec<TokenId>) -> Vec<Balance> {
+            let mut output = Vec::with_capacity(owners.len() * ids.len());
+            for owner in &owners {
+                for id in &ids {
+                    output.push(self.balance_of(*owner, *id));
+                }
+            }
+            output
+        }
+
+        #[ink(message)]
+        pub fn set_approval_for_all(&mut self, operator: AccountId, approved: bool) -> Result<()> {
+            let owner = self.env().caller();
+            if owner == operator {
+                return Err(Error::SelfApproval);
+            }
+            if approved {
+                self.approvals.insert((owner, operator), &());
+            } else {
+                self.approvals.remove((owner, operator));
+            }
+            self.env().emit_event(ApprovalForAll {
+                owner,
+                operator,
+                approved,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.approvals.contains((owner, operator))
+        }
+
+        #[ink(message)]
+        pub fn safe_transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            let operator = self.ensure_can_transfer(from, to)?;
+            self.move_balance(from, to, id, value)?;
+            self.env().emit_event(TransferSingle {
+                operator,
+                from: Some(from),
+                to: Some(to),
+                id,
+                value,
+            });
+            if self.env().is_contract(&to) {
+                let accepted = build_call::<ink::env::DefaultEnvironment>()
+                    .call(to)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(ON_RECEIVED))
+                            .push_arg(operator)
+                            .push_arg(from)
+                            .push_arg(id)
+                            .push_arg(value)
+                            .push_arg(data),
+                    )
+                    .returns::<bool>()
+                    .try_invoke()
+                    == Ok(Ok(true));
+                if !accepted {
+                    return Err(Error::TransferRejected);
+                }
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn safe_batch_transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            ids: Vec<TokenId>,
+            values: Vec<Balance>,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            let operator = self.ensure_can_transfer(from, to)?;
+            if ids.is_empty() || ids.len() != values.len() {
+                return Err(Error::BatchTransferMismatch);
+            }
+            for (id, value) in ids.iter().zip(values.iter()) {
+                self.move_balance(from, to, *id, *value)?;
+            }
+            self.env().emit_event(TransferBatch {
+                operator,
+                from: Some(from),
+                to: Some(to),
+                ids: ids.clone(),
+                values: values.clone(),
+            });
+            if self.env().is_contract(&to) {
+                let accepted = build_call::<ink::env::DefaultEnvironment>()
+                    .call(to)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(ON_BATCH_RECEIVED))
+                            .push_arg(operator)
+                            .push_arg(from)
+                            .push_arg(ids)
+                            .push_arg(values)
+                            .push_arg(data),
+                    )
+                    .returns::<bool>()
+                    .try_invoke()
+                    == Ok(Ok(true));
+                if !accepted {
+                    return Err(Error::TransferRejected);
+                }
+            }
+            Ok(())
+        }
+
+        /// Returns the operator if it may move tokens of `from` to `to`.
+        fn ensure_can_transfer(&self, from: AccountId, to: AccountId) -> Result<AccountId> {
+            let operator = self.env().caller();
+            if operator != from && !self.is_approved_for_all(from, operator) {
+                return Err(Error::NotApproved);
+            }
+            if to == AccountId::from([0x0; 32]) {
+                return Err(Error::ZeroAddressTransfer);
+            }
+            Ok(operator)
+        }
+
+        fn move_balance(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+            value: Balance,
+        ) -> Result<()> {
+            let from_balance = self.balance_of(from, id);
+            if from_balance < value {
+                return Err(Error::InsufficientBalance);
+            }
+            self.balances.insert((from, id), &(from_balance - value));
+            let to_balance = self.balance_of(to, id);
+            self.balances.insert((to, id), &(to_balance + value));
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        #[ink::test]
+        fn create_works() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            assert_eq!(erc1155.create(100), 1);
+            assert_eq!(erc1155.create(5), 2);
+            assert_eq!(erc1155.balance_of(owner, 1), 100);
+            assert_eq!(erc1155.balance_of(owner, 2), 5);
+        }
+
+        #[ink::test]
+        fn mint_works() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            let id = erc1155.create(100);
+            assert_eq!(erc1155.mint(id, 50), Ok(()));
+            assert_eq!(erc1155.balance_of(owner, id), 150);
+            assert_eq!(erc1155.mint(id + 1, 50), Err(Error::UnexistentToken));
+        }
+
+        #[ink::test]
+        fn balance_of_batch_works() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            let other = AccountId::from([0x2; 32]);
+            erc1155.create(100);
+            erc1155.create(5);
+            assert_eq!(
+                erc1155.balance_of_batch(vec![owner, other], vec![1, 2]),
+                vec![100, 5, 0, 0]
+            );
+        }
+
+        #[ink::test]
+        fn safe_transfer_from_works() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            let to = AccountId::from([0x2; 32]);
+            let id = erc1155.create(100);
+            assert_eq!(
+                erc1155.safe_transfer_from(owner, to, id, 40, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(erc1155.balance_of(owner, id), 60);
+            assert_eq!(erc1155.balance_of(to, id), 40);
+        }
+
+        #[ink::test]
+        fn safe_transfer_from_failed_for_lower_balance() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            let to = AccountId::from([0x2; 32]);
+            let id = erc1155.create(100);
+            assert_eq!(
+                erc1155.safe_transfer_from(owner, to, id, 200, Vec::new()),
+                Err(Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn safe_transfer_from_requires_approval() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            let operator = AccountId::from([0x2; 32]);
+            let id = erc1155.create(100);
+            set_caller(operator);
+            assert_eq!(
+                erc1155.safe_transfer_from(owner, operator, id, 10, Vec::new()),
+                Err(Error::NotApproved)
+            );
+            set_caller(owner);
+            assert_eq!(erc1155.set_approval_for_all(operator, true), Ok(()));
+            assert!(erc1155.is_approved_for_all(owner, operator));
+            set_caller(operator);
+            assert_eq!(
+                erc1155.safe_transfer_from(owner, operator, id, 10, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(erc1155.balance_of(operator, id), 10);
+        }
+
+        #[ink::test]
+        fn safe_batch_transfer_from_works() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            let to = AccountId::from([0x2; 32]);
+            erc1155.create(100);
+            erc1155.create(5);
+            assert_eq!(
+                erc1155.safe_batch_transfer_from(owner, to, vec![1, 2], vec![10, 5], Vec::new()),
+                Ok(())
+            );
+            assert_eq!(
+                erc1155.balance_of_batch(vec![owner, to], vec![1, 2]),
+                vec![90, 0, 10, 5]
+            );
+        }
+
+        #[ink::test]
+        fn safe_batch_transfer_from_rejects_mismatch() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            let to = AccountId::from([0x2; 32]);
+            erc1155.create(100);
+            assert_eq!(
+                erc1155.safe_batch_transfer_from(owner, to, vec![1, 2], vec![10], Vec::new()),
+                Err(Error::BatchTransferMismatch)
+            );
+        }
+
+        #[ink::test]
+        fn self_approval_fails() {
+            let mut erc1155 = Erc1155::new();
+            let owner = AccountId::from([0x1; 32]);
+            assert_eq!(
+                erc1155.set_approval_for_all(owner, true),
+                Err(Error::SelfApproval)
+            );
+        }
+    }
+}
@@ -0,0 +1,357 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::erc721::{Erc721, Erc721Ref, Error, TokenId};
+
+#[ink::contract]
+mod erc721 {
+    use ink::storage::Mapping;
+
+    pub type TokenId = u32;
+
+    #[ink(storage)]
+    pub struct Erc721 {
+        token_owner: Mapping<TokenId, AccountId>,
+        token_approvals: Mapping<TokenId, AccountId>,
+        owned_tokens_count: Mapping<AccountId, u32>,
+        operator_approvals: Mapping<(AccountId, AccountId), bool>,
+        /// Who minted each token, e.g. to pay royalties to.
+        creators: Mapping<TokenId, AccountId>,
+    }
+
+    /// Emitted on mint (`from` is `None`), burn (`to` is `None`) and transfer.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        approved: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    #[ink(event)]
+    pub struct ApprovalForAll {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        operator: AccountId,
+        approved: bool,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        NotApproved,
+        TokenExists,
+        TokenNotFound,
+        NotAllowed,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl Default for Erc721 {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Erc721 {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                token_owner: Mapping::default(),
+                token_approvals: Mapping::default(),
+                owned_tokens_count: Mapping::default(),
+                operator_approvals: Mapping::default(),
+                creators: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> u32 {
+            self.owned_tokens_count.get(owner).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn owner_of(&self, id: TokenId) -> Option<AccountId> {
+            self.token_owner.get(id)
+        }
+
+        #[ink(message)]
+        pub fn creator_of(&self, id: TokenId) -> Option<AccountId> {
+            self.creators.get(id)
+        }
+
+        #[ink(message)]
+        pub fn get_approved(&self, id: TokenId) -> Option<AccountId> {
+            self.token_approvals.get(id)
+        }
+
+        #[ink(message)]
+        pub fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.operator_approvals
+                .get((owner, operator))
+                .unwrap_or(false)
+        }
+
+        /// Lets `operator` move all of the caller's tokens, or revokes that.
+        #[ink(message)]
+        pub fn set_approval_for_all(&mut self, operator: AccountId, approved: bool) -> Result<()> {
+            let owner = self.env().caller();
+            if owner == operator {
+                return Err(Error::NotAllowed);
+            }
+            if approved {
+                self.operator_approvals.insert((owner, operator), &true);
+            } else {
+                self.operator_approvals.remove((owner, operator));
+            }
+            self.env().emit_event(ApprovalForAll {
+                owner,
+                operator,
+                approved,
+            });
+            Ok(())
+        }
+
+        /// Lets `to` move token `id` once. Callable by the owner or an operator.
+        #[ink(message)]
+        pub fn approve(&mut self, to: AccountId, id: TokenId) -> Result<()> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if !(owner == caller || self.is_approved_for_all(owner, caller)) {
+                return Err(Error::NotAllowed);
+            }
+            if to == owner {
+                return Err(Error::NotAllowed);
+            }
+            self.token_approvals.insert(id, &to);
+            self.env().emit_event(Approval {
+                owner,
+                approved: to,
+                id,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer(&mut self, to: AccountId, id: TokenId) -> Result<()> {
+            let caller = self.env().caller();
+            self.transfer_token_from(caller, to, id)
+        }
+
+        #[ink(message)]
+        pub fn transfer_from(&mut self, from: AccountId, to: AccountId, id: TokenId) -> Result<()> {
+            self.transfer_token_from(from, to, id)
+        }
+
+        #[ink(message)]
+        pub fn mint(&mut self, id: TokenId) -> Result<()> {
+            let caller = self.env().caller();
+            if self.token_owner.contains(id) {
+                return Err(Error::TokenExists);
+            }
+            self.token_owner.insert(id, &caller);
+            self.creators.insert(id, &caller);
+            self.increase_count(caller);
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(caller),
+                id,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn burn(&mut self, id: TokenId) -> Result<()> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != caller {
+                return Err(Error::NotOwner);
+            }
+            self.token_owner.remove(id);
+            self.token_approvals.remove(id);
+            self.creators.remove(id);
+            self.decrease_count(owner);
+            self.env().emit_event(Transfer {
+                from: Some(owner),
+                to: None,
+                id,
+            });
+            Ok(())
+        }
+
+        fn transfer_token_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+        ) -> Result<()> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != from {
+                return Err(Error::NotOwner);
+            }
+            if !self.approved_or_owner(caller, owner, id) {
+                return Err(Error::NotApproved);
+            }
+            self.token_approvals.remove(id);
+            self.decrease_count(from);
+            self.increase_count(to);
+            self.token_owner.insert(id, &to);
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                id,
+            });
+            Ok(())
+        }
+
+        fn approved_or_owner(&self, caller: AccountId, owner: AccountId, id: TokenId) -> bool {
+            caller == owner
+                || self.get_approved(id) == Some(caller)
+                || self.is_approved_for_all(owner, caller)
+        }
+
+        fn increase_count(&mut self, of: AccountId) {
+            let count = self.balance_of(of) + 1;
+            self.owned_tokens_count.insert(of, &count);
+        }
+
+        fn decrease_count(&mut self, of: AccountId) {
+            let count = self.balance_of(of) - 1;
+            self.owned_tokens_count.insert(of, &count);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        #[ink::test]
+        fn mint_works() {
+            let mut erc721 = Erc721::new();
+            let owner = AccountId::from([0x1; 32]);
+            assert_eq!(erc721.balance_of(owner), 0);
+            assert_eq!(erc721.mint(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(owner));
+            assert_eq!(erc721.creator_of(1), Some(owner));
+            assert_eq!(erc721.balance_of(owner), 1);
+        }
+
+        #[ink::test]
+        fn mint_existing_token_fails() {
+            let mut erc721 = Erc721::new();
+            erc721.mint(1).unwrap();
+            assert_eq!(erc721.mint(1), Err(Error::TokenExists));
+        }
+
+        #[ink::test]
+        fn transfer_works() {
+            let mut erc721 = Erc721::new();
+            let owner = AccountId::from([0x1; 32]);
+            let to = AccountId::from([0x2; 32]);
+            erc721.mint(1).unwrap();
+            assert_eq!(erc721.transfer(to, 1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(to));
+            assert_eq!(erc721.creator_of(1), Some(owner));
+            assert_eq!(erc721.balance_of(owner), 0);
+            assert_eq!(erc721.balance_of(to), 1);
+        }
+
+        #[ink::test]
+        fn transfer_failed_for_missing_token() {
+            let mut erc721 = Erc721::new();
+            let to = AccountId::from([0x2; 32]);
+            assert_eq!(erc721.transfer(to, 1), Err(Error::TokenNotFound));
+        }
+
+        #[ink::test]
+        fn transfer_failed_for_non_owner() {
+            let mut erc721 = Erc721::new();
+            let to = AccountId::from([0x2; 32]);
+            erc721.mint(1).unwrap();
+            set_caller(to);
+            assert_eq!(erc721.transfer(to, 1), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn approved_transfer_works() {
+            let mut erc721 = Erc721::new();
+            let owner = AccountId::from([0x1; 32]);
+            let spender = AccountId::from([0x2; 32]);
+            let to = AccountId::from([0x3; 32]);
+            erc721.mint(1).unwrap();
+            assert_eq!(erc721.approve(spender, 1), Ok(()));
+            assert_eq!(erc721.get_approved(1), Some(spender));
+            set_caller(spender);
+            assert_eq!(erc721.transfer_from(owner, to, 1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(to));
+            assert_eq!(erc721.get_approved(1), None);
+        }
+
+        #[ink::test]
+        fn not_approved_transfer_fails() {
+            let mut erc721 = Erc721::new();
+            let owner = AccountId::from([0x1; 32]);
+            let spender = AccountId::from([0x2; 32]);
+            erc721.mint(1).unwrap();
+            set_caller(spender);
+            assert_eq!(
+                erc721.transfer_from(owner, spender, 1),
+                Err(Error::NotApproved)
+            );
+        }
+
+        #[ink::test]
+        fn approval_for_all_works() {
+            let mut erc721 = Erc721::new();
+            let owner = AccountId::from([0x1; 32]);
+            let operator = AccountId::from([0x2; 32]);
+            erc721.mint(1).unwrap();
+            erc721.mint(2).unwrap();
+            assert_eq!(erc721.set_approval_for_all(operator, true), Ok(()));
+            assert!(erc721.is_approved_for_all(owner, operator));
+            set_caller(operator);
+            assert_eq!(erc721.transfer_from(owner, operator, 1), Ok(()));
+            assert_eq!(erc721.transfer_from(owner, operator, 2), Ok(()));
+            assert_eq!(erc721.balance_of(operator), 2);
+            set_caller(owner);
+            assert_eq!(erc721.set_approval_for_all(operator, false), Ok(()));
+            assert!(!erc721.is_approved_for_all(owner, operator));
+        }
+
+        #[ink::test]
+        fn burn_works() {
+            let mut erc721 = Erc721::new();
+            let owner = AccountId::from([0x1; 32]);
+            erc721.mint(1).unwrap();
+            assert_eq!(erc721.burn(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), None);
+            assert_eq!(erc721.creator_of(1), None);
+            assert_eq!(erc721.balance_of(owner), 0);
+        }
+
+        #[ink::test]
+        fn burn_failed_for_non_owner() {
+            let mut erc721 = Erc721::new();
+            erc721.mint(1).unwrap();
+            set_caller(AccountId::from([0x2; 32]));
+            assert_eq!(erc721.burn(1), Err(Error::NotOwner));
+        }
+    }
+}
@@ -0,0 +1,286 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+#[ink::contract]
+mod escrow {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    pub type OrderId = u64;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Status {
+        Pending,
+        Released,
+        Refunded,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Deal {
+        pub payer: AccountId,
+        pub payee: AccountId,
+        pub amount: Balance,
+        /// After this timestamp the arbiter may refund the payer.
+        pub deadline: Timestamp,
+        pub status: Status,
+    }
+
+    #[ink(storage)]
+    pub struct Escrow {
+        token: AccountId,
+        arbiter: AccountId,
+        deals: Mapping<OrderId, Deal>,
+    }
+
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        payer: AccountId,
+        #[ink(topic)]
+        payee: AccountId,
+        amount: Balance,
+        deadline: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Released {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        payee: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Refunded {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        payer: AccountId,
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        OrderExists,
+        OrderNotFound,
+        NotArbiter,
+        NotPending,
+        DeadlineNotReached,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Escrow {
+        #[ink(constructor)]
+        pub fn new(token: AccountId, arbiter: AccountId) -> Self {
+            Self {
+                token,
+                arbiter,
+                deals: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn token(&self) -> AccountId {
+            self.token
+        }
+
+        #[ink(message)]
+        pub fn arbiter(&self) -> AccountId {
+            self.arbiter
+        }
+
+        #[ink(message)]
+        pub fn deal(&self, order_id: OrderId) -> Option<Deal> {
+            self.deals.get(order_id)
+        }
+
+        /// Pulls `amount` tokens from the caller into the escrow for `order_id`.
+        /// The caller must have approved the escrow for at least `amount`.
+        /// The deal holds what the escrow actually received, which is less
+        /// than `amount` if the token charges a transfer fee.
+        #[ink(message)]
+        pub fn deposit(
+            &mut self,
+            order_id: OrderId,
+            payee: AccountId,
+            amount: Balance,
+            deadline: Timestamp,
+        ) -> Result<()> {
+            if self.deals.contains(order_id) {
+                return Err(Error::OrderExists);
+            }
+            let payer = self.env().caller();
+            let this = self.env().account_id();
+            let mut token = self.token_contract();
+            let before = token.balance_of(this);
+            token.transfer_from(payer, this, amount)?;
+            let amount = token.balance_of(this).saturating_sub(before);
+            self.deals.insert(
+                order_id,
+                &Deal {
+                    payer,
+                    payee,
+                    amount,
+                    deadline,
+                    status: Status::Pending,
+                },
+            );
+            ink::codegen::EmitEvent::<Escrow>::emit_event(
+                self.env(),
+                Deposited {
+                    order_id,
+                    payer,
+                    payee,
+                    amount,
+                    deadline,
+                },
+            );
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn release(&mut self, order_id: OrderId) -> Result<()> {
+            self.ensure_arbiter()?;
+            let deal = self.settle(order_id, Status::Released)?;
+            self.token_contract().transfer(deal.payee, deal.amount)?;
+            ink::codegen::EmitEvent::<Escrow>::emit_event(
+                self.env(),
+                Released {
+                    order_id,
+                    payee: deal.payee,
+                    amount: deal.amount,
+                },
+            );
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn refund(&mut self, order_id: OrderId) -> Result<()> {
+            self.ensure_arbiter()?;
+            let deal = self.deal(order_id).ok_or(Error::OrderNotFound)?;
+            if self.env().block_timestamp() < deal.deadline {
+                return Err(Error::DeadlineNotReached);
+            }
+            let deal = self.settle(order_id, Status::Refunded)?;
+            self.token_contract().transfer(deal.payer, deal.amount)?;
+            ink::codegen::EmitEvent::<Escrow>::emit_event(
+                self.env(),
+                Refunded {
+                    order_id,
+                    payer: deal.payer,
+                    amount: deal.amount,
+                },
+            );
+            Ok(())
+        }
+
+        fn ensure_arbiter(&self) -> Result<()> {
+            if self.env().caller() != self.arbiter {
+                return Err(Error::NotArbiter);
+            }
+            Ok(())
+        }
+
+        /// Moves a pending deal into its final `status`.
+        fn settle(&mut self, order_id: OrderId, status: Status) -> Result<Deal> {
+            let mut deal = self.deals.get(order_id).ok_or(Error::OrderNotFound)?;
+            if deal.status != Status::Pending {
+                return Err(Error::NotPending);
+            }
+            deal.status = status;
+            self.deals.insert(order_id, &deal);
+            Ok(deal)
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn insert_pending(escrow: &mut Escrow, order_id: OrderId, deadline: Timestamp) {
+            escrow.deals.insert(
+                order_id,
+                &Deal {
+                    payer: AccountId::from([0x1; 32]),
+                    payee: AccountId::from([0x2; 32]),
+                    amount: 100,
+                    deadline,
+                    status: Status::Pending,
+                },
+            );
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let token = AccountId::from([0x9; 32]);
+            let arbiter = AccountId::from([0x1; 32]);
+            let escrow = Escrow::new(token, arbiter);
+            assert_eq!(escrow.token(), token);
+            assert_eq!(escrow.arbiter(), arbiter);
+            assert_eq!(escrow.deal(1), None);
+        }
+
+        #[ink::test]
+        fn deposit_rejects_existing_order() {
+            let mut escrow = Escrow::new(AccountId::from([0x9; 32]), AccountId::from([0x1; 32]));
+            insert_pending(&mut escrow, 1, 0);
+            assert_eq!(
+                escrow.deposit(1, AccountId::from([0x2; 32]), 100, 0),
+                Err(Error::OrderExists)
+            );
+        }
+
+        #[ink::test]
+        fn release_only_by_arbiter() {
+            let mut escrow = Escrow::new(AccountId::from([0x9; 32]), AccountId::from([0x3; 32]));
+            insert_pending(&mut escrow, 1, 0);
+            assert_eq!(escrow.release(1), Err(Error::NotArbiter));
+        }
+
+        #[ink::test]
+        fn release_unknown_order_fails() {
+            let mut escrow = Escrow::new(AccountId::from([0x9; 32]), AccountId::from([0x1; 32]));
+            assert_eq!(escrow.release(1), Err(Error::OrderNotFound));
+        }
+
+        #[ink::test]
+        fn settled_order_cannot_be_settled_again() {
+            let mut escrow = Escrow::new(AccountId::from([0x9; 32]), AccountId::from([0x1; 32]));
+            insert_pending(&mut escrow, 1, 0);
+            let mut deal = escrow.deals.get(1).unwrap();
+            deal.status = Status::Released;
+            escrow.deals.insert(1, &deal);
+            assert_eq!(escrow.release(1), Err(Error::NotPending));
+            assert_eq!(escrow.refund(1), Err(Error::NotPending));
+        }
+
+        #[ink::test]
+        fn refund_before_deadline_fails() {
+            let mut escrow = Escrow::new(AccountId::from([0x9; 32]), AccountId::from([0x1; 32]));
+            insert_pending(&mut escrow, 1, Timestamp::MAX);
+            assert_eq!(escrow.refund(1), Err(Error::DeadlineNotReached));
+        }
+    }
+}
@@ -0,0 +1,466 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Liquidity mining: stakers of PSP22 LP tokens earn the course `Erc20`.
+///
+/// Every block `reward_per_block` tokens are emitted and split between the
+/// pools by their allocation points, and within a pool between the stakers
+/// by their stake. Each pool tracks the rewards accumulated per staked unit
+/// since it was added; a staker is owed their stake times that, minus the
+/// `reward_debt` recorded when they last settled. Rewards are minted on
+/// harvest, so the farm needs a minter allowance on the token.
+#[ink::contract]
+mod farm {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+
+    pub type PoolId = u32;
+
+    /// Scale of `acc_reward_per_share`, so small rewards per unit don't
+    /// round to zero.
+    const ACC_PRECISION: Balance = 1_000_000_000_000;
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Pool {
+        pub lp_token: AccountId,
+        pub alloc_point: u32,
+        pub total_staked: Balance,
+        pub last_reward_block: BlockNumber,
+        /// Rewards per staked unit since the pool was added, times
+        /// `ACC_PRECISION`.
+        pub acc_reward_per_share: Balance,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Stake {
+        pub amount: Balance,
+        /// Part of `amount * acc_reward_per_share` already paid or not owed.
+        pub reward_debt: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct Farm {
+        reward_token: AccountId,
+        owner: AccountId,
+        reward_per_block: Balance,
+        total_alloc_point: u32,
+        pools: Mapping<PoolId, Pool>,
+        pool_count: u32,
+        stakes: Mapping<(PoolId, AccountId), Stake>,
+    }
+
+    #[ink(event)]
+    pub struct PoolAdded {
+        #[ink(topic)]
+        pool_id: PoolId,
+        #[ink(topic)]
+        lp_token: AccountId,
+        alloc_point: u32,
+    }
+
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        pool_id: PoolId,
+        #[ink(topic)]
+        staker: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        pool_id: PoolId,
+        #[ink(topic)]
+        staker: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Harvested {
+        #[ink(topic)]
+        pool_id: PoolId,
+        #[ink(topic)]
+        staker: AccountId,
+        reward: Balance,
+    }
+
+    #[ink(event)]
+    pub struct EmergencyWithdrawn {
+        #[ink(topic)]
+        pool_id: PoolId,
+        #[ink(topic)]
+        staker: AccountId,
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        PoolNotFound,
+        InsufficientStake,
+        Overflow,
+        TokenError(erc20::Error),
+        LpTokenError(PSP22Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::LpTokenError(error)
+        }
+    }
+
+    impl Farm {
+        #[ink(constructor)]
+        pub fn new(reward_token: AccountId, reward_per_block: Balance) -> Self {
+            Self {
+                reward_token,
+                owner: Self::env().caller(),
+                reward_per_block,
+                total_alloc_point: 0,
+                pools: Mapping::default(),
+                pool_count: 0,
+                stakes: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn pool_count(&self) -> u32 {
+            self.pool_count
+        }
+
+        #[ink(message)]
+        pub fn pool(&self, pool_id: PoolId) -> Option<Pool> {
+            self.pools.get(pool_id)
+        }
+
+        #[ink(message)]
+        pub fn stake_of(&self, pool_id: PoolId, staker: AccountId) -> Stake {
+            self.stakes.get((pool_id, staker)).unwrap_or_default()
+        }
+
+        /// Rewards `staker` could harvest from `pool_id` now.
+        #[ink(message)]
+        pub fn pending_reward(&self, pool_id: PoolId, staker: AccountId) -> Result<Balance> {
+            let pool = self.updated_pool(pool_id)?;
+            let stake = self.stake_of(pool_id, staker);
+            Ok(accrued(&stake, &pool)? - stake.reward_debt)
+        }
+
+        /// Adds a pool for `lp_token` that gets `alloc_point` shares of the
+        /// emissions.
+        #[ink(message)]
+        pub fn add_pool(&mut self, lp_token: AccountId, alloc_point: u32) -> Result<PoolId> {
+            self.ensure_owner()?;
+            self.update_all_pools()?;
+            let pool_id = self.pool_count;
+            self.pools.insert(
+                pool_id,
+                &Pool {
+                    lp_token,
+                    alloc_point,
+                    total_staked: 0,
+                    last_reward_block: self.env().block_number(),
+                    acc_reward_per_share: 0,
+                },
+            );
+            self.pool_count += 1;
+            self.total_alloc_point += alloc_point;
+            ink::codegen::EmitEvent::<Farm>::emit_event(
+                self.env(),
+                PoolAdded {
+                    pool_id,
+                    lp_token,
+                    alloc_point,
+                },
+            );
+            Ok(pool_id)
+        }
+
+        /// Changes the share of the emissions `pool_id` gets from now on.
+        #[ink(message)]
+        pub fn set_alloc_point(&mut self, pool_id: PoolId, alloc_point: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.update_all_pools()?;
+            let mut pool = self.pool(pool_id).ok_or(Error::PoolNotFound)?;
+            self.total_alloc_point = self.total_alloc_point - pool.alloc_point + alloc_point;
+            pool.alloc_point = alloc_point;
+            self.pools.insert(pool_id, &pool);
+            Ok(())
+        }
+
+        /// Stakes `amount` LP tokens of the caller, who must have approved the
+        /// farm for them, and harvests what was pending.
+        #[ink(message)]
+        pub fn deposit(&mut self, pool_id: PoolId, amount: Balance) -> Result<()> {
+            let staker = self.env().caller();
+            let pool = self.update_pool(pool_id)?;
+            self.harvest_helper(pool_id, staker)?;
+            psp22::transfer_from(pool.lp_token, staker, self.env().account_id(), amount)?;
+            self.change_stake(pool_id, staker, amount, true)?;
+            ink::codegen::EmitEvent::<Farm>::emit_event(
+                self.env(),
+                Deposited {
+                    pool_id,
+                    staker,
+                    amount,
+                },
+            );
+            Ok(())
+        }
+
+        /// Unstakes `amount` LP tokens and harvests what was pending.
+        #[ink(message)]
+        pub fn withdraw(&mut self, pool_id: PoolId, amount: Balance) -> Result<()> {
+            let staker = self.env().caller();
+            if self.stake_of(pool_id, staker).amount < amount {
+                return Err(Error::InsufficientStake);
+            }
+            let pool = self.update_pool(pool_id)?;
+            self.harvest_helper(pool_id, staker)?;
+            self.change_stake(pool_id, staker, amount, false)?;
+            psp22::transfer(pool.lp_token, staker, amount)?;
+            ink::codegen::EmitEvent::<Farm>::emit_event(
+                self.env(),
+                Withdrawn {
+                    pool_id,
+                    staker,
+                    amount,
+                },
+            );
+            Ok(())
+        }
+
+        /// Pays out the caller's pending rewards from `pool_id`.
+        #[ink(message)]
+        pub fn harvest(&mut self, pool_id: PoolId) -> Result<Balance> {
+            let staker = self.env().caller();
+            self.update_pool(pool_id)?;
+            self.harvest_helper(pool_id, staker)
+        }
+
+        /// Returns the caller's whole stake without paying rewards, which are
+        /// forfeited. For when harvesting fails, e.g. because the farm lost
+        /// its minter allowance.
+        #[ink(message)]
+        pub fn emergency_withdraw(&mut self, pool_id: PoolId) -> Result<()> {
+            let staker = self.env().caller();
+            let mut pool = self.pool(pool_id).ok_or(Error::PoolNotFound)?;
+            let amount = self
+                .stakes
+                .take((pool_id, staker))
+                .map(|stake| stake.amount)
+                .unwrap_or(0);
+            pool.total_staked -= amount;
+            self.pools.insert(pool_id, &pool);
+            psp22::transfer(pool.lp_token, staker, amount)?;
+            ink::codegen::EmitEvent::<Farm>::emit_event(
+                self.env(),
+                EmergencyWithdrawn {
+                    pool_id,
+                    staker,
+                    amount,
+                },
+            );
+            Ok(())
+        }
+
+        /// `pool_id` with its accumulator brought up to the current block.
+        fn updated_pool(&self, pool_id: PoolId) -> Result<Pool> {
+            let mut pool = self.pool(pool_id).ok_or(Error::PoolNotFound)?;
+            let block = self.env().block_number();
+            if block <= pool.last_reward_block {
+                return Ok(pool);
+            }
+            if pool.total_staked > 0 && self.total_alloc_point > 0 {
+                let reward = Balance::from(block - pool.last_reward_block)
+                    .checked_mul(self.reward_per_block)
+                    .and_then(|reward| reward.checked_mul(Balance::from(pool.alloc_point)))
+                    .ok_or(Error::Overflow)?
+                    / Balance::from(self.total_alloc_point);
+                pool.acc_reward_per_share +=
+                    reward.checked_mul(ACC_PRECISION).ok_or(Error::Overflow)? / pool.total_staked;
+            }
+            pool.last_reward_block = block;
+            Ok(pool)
+        }
+
+        fn update_pool(&mut self, pool_id: PoolId) -> Result<Pool> {
+            let pool = self.updated_pool(pool_id)?;
+            self.pools.insert(pool_id, &pool);
+            Ok(pool)
+        }
+
+        fn update_all_pools(&mut self) -> Result<()> {
+            for pool_id in 0..self.pool_count {
+                self.update_pool(pool_id)?;
+            }
+            Ok(())
+        }
+
+        /// Mints what `staker` has pending in the updated pool `pool_id`.
+        fn harvest_helper(&mut self, pool_id: PoolId, staker: AccountId) -> Result<Balance> {
+            let pool = self.pool(pool_id).ok_or(Error::PoolNotFound)?;
+            let mut stake = self.stake_of(pool_id, staker);
+            let accrued = accrued(&stake, &pool)?;
+            let reward = accrued - stake.reward_debt;
+            if stake.amount == 0 {
+                return Ok(0);
+            }
+            stake.reward_debt = accrued;
+            self.stakes.insert((pool_id, staker), &stake);
+            if reward > 0 {
+                let mut reward_token: Erc20Ref =
+                    ink::env::call::FromAccountId::from_account_id(self.reward_token);
+                reward_token.mint(staker, reward)?;
+                ink::codegen::EmitEvent::<Farm>::emit_event(
+                    self.env(),
+                    Harvested {
+                        pool_id,
+                        staker,
+                        reward,
+                    },
+                );
+            }
+            Ok(reward)
+        }
+
+        /// Adds `amount` to or takes it from the stake of `staker`, which has
+        /// just been harvested.
+        fn change_stake(
+            &mut self,
+            pool_id: PoolId,
+            staker: AccountId,
+            amount: Balance,
+            add: bool,
+        ) -> Result<()> {
+            let mut pool = self.pool(pool_id).ok_or(Error::PoolNotFound)?;
+            let mut stake = self.stake_of(pool_id, staker);
+            if add {
+                stake.amount = stake.amount.checked_add(amount).ok_or(Error::Overflow)?;
+                pool.total_staked += amount;
+            } else {
+                stake.amount -= amount;
+                pool.total_staked -= amount;
+            }
+            stake.reward_debt = accrued(&stake, &pool)?;
+            self.pools.insert(pool_id, &pool);
+            if stake.amount == 0 {
+                self.stakes.remove((pool_id, staker));
+            } else {
+                self.stakes.insert((pool_id, staker), &stake);
+            }
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+    }
+
+    /// Rewards of `stake` since `pool` was added, paid or not.
+    fn accrued(stake: &Stake, pool: &Pool) -> Result<Balance> {
+        Ok(stake
+            .amount
+            .checked_mul(pool.acc_reward_per_share)
+            .ok_or(Error::Overflow)?
+            / ACC_PRECISION)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn advance_blocks(count: u32) {
+            for _ in 0..count {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+        }
+
+        fn stake(farm: &mut Farm, pool_id: PoolId, staker: AccountId, amount: Balance) {
+            farm.change_stake(pool_id, staker, amount, true).unwrap();
+        }
+
+        #[ink::test]
+        fn add_pool_works() {
+            let mut farm = Farm::new(AccountId::from([0x9; 32]), 100);
+            assert_eq!(farm.add_pool(AccountId::from([0x7; 32]), 1), Ok(0));
+            assert_eq!(farm.add_pool(AccountId::from([0x8; 32]), 3), Ok(1));
+            assert_eq!(farm.pool_count(), 2);
+            assert_eq!(farm.total_alloc_point, 4);
+            farm.owner = AccountId::from([0x2; 32]);
+            assert_eq!(
+                farm.add_pool(AccountId::from([0x7; 32]), 1),
+                Err(Error::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn rewards_split_by_alloc_point_and_stake() {
+            let mut farm = Farm::new(AccountId::from([0x9; 32]), 100);
+            farm.add_pool(AccountId::from([0x7; 32]), 1).unwrap();
+            farm.add_pool(AccountId::from([0x8; 32]), 3).unwrap();
+            let alice = AccountId::from([0x1; 32]);
+            let bob = AccountId::from([0x2; 32]);
+            stake(&mut farm, 0, alice, 10);
+            stake(&mut farm, 1, alice, 10);
+            stake(&mut farm, 1, bob, 30);
+            advance_blocks(4);
+            assert_eq!(farm.pending_reward(0, alice), Ok(100));
+            assert_eq!(farm.pending_reward(1, alice), Ok(75));
+            assert_eq!(farm.pending_reward(1, bob), Ok(225));
+        }
+
+        #[ink::test]
+        fn new_stake_earns_from_now_on() {
+            let mut farm = Farm::new(AccountId::from([0x9; 32]), 100);
+            farm.add_pool(AccountId::from([0x7; 32]), 1).unwrap();
+            let alice = AccountId::from([0x1; 32]);
+            let bob = AccountId::from([0x2; 32]);
+            stake(&mut farm, 0, alice, 10);
+            advance_blocks(2);
+            farm.update_pool(0).unwrap();
+            stake(&mut farm, 0, bob, 10);
+            advance_blocks(2);
+            assert_eq!(farm.pending_reward(0, alice), Ok(300));
+            assert_eq!(farm.pending_reward(0, bob), Ok(100));
+        }
+
+        #[ink::test]
+        fn set_alloc_point_works() {
+            let mut farm = Farm::new(AccountId::from([0x9; 32]), 100);
+            farm.add_pool(AccountId::from([0x7; 32]), 1).unwrap();
+            assert_eq!(farm.set_alloc_point(0, 5), Ok(()));
+            assert_eq!(farm.pool(0).map(|pool| pool.alloc_point), Some(5));
+            assert_eq!(farm.total_alloc_point, 5);
+            assert_eq!(farm.set_alloc_point(1, 5), Err(Error::PoolNotFound));
+        }
+
+        #[ink::test]
+        fn withdraw_needs_stake() {
+            let mut farm = Farm::new(AccountId::from([0x9; 32]), 100);
+            farm.add_pool(AccountId::from([0x7; 32]), 1).unwrap();
+            assert_eq!(farm.withdraw(0, 1), Err(Error::InsufficientStake));
+            assert_eq!(farm.harvest(1), Err(Error::PoolNotFound));
+        }
+    }
+}
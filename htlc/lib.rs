@@ -0,0 +1,331 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Hashed-timelock contract for atomic swaps between two parties.
+///
+/// The sender locks `Erc20` tokens or native currency for a recipient under
+/// the SHA2-256 hash of a secret. Whoever knows the secret can pay the
+/// recipient out before the timelock expires; afterwards only the sender can
+/// take the funds back.
+///
+/// For a swap, Alice locks on one side with a secret only she knows and Bob
+/// locks on the other side under the same hash with a shorter timelock. Alice
+/// claims Bob's funds, which publishes the secret in the `Claimed` event, and
+/// Bob uses it to claim Alice's funds before her timelock runs out.
+#[ink::contract]
+mod htlc {
+    use erc20::Erc20Ref;
+    use ink::env::hash::Sha2x256;
+    use ink::storage::Mapping;
+
+    pub type Hashlock = [u8; 32];
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Status {
+        Locked,
+        Claimed,
+        Refunded,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Lock {
+        pub sender: AccountId,
+        pub recipient: AccountId,
+        /// The `Erc20` locked, or `None` for native currency.
+        pub token: Option<AccountId>,
+        pub amount: Balance,
+        /// The lock can be claimed before and refunded from this timestamp on.
+        pub timelock: Timestamp,
+        pub status: Status,
+    }
+
+    #[ink(storage)]
+    pub struct Htlc {
+        /// Locks by hashlock. Settled locks are kept so that a hashlock, whose
+        /// secret is public after a claim, can't be used again.
+        locks: Mapping<Hashlock, Lock>,
+    }
+
+    #[ink(event)]
+    pub struct Locked {
+        #[ink(topic)]
+        hashlock: Hashlock,
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        recipient: AccountId,
+        token: Option<AccountId>,
+        amount: Balance,
+        timelock: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Claimed {
+        #[ink(topic)]
+        hashlock: Hashlock,
+        preimage: [u8; 32],
+    }
+
+    #[ink(event)]
+    pub struct Refunded {
+        #[ink(topic)]
+        hashlock: Hashlock,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        HashlockUsed,
+        LockNotFound,
+        NotLocked,
+        /// The timelock is not in the future.
+        InvalidTimelock,
+        /// The value sent with a native lock differs from `amount`.
+        ValueMismatch,
+        Expired,
+        NotExpired,
+        NotSender,
+        NativeTransferFailed,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Htlc {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                locks: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn lock_of(&self, hashlock: Hashlock) -> Option<Lock> {
+            self.locks.get(hashlock)
+        }
+
+        /// Locks `amount` of `token` for `recipient` under `hashlock` until
+        /// `timelock`. With `token` set the caller must have approved this
+        /// contract for `amount`; with `None` it has to send `amount` as value.
+        #[ink(message, payable)]
+        pub fn lock(
+            &mut self,
+            token: Option<AccountId>,
+            amount: Balance,
+            hashlock: Hashlock,
+            timelock: Timestamp,
+            recipient: AccountId,
+        ) -> Result<()> {
+            if self.locks.contains(hashlock) {
+                return Err(Error::HashlockUsed);
+            }
+            if timelock <= self.env().block_timestamp() {
+                return Err(Error::InvalidTimelock);
+            }
+            let sender = self.env().caller();
+            match token {
+                Some(token) => {
+                    if self.env().transferred_value() != 0 {
+                        return Err(Error::ValueMismatch);
+                    }
+                    token_contract(token).transfer_from(sender, self.env().account_id(), amount)?
+                }
+                None => {
+                    if self.env().transferred_value() != amount {
+                        return Err(Error::ValueMismatch);
+                    }
+                }
+            }
+            self.locks.insert(
+                hashlock,
+                &Lock {
+                    sender,
+                    recipient,
+                    token,
+                    amount,
+                    timelock,
+                    status: Status::Locked,
+                },
+            );
+            ink::codegen::EmitEvent::<Htlc>::emit_event(
+                self.env(),
+                Locked {
+                    hashlock,
+                    sender,
+                    recipient,
+                    token,
+                    amount,
+                    timelock,
+                },
+            );
+            Ok(())
+        }
+
+        /// Pays the lock whose hashlock is the hash of `preimage` out to its
+        /// recipient. Anyone knowing the preimage may call this before the
+        /// timelock expires.
+        #[ink(message)]
+        pub fn claim(&mut self, preimage: [u8; 32]) -> Result<()> {
+            let hashlock = self.env().hash_bytes::<Sha2x256>(&preimage);
+            let lock = self.lock_of(hashlock).ok_or(Error::LockNotFound)?;
+            if self.env().block_timestamp() >= lock.timelock {
+                return Err(Error::Expired);
+            }
+            self.settle(hashlock, Status::Claimed)?;
+            self.pay_out(&lock, lock.recipient)?;
+            ink::codegen::EmitEvent::<Htlc>::emit_event(self.env(), Claimed { hashlock, preimage });
+            Ok(())
+        }
+
+        /// Returns the funds of an expired lock to its sender.
+        #[ink(message)]
+        pub fn refund(&mut self, hashlock: Hashlock) -> Result<()> {
+            let lock = self.lock_of(hashlock).ok_or(Error::LockNotFound)?;
+            if self.env().caller() != lock.sender {
+                return Err(Error::NotSender);
+            }
+            if self.env().block_timestamp() < lock.timelock {
+                return Err(Error::NotExpired);
+            }
+            self.settle(hashlock, Status::Refunded)?;
+            self.pay_out(&lock, lock.sender)?;
+            ink::codegen::EmitEvent::<Htlc>::emit_event(self.env(), Refunded { hashlock });
+            Ok(())
+        }
+
+        /// Moves a locked lock into its final `status`.
+        fn settle(&mut self, hashlock: Hashlock, status: Status) -> Result<()> {
+            let mut lock = self.locks.get(hashlock).ok_or(Error::LockNotFound)?;
+            if lock.status != Status::Locked {
+                return Err(Error::NotLocked);
+            }
+            lock.status = status;
+            self.locks.insert(hashlock, &lock);
+            Ok(())
+        }
+
+        fn pay_out(&mut self, lock: &Lock, to: AccountId) -> Result<()> {
+            match lock.token {
+                Some(token) => token_contract(token).transfer(to, lock.amount)?,
+                None => self
+                    .env()
+                    .transfer(to, lock.amount)
+                    .map_err(|_| Error::NativeTransferFailed)?,
+            }
+            Ok(())
+        }
+    }
+
+    fn token_contract(token: AccountId) -> Erc20Ref {
+        ink::env::call::FromAccountId::from_account_id(token)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const PREIMAGE: [u8; 32] = [0x42; 32];
+
+        fn hashlock() -> Hashlock {
+            let mut output = Hashlock::default();
+            ink::env::hash_bytes::<Sha2x256>(&PREIMAGE, &mut output);
+            output
+        }
+
+        fn now() -> Timestamp {
+            ink::env::block_timestamp::<ink::env::DefaultEnvironment>()
+        }
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        fn insert_native_lock(htlc: &mut Htlc, timelock: Timestamp) {
+            htlc.locks.insert(
+                hashlock(),
+                &Lock {
+                    sender: AccountId::from([0x1; 32]),
+                    recipient: AccountId::from([0x2; 32]),
+                    token: None,
+                    amount: 100,
+                    timelock,
+                    status: Status::Locked,
+                },
+            );
+        }
+
+        #[ink::test]
+        fn lock_native_checks_value() {
+            let mut htlc = Htlc::new();
+            let recipient = AccountId::from([0x2; 32]);
+            assert_eq!(
+                htlc.lock(None, 100, hashlock(), now() + 10, recipient),
+                Err(Error::ValueMismatch)
+            );
+            assert_eq!(
+                htlc.lock(None, 0, hashlock(), now(), recipient),
+                Err(Error::InvalidTimelock)
+            );
+            assert_eq!(
+                htlc.lock(None, 0, hashlock(), now() + 10, recipient),
+                Ok(())
+            );
+            assert_eq!(
+                htlc.lock_of(hashlock()).map(|lock| lock.status),
+                Some(Status::Locked)
+            );
+            assert_eq!(
+                htlc.lock(None, 0, hashlock(), now() + 10, recipient),
+                Err(Error::HashlockUsed)
+            );
+        }
+
+        #[ink::test]
+        fn claim_works() {
+            let mut htlc = Htlc::new();
+            insert_native_lock(&mut htlc, now() + 10);
+            assert_eq!(htlc.claim([0x0; 32]), Err(Error::LockNotFound));
+            assert_eq!(htlc.claim(PREIMAGE), Ok(()));
+            assert_eq!(
+                htlc.lock_of(hashlock()).map(|lock| lock.status),
+                Some(Status::Claimed)
+            );
+            assert_eq!(htlc.claim(PREIMAGE), Err(Error::NotLocked));
+        }
+
+        #[ink::test]
+        fn claim_failed_after_expiry() {
+            let mut htlc = Htlc::new();
+            insert_native_lock(&mut htlc, now());
+            assert_eq!(htlc.claim(PREIMAGE), Err(Error::Expired));
+        }
+
+        #[ink::test]
+        fn refund_works_after_expiry() {
+            let mut htlc = Htlc::new();
+            insert_native_lock(&mut htlc, now() + 1);
+            assert_eq!(htlc.refund(hashlock()), Err(Error::NotExpired));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            set_caller(AccountId::from([0x2; 32]));
+            assert_eq!(htlc.refund(hashlock()), Err(Error::NotSender));
+            set_caller(AccountId::from([0x1; 32]));
+            assert_eq!(htlc.refund(hashlock()), Ok(()));
+            assert_eq!(
+                htlc.lock_of(hashlock()).map(|lock| lock.status),
+                Some(Status::Refunded)
+            );
+        }
+    }
+}
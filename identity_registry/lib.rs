@@ -0,0 +1,282 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Verified accounts shared by every token that needs an allowlist, so KYC
+/// state lives in one place.
+///
+/// Registrars, appointed by the owner, attach claims to accounts: a topic,
+/// like "KYC done" or "accredited investor", valid until an expiry. An account
+/// is verified while it holds unexpired claims on all `required_topics`. The
+/// registry implements `psp22::TransferRules`, allowing transfers between
+/// verified accounts only, so a token can use it as its rules registry.
+#[ink::contract]
+mod identity_registry {
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    /// What a claim attests, numbered by convention between the registrars
+    /// and the tokens.
+    pub type ClaimTopic = u32;
+
+    #[ink(storage)]
+    pub struct IdentityRegistry {
+        owner: AccountId,
+        registrars: Mapping<AccountId, ()>,
+        /// Expiry of each claim, keyed by `(account, topic)`.
+        claims: Mapping<(AccountId, ClaimTopic), Timestamp>,
+        required_topics: Vec<ClaimTopic>,
+    }
+
+    #[ink(event)]
+    pub struct RegistrarUpdated {
+        #[ink(topic)]
+        registrar: AccountId,
+        active: bool,
+    }
+
+    #[ink(event)]
+    pub struct ClaimAdded {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        topic: ClaimTopic,
+        expires_at: Timestamp,
+        registrar: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ClaimRevoked {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        topic: ClaimTopic,
+        registrar: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RequiredTopicsUpdated {
+        topics: Vec<ClaimTopic>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        NotRegistrar,
+        ClaimNotFound,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl IdentityRegistry {
+        /// The caller becomes the owner and accounts are verified once they
+        /// hold claims on `required_topics`.
+        #[ink(constructor)]
+        pub fn new(required_topics: Vec<ClaimTopic>) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                registrars: Mapping::default(),
+                claims: Mapping::default(),
+                required_topics,
+            }
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn is_registrar(&self, account: AccountId) -> bool {
+            self.registrars.contains(account)
+        }
+
+        #[ink(message)]
+        pub fn set_registrar(&mut self, registrar: AccountId, active: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if active {
+                self.registrars.insert(registrar, &());
+            } else {
+                self.registrars.remove(registrar);
+            }
+            self.env()
+                .emit_event(RegistrarUpdated { registrar, active });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn required_topics(&self) -> Vec<ClaimTopic> {
+            self.required_topics.clone()
+        }
+
+        #[ink(message)]
+        pub fn set_required_topics(&mut self, topics: Vec<ClaimTopic>) -> Result<()> {
+            self.ensure_owner()?;
+            self.required_topics = topics.clone();
+            self.env().emit_event(RequiredTopicsUpdated { topics });
+            Ok(())
+        }
+
+        /// When `account`'s claim on `topic` expires, if it has one.
+        #[ink(message)]
+        pub fn claim_expiry(&self, account: AccountId, topic: ClaimTopic) -> Option<Timestamp> {
+            self.claims.get((account, topic))
+        }
+
+        /// Whether `account` holds an unexpired claim on `topic`.
+        #[ink(message)]
+        pub fn has_claim(&self, account: AccountId, topic: ClaimTopic) -> bool {
+            self.claim_expiry(account, topic)
+                .is_some_and(|expires_at| self.env().block_timestamp() < expires_at)
+        }
+
+        /// Whether `account` holds unexpired claims on all required topics.
+        #[ink(message)]
+        pub fn is_verified(&self, account: AccountId) -> bool {
+            self.required_topics
+                .iter()
+                .all(|&topic| self.has_claim(account, topic))
+        }
+
+        /// Attests `topic` for `account` until `expires_at`, replacing any
+        /// claim on it.
+        #[ink(message)]
+        pub fn add_claim(
+            &mut self,
+            account: AccountId,
+            topic: ClaimTopic,
+            expires_at: Timestamp,
+        ) -> Result<()> {
+            let registrar = self.ensure_registrar()?;
+            self.claims.insert((account, topic), &expires_at);
+            self.env().emit_event(ClaimAdded {
+                account,
+                topic,
+                expires_at,
+                registrar,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn revoke_claim(&mut self, account: AccountId, topic: ClaimTopic) -> Result<()> {
+            let registrar = self.ensure_registrar()?;
+            self.claims
+                .take((account, topic))
+                .ok_or(Error::ClaimNotFound)?;
+            self.env().emit_event(ClaimRevoked {
+                account,
+                topic,
+                registrar,
+            });
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        /// Returns the caller if it is a registrar.
+        fn ensure_registrar(&self) -> Result<AccountId> {
+            let caller = self.env().caller();
+            if !self.is_registrar(caller) {
+                return Err(Error::NotRegistrar);
+            }
+            Ok(caller)
+        }
+    }
+
+    impl psp22::TransferRules for IdentityRegistry {
+        /// Allows transfers between verified accounts.
+        #[ink(message)]
+        fn can_transfer(&self, from: AccountId, to: AccountId, _value: Balance) -> bool {
+            self.is_verified(from) && self.is_verified(to)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use psp22::TransferRules;
+
+        const KYC: ClaimTopic = 1;
+        const ACCREDITED: ClaimTopic = 2;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn registry() -> IdentityRegistry {
+            let mut registry = IdentityRegistry::new(vec![KYC, ACCREDITED]);
+            registry.set_registrar(accounts().alice, true).unwrap();
+            registry
+        }
+
+        #[ink::test]
+        fn verified_accounts_need_all_topics() {
+            let accounts = accounts();
+            let mut registry = registry();
+            assert_eq!(
+                registry.add_claim(accounts.bob, KYC, Timestamp::MAX),
+                Ok(())
+            );
+            assert!(!registry.is_verified(accounts.bob));
+            assert_eq!(
+                registry.add_claim(accounts.bob, ACCREDITED, Timestamp::MAX),
+                Ok(())
+            );
+            assert!(registry.is_verified(accounts.bob));
+            assert_eq!(registry.revoke_claim(accounts.bob, KYC), Ok(()));
+            assert!(!registry.is_verified(accounts.bob));
+            assert_eq!(
+                registry.revoke_claim(accounts.bob, KYC),
+                Err(Error::ClaimNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn claims_expire() {
+            let accounts = accounts();
+            let mut registry = registry();
+            let now = ink::env::block_timestamp::<ink::env::DefaultEnvironment>();
+            registry.add_claim(accounts.bob, KYC, now + 1).unwrap();
+            assert!(registry.has_claim(accounts.bob, KYC));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert!(!registry.has_claim(accounts.bob, KYC));
+            assert_eq!(registry.claim_expiry(accounts.bob, KYC), Some(now + 1));
+        }
+
+        #[ink::test]
+        fn transfers_need_verified_parties() {
+            let accounts = accounts();
+            let mut registry = registry();
+            registry.set_required_topics(vec![KYC]).unwrap();
+            registry
+                .add_claim(accounts.bob, KYC, Timestamp::MAX)
+                .unwrap();
+            assert!(!registry.can_transfer(accounts.alice, accounts.bob, 1));
+            registry
+                .add_claim(accounts.alice, KYC, Timestamp::MAX)
+                .unwrap();
+            assert!(registry.can_transfer(accounts.alice, accounts.bob, 1));
+        }
+
+        #[ink::test]
+        fn only_registrars_add_claims() {
+            let accounts = accounts();
+            let mut registry = registry();
+            assert_eq!(registry.set_registrar(accounts.alice, false), Ok(()));
+            assert_eq!(
+                registry.add_claim(accounts.bob, KYC, Timestamp::MAX),
+                Err(Error::NotRegistrar)
+            );
+            registry.owner = accounts.bob;
+            assert_eq!(
+                registry.set_registrar(accounts.alice, true),
+                Err(Error::NotOwner)
+            );
+        }
+    }
+}
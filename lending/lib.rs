@@ -0,0 +1,636 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Borrowing native currency against the course `Erc20`, in the style of
+/// Compound.
+///
+/// Borrowers deposit tokens as collateral and borrow native currency up to
+/// `collateral_factor_bps` of the collateral's value. Both prices come from
+/// the runtime's oracle pallet through `chain_extensions::oracle`, in USD, and
+/// both assets are assumed to have the same number of decimals. The owner
+/// supplies the native currency that is lent out and collects it back with
+/// the interest paid.
+///
+/// Debt grows by `rate_per_block` every block: a global borrow index
+/// compounds whenever someone touches the pool, and each loan remembers the
+/// index it was last updated at. Once a loan exceeds
+/// `liquidation_threshold_bps` of its collateral's value, anyone can repay up
+/// to half of it and receive collateral worth what they repaid plus
+/// `liquidation_bonus_bps`, credited as collateral in the pool.
+#[ink::contract(env = chain_extensions::oracle::OracleEnvironment)]
+mod lending {
+    use chain_extensions::oracle::{OracleError, PairId, Price};
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    /// Interest rates and the borrow index are fractions of this.
+    pub const RATE_PRECISION: Balance = 1_000_000_000_000;
+
+    /// How much of a loan one liquidation can repay, in basis points.
+    pub const CLOSE_FACTOR_BPS: u16 = 5_000;
+
+    /// Limits of the loans, in basis points of the collateral's value.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct RiskParams {
+        /// Borrowers can borrow up to this.
+        pub collateral_factor_bps: u16,
+        /// Loans above this can be liquidated.
+        pub liquidation_threshold_bps: u16,
+        /// Extra collateral liquidators get on top of what they repaid.
+        pub liquidation_bonus_bps: u16,
+    }
+
+    impl RiskParams {
+        fn is_valid(&self) -> bool {
+            self.collateral_factor_bps <= self.liquidation_threshold_bps
+                && self.liquidation_threshold_bps <= 10_000
+        }
+    }
+
+    /// What a borrower owed at `index`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Loan {
+        pub principal: Balance,
+        pub index: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct Lending {
+        token: AccountId,
+        owner: AccountId,
+        /// Oracle feeds of the token and of the native currency in USD.
+        token_pair: PairId,
+        native_pair: PairId,
+        max_price_age: Timestamp,
+        risk: RiskParams,
+        rate_per_block: Balance,
+        borrow_index: Balance,
+        last_accrual_block: BlockNumber,
+        total_borrows: Balance,
+        collateral: Mapping<AccountId, Balance>,
+        loans: Mapping<AccountId, Loan>,
+    }
+
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Borrowed {
+        #[ink(topic)]
+        borrower: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Repaid {
+        #[ink(topic)]
+        borrower: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Liquidated {
+        #[ink(topic)]
+        borrower: AccountId,
+        #[ink(topic)]
+        liquidator: AccountId,
+        repaid: Balance,
+        seized: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        OracleError(OracleError),
+        StalePrice,
+        ZeroAmount,
+        /// The loan would exceed what the collateral allows.
+        InsufficientCollateral,
+        InsufficientLiquidity,
+        NoDebt,
+        /// The loan is within the liquidation threshold.
+        Healthy,
+        /// A liquidation can repay at most `CLOSE_FACTOR_BPS` of the loan.
+        RepayTooLarge,
+        Overflow,
+        NativeTransferFailed,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<OracleError> for Error {
+        fn from(error: OracleError) -> Self {
+            Error::OracleError(error)
+        }
+    }
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Lending {
+        /// The caller becomes the owner. `rate_per_block` is a fraction of
+        /// `RATE_PRECISION`.
+        #[ink(constructor)]
+        pub fn new(
+            token: AccountId,
+            token_pair: PairId,
+            native_pair: PairId,
+            max_price_age: Timestamp,
+            risk: RiskParams,
+            rate_per_block: Balance,
+        ) -> Self {
+            assert!(risk.is_valid(), "invalid risk parameters");
+            Self {
+                token,
+                owner: Self::env().caller(),
+                token_pair,
+                native_pair,
+                max_price_age,
+                risk,
+                rate_per_block,
+                borrow_index: RATE_PRECISION,
+                last_accrual_block: Self::env().block_number(),
+                total_borrows: 0,
+                collateral: Mapping::default(),
+                loans: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn risk_params(&self) -> RiskParams {
+            self.risk
+        }
+
+        #[ink(message)]
+        pub fn collateral_of(&self, account: AccountId) -> Balance {
+            self.collateral.get(account).unwrap_or(0)
+        }
+
+        /// What `account` owes now, interest included.
+        #[ink(message)]
+        pub fn debt_of(&self, account: AccountId) -> Result<Balance> {
+            let index = self.current_index()?;
+            self.debt_at(account, index)
+        }
+
+        /// What all borrowers owe now, interest included.
+        #[ink(message)]
+        pub fn total_borrows(&self) -> Result<Balance> {
+            mul_div(self.total_borrows, self.current_index()?, self.borrow_index)
+        }
+
+        /// How much `account` could borrow in total with its collateral.
+        #[ink(message)]
+        pub fn borrow_limit(&self, account: AccountId) -> Result<Balance> {
+            let value = self.collateral_value(self.collateral_of(account))?;
+            Ok(bps_of(value, self.risk.collateral_factor_bps))
+        }
+
+        /// The liquidation threshold of `account`'s collateral over its debt,
+        /// in basis points, or `None` without debt. Below 10 000 the loan can
+        /// be liquidated.
+        #[ink(message)]
+        pub fn health_factor(&self, account: AccountId) -> Result<Option<Balance>> {
+            let debt = self.debt_of(account)?;
+            if debt == 0 {
+                return Ok(None);
+            }
+            let threshold = self.liquidation_value(self.collateral_of(account))?;
+            mul_div(threshold, 10_000, debt).map(Some)
+        }
+
+        /// Adds native currency for borrowers to borrow.
+        #[ink(message, payable)]
+        pub fn supply(&mut self) -> Result<()> {
+            self.ensure_owner()
+        }
+
+        /// Takes back `amount` of the native currency not lent out.
+        #[ink(message)]
+        pub fn withdraw_liquidity(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.pay(self.owner, amount)
+        }
+
+        /// Moves `amount` of the caller's tokens, which must be approved, into
+        /// the pool as collateral.
+        #[ink(message)]
+        pub fn deposit(&mut self, amount: Balance) -> Result<()> {
+            let account = self.env().caller();
+            if amount == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            self.token_contract()
+                .transfer_from(account, self.env().account_id(), amount)?;
+            let collateral = self
+                .collateral_of(account)
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+            self.collateral.insert(account, &collateral);
+            ink::codegen::EmitEvent::<Lending>::emit_event(
+                self.env(),
+                Deposited { account, amount },
+            );
+            Ok(())
+        }
+
+        /// Takes `amount` of the caller's collateral out, as long as what
+        /// stays covers the caller's loan.
+        #[ink(message)]
+        pub fn withdraw(&mut self, amount: Balance) -> Result<()> {
+            let account = self.env().caller();
+            self.accrue()?;
+            let collateral = self
+                .collateral_of(account)
+                .checked_sub(amount)
+                .ok_or(Error::InsufficientCollateral)?;
+            self.ensure_within_limit(collateral, self.debt_at(account, self.borrow_index)?)?;
+            self.collateral.insert(account, &collateral);
+            self.token_contract().transfer(account, amount)?;
+            ink::codegen::EmitEvent::<Lending>::emit_event(
+                self.env(),
+                Withdrawn { account, amount },
+            );
+            Ok(())
+        }
+
+        /// Lends the caller `amount` of native currency against its
+        /// collateral.
+        #[ink(message)]
+        pub fn borrow(&mut self, amount: Balance) -> Result<()> {
+            let borrower = self.env().caller();
+            if amount == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            self.accrue()?;
+            let debt = self
+                .debt_at(borrower, self.borrow_index)?
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+            self.ensure_within_limit(self.collateral_of(borrower), debt)?;
+            self.set_debt(borrower, debt);
+            self.total_borrows = self
+                .total_borrows
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+            self.pay(borrower, amount)?;
+            ink::codegen::EmitEvent::<Lending>::emit_event(
+                self.env(),
+                Borrowed { borrower, amount },
+            );
+            Ok(())
+        }
+
+        /// Repays the caller's loan with the native currency sent along and
+        /// refunds what exceeds the loan.
+        #[ink(message, payable)]
+        pub fn repay(&mut self) -> Result<()> {
+            let borrower = self.env().caller();
+            let paid = self.env().transferred_value();
+            self.accrue()?;
+            let debt = self.debt_at(borrower, self.borrow_index)?;
+            if debt == 0 {
+                return Err(Error::NoDebt);
+            }
+            let amount = paid.min(debt);
+            self.set_debt(borrower, debt - amount);
+            self.total_borrows = self.total_borrows.saturating_sub(amount);
+            if paid > amount {
+                self.pay(borrower, paid - amount)?;
+            }
+            ink::codegen::EmitEvent::<Lending>::emit_event(self.env(), Repaid { borrower, amount });
+            Ok(())
+        }
+
+        /// Repays part of `borrower`'s loan with the native currency sent
+        /// along and credits the caller with the collateral it buys, bonus
+        /// included. Returns the collateral seized.
+        #[ink(message, payable)]
+        pub fn liquidate(&mut self, borrower: AccountId) -> Result<Balance> {
+            let liquidator = self.env().caller();
+            let repaid = self.env().transferred_value();
+            if repaid == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            self.accrue()?;
+            let debt = self.debt_at(borrower, self.borrow_index)?;
+            if debt == 0 {
+                return Err(Error::NoDebt);
+            }
+            let collateral = self.collateral_of(borrower);
+            if debt <= self.liquidation_value(collateral)? {
+                return Err(Error::Healthy);
+            }
+            if repaid > bps_of(debt, CLOSE_FACTOR_BPS) {
+                return Err(Error::RepayTooLarge);
+            }
+            let (token_price, native_price) = self.prices()?;
+            let seized = mul_div(repaid, native_price, token_price)?;
+            let seized = mul_div(
+                seized,
+                10_000 + Balance::from(self.risk.liquidation_bonus_bps),
+                10_000,
+            )?
+            .min(collateral);
+            self.set_debt(borrower, debt - repaid);
+            self.total_borrows = self.total_borrows.saturating_sub(repaid);
+            self.collateral.insert(borrower, &(collateral - seized));
+            let liquidator_collateral = self
+                .collateral_of(liquidator)
+                .checked_add(seized)
+                .ok_or(Error::Overflow)?;
+            self.collateral.insert(liquidator, &liquidator_collateral);
+            ink::codegen::EmitEvent::<Lending>::emit_event(
+                self.env(),
+                Liquidated {
+                    borrower,
+                    liquidator,
+                    repaid,
+                    seized,
+                },
+            );
+            Ok(seized)
+        }
+
+        /// The borrow index as of the current block.
+        fn current_index(&self) -> Result<Balance> {
+            let blocks = Balance::from(self.env().block_number() - self.last_accrual_block);
+            let interest = self
+                .borrow_index
+                .checked_mul(self.rate_per_block)
+                .and_then(|interest| interest.checked_mul(blocks))
+                .ok_or(Error::Overflow)?
+                / RATE_PRECISION;
+            self.borrow_index
+                .checked_add(interest)
+                .ok_or(Error::Overflow)
+        }
+
+        /// Compounds the interest of all loans up to the current block.
+        fn accrue(&mut self) -> Result<()> {
+            let index = self.current_index()?;
+            self.total_borrows = mul_div(self.total_borrows, index, self.borrow_index)?;
+            self.borrow_index = index;
+            self.last_accrual_block = self.env().block_number();
+            Ok(())
+        }
+
+        fn debt_at(&self, account: AccountId, index: Balance) -> Result<Balance> {
+            match self.loans.get(account) {
+                Some(loan) => mul_div(loan.principal, index, loan.index),
+                None => Ok(0),
+            }
+        }
+
+        /// Records `debt` as owed at the current borrow index.
+        fn set_debt(&mut self, account: AccountId, debt: Balance) {
+            if debt == 0 {
+                self.loans.remove(account);
+            } else {
+                self.loans.insert(
+                    account,
+                    &Loan {
+                        principal: debt,
+                        index: self.borrow_index,
+                    },
+                );
+            }
+        }
+
+        fn ensure_within_limit(&self, collateral: Balance, debt: Balance) -> Result<()> {
+            if debt == 0 {
+                return Ok(());
+            }
+            let value = self.collateral_value(collateral)?;
+            if debt > bps_of(value, self.risk.collateral_factor_bps) {
+                return Err(Error::InsufficientCollateral);
+            }
+            Ok(())
+        }
+
+        /// The debt `collateral` can carry before it can be liquidated.
+        fn liquidation_value(&self, collateral: Balance) -> Result<Balance> {
+            let value = self.collateral_value(collateral)?;
+            Ok(bps_of(value, self.risk.liquidation_threshold_bps))
+        }
+
+        /// What `amount` tokens are worth in native currency.
+        fn collateral_value(&self, amount: Balance) -> Result<Balance> {
+            let (token_price, native_price) = self.prices()?;
+            mul_div(amount, token_price, native_price)
+        }
+
+        /// The USD prices of the token and of the native currency.
+        fn prices(&self) -> Result<(Price, Price)> {
+            Ok((self.price(self.token_pair)?, self.price(self.native_pair)?))
+        }
+
+        fn price(&self, pair: PairId) -> Result<Price> {
+            let (price, reported_at) = self.env().extension().latest_price(pair)?;
+            if self.env().block_timestamp().saturating_sub(reported_at) > self.max_price_age {
+                return Err(Error::StalePrice);
+            }
+            Ok(price)
+        }
+
+        /// Sends `amount` of native currency, keeping what was not lent out
+        /// above the existential deposit.
+        fn pay(&self, to: AccountId, amount: Balance) -> Result<()> {
+            let available = self
+                .env()
+                .balance()
+                .saturating_sub(self.env().minimum_balance());
+            if amount > available {
+                return Err(Error::InsufficientLiquidity);
+            }
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| Error::NativeTransferFailed)
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    /// `bps` basis points of `amount`, rounded down.
+    fn bps_of(amount: Balance, bps: u16) -> Balance {
+        // Split so that large amounts can't overflow.
+        amount / 10_000 * Balance::from(bps) + amount % 10_000 * Balance::from(bps) / 10_000
+    }
+
+    /// `a * b / d` rounded down.
+    fn mul_div(a: Balance, b: Balance, d: Balance) -> Result<Balance> {
+        a.checked_mul(b)
+            .and_then(|product| product.checked_div(d))
+            .ok_or(Error::Overflow)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use scale::{Decode, Encode};
+
+        const TOKEN_USD: PairId = *b"CRS/USD\0";
+        const DOT_USD: PairId = *b"DOT/USD\0";
+
+        /// Reports `token_price` for `TOKEN_USD` and `native_price` for
+        /// `DOT_USD`, both as of `reported_at`.
+        struct MockOracle {
+            token_price: Price,
+            native_price: Price,
+            reported_at: Timestamp,
+        }
+
+        impl ink::env::test::ChainExtension for MockOracle {
+            fn func_id(&self) -> u32 {
+                0x1101
+            }
+
+            fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+                // The off-chain engine hands over the input encoded once more.
+                let input = Vec::<u8>::decode(&mut &input[..]).unwrap();
+                let price = match PairId::decode(&mut &input[..]).unwrap() {
+                    TOKEN_USD => self.token_price,
+                    DOT_USD => self.native_price,
+                    _ => return 1,
+                };
+                (price, self.reported_at).encode_to(output);
+                0
+            }
+        }
+
+        fn set_prices(token_price: Price, native_price: Price) {
+            ink::env::test::register_chain_extension(MockOracle {
+                token_price,
+                native_price,
+                reported_at: ink::env::block_timestamp::<ink::env::DefaultEnvironment>(),
+            });
+        }
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        /// A pool lending 50% of the collateral's value at 1% per block,
+        /// holding 1000 in native currency on top of the existential deposit.
+        fn lending() -> Lending {
+            let contract = AccountId::from([0xff; 32]);
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract);
+            let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract,
+                minimum_balance + 1000,
+            );
+            // The token is worth 2 USD and the native currency 5 USD.
+            set_prices(200_000_000, 500_000_000);
+            Lending::new(
+                AccountId::from([0x9; 32]),
+                TOKEN_USD,
+                DOT_USD,
+                60_000,
+                RiskParams {
+                    collateral_factor_bps: 5_000,
+                    liquidation_threshold_bps: 8_000,
+                    liquidation_bonus_bps: 500,
+                },
+                RATE_PRECISION / 100,
+            )
+        }
+
+        #[ink::test]
+        fn borrowing_is_limited_by_collateral() {
+            let accounts = accounts();
+            let mut lending = lending();
+            // 1000 tokens are worth 400 in native currency.
+            lending.collateral.insert(accounts.alice, &1000);
+            assert_eq!(lending.borrow_limit(accounts.alice), Ok(200));
+            assert_eq!(lending.borrow(201), Err(Error::InsufficientCollateral));
+            assert_eq!(lending.borrow(200), Ok(()));
+            assert_eq!(lending.debt_of(accounts.alice), Ok(200));
+            assert_eq!(lending.health_factor(accounts.alice), Ok(Some(16_000)));
+            set_caller(accounts.bob);
+            assert_eq!(lending.borrow(1), Err(Error::InsufficientCollateral));
+        }
+
+        #[ink::test]
+        fn interest_accrues_per_block() {
+            let accounts = accounts();
+            let mut lending = lending();
+            lending.collateral.insert(accounts.alice, &1000);
+            lending.borrow(100).unwrap();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(lending.debt_of(accounts.alice), Ok(102));
+            assert_eq!(lending.total_borrows(), Ok(102));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(150);
+            assert_eq!(lending.repay(), Ok(()));
+            assert_eq!(lending.debt_of(accounts.alice), Ok(0));
+            assert_eq!(lending.repay(), Err(Error::NoDebt));
+        }
+
+        #[ink::test]
+        fn withdrawals_keep_the_loan_covered() {
+            let accounts = accounts();
+            let mut lending = lending();
+            lending.collateral.insert(accounts.alice, &1000);
+            lending.borrow(100).unwrap();
+            assert_eq!(lending.withdraw(501), Err(Error::InsufficientCollateral));
+            assert_eq!(lending.withdraw(1001), Err(Error::InsufficientCollateral));
+        }
+
+        #[ink::test]
+        fn unhealthy_loans_are_liquidated() {
+            let accounts = accounts();
+            let mut lending = lending();
+            lending.collateral.insert(accounts.alice, &1000);
+            lending.borrow(200).unwrap();
+            set_caller(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(100);
+            assert_eq!(lending.liquidate(accounts.alice), Err(Error::Healthy));
+            // At 1 USD per token the collateral is worth 200 and carries 160.
+            set_prices(100_000_000, 500_000_000);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(101);
+            assert_eq!(lending.liquidate(accounts.alice), Err(Error::RepayTooLarge));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(100);
+            assert_eq!(lending.liquidate(accounts.alice), Ok(525));
+            assert_eq!(lending.debt_of(accounts.alice), Ok(100));
+            assert_eq!(lending.collateral_of(accounts.alice), 475);
+            assert_eq!(lending.collateral_of(accounts.bob), 525);
+        }
+    }
+}
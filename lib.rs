@@ -1,198 +1,5500 @@
-#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
 
-use ink_lang as ink;
+pub use self::erc20::{Erc20, Erc20Ref, Error};
+
+pub mod amount;
 
 #[ink::contract]
-mod erc20 {
+pub mod erc20 {
+
+    use ink::prelude::{format, string::String, vec::Vec};
+    use ink::storage::{Lazy, Mapping};
+    use scale::Encode;
+
+    /// Storage layout version written by this code. `migrate` brings storage
+    /// written by older code up to it. Version 4 is the first ink! 4 layout:
+    /// storage of the ink! 3 versions before it can't be read at all.
+    /// Version 5 keeps balance histories in ring buffers and tracks what
+    /// each native distribution paid out.
+    pub const STORAGE_VERSION: u32 = 5;
+
+    /// How many balance changes `balance_history` keeps per account. Older
+    /// ones are overwritten, so storage per account stays bounded.
+    pub const MAX_BALANCE_CHECKPOINTS: u32 = 64;
+
+    /// How many blocks after its snapshot a native distribution can be
+    /// claimed, about 30 days. The owner can reclaim what is left after that.
+    pub const NATIVE_DIVIDEND_CLAIM_BLOCKS: BlockNumber = 432_000;
+
+    /// How many holders one call of `migrate` converts.
+    pub const MIGRATION_BATCH_SIZE: u32 = 50;
+
+    /// How many spenders a page of `active_approvals` lists.
+    pub const APPROVALS_PAGE_SIZE: u32 = 20;
+
+    /// Savings rates and the savings index are fractions of this.
+    pub const SAVINGS_RATE_PRECISION: Balance = 1_000_000_000_000;
+
+    /// How many of the latest balance changes `get_receipt` can look up.
+    /// Older receipts are overwritten.
+    pub const MAX_RECEIPTS: TxId = 256;
+
+    /// How long a new fee policy waits after `queue_fee_policy` before it can
+    /// be applied, so holders can react to it: two days, in milliseconds.
+    pub const FEE_POLICY_DELAY: Timestamp = 2 * 24 * 60 * 60 * 1000;
+
+    /// Digits of a raw amount that are below one whole token.
+    pub const DECIMALS: u8 = 12;
+
+    /// Capabilities that can be paused on their own, as bits of the mask given
+    /// to `set_paused` and `is_paused`.
+    pub const PAUSE_TRANSFERS: u32 = 1 << 0;
+    pub const PAUSE_MINTS: u32 = 1 << 1;
+    pub const PAUSE_BURNS: u32 = 1 << 2;
+    /// The receiver callbacks of `transfer_and_call` and `safe_transfer`.
+    pub const PAUSE_CALLBACKS: u32 = 1 << 3;
+    pub const PAUSE_FLASH_LOANS: u32 = 1 << 4;
+    /// Pauses everything, including capabilities added later.
+    pub const PAUSE_ALL: u32 = u32::MAX;
+
+    /// What `owner` allowed `spender` to move with `transfer_from`.
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Allowance {
+        pub amount: Balance,
+        /// The grant can no longer be used after this timestamp.
+        pub expires_at: Option<Timestamp>,
+    }
+
+    /// Everything stored per account since storage version 4, packed into one
+    /// storage cell so a transfer reads and writes a single cell per party.
+    /// Its encoding is fixed: adding a field would make every existing entry
+    /// unreadable, so per-account state added later gets a `Mapping` of its
+    /// own.
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct AccountData {
+        pub balance: Balance,
+        /// Position in the holder list while the account has a balance.
+        pub holder_index: Option<u32>,
+        pub inheritance: Option<Inheritance>,
+    }
+
+    /// Lets `heir` take the whole balance of an account that did not send a
+    /// `heartbeat` for `inactivity_period`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Inheritance {
+        pub heir: AccountId,
+        pub inactivity_period: Timestamp,
+        pub last_heartbeat: Timestamp,
+    }
+
+    /// Everything `spender` was granted by an owner, as listed by
+    /// `active_approvals`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct SpenderGrants {
+        pub spender: AccountId,
+        pub allowance: Option<Allowance>,
+        pub recurring: Option<RecurringAllowance>,
+        pub operator: bool,
+    }
+
+    /// What `sponsor_account` gives a new account on top of the native
+    /// currency sent along, and how often one sponsor may use it.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Sponsorship {
+        /// Tokens moved from the sponsor to every account it sponsors.
+        pub starter_amount: Balance,
+        pub max_accounts_per_sponsor: u32,
+    }
+
+    /// Tokens the contract holds for savers, and the interest they earn.
+    ///
+    /// Savings are stored scaled down by `index`, which grows by
+    /// `rate_per_block` every block, so a saver's balance is their scaled
+    /// balance times the current index. Interest is paid from `reserve` and
+    /// stops growing the index once the reserve runs dry.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct SavingsPool {
+        /// A fraction of `SAVINGS_RATE_PRECISION`.
+        pub rate_per_block: Balance,
+        pub index: Balance,
+        pub last_accrual_block: BlockNumber,
+        /// The sum of all savers' scaled balances.
+        pub scaled_total: Balance,
+        /// Tokens set aside to pay interest from.
+        pub reserve: Balance,
+        /// Everything deposited or funded and not withdrawn yet, reserve
+        /// included.
+        pub held: Balance,
+    }
+
+    /// Rounds every transfer an account sends itself up to a multiple of
+    /// `unit` and gives the difference to `charity`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct RoundUp {
+        pub charity: AccountId,
+        pub unit: Balance,
+        pub enabled: bool,
+        /// Everything the account gave through round-ups so far.
+        pub donated: Balance,
+    }
+
+    /// Which code is live, so operators can check an upgrade took.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ContractInfo {
+        /// The crate version the code was built from.
+        pub semver: String,
+        /// How many times the code was upgraded plus one, as in `CodeUpgraded`.
+        pub version: u32,
+        /// The layout storage is in; below `code_storage_version` until
+        /// `migrate` runs.
+        pub storage_version: u32,
+        /// The layout this code reads and writes.
+        pub code_storage_version: u32,
+        /// `ERC20_BUILD_HASH` at build time, usually the git commit, if set.
+        pub build_hash: Option<String>,
+    }
+
+    /// `value` as of the end of `block`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Checkpoint {
+        pub block: BlockNumber,
+        pub value: Balance,
+    }
+
+    /// Where an account's balance checkpoints sit among its
+    /// `MAX_BALANCE_CHECKPOINTS` slots.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct CheckpointRing {
+        /// Slot the next checkpoint is written to.
+        pub head: u32,
+        /// Number of checkpoints kept. Slots fill up from 0, so until the
+        /// ring wraps around these are the first `len` slots.
+        pub len: u32,
+    }
+
+    /// A pull allowance that refills every `period_length` milliseconds.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct RecurringAllowance {
+        pub amount_per_period: Balance,
+        pub period_length: Timestamp,
+        /// Start of the period `spent_in_period` belongs to.
+        pub period_start: Timestamp,
+        pub spent_in_period: Balance,
+    }
+
+    /// Inflation minted to `distributor` over time by `mint_scheduled`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct EmissionSchedule {
+        pub distributor: AccountId,
+        /// Emission accrues from this timestamp on.
+        pub start: Timestamp,
+        pub era_length: Timestamp,
+        /// Emitted over each of the first `halving_interval` eras, then half
+        /// of it over each of the next ones, and so on.
+        pub emission_per_era: Balance,
+        /// Zero never halves the emission.
+        pub halving_interval: u32,
+    }
+
+    impl EmissionSchedule {
+        /// How much the schedule emits from `start` until `now`. Emission
+        /// accrues evenly within an era.
+        pub fn emitted_by(&self, now: Timestamp) -> Balance {
+            if now <= self.start {
+                return 0;
+            }
+            let elapsed = now - self.start;
+            let mut eras = elapsed / self.era_length;
+            let mut per_era = self.emission_per_era;
+            let mut emitted: Balance = 0;
+            let interval = self.halving_interval as Timestamp;
+            while interval > 0 && eras >= interval {
+                emitted = emitted.saturating_add(per_era.saturating_mul(interval as Balance));
+                eras -= interval;
+                per_era /= 2;
+                if per_era == 0 {
+                    return emitted;
+                }
+            }
+            let into_era = (elapsed % self.era_length) as Balance;
+            let era_length = self.era_length as Balance;
+            let partial =
+                per_era / era_length * into_era + per_era % era_length * into_era / era_length;
+            emitted
+                .saturating_add(per_era.saturating_mul(eras as Balance))
+                .saturating_add(partial)
+        }
+    }
+
+    /// A pause by the guardian, which lifts itself at `expires_at` unless the
+    /// owner ratifies it first.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct GuardianPause {
+        /// `PAUSE_*` bits paused by the guardian.
+        pub features: u32,
+        pub expires_at: BlockNumber,
+    }
+
+    /// Accounts that can together hand the owner role to a new key, for when
+    /// the owner key is lost.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct RecoveryConfig {
+        pub guardians: Vec<AccountId>,
+        /// Votes needed for the same new owner.
+        pub threshold: u32,
+        /// Time the current owner has to veto a recovery.
+        pub delay: Timestamp,
+    }
+
+    /// A recovery the guardians agreed on, waiting out the veto delay.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Recovery {
+        pub new_owner: AccountId,
+        pub executable_at: Timestamp,
+    }
+
+    /// The fee taken from every transfer and where it goes. The shares are in
+    /// basis points of the fee and have to add up to 10 000.
+    ///
+    /// `transfer`, `transfer_from`, their variants and executed scheduled
+    /// transfers pay it, unless the sender or the recipient is fee exempt.
+    /// Inheritance claims, sponsorship starter amounts and round-up donations
+    /// never do. A contract taking tokens in with `transfer_from` receives the
+    /// amount less the fee, so it has to be exempted or to book what arrived.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct FeePolicy {
+        /// Fee in basis points of the amount transferred.
+        pub fee_bps: u16,
+        pub treasury: AccountId,
+        pub treasury_bps: u16,
+        /// The staking rewards contract.
+        pub rewards: AccountId,
+        pub rewards_bps: u16,
+        pub burn_bps: u16,
+    }
+
+    impl FeePolicy {
+        fn is_valid(&self) -> bool {
+            self.fee_bps <= 10_000
+                && u32::from(self.treasury_bps)
+                    + u32::from(self.rewards_bps)
+                    + u32::from(self.burn_bps)
+                    == 10_000
+        }
+    }
+
+    /// A fee policy change waiting out `FEE_POLICY_DELAY`. A `None` policy
+    /// removes the fee.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct PendingFeePolicy {
+        pub policy: Option<FeePolicy>,
+        pub applicable_at: Timestamp,
+    }
+
+    /// A change of an account's fee exemption waiting out `FEE_POLICY_DELAY`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct PendingFeeExemption {
+        pub exempt: bool,
+        pub applicable_at: Timestamp,
+    }
+
+    pub type ScheduledTransferId = u64;
+
+    /// Tokens held by the contract until they can be sent on to `to`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ScheduledTransfer {
+        pub from: AccountId,
+        pub to: AccountId,
+        pub value: Balance,
+        pub execute_after: Timestamp,
+    }
+
+    pub type DistributionId = u32;
+
+    /// Numbers every balance change, starting from 1.
+    pub type TxId = u64;
+
+    /// A balance change as `Transfer` reported it, kept so it can be quoted
+    /// without an indexer.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Receipt {
+        pub tx_id: TxId,
+        pub from: Option<AccountId>,
+        pub to: Option<AccountId>,
+        pub value: Balance,
+        pub block: BlockNumber,
+    }
+
+    /// Native currency paid out to the holders as of `snapshot_block`, pro
+    /// rata to their balances then, for `NATIVE_DIVIDEND_CLAIM_BLOCKS`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct NativeDistribution {
+        pub snapshot_block: BlockNumber,
+        pub amount: Balance,
+        /// `total_supply` at the snapshot.
+        pub total_supply: Balance,
+    }
+
+    /// A message of this contract for `multicall`: its selector and its
+    /// SCALE encoded arguments.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Call {
+        pub selector: [u8; 4],
+        pub input: Vec<u8>,
+    }
+
+    /// Everything the owner can set, as returned by `config`. Minter
+    /// allowances are per minter and queried with `minter_allowance`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Config {
+        pub owner: AccountId,
+        /// The `PAUSE_*` bits paused by the owner or a running guardian pause.
+        pub paused: u32,
+        pub cap: Option<Balance>,
+        pub fee_policy: Option<FeePolicy>,
+        pub pending_fee_policy: Option<PendingFeePolicy>,
+        pub flash_fee_bps: u16,
+        pub flash_fee_receiver: Option<AccountId>,
+        pub rules_registry: Option<AccountId>,
+        pub transfer_to_self_allowed: bool,
+        pub guardian: Option<AccountId>,
+        pub guardian_pause_blocks: BlockNumber,
+        pub emission_schedule: Option<EmissionSchedule>,
+        pub sponsorship: Option<Sponsorship>,
+        pub savings_rate_per_block: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct Erc20 {
+        owner: AccountId,
+        /// Bumped on every `upgrade_code`.
+        version: u32,
+        total_supply: Balance,
+        accounts: Mapping<AccountId, AccountData>,
+        allowance: Mapping<(AccountId, AccountId), Allowance>,
+        storage_version: u32,
+        /// Every account with a nonzero balance, in no particular order,
+        /// by position.
+        holders: Mapping<u32, AccountId>,
+        holder_count: u32,
+        operator_approvals: Mapping<(AccountId, AccountId), ()>,
+        recurring_allowances: Mapping<(AccountId, AccountId), RecurringAllowance>,
+        /// Whether the contract may hold its own tokens, e.g. as a treasury.
+        transfer_to_self_allowed: bool,
+        /// Flash loan fee in basis points of the loan.
+        flash_fee_bps: u16,
+        /// Gets the flash loan fees. They are burned while this is `None`.
+        flash_fee_receiver: Option<AccountId>,
+        /// How much each minter, like a bridge, may still `mint` without being
+        /// the owner.
+        minter_allowances: Mapping<AccountId, Balance>,
+        /// Upper bound of `total_supply`, if any.
+        cap: Option<Balance>,
+        emission_schedule: Option<EmissionSchedule>,
+        /// Minted so far under the current `emission_schedule`.
+        emitted: Balance,
+        /// `PAUSE_*` bits of the capabilities paused by the owner.
+        paused: u32,
+        /// May pause without the owner, for fast incident response.
+        guardian: Option<AccountId>,
+        /// How many blocks a guardian pause lasts unless ratified.
+        guardian_pause_blocks: BlockNumber,
+        guardian_pause: Option<GuardianPause>,
+        recovery_config: Option<RecoveryConfig>,
+        /// The new owner each recovery guardian voted for.
+        recovery_votes: Mapping<AccountId, AccountId>,
+        pending_recovery: Option<Recovery>,
+        scheduled_transfers: Mapping<ScheduledTransferId, ScheduledTransfer>,
+        next_scheduled_transfer_id: ScheduledTransferId,
+        /// Part of the contract's own balance held for scheduled transfers.
+        escrowed: Balance,
+        /// Compliance contract implementing `psp22::TransferRules` that has to
+        /// approve every transfer, if any.
+        rules_registry: Option<AccountId>,
+        /// Where wallets find the token's description and icon; empty if unset.
+        token_uri: String,
+        /// Hash of the logo, so wallets can check the image they fetched.
+        logo_hash: Option<Hash>,
+        /// `total_supply` after every block that changed it, oldest first.
+        supply_checkpoints: Mapping<u32, Checkpoint>,
+        supply_checkpoint_count: u32,
+        /// Balance histories written before version 5, oldest first. `migrate`
+        /// and the next balance change of an account move them into
+        /// `balance_checkpoint_slots`.
+        balance_checkpoints: Mapping<AccountId, Vec<Checkpoint>>,
+        native_distributions: Mapping<DistributionId, NativeDistribution>,
+        native_distribution_count: u32,
+        native_dividends_claimed: Mapping<(DistributionId, AccountId), ()>,
+        /// Native balance distributed and not claimed yet.
+        native_dividends_unclaimed: Balance,
+        fee_policy: Option<FeePolicy>,
+        pending_fee_policy: Option<PendingFeePolicy>,
+        token_name: Option<String>,
+        token_symbol: Option<String>,
+        /// Who sponsored each account brought in through `sponsor_account`.
+        sponsors: Mapping<AccountId, AccountId>,
+        /// The latest `MAX_RECEIPTS` receipts, by `tx_id % MAX_RECEIPTS`.
+        receipts: Mapping<TxId, Receipt>,
+        round_ups: Mapping<AccountId, RoundUp>,
+        /// What other accounts' round-ups sent to each charity.
+        round_ups_received: Mapping<AccountId, Balance>,
+        /// Each owner's spenders with any kind of grant, by index, so that
+        /// `revoke_all_allowances` can find them.
+        spenders: Mapping<(AccountId, u32), AccountId>,
+        spender_index: Mapping<(AccountId, AccountId), u32>,
+        /// How many spenders hold a grant from each owner.
+        spender_counts: Mapping<AccountId, u32>,
+        last_tx_id: Lazy<TxId>,
+        sponsorship: Lazy<Option<Sponsorship>>,
+        /// How many new accounts each sponsor brought in.
+        sponsored_counts: Mapping<AccountId, u32>,
+        /// As of the last change, see `savings_pool` for the current state.
+        savings: Lazy<SavingsPool>,
+        /// Each saver's savings divided by the savings index at the time, so
+        /// that interest accrues without touching the saver.
+        savings_scaled: Mapping<AccountId, Balance>,
+        /// The latest balances of each account, in the slots described by
+        /// its `balance_checkpoint_rings` entry.
+        balance_checkpoint_slots: Mapping<(AccountId, u32), Checkpoint>,
+        balance_checkpoint_rings: Mapping<AccountId, CheckpointRing>,
+        /// Holders `migrate` has yet to convert to version 5, counting down
+        /// from the end of the holder list; unset before it started.
+        migration_cursor: Lazy<u32>,
+        /// Accounts whose transfers pay no fee, like escrow contracts.
+        fee_exempt: Mapping<AccountId, ()>,
+        pending_fee_exemptions: Mapping<AccountId, PendingFeeExemption>,
+        /// What each native distribution paid out so far, reclaimed
+        /// leftovers included.
+        native_dividends_paid: Mapping<DistributionId, Balance>,
+        /// The first native distribution whose payouts `native_dividends_paid`
+        /// tracks from the start, so that its leftover can be reclaimed.
+        reclaimable_distributions_from: Lazy<DistributionId>,
+    }
+
+    /// Emitted for every balance change: mints have no `from`, burns no `to`.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+        tx_id: TxId,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+        expires_at: Option<Timestamp>,
+    }
+
+    #[ink(event)]
+    pub struct RecurringApproval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        amount_per_period: Balance,
+        period_length: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct ApprovalForAll {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        operator: AccountId,
+        approved: bool,
+    }
+
+    /// Emitted by `revoke_all_allowances` instead of an event per grant.
+    #[ink(event)]
+    pub struct AllowancesRevoked {
+        #[ink(topic)]
+        owner: AccountId,
+        spender_count: u32,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct CodeUpgraded {
+        #[ink(topic)]
+        code_hash: Hash,
+        version: u32,
+    }
+
+    /// Emitted with the new values whenever `token_uri` or `logo_hash` changes.
+    #[ink(event)]
+    pub struct MetadataUpdated {
+        token_uri: String,
+        logo_hash: Option<Hash>,
+    }
+
+    #[ink(event)]
+    pub struct TokenSwept {
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct NativeDistributed {
+        #[ink(topic)]
+        id: DistributionId,
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+        snapshot_block: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct NativeDividendClaimed {
+        #[ink(topic)]
+        id: DistributionId,
+        #[ink(topic)]
+        holder: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct NativeDividendsReclaimed {
+        #[ink(topic)]
+        id: DistributionId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct NativeSwept {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct FlashLoan {
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        initiator: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
+    #[ink(event)]
+    pub struct FeePolicyQueued {
+        policy: Option<FeePolicy>,
+        applicable_at: Timestamp,
+    }
+
+    // The events of admin settings carry the previous value next to the new
+    // one, so the history of the configuration can be rebuilt from them.
+
+    #[ink(event)]
+    pub struct FeePolicyUpdated {
+        previous_policy: Option<FeePolicy>,
+        policy: Option<FeePolicy>,
+    }
+
+    #[ink(event)]
+    pub struct FeeExemptionQueued {
+        #[ink(topic)]
+        account: AccountId,
+        exempt: bool,
+        applicable_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct FeeExemptionUpdated {
+        #[ink(topic)]
+        account: AccountId,
+        previous_exempt: bool,
+        exempt: bool,
+    }
+
+    #[ink(event)]
+    pub struct FlashFeeUpdated {
+        previous_fee_bps: u16,
+        fee_bps: u16,
+        previous_receiver: Option<AccountId>,
+        receiver: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct MinterAllowanceUpdated {
+        #[ink(topic)]
+        minter: AccountId,
+        previous_amount: Balance,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct CapUpdated {
+        previous_cap: Option<Balance>,
+        cap: Option<Balance>,
+    }
+
+    #[ink(event)]
+    pub struct EmissionScheduleUpdated {
+        previous_schedule: Option<EmissionSchedule>,
+        schedule: Option<EmissionSchedule>,
+    }
+
+    #[ink(event)]
+    pub struct PauseUpdated {
+        /// All paused capabilities before and after the update.
+        previous_paused: u32,
+        paused: u32,
+    }
+
+    #[ink(event)]
+    pub struct RulesRegistryUpdated {
+        previous_registry: Option<AccountId>,
+        registry: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct TransferToSelfAllowedUpdated {
+        previous_allowed: bool,
+        allowed: bool,
+    }
+
+    #[ink(event)]
+    pub struct GuardianUpdated {
+        previous_guardian: Option<AccountId>,
+        guardian: Option<AccountId>,
+        previous_pause_blocks: BlockNumber,
+        pause_blocks: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct SponsorshipUpdated {
+        previous_sponsorship: Option<Sponsorship>,
+        sponsorship: Option<Sponsorship>,
+    }
+
+    #[ink(event)]
+    pub struct GuardianPaused {
+        #[ink(topic)]
+        guardian: AccountId,
+        features: u32,
+        expires_at: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct GuardianPauseRatified {
+        features: u32,
+    }
+
+    #[ink(event)]
+    pub struct RecoveryConfigured {
+        guardians: Vec<AccountId>,
+        threshold: u32,
+        delay: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct RecoveryVoted {
+        #[ink(topic)]
+        guardian: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RecoveryStarted {
+        #[ink(topic)]
+        new_owner: AccountId,
+        executable_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct RecoveryVetoed {
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct TransferScheduled {
+        #[ink(topic)]
+        id: ScheduledTransferId,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+        execute_after: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct ScheduledTransferExecuted {
+        #[ink(topic)]
+        id: ScheduledTransferId,
+    }
+
+    #[ink(event)]
+    pub struct ScheduledTransferCancelled {
+        #[ink(topic)]
+        id: ScheduledTransferId,
+    }
+
+    #[ink(event)]
+    pub struct HeirSet {
+        #[ink(topic)]
+        holder: AccountId,
+        #[ink(topic)]
+        heir: AccountId,
+        inactivity_period: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Heartbeat {
+        #[ink(topic)]
+        holder: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct InheritanceCancelled {
+        #[ink(topic)]
+        holder: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct InheritanceClaimed {
+        #[ink(topic)]
+        holder: AccountId,
+        #[ink(topic)]
+        heir: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct RoundUpSet {
+        #[ink(topic)]
+        holder: AccountId,
+        #[ink(topic)]
+        charity: AccountId,
+        unit: Balance,
+    }
+
+    #[ink(event)]
+    pub struct RoundUpToggled {
+        #[ink(topic)]
+        holder: AccountId,
+        enabled: bool,
+    }
+
+    #[ink(event)]
+    pub struct RoundedUp {
+        #[ink(topic)]
+        holder: AccountId,
+        #[ink(topic)]
+        charity: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AccountSponsored {
+        #[ink(topic)]
+        sponsor: AccountId,
+        #[ink(topic)]
+        account: AccountId,
+        native: Balance,
+        tokens: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SavingsDeposited {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SavingsWithdrawn {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SavingsReserveFunded {
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SavingsRateSet {
+        previous_rate_per_block: Balance,
+        rate_per_block: Balance,
+    }
+
+    #[ink(event)]
+    pub struct StorageMigrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    /// Errors returned by the token. The codec index of a variant is its error
+    /// code: never change or reuse one once released.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        #[codec(index = 0)]
+        InsufficientBalance {
+            required: Balance,
+            available: Balance,
+        },
+        #[codec(index = 1)]
+        InsufficientAllowance {
+            required: Balance,
+            available: Balance,
+        },
+        #[codec(index = 2)]
+        AllowanceExpired,
+        #[codec(index = 3)]
+        InvalidPeriod,
+        #[codec(index = 4)]
+        NotOwner,
+        #[codec(index = 5)]
+        UpgradeFailed,
+        #[codec(index = 6)]
+        AlreadyMigrated,
+        #[codec(index = 7)]
+        SweepFailed,
+        #[codec(index = 8)]
+        ReentrancyDetected,
+        #[codec(index = 9)]
+        TransferRejected,
+        #[codec(index = 10)]
+        UnsafeRecipient,
+        /// A balance or the total supply would exceed `Balance::MAX`.
+        #[codec(index = 11)]
+        Overflow,
+        /// The operation is paused.
+        #[codec(index = 12)]
+        Paused,
+        /// The all-zeros account can't be given ownership or an allowance.
+        #[codec(index = 13)]
+        ZeroAddressNotAllowed,
+        /// Tokens sent to the recipient could never be moved again.
+        #[codec(index = 14)]
+        InvalidRecipient,
+        /// The flash loan receiver returned `false` from `on_flash_loan`.
+        #[codec(index = 15)]
+        FlashLoanRejected,
+        /// A fee above 100%.
+        #[codec(index = 16)]
+        InvalidFee,
+        #[codec(index = 17)]
+        NotMinter,
+        /// Minting would take `total_supply` above the cap.
+        #[codec(index = 18)]
+        CapExceeded,
+        /// The minter's remaining allowance is too small.
+        #[codec(index = 19)]
+        MinterAllowanceExceeded,
+        #[codec(index = 20)]
+        NoEmissionSchedule,
+        #[codec(index = 21)]
+        NotGuardian,
+        /// There is no running guardian pause to ratify.
+        #[codec(index = 22)]
+        NoGuardianPause,
+        #[codec(index = 23)]
+        NotRecoveryGuardian,
+        /// The threshold is zero or above the number of guardians.
+        #[codec(index = 24)]
+        InvalidThreshold,
+        /// Another recovery is already waiting out its delay.
+        #[codec(index = 25)]
+        RecoveryPending,
+        #[codec(index = 26)]
+        NoRecovery,
+        /// The veto delay of the recovery is not over yet.
+        #[codec(index = 27)]
+        RecoveryDelayNotOver,
+        #[codec(index = 28)]
+        ScheduledTransferNotFound,
+        /// The scheduled transfer can't be executed yet.
+        #[codec(index = 29)]
+        ScheduledTransferNotDue,
+        /// Only the sender may cancel a scheduled transfer.
+        #[codec(index = 30)]
+        NotTransferSender,
+        #[codec(index = 31)]
+        NoInheritance,
+        #[codec(index = 32)]
+        NotHeir,
+        /// The holder sent a heartbeat within its inactivity period.
+        #[codec(index = 33)]
+        HolderStillActive,
+        /// The rules registry rejected the transfer.
+        #[codec(index = 34)]
+        TransferNotAllowed,
+        /// A fractional part of a whole token or more.
+        #[codec(index = 35)]
+        InvalidAmount,
+        /// `multicall` does not support the selector.
+        #[codec(index = 36)]
+        UnknownSelector,
+        /// The arguments of a call don't decode.
+        #[codec(index = 37)]
+        InvalidCallInput,
+        /// No value was sent, or there were no tokens at the snapshot.
+        #[codec(index = 38)]
+        NothingToDistribute,
+        #[codec(index = 39)]
+        DistributionNotFound,
+        #[codec(index = 40)]
+        AlreadyClaimed,
+        /// The balance at the snapshot fell out of the bounded balance history.
+        #[codec(index = 41)]
+        SnapshotUnavailable,
+        #[codec(index = 42)]
+        NativeTransferFailed,
+        #[codec(index = 43)]
+        NoPendingFeePolicy,
+        /// The pending fee policy can't be applied yet.
+        #[codec(index = 44)]
+        FeePolicyNotDue,
+        #[codec(index = 45)]
+        NoRoundUp,
+        #[codec(index = 46)]
+        SponsorshipDisabled,
+        /// The account was sponsored before or already holds tokens.
+        #[codec(index = 47)]
+        AccountNotNew,
+        /// The sponsor used up its `max_accounts_per_sponsor`.
+        #[codec(index = 48)]
+        SponsorLimitReached,
+        #[codec(index = 49)]
+        NoPendingFeeExemption,
+        /// The pending fee exemption change can't be applied yet.
+        #[codec(index = 50)]
+        FeeExemptionNotDue,
+        /// The claim period of the native distribution is over.
+        #[codec(index = 51)]
+        ClaimPeriodOver,
+        /// The claim period of the native distribution is still running.
+        #[codec(index = 52)]
+        ClaimPeriodNotOver,
+        /// Nothing of the native distribution is left to reclaim, or it
+        /// predates tracking what was claimed.
+        #[codec(index = 53)]
+        NothingToReclaim,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Lets contracts that speak PSP22 handle errors of this token without
+    /// knowing its own error type.
+    impl From<Error> for psp22::PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::InsufficientBalance { .. } => psp22::PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance { .. } | Error::AllowanceExpired => {
+                    psp22::PSP22Error::InsufficientAllowance
+                }
+                Error::ZeroAddressNotAllowed => psp22::PSP22Error::ZeroRecipientAddress,
+                Error::TransferRejected | Error::UnsafeRecipient => {
+                    psp22::PSP22Error::SafeTransferCheckFailed(format!("{:?}", error))
+                }
+                error => psp22::PSP22Error::Custom(format!("{:?}", error)),
+            }
+        }
+    }
+
+    /// The last block in which `distribution` can be claimed.
+    fn native_dividend_deadline(distribution: &NativeDistribution) -> BlockNumber {
+        distribution
+            .snapshot_block
+            .saturating_add(NATIVE_DIVIDEND_CLAIM_BLOCKS)
+    }
+
+    fn zero_address() -> AccountId {
+        AccountId::from([0x0; 32])
+    }
+
+    impl From<reentrancy_guard::ReentrancyDetected> for Error {
+        fn from(_: reentrancy_guard::ReentrancyDetected) -> Self {
+            Error::ReentrancyDetected
+        }
+    }
+    // Every message has a fixed selector so that renaming a message can't change
+    // it. Never change or reuse a selector once released.
+    impl Erc20 {
+        /// Constructor that initializes the `bool` value to the given `init_value`.
+        #[ink(constructor)]
+        pub fn new(total_supply: Balance) -> Self {
+            Self::new_token(None, None, total_supply, Self::env().caller())
+        }
+
+        /// Creates a token named `name`, with `owner` owning it and holding
+        /// the whole initial supply, so factories can deploy tokens for
+        /// others.
+        #[ink(constructor)]
+        pub fn new_token(
+            name: Option<String>,
+            symbol: Option<String>,
+            total_supply: Balance,
+            owner: AccountId,
+        ) -> Self {
+            let mut erc20 = Self {
+                owner,
+                version: 1,
+                total_supply,
+                accounts: Mapping::default(),
+                allowance: Mapping::default(),
+                storage_version: STORAGE_VERSION,
+                holders: Mapping::default(),
+                holder_count: 0,
+                operator_approvals: Mapping::default(),
+                recurring_allowances: Mapping::default(),
+                transfer_to_self_allowed: false,
+                flash_fee_bps: 0,
+                flash_fee_receiver: None,
+                minter_allowances: Mapping::default(),
+                cap: None,
+                emission_schedule: None,
+                emitted: 0,
+                paused: 0,
+                guardian: None,
+                guardian_pause_blocks: 0,
+                guardian_pause: None,
+                recovery_config: None,
+                recovery_votes: Mapping::default(),
+                pending_recovery: None,
+                scheduled_transfers: Mapping::default(),
+                next_scheduled_transfer_id: 0,
+                escrowed: 0,
+                rules_registry: None,
+                token_uri: String::new(),
+                logo_hash: None,
+                supply_checkpoints: Mapping::default(),
+                supply_checkpoint_count: 0,
+                native_distributions: Mapping::default(),
+                native_distribution_count: 0,
+                native_dividends_claimed: Mapping::default(),
+                native_dividends_unclaimed: 0,
+                fee_policy: None,
+                pending_fee_policy: None,
+                balance_checkpoints: Mapping::default(),
+                token_name: name,
+                token_symbol: symbol,
+                sponsors: Mapping::default(),
+                receipts: Mapping::default(),
+                round_ups: Mapping::default(),
+                round_ups_received: Mapping::default(),
+                spenders: Mapping::default(),
+                spender_index: Mapping::default(),
+                spender_counts: Mapping::default(),
+                last_tx_id: Lazy::default(),
+                sponsorship: Lazy::default(),
+                sponsored_counts: Mapping::default(),
+                savings: Lazy::default(),
+                savings_scaled: Mapping::default(),
+                balance_checkpoint_slots: Mapping::default(),
+                balance_checkpoint_rings: Mapping::default(),
+                migration_cursor: Lazy::default(),
+                fee_exempt: Mapping::default(),
+                pending_fee_exemptions: Mapping::default(),
+                native_dividends_paid: Mapping::default(),
+                reclaimable_distributions_from: Lazy::default(),
+            };
+            erc20.set_balance(owner, total_supply);
+            erc20.checkpoint_total_supply();
+            erc20
+        }
+
+        /// Constructor that initializes the `bool` value to `false`.
+        ///
+        /// Constructors can delegate to other constructors.
+        #[ink(constructor)]
+        #[allow(clippy::should_implement_trait)]
+        pub fn default() -> Self {
+            Self::new(Default::default())
+        }
+
+        #[ink(message, selector = 0xFEAEA4FA)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// The whole current configuration in one call.
+        #[ink(message, selector = 0x70714744)]
+        pub fn config(&self) -> Config {
+            Config {
+                owner: self.owner,
+                paused: self.paused_features(),
+                cap: self.cap,
+                fee_policy: self.fee_policy.clone(),
+                pending_fee_policy: self.pending_fee_policy.clone(),
+                flash_fee_bps: self.flash_fee_bps,
+                flash_fee_receiver: self.flash_fee_receiver,
+                rules_registry: self.rules_registry,
+                transfer_to_self_allowed: self.transfer_to_self_allowed,
+                guardian: self.guardian,
+                guardian_pause_blocks: self.guardian_pause_blocks,
+                emission_schedule: self.emission_schedule.clone(),
+                sponsorship: self.sponsorship(),
+                savings_rate_per_block: self.savings_pool().rate_per_block,
+            }
+        }
+
+        /// Hands the owner role, and with it the right to `issue`, to `new_owner`.
+        ///
+        /// The new owner may be a contract, e.g. a multisig wallet.
+        #[ink(message, selector = 0x107E33EA)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.set_owner(new_owner)
+        }
+
+        fn set_owner(&mut self, new_owner: AccountId) -> Result<()> {
+            if new_owner == zero_address() {
+                return Err(Error::ZeroAddressNotAllowed);
+            }
+            let previous_owner = self.owner;
+            self.owner = new_owner;
+            self.env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xEC6D41E1)]
+        pub fn version(&self) -> u32 {
+            self.version
+        }
+
+        /// Replaces the code of this contract with the code at `code_hash`, keeping
+        /// its storage and address.
+        ///
+        /// The new code reads the existing storage as-is. The plain fields of
+        /// `Erc20` are stored together in one cell and the values of a mapping
+        /// in one cell per key, so none of them may change their encoding, not
+        /// even by appending a field. New state goes into new `Mapping` or
+        /// `Lazy` fields, which live under keys of their own; anything else
+        /// needs a `migrate` step. Checking that is the upgrader's
+        /// responsibility; nothing here can detect an incompatible layout.
+        /// Only the owner can upgrade.
+        #[ink(message, selector = 0x52084EC9)]
+        pub fn upgrade_code(&mut self, code_hash: [u8; 32]) -> Result<()> {
+            self.ensure_owner()?;
+            ink::env::set_code_hash(&code_hash).map_err(|_| Error::UpgradeFailed)?;
+            self.version += 1;
+            self.env().emit_event(CodeUpgraded {
+                code_hash: Hash::from(code_hash),
+                version: self.version,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x3B47039B)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        /// Describes the running code. After a `CodeUpgraded` event, this
+        /// shows whether the new code is live and its storage migrated.
+        #[ink(message, selector = 0x9DA4FD46)]
+        pub fn contract_info(&self) -> ContractInfo {
+            ContractInfo {
+                semver: String::from(env!("CARGO_PKG_VERSION")),
+                version: self.version,
+                storage_version: self.storage_version,
+                code_storage_version: STORAGE_VERSION,
+                build_hash: option_env!("ERC20_BUILD_HASH").map(String::from),
+            }
+        }
+
+        /// Rewrites storage left behind by older code into the layout of
+        /// `STORAGE_VERSION`. Run it by the owner right after `upgrade_code`.
+        /// Each call converts up to `MIGRATION_BATCH_SIZE` holders; call it
+        /// until `storage_version` reaches `STORAGE_VERSION`.
+        #[ink(message, selector = 0x060D3F50)]
+        pub fn migrate(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            let from_version = self.storage_version;
+            if from_version >= STORAGE_VERSION {
+                return Err(Error::AlreadyMigrated);
+            }
+            // Storage of versions before 4 was written by ink! 3 code and
+            // can't be migrated from here; that includes the version 2 step
+            // moving balances out of the eager `HashMap` into `Mapping` keys,
+            // which ink! 4 made obsolete. Version 5 moved balance histories
+            // into ring buffers. Holders are converted from the end of the
+            // list, so removals, which move the last holder forward, never
+            // move an unconverted one past the cursor. Anyone else is
+            // converted by their next balance change.
+            let remaining = self
+                .migration_cursor
+                .get()
+                .unwrap_or(self.holder_count)
+                .min(self.holder_count);
+            let next = remaining.saturating_sub(MIGRATION_BATCH_SIZE);
+            for index in next..remaining {
+                if let Some(holder) = self.holders.get(index) {
+                    self.balance_checkpoint_ring(holder);
+                }
+            }
+            self.migration_cursor.set(&next);
+            if next > 0 {
+                return Ok(());
+            }
+            // Claims made by older code weren't tracked, so what is left of
+            // the distributions before now can't be told apart.
+            self.reclaimable_distributions_from
+                .set(&self.native_distribution_count);
+            self.storage_version = STORAGE_VERSION;
+            self.env().emit_event(StorageMigrated {
+                from_version,
+                to_version: self.storage_version,
+            });
+            Ok(())
+        }
+
+        /// Removes the contract for good and sends its remaining native balance,
+        /// including the freed storage deposit, to `beneficiary`.
+        ///
+        /// All balances, allowances and the code instance are gone afterwards,
+        /// and tokens held by anyone become unrecoverable. There is no undo.
+        #[ink(message, selector = 0x71C7BD62)]
+        pub fn kill(&mut self, beneficiary: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.env().terminate_contract(beneficiary)
+        }
+
+        /// Sends the contract's whole balance of `token` to `to`. Recovers any
+        /// PSP22 token, including this one, that was sent to the token contract
+        /// by mistake. Tokens held for scheduled transfers and savings stay.
+        #[ink(message, selector = 0xDC19AE90)]
+        pub fn sweep_token(&mut self, token: AccountId, to: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            reentrancy_guard::non_reentrant(|| {
+                let contract = self.env().account_id();
+                let amount = if token == contract {
+                    let amount =
+                        self.balance_of(contract) - self.escrowed - self.savings_pool().held;
+                    self.transfer_helper(contract, to, amount)?;
+                    amount
+                } else {
+                    let amount =
+                        psp22::balance_of(token, contract).map_err(|_| Error::SweepFailed)?;
+                    psp22::transfer(token, to, amount).map_err(|_| Error::SweepFailed)?;
+                    amount
+                };
+                self.env().emit_event(TokenSwept { token, to, amount });
+                Ok(())
+            })
+        }
+
+        /// Sends the native balance above the existential deposit to `to`,
+        /// except for unclaimed native dividends.
+        #[ink(message, selector = 0xC8545064)]
+        pub fn sweep_native(&mut self, to: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            let amount = self
+                .env()
+                .balance()
+                .saturating_sub(self.env().minimum_balance())
+                .saturating_sub(self.native_dividends_unclaimed);
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| Error::SweepFailed)?;
+            self.env().emit_event(NativeSwept { to, amount });
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x2E213324)]
+        pub fn transfer_to_self_allowed(&self) -> bool {
+            self.transfer_to_self_allowed
+        }
+
+        /// Lets the contract's own account receive tokens, for when the owner
+        /// can move them out again, e.g. with `sweep_token`.
+        #[ink(message, selector = 0x758C23EC)]
+        pub fn set_transfer_to_self_allowed(&mut self, allowed: bool) -> Result<()> {
+            self.ensure_owner()?;
+            let previous_allowed = self.transfer_to_self_allowed;
+            self.transfer_to_self_allowed = allowed;
+            self.env().emit_event(TransferToSelfAllowedUpdated {
+                previous_allowed,
+                allowed,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x05732BD7)]
+        pub fn rules_registry(&self) -> Option<AccountId> {
+            self.rules_registry
+        }
+
+        /// Makes every transfer ask `registry` through `psp22::TransferRules`
+        /// first, or with `None` stops doing so. Mints and burns are not
+        /// checked, nor are moves into or out of the contract's own balance:
+        /// a scheduled transfer is checked between its sender and recipient
+        /// instead.
+        #[ink(message, selector = 0xFBA41D69)]
+        pub fn set_rules_registry(&mut self, registry: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            let previous_registry = self.rules_registry;
+            self.rules_registry = registry;
+            self.env().emit_event(RulesRegistryUpdated {
+                previous_registry,
+                registry,
+            });
+            Ok(())
+        }
+
+        /// Asks the rules registry, if one is set, whether the transfer may
+        /// happen.
+        fn ensure_transfer_allowed(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            match self.rules_registry {
+                Some(registry) if !psp22::can_transfer(registry, from, to, value) => {
+                    Err(Error::TransferNotAllowed)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        /// Rejects the all-zeros account and, unless allowed, the contract
+        /// itself, as nobody holds a key for them.
+        fn ensure_valid_recipient(&self, to: AccountId) -> Result<()> {
+            if to == zero_address()
+                || (to == self.env().account_id() && !self.transfer_to_self_allowed)
+            {
+                return Err(Error::InvalidRecipient);
+            }
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x5B64E66A)]
+        pub fn token_uri(&self) -> String {
+            self.token_uri.clone()
+        }
+
+        #[ink(message, selector = 0xA7E8B6ED)]
+        pub fn set_token_uri(&mut self, uri: String) -> Result<()> {
+            self.ensure_owner()?;
+            self.token_uri = uri;
+            self.emit_metadata_updated();
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xCB2C83DB)]
+        pub fn logo_hash(&self) -> Option<Hash> {
+            self.logo_hash
+        }
+
+        #[ink(message, selector = 0x9F98005D)]
+        pub fn set_logo_hash(&mut self, hash: Hash) -> Result<()> {
+            self.ensure_owner()?;
+            self.logo_hash = Some(hash);
+            self.emit_metadata_updated();
+            Ok(())
+        }
+
+        fn emit_metadata_updated(&self) {
+            self.env().emit_event(MetadataUpdated {
+                token_uri: self.token_uri.clone(),
+                logo_hash: self.logo_hash,
+            });
+        }
+
+        /// PSP22Metadata::token_name
+        #[ink(message, selector = 0x3D261BD4)]
+        pub fn token_name(&self) -> Option<String> {
+            self.token_name.clone()
+        }
+
+        /// PSP22Metadata::token_symbol
+        #[ink(message, selector = 0x34205BE5)]
+        pub fn token_symbol(&self) -> Option<String> {
+            self.token_symbol.clone()
+        }
+
+        #[ink(message, selector = 0x81C09D87)]
+        pub fn decimals(&self) -> u8 {
+            DECIMALS
+        }
+
+        /// Splits `raw` into whole tokens and the raw units left over.
+        #[ink(message, selector = 0x59123F07)]
+        pub fn to_display_amount(&self, raw: Balance) -> (Balance, Balance) {
+            crate::amount::split_amount(raw, DECIMALS)
+        }
+
+        /// The raw amount of `display` whole tokens plus `frac` raw units.
+        #[ink(message, selector = 0x8E2C3AFA)]
+        #[allow(clippy::wrong_self_convention)]
+        pub fn from_display_amount(&self, display: Balance, frac: Balance) -> Result<Balance> {
+            if crate::amount::unit(DECIMALS).is_some_and(|unit| frac >= unit) {
+                return Err(Error::InvalidAmount);
+            }
+            crate::amount::join_amount(display, frac, DECIMALS).ok_or(Error::Overflow)
+        }
+
+        #[ink(message, selector = 0xDB6375A8)]
+        pub fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        /// `total_supply` as of the end of `block`, or so far if `block` is the
+        /// current one.
+        #[ink(message, selector = 0x3727369D)]
+        pub fn total_supply_at(&self, block: BlockNumber) -> Balance {
+            // Binary search for the last checkpoint at or before `block`.
+            let (mut low, mut high) = (0, self.supply_checkpoint_count);
+            while low < high {
+                let middle = low + (high - low) / 2;
+                match self.supply_checkpoints.get(middle) {
+                    Some(checkpoint) if checkpoint.block <= block => low = middle + 1,
+                    _ => high = middle,
+                }
+            }
+            low.checked_sub(1)
+                .and_then(|index| self.supply_checkpoints.get(index))
+                .map_or(0, |checkpoint| checkpoint.value)
+        }
+
+        /// `account`'s balance as of the end of `block`, or so far if `block`
+        /// is the current one. `None` if the balance history no longer goes
+        /// back that far.
+        #[ink(message, selector = 0x5A2F8344)]
+        pub fn balance_of_at(&self, account: AccountId, block: BlockNumber) -> Option<Balance> {
+            self.balance_at(account, block)
+        }
+
+        /// Records the current `total_supply` for `total_supply_at`, replacing
+        /// the checkpoint of the current block if there is one.
+        fn checkpoint_total_supply(&mut self) {
+            let checkpoint = Checkpoint {
+                block: self.env().block_number(),
+                value: self.total_supply,
+            };
+            let last = self.supply_checkpoint_count.checked_sub(1);
+            let index = match last.and_then(|last| self.supply_checkpoints.get(last)) {
+                Some(last) if last.block == checkpoint.block => self.supply_checkpoint_count - 1,
+                _ => {
+                    self.supply_checkpoint_count += 1;
+                    self.supply_checkpoint_count - 1
+                }
+            };
+            self.supply_checkpoints.insert(index, &checkpoint);
+        }
+
+        #[ink(message, selector = 0x0F755A56)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.accounts.get(owner).map_or(0, |data| data.balance)
+        }
+
+        /// The amount `spender` may still move for `owner`; 0 once the grant expired.
+        #[ink(message, selector = 0x6A00165E)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            match self.allowance.get((owner, spender)) {
+                Some(grant) if !self.is_expired(&grant) => grant.amount,
+                _ => 0,
+            }
+        }
+
+        #[ink(message, selector = 0x91C01671)]
+        pub fn allowance_expiry(&self, owner: AccountId, spender: AccountId) -> Option<Timestamp> {
+            self.allowance
+                .get((owner, spender))
+                .and_then(|grant| grant.expires_at)
+        }
+
+        fn is_expired(&self, grant: &Allowance) -> bool {
+            matches!(grant.expires_at, Some(expires_at) if self.env().block_timestamp() > expires_at)
+        }
+
+        #[ink(message, selector = 0x84A15DA1)]
+        pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            let who = Self::env().caller();
+            self.transfer_helper(who, to, value)
+        }
+
+        /// Deprecated misspelling of `transfer`, kept so callers using its
+        /// selector keep working.
+        #[ink(message, selector = 0x86D06EB0)]
+        pub fn transer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.transfer(to, value)
+        }
+
+        /// Transfers `value` to `to` and then calls `on_token_received` on it, so a
+        /// contract can react to a deposit in the same transaction. Fails with
+        /// `TransferRejected`, reverting the transfer, if `to` does not accept.
+        #[ink(message, selector = 0xACD10E50)]
+        pub fn transfer_and_call(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            let from = self.env().caller();
+            self.ensure_not_paused(PAUSE_CALLBACKS)?;
+            reentrancy_guard::non_reentrant(|| {
+                self.transfer_helper(from, to, value)?;
+                if !psp22::on_token_received(to, from, from, value, data) {
+                    return Err(Error::TransferRejected);
+                }
+                Ok(())
+            })
+        }
+
+        /// Like `transfer`, but if `to` is a contract it has to acknowledge the
+        /// tokens through `TokenReceiver`, otherwise the transfer fails with
+        /// `UnsafeRecipient` so tokens don't get stranded in contracts that
+        /// cannot move them.
+        #[ink(message, selector = 0xE9B96481)]
+        pub fn safe_transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            let from = self.env().caller();
+            reentrancy_guard::non_reentrant(|| {
+                self.transfer_helper(from, to, value)?;
+                if self.env().is_contract(&to) {
+                    self.ensure_not_paused(PAUSE_CALLBACKS)?;
+                    if !psp22::on_token_received(to, from, from, value, Vec::new()) {
+                        return Err(Error::UnsafeRecipient);
+                    }
+                }
+                Ok(())
+            })
+        }
+
+        /// Mints `amount` to `receiver`, calls its `on_flash_loan` and then takes
+        /// back `amount` plus the fee through the allowance `receiver` gave this
+        /// contract meanwhile. Fails, reverting the loan, if `receiver` rejects
+        /// it or did not approve enough.
+        #[ink(message, selector = 0x90257A58)]
+        pub fn flash_loan(
+            &mut self,
+            receiver: AccountId,
+            amount: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            let initiator = self.env().caller();
+            self.ensure_not_paused(PAUSE_FLASH_LOANS)?;
+            reentrancy_guard::non_reentrant(|| {
+                self.ensure_valid_recipient(receiver)?;
+                let fee = self.flash_fee(amount);
+                let repayment = amount.checked_add(fee).ok_or(Error::Overflow)?;
+                self.update(None, Some(receiver), amount)?;
+                self.flush();
+                let accepted = psp22::on_flash_loan(receiver, initiator, amount, fee, data);
+                self.reload();
+                if !accepted {
+                    return Err(Error::FlashLoanRejected);
+                }
+                self.spend_allowance(receiver, self.env().account_id(), repayment)?;
+                self.update(Some(receiver), None, amount)?;
+                if let Some(fee_receiver) = self.flash_fee_receiver {
+                    self.transfer_helper(receiver, fee_receiver, fee)?;
+                } else {
+                    self.update(Some(receiver), None, fee)?;
+                }
+                self.env().emit_event(FlashLoan {
+                    receiver,
+                    initiator,
+                    amount,
+                    fee,
+                });
+                Ok(())
+            })
+        }
+
+        /// Fee charged on a flash loan of `amount`.
+        #[ink(message, selector = 0x735693B6)]
+        pub fn flash_fee(&self, amount: Balance) -> Balance {
+            // Split so that large amounts can't overflow.
+            amount / 10_000 * Balance::from(self.flash_fee_bps)
+                + amount % 10_000 * Balance::from(self.flash_fee_bps) / 10_000
+        }
+
+        #[ink(message, selector = 0xA65F9051)]
+        pub fn flash_fee_receiver(&self) -> Option<AccountId> {
+            self.flash_fee_receiver
+        }
+
+        /// Sets the flash loan fee to `fee_bps` basis points and switches its
+        /// payout: to `receiver`, or burned if `None`.
+        #[ink(message, selector = 0xE0261B9E)]
+        pub fn set_flash_fee(&mut self, fee_bps: u16, receiver: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            if fee_bps > 10_000 {
+                return Err(Error::InvalidFee);
+            }
+            let previous_fee_bps = self.flash_fee_bps;
+            let previous_receiver = self.flash_fee_receiver;
+            self.flash_fee_bps = fee_bps;
+            self.flash_fee_receiver = receiver;
+            self.env().emit_event(FlashFeeUpdated {
+                previous_fee_bps,
+                fee_bps,
+                previous_receiver,
+                receiver,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x8FD1AD62)]
+        pub fn fee_policy(&self) -> Option<FeePolicy> {
+            self.fee_policy.clone()
+        }
+
+        #[ink(message, selector = 0xF44387D7)]
+        pub fn pending_fee_policy(&self) -> Option<PendingFeePolicy> {
+            self.pending_fee_policy.clone()
+        }
+
+        /// Queues `policy` to replace the transfer fee policy, or with `None`
+        /// to remove the fee, once `FEE_POLICY_DELAY` passed. Replaces a policy
+        /// queued before.
+        #[ink(message, selector = 0xA60C6C9F)]
+        pub fn queue_fee_policy(&mut self, policy: Option<FeePolicy>) -> Result<()> {
+            self.ensure_owner()?;
+            if policy.as_ref().is_some_and(|policy| !policy.is_valid()) {
+                return Err(Error::InvalidFee);
+            }
+            let applicable_at = self
+                .env()
+                .block_timestamp()
+                .saturating_add(FEE_POLICY_DELAY);
+            self.pending_fee_policy = Some(PendingFeePolicy {
+                policy: policy.clone(),
+                applicable_at,
+            });
+            self.env().emit_event(FeePolicyQueued {
+                policy,
+                applicable_at,
+            });
+            Ok(())
+        }
+
+        /// Applies the queued fee policy once its delay is over. Anyone can
+        /// call it.
+        #[ink(message, selector = 0xB9156F62)]
+        pub fn apply_fee_policy(&mut self) -> Result<()> {
+            let pending = self
+                .pending_fee_policy
+                .clone()
+                .ok_or(Error::NoPendingFeePolicy)?;
+            if self.env().block_timestamp() < pending.applicable_at {
+                return Err(Error::FeePolicyNotDue);
+            }
+            self.pending_fee_policy = None;
+            let previous_policy = self.fee_policy.clone();
+            self.fee_policy = pending.policy.clone();
+            self.env().emit_event(FeePolicyUpdated {
+                previous_policy,
+                policy: pending.policy,
+            });
+            Ok(())
+        }
+
+        /// The fee a transfer of `value` pays under the current policy, unless
+        /// one of its sides is fee exempt.
+        #[ink(message, selector = 0xC374761C)]
+        pub fn transfer_fee(&self, value: Balance) -> Balance {
+            self.fee_policy
+                .as_ref()
+                .map_or(0, |policy| bps_of(value, policy.fee_bps))
+        }
+
+        #[ink(message, selector = 0x77D423C1)]
+        pub fn is_fee_exempt(&self, account: AccountId) -> bool {
+            self.fee_exempt.contains(account)
+        }
+
+        #[ink(message, selector = 0x4F720467)]
+        pub fn pending_fee_exemption(&self, account: AccountId) -> Option<PendingFeeExemption> {
+            self.pending_fee_exemptions.get(account)
+        }
+
+        /// Queues exempting `account` from the transfer fee, or ending its
+        /// exemption, once `FEE_POLICY_DELAY` passed, like a fee policy
+        /// change. Replaces a change of `account` queued before.
+        #[ink(message, selector = 0xA9D77542)]
+        pub fn queue_fee_exemption(&mut self, account: AccountId, exempt: bool) -> Result<()> {
+            self.ensure_owner()?;
+            let applicable_at = self
+                .env()
+                .block_timestamp()
+                .saturating_add(FEE_POLICY_DELAY);
+            self.pending_fee_exemptions.insert(
+                account,
+                &PendingFeeExemption {
+                    exempt,
+                    applicable_at,
+                },
+            );
+            self.env().emit_event(FeeExemptionQueued {
+                account,
+                exempt,
+                applicable_at,
+            });
+            Ok(())
+        }
+
+        /// Applies the queued exemption change of `account` once its delay is
+        /// over. Anyone can call it.
+        #[ink(message, selector = 0x8A4C3624)]
+        pub fn apply_fee_exemption(&mut self, account: AccountId) -> Result<()> {
+            let pending = self
+                .pending_fee_exemption(account)
+                .ok_or(Error::NoPendingFeeExemption)?;
+            if self.env().block_timestamp() < pending.applicable_at {
+                return Err(Error::FeeExemptionNotDue);
+            }
+            self.pending_fee_exemptions.remove(account);
+            let previous_exempt = self.is_fee_exempt(account);
+            if pending.exempt {
+                self.fee_exempt.insert(account, &());
+            } else {
+                self.fee_exempt.remove(account);
+            }
+            self.env().emit_event(FeeExemptionUpdated {
+                account,
+                previous_exempt,
+                exempt: pending.exempt,
+            });
+            Ok(())
+        }
+
+        /// The fee a transfer of `value` from `from` to `to` pays.
+        fn fee_between(&self, from: AccountId, to: AccountId, value: Balance) -> Balance {
+            if self.is_fee_exempt(from) || self.is_fee_exempt(to) {
+                return 0;
+            }
+            self.transfer_fee(value)
+        }
+
+        /// Takes `fee` from `from` and splits it as the fee policy says. The
+        /// burned part gets the rounding remainder.
+        fn charge_fee(&mut self, from: AccountId, fee: Balance) -> Result<()> {
+            let policy = match self.fee_policy.clone() {
+                Some(policy) if fee > 0 => policy,
+                _ => return Ok(()),
+            };
+            let to_treasury = bps_of(fee, policy.treasury_bps);
+            let to_rewards = bps_of(fee, policy.rewards_bps);
+            let to_burn = fee - to_treasury - to_rewards;
+            for &(to, amount) in &[
+                (Some(policy.treasury), to_treasury),
+                (Some(policy.rewards), to_rewards),
+                (None, to_burn),
+            ] {
+                if amount > 0 {
+                    self.update(Some(from), to, amount)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Writes the fields to storage before a call that may re-enter this
+        /// contract, which would otherwise see them as of before this message.
+        fn flush(&self) {
+            ink::env::set_contract_storage(&<Self as ink::storage::traits::StorageKey>::KEY, self);
+        }
+
+        /// Reads the fields back after such a call, picking up its changes.
+        fn reload(&mut self) {
+            *self =
+                ink::env::get_contract_storage(&<Self as ink::storage::traits::StorageKey>::KEY)
+                    .ok()
+                    .flatten()
+                    .expect("the contract is stored");
+        }
+
+        fn transfer_helper(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_valid_recipient(to)?;
+            let fee = self.fee_between(from, to, value);
+            self.update(Some(from), Some(to), value - fee)?;
+            self.charge_fee(from, fee)?;
+            // Only transfers the holder sends itself are rounded up, not those
+            // of spenders.
+            if from == self.env().caller() {
+                self.round_up(from, value)?;
+            }
+            Ok(())
+        }
+
+        /// Moves `value` from `from` to `to`. A `None` sender mints, a `None`
+        /// recipient burns. Every balance change goes through here so that the
+        /// transfer hooks and the `Transfer` event see all of them.
+        fn update(
+            &mut self,
+            from: Option<AccountId>,
+            to: Option<AccountId>,
+            value: Balance,
+        ) -> Result<()> {
+            self.before_token_transfer(from, to, value)?;
+            match from {
+                Some(from) => {
+                    let from_balance = self.balance_of(from);
+                    if from_balance < value {
+                        return Err(Error::InsufficientBalance {
+                            required: value,
+                            available: from_balance,
+                        });
+                    }
+                    self.set_balance(from, from_balance - value);
+                }
+                None => {
+                    self.ensure_within_cap(value)?;
+                    self.total_supply += value;
+                }
+            }
+            match to {
+                Some(to) => {
+                    let to_balance = self
+                        .balance_of(to)
+                        .checked_add(value)
+                        .ok_or(Error::Overflow)?;
+                    self.set_balance(to, to_balance);
+                }
+                None => self.total_supply -= value,
+            }
+            if from.is_none() || to.is_none() {
+                self.checkpoint_total_supply();
+            }
+            let tx_id = self.record_receipt(from, to, value);
+            self.env().emit_event(Transfer {
+                from,
+                to,
+                value,
+                tx_id,
+            });
+            self.after_token_transfer(from, to, value)
+        }
+
+        /// Numbers a balance change and stores its receipt in the ring buffer.
+        fn record_receipt(
+            &mut self,
+            from: Option<AccountId>,
+            to: Option<AccountId>,
+            value: Balance,
+        ) -> TxId {
+            let tx_id = self.last_tx_id() + 1;
+            self.last_tx_id.set(&tx_id);
+            let receipt = Receipt {
+                tx_id,
+                from,
+                to,
+                value,
+                block: self.env().block_number(),
+            };
+            self.receipts.insert(tx_id % MAX_RECEIPTS, &receipt);
+            tx_id
+        }
+
+        /// The `tx_id` of the latest balance change, 0 before the first one.
+        #[ink(message, selector = 0x801BC9FC)]
+        pub fn last_tx_id(&self) -> TxId {
+            self.last_tx_id.get_or_default()
+        }
+
+        /// The receipt of balance change `tx_id`, if it is among the latest
+        /// `MAX_RECEIPTS`.
+        #[ink(message, selector = 0x137A03B0)]
+        pub fn get_receipt(&self, tx_id: TxId) -> Option<Receipt> {
+            self.receipts
+                .get(tx_id % MAX_RECEIPTS)
+                .filter(|receipt| receipt.tx_id == tx_id)
+        }
+
+        /// Stores `balance` and keeps the holder list in sync with it. The
+        /// entry of an emptied account is removed instead of storing a zero,
+        /// so it stops paying storage deposit.
+        fn set_balance(&mut self, account: AccountId, balance: Balance) {
+            self.checkpoint_balance(account, balance);
+            let mut data = self.accounts.get(account).unwrap_or_default();
+            data.balance = balance;
+            match (balance > 0, data.holder_index) {
+                (true, None) => {
+                    data.holder_index = Some(self.holder_count);
+                    self.holders.insert(self.holder_count, &account);
+                    self.holder_count += 1;
+                }
+                (false, Some(index)) => {
+                    // Move the last holder into the gap.
+                    data.holder_index = None;
+                    self.holder_count -= 1;
+                    let last = self.holders.take(self.holder_count);
+                    if let Some(moved) = last.filter(|_| index < self.holder_count) {
+                        self.holders.insert(index, &moved);
+                        if let Some(mut moved_data) = self.accounts.get(moved) {
+                            moved_data.holder_index = Some(index);
+                            self.accounts.insert(moved, &moved_data);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            self.store_account(account, data);
+        }
+
+        /// Stores `data`, removing the entry of an account with nothing left.
+        fn store_account(&mut self, account: AccountId, data: AccountData) {
+            if data == AccountData::default() {
+                self.accounts.remove(account);
+            } else {
+                self.accounts.insert(account, &data);
+            }
+        }
+
+        /// Runs before any balance change. An error aborts the change.
+        fn before_token_transfer(
+            &mut self,
+            from: Option<AccountId>,
+            to: Option<AccountId>,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_not_paused(match (from, to) {
+                (None, _) => PAUSE_MINTS,
+                (_, None) => PAUSE_BURNS,
+                _ => PAUSE_TRANSFERS,
+            })?;
+            let custody = self.env().account_id();
+            match (from, to) {
+                (Some(from), Some(to)) if from != custody && to != custody => {
+                    self.ensure_transfer_allowed(from, to, value)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        /// Runs after any balance change.
+        fn after_token_transfer(
+            &mut self,
+            _from: Option<AccountId>,
+            _to: Option<AccountId>,
+            _value: Balance,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xCE83A421)]
+        pub fn holder_count(&self) -> u32 {
+            self.holder_count
+        }
+
+        /// Page `page` (counting from 0) of at most `page_size` balances
+        /// `account` had at the end of the blocks from `from_block` to
+        /// `to_block`, oldest first. Only blocks that changed the balance are
+        /// listed, and only the last `MAX_BALANCE_CHECKPOINTS` of them are kept.
+        #[ink(message, selector = 0x09D2794C)]
+        pub fn balance_history(
+            &self,
+            account: AccountId,
+            from_block: BlockNumber,
+            to_block: BlockNumber,
+            page: u32,
+            page_size: u32,
+        ) -> Vec<Checkpoint> {
+            let checkpoints = match self.balance_checkpoint_rings.get(account) {
+                Some(ring) => (0..ring.len)
+                    .filter_map(|index| self.ring_checkpoint(account, ring, index))
+                    .collect(),
+                None => self.balance_checkpoints.get(account).unwrap_or_default(),
+            };
+            checkpoints
+                .into_iter()
+                .filter(|checkpoint| from_block <= checkpoint.block && checkpoint.block <= to_block)
+                .skip(page.saturating_mul(page_size) as usize)
+                .take(page_size as usize)
+                .collect()
+        }
+
+        /// The checkpoint `index` places after the oldest one `account` has.
+        fn ring_checkpoint(
+            &self,
+            account: AccountId,
+            ring: CheckpointRing,
+            index: u32,
+        ) -> Option<Checkpoint> {
+            let slot =
+                (ring.head + MAX_BALANCE_CHECKPOINTS - ring.len + index) % MAX_BALANCE_CHECKPOINTS;
+            self.balance_checkpoint_slots.get((account, slot))
+        }
+
+        /// `account`'s checkpoint ring, after moving a balance history written
+        /// before version 5 into it.
+        fn balance_checkpoint_ring(&mut self, account: AccountId) -> CheckpointRing {
+            if let Some(ring) = self.balance_checkpoint_rings.get(account) {
+                return ring;
+            }
+            let checkpoints = self.balance_checkpoints.take(account).unwrap_or_default();
+            if checkpoints.is_empty() {
+                return CheckpointRing::default();
+            }
+            let len = checkpoints.len() as u32;
+            for (slot, checkpoint) in (0..len).zip(checkpoints.iter()) {
+                self.balance_checkpoint_slots
+                    .insert((account, slot), checkpoint);
+            }
+            let ring = CheckpointRing {
+                head: len % MAX_BALANCE_CHECKPOINTS,
+                len,
+            };
+            self.balance_checkpoint_rings.insert(account, &ring);
+            ring
+        }
+
+        /// Records `balance` for `balance_history`, replacing the checkpoint of
+        /// the current block if there is one. Emptying an account records a
+        /// zero like any other balance, so snapshots from before still see
+        /// what it held.
+        fn checkpoint_balance(&mut self, account: AccountId, balance: Balance) {
+            let mut ring = self.balance_checkpoint_ring(account);
+            let checkpoint = Checkpoint {
+                block: self.env().block_number(),
+                value: balance,
+            };
+            let last = ring
+                .len
+                .checked_sub(1)
+                .and_then(|last| self.ring_checkpoint(account, ring, last));
+            let slot = match last {
+                Some(last) if last.block == checkpoint.block => {
+                    (ring.head + MAX_BALANCE_CHECKPOINTS - 1) % MAX_BALANCE_CHECKPOINTS
+                }
+                _ => {
+                    let slot = ring.head;
+                    ring.head = (ring.head + 1) % MAX_BALANCE_CHECKPOINTS;
+                    ring.len = (ring.len + 1).min(MAX_BALANCE_CHECKPOINTS);
+                    slot
+                }
+            };
+            self.balance_checkpoint_slots
+                .insert((account, slot), &checkpoint);
+            self.balance_checkpoint_rings.insert(account, &ring);
+        }
+
+        /// Page `page` (counting from 0) of at most `page_size` holders.
+        #[ink(message, selector = 0xD6F3E41E)]
+        pub fn holders(&self, page: u32, page_size: u32) -> Vec<AccountId> {
+            let start = page.saturating_mul(page_size);
+            let end = start.saturating_add(page_size).min(self.holder_count);
+            (start..end)
+                .filter_map(|index| self.holders.get(index))
+                .collect()
+        }
+
+        /// Runs `calls` in order, as the caller, and returns the SCALE encoded
+        /// output of each: the return value, or for messages returning a
+        /// `Result` the `Ok` value. If any call fails, `multicall` fails with
+        /// its error and none of the calls take effect.
+        ///
+        /// Supports `total_supply`, `balance_of`, `allowance`, `transfer`,
+        /// `transfer_from`, `approve`, `approve_with_expiry` and `burn`.
+        #[ink(message, selector = 0x34DD1B12)]
+        pub fn multicall(&mut self, calls: Vec<Call>) -> Result<Vec<Vec<u8>>> {
+            calls.iter().map(|call| self.dispatch(call)).collect()
+        }
+
+        fn dispatch(&mut self, call: &Call) -> Result<Vec<u8>> {
+            let input = &call.input[..];
+            let output = match u32::from_be_bytes(call.selector) {
+                0xDB6375A8 => {
+                    decode_input::<()>(input)?;
+                    self.total_supply().encode()
+                }
+                0x0F755A56 => self.balance_of(decode_input(input)?).encode(),
+                0x6A00165E => {
+                    let (owner, spender) = decode_input(input)?;
+                    self.allowance(owner, spender).encode()
+                }
+                0x84A15DA1 => {
+                    let (to, value) = decode_input(input)?;
+                    self.transfer(to, value)?.encode()
+                }
+                0x0B396F18 => {
+                    let (from, to, value) = decode_input(input)?;
+                    self.transfer_from(from, to, value)?.encode()
+                }
+                0x681266A0 => {
+                    let (spender, value) = decode_input(input)?;
+                    self.approve(spender, value)?.encode()
+                }
+                0xDD0FCF68 => {
+                    let (spender, value, deadline) = decode_input(input)?;
+                    self.approve_with_expiry(spender, value, deadline)?.encode()
+                }
+                0xB1EFC17B => self.burn(decode_input(input)?)?.encode(),
+                _ => return Err(Error::UnknownSelector),
+            };
+            Ok(output)
+        }
+
+        /// Moves `value` of `from`'s tokens to `to`, spending what `from` allowed
+        /// the caller.
+        #[ink(message, selector = 0x0B396F18)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let who = Self::env().caller();
+            self.spend_allowance(from, who, value)?;
+            self.transfer_helper(from, to, value)
+        }
+
+        /// Deprecated misspelling of `transfer_from` that always sends to the
+        /// caller, kept so callers using its selector keep working.
+        #[ink(message, selector = 0x41C3DA48)]
+        pub fn transer_from(&mut self, from: AccountId, value: Balance) -> Result<()> {
+            let who = Self::env().caller();
+            self.transfer_from(from, who, value)
+        }
+
+        #[ink(message, selector = 0x681266A0)]
+        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()> {
+            self.approve_helper(spender, value, None)
+        }
+
+        /// Like `approve`, but the grant can't be used after `deadline`.
+        #[ink(message, selector = 0xDD0FCF68)]
+        pub fn approve_with_expiry(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+        ) -> Result<()> {
+            self.approve_helper(spender, value, Some(deadline))
+        }
+
+        // The core messages once more under the selectors and signatures of the
+        // PSP22 standard, so contracts using the `psp22` client can hold and
+        // move this token like any other.
+
+        /// PSP22::total_supply
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn psp22_total_supply(&self) -> Balance {
+            self.total_supply()
+        }
+
+        /// PSP22::balance_of
+        #[ink(message, selector = 0x6568382F)]
+        pub fn psp22_balance_of(&self, owner: AccountId) -> Balance {
+            self.balance_of(owner)
+        }
+
+        /// PSP22::allowance
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn psp22_allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowance(owner, spender)
+        }
+
+        /// PSP22::transfer
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn psp22_transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), psp22::PSP22Error> {
+            Ok(self.transfer(to, value)?)
+        }
+
+        /// PSP22::transfer_from
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn psp22_transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), psp22::PSP22Error> {
+            Ok(self.transfer_from(from, to, value)?)
+        }
+
+        /// PSP22::approve
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn psp22_approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), psp22::PSP22Error> {
+            Ok(self.approve(spender, value)?)
+        }
+
+        fn approve_helper(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+            expires_at: Option<Timestamp>,
+        ) -> Result<()> {
+            if spender == zero_address() {
+                return Err(Error::ZeroAddressNotAllowed);
+            }
+            let owner = self.env().caller();
+            if value == 0 {
+                self.allowance.remove((owner, spender));
+            } else {
+                self.allowance.insert(
+                    (owner, spender),
+                    &Allowance {
+                        amount: value,
+                        expires_at,
+                    },
+                );
+            }
+            self.track_spender(owner, spender);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+                expires_at,
+            });
+            Ok(())
+        }
+
+        /// Lets `operator` move any amount of the caller's tokens with
+        /// `transfer_from`, without per-amount approvals, or revokes that.
+        #[ink(message, selector = 0xCFD0C27B)]
+        pub fn set_approval_for_all(&mut self, operator: AccountId, approved: bool) -> Result<()> {
+            let owner = self.env().caller();
+            if approved {
+                self.operator_approvals.insert((owner, operator), &());
+            } else {
+                self.operator_approvals.remove((owner, operator));
+            }
+            self.track_spender(owner, operator);
+            self.env().emit_event(ApprovalForAll {
+                owner,
+                operator,
+                approved,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x0F5922E9)]
+        pub fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.operator_approvals.contains((owner, operator))
+        }
+
+        /// Page `page` (counting from 0) of the spenders holding any grant
+        /// from `owner`, with their grants.
+        #[ink(message, selector = 0xD6B394C7)]
+        pub fn active_approvals(&self, owner: AccountId, page: u32) -> Vec<SpenderGrants> {
+            let count = self.spender_counts.get(owner).unwrap_or(0);
+            let start = page.saturating_mul(APPROVALS_PAGE_SIZE);
+            let end = start.saturating_add(APPROVALS_PAGE_SIZE).min(count);
+            (start..end)
+                .filter_map(|index| self.spenders.get((owner, index)))
+                .map(|spender| SpenderGrants {
+                    spender,
+                    allowance: self.allowance.get((owner, spender)),
+                    recurring: self.recurring_allowance(owner, spender),
+                    operator: self.is_approved_for_all(owner, spender),
+                })
+                .collect()
+        }
+
+        /// Revokes every allowance, recurring allowance and operator approval
+        /// the caller gave, e.g. after a dapp it approved got compromised.
+        /// Returns how many spenders lost their grants. Grants left unchanged
+        /// since before spenders were tracked, in storage version 4, are not
+        /// known and have to be revoked one by one.
+        #[ink(message, selector = 0x3C6CC099)]
+        pub fn revoke_all_allowances(&mut self) -> Result<u32> {
+            let owner = self.env().caller();
+            let spender_count = self.spender_counts.take(owner).unwrap_or(0);
+            for index in 0..spender_count {
+                if let Some(spender) = self.spenders.take((owner, index)) {
+                    self.spender_index.remove((owner, spender));
+                    self.allowance.remove((owner, spender));
+                    self.recurring_allowances.remove((owner, spender));
+                    self.operator_approvals.remove((owner, spender));
+                }
+            }
+            self.env().emit_event(AllowancesRevoked {
+                owner,
+                spender_count,
+            });
+            Ok(spender_count)
+        }
+
+        /// Adds `spender` to `owner`'s spenders if it holds a grant now, or
+        /// removes it if it no longer holds any.
+        fn track_spender(&mut self, owner: AccountId, spender: AccountId) {
+            let active = self.allowance.contains((owner, spender))
+                || self.recurring_allowances.contains((owner, spender))
+                || self.operator_approvals.contains((owner, spender));
+            let mut count = self.spender_counts.get(owner).unwrap_or(0);
+            match (active, self.spender_index.get((owner, spender))) {
+                (true, None) => {
+                    self.spenders.insert((owner, count), &spender);
+                    self.spender_index.insert((owner, spender), &count);
+                    count += 1;
+                }
+                (false, Some(index)) => {
+                    // Move the last spender into the gap.
+                    self.spender_index.remove((owner, spender));
+                    count -= 1;
+                    let last = self.spenders.take((owner, count));
+                    if let Some(moved) = last.filter(|_| index < count) {
+                        self.spenders.insert((owner, index), &moved);
+                        self.spender_index.insert((owner, moved), &index);
+                    }
+                }
+                _ => return,
+            }
+            if count == 0 {
+                self.spender_counts.remove(owner);
+            } else {
+                self.spender_counts.insert(owner, &count);
+            }
+        }
+
+        /// Uses up `value` of what `owner` allowed `spender`: the plain allowance
+        /// first, then the recurring one, and only then operator approval.
+        fn spend_allowance(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let mut error = Error::InsufficientAllowance {
+                required: value,
+                available: self.allowance(owner, spender),
+            };
+            if let Some(grant) = self.allowance.get((owner, spender)) {
+                if grant.amount >= value {
+                    if !self.is_expired(&grant) {
+                        if grant.amount == value {
+                            self.allowance.remove((owner, spender));
+                            self.track_spender(owner, spender);
+                        } else {
+                            self.allowance.insert(
+                                (owner, spender),
+                                &Allowance {
+                                    amount: grant.amount - value,
+                                    ..grant
+                                },
+                            );
+                        }
+                        return Ok(());
+                    }
+                    error = Error::AllowanceExpired;
+                }
+            }
+            if self.spend_recurring(owner, spender, value)
+                || self.is_approved_for_all(owner, spender)
+            {
+                return Ok(());
+            }
+            Err(error)
+        }
+
+        /// Lets `spender` pull up to `amount_per_period` of the caller's tokens
+        /// in every window of `period_length` milliseconds, starting now. The
+        /// unused part of a period does not carry over. An amount of 0 revokes.
+        #[ink(message, selector = 0x66E0A70E)]
+        pub fn approve_recurring(
+            &mut self,
+            spender: AccountId,
+            amount_per_period: Balance,
+            period_length: Timestamp,
+        ) -> Result<()> {
+            if period_length == 0 {
+                return Err(Error::InvalidPeriod);
+            }
+            let owner = self.env().caller();
+            if amount_per_period == 0 {
+                self.recurring_allowances.remove((owner, spender));
+            } else {
+                self.recurring_allowances.insert(
+                    (owner, spender),
+                    &RecurringAllowance {
+                        amount_per_period,
+                        period_length,
+                        period_start: self.env().block_timestamp(),
+                        spent_in_period: 0,
+                    },
+                );
+            }
+            self.track_spender(owner, spender);
+            self.env().emit_event(RecurringApproval {
+                owner,
+                spender,
+                amount_per_period,
+                period_length,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x7B4D929E)]
+        pub fn recurring_allowance(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+        ) -> Option<RecurringAllowance> {
+            self.recurring_allowances
+                .get((owner, spender))
+                .map(|grant| self.roll_period(grant))
+        }
+
+        /// Moves a recurring grant forward to the period containing now.
+        fn roll_period(&self, mut grant: RecurringAllowance) -> RecurringAllowance {
+            let now = self.env().block_timestamp();
+            let elapsed_periods = (now - grant.period_start) / grant.period_length;
+            if elapsed_periods > 0 {
+                grant.period_start += elapsed_periods * grant.period_length;
+                grant.spent_in_period = 0;
+            }
+            grant
+        }
+
+        fn spend_recurring(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> bool {
+            match self.recurring_allowance(owner, spender) {
+                Some(mut grant) if grant.amount_per_period - grant.spent_in_period >= value => {
+                    grant.spent_in_period += value;
+                    self.recurring_allowances.insert((owner, spender), &grant);
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        #[ink(message, selector = 0xB1EFC17B)]
+        pub fn burn(&mut self, value: Balance) -> Result<()> {
+            let who = Self::env().caller();
+            let burned = core::cmp::min(self.balance_of(who), value);
+            self.update(Some(who), None, burned)
+        }
+
+        #[ink(message, selector = 0xC392BA4D)]
+        pub fn issue(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_valid_recipient(to)?;
+            self.update(None, Some(to), value)
+        }
+
+        /// Like `issue`, for minters within the allowance the owner gave them.
+        #[ink(message, selector = 0xCFDD9AA2)]
+        pub fn mint(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.spend_minter_allowance(value)?;
+            self.ensure_valid_recipient(to)?;
+            self.update(None, Some(to), value)
+        }
+
+        /// Mints to every recipient in one call, e.g. for a token generation
+        /// event. The cap is checked for the whole batch before minting, so
+        /// either all recipients get their tokens or none do.
+        #[ink(message, selector = 0xA242EC90)]
+        pub fn issue_batch(&mut self, recipients: Vec<(AccountId, Balance)>) -> Result<()> {
+            let total = recipients
+                .iter()
+                .try_fold(0 as Balance, |total, (_, value)| total.checked_add(*value))
+                .ok_or(Error::Overflow)?;
+            self.spend_minter_allowance(total)?;
+            self.ensure_within_cap(total)?;
+            for (to, value) in recipients {
+                self.ensure_valid_recipient(to)?;
+                self.update(None, Some(to), value)?;
+            }
+            Ok(())
+        }
+
+        /// Takes `value` off the caller's minter allowance.
+        fn spend_minter_allowance(&mut self, value: Balance) -> Result<()> {
+            let minter = self.env().caller();
+            if !self.is_minter(minter) {
+                return Err(Error::NotMinter);
+            }
+            let allowance = self.minter_allowance(minter);
+            if allowance < value {
+                return Err(Error::MinterAllowanceExceeded);
+            }
+            self.minter_allowances.insert(minter, &(allowance - value));
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xB00B03C6)]
+        pub fn cap(&self) -> Option<Balance> {
+            self.cap
+        }
+
+        /// Sets or, with `None`, lifts the cap. It can't be set below the
+        /// current supply.
+        #[ink(message, selector = 0x498ECDC9)]
+        pub fn set_cap(&mut self, cap: Option<Balance>) -> Result<()> {
+            self.ensure_owner()?;
+            if cap.is_some_and(|cap| cap < self.total_supply) {
+                return Err(Error::CapExceeded);
+            }
+            let previous_cap = self.cap;
+            self.cap = cap;
+            self.env().emit_event(CapUpdated { previous_cap, cap });
+            Ok(())
+        }
+
+        /// Fails unless `value` more tokens can be minted.
+        fn ensure_within_cap(&self, value: Balance) -> Result<()> {
+            let total_supply = self
+                .total_supply
+                .checked_add(value)
+                .ok_or(Error::Overflow)?;
+            if self.cap.is_some_and(|cap| total_supply > cap) {
+                return Err(Error::CapExceeded);
+            }
+            Ok(())
+        }
+
+        /// Whether `account` was made a minter. A minter that used up its
+        /// allowance stays one until the owner removes it.
+        #[ink(message, selector = 0x13FC1F2D)]
+        pub fn is_minter(&self, account: AccountId) -> bool {
+            self.minter_allowances.contains(account)
+        }
+
+        #[ink(message, selector = 0x5C1570A8)]
+        pub fn minter_allowance(&self, minter: AccountId) -> Balance {
+            self.minter_allowances.get(minter).unwrap_or(0)
+        }
+
+        /// Lets `minter` mint `amount` more tokens, replacing what was left of
+        /// its previous allowance. An `amount` of zero removes the minter.
+        #[ink(message, selector = 0x34CD75BB)]
+        pub fn set_minter_allowance(&mut self, minter: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let previous_amount = self.minter_allowance(minter);
+            if amount == 0 {
+                self.minter_allowances.remove(minter);
+            } else {
+                self.minter_allowances.insert(minter, &amount);
+            }
+            self.env().emit_event(MinterAllowanceUpdated {
+                minter,
+                previous_amount,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Whether any of the `PAUSE_*` capabilities in `features` is paused.
+        #[ink(message, selector = 0xFA7D505B)]
+        pub fn is_paused(&self, features: u32) -> bool {
+            self.paused_features() & features != 0
+        }
+
+        /// The capabilities paused by the owner or by a running guardian pause.
+        #[ink(message, selector = 0xC0AE4EAB)]
+        pub fn paused_features(&self) -> u32 {
+            self.paused | self.guardian_pause().map_or(0, |pause| pause.features)
+        }
+
+        /// Pauses or unpauses the `PAUSE_*` capabilities in `features`, leaving
+        /// the others as they are. `PAUSE_ALL` stops the whole token.
+        /// Unpausing also lifts a guardian pause of those capabilities.
+        #[ink(message, selector = 0x4B7659E5)]
+        pub fn set_paused(&mut self, features: u32, paused: bool) -> Result<()> {
+            self.ensure_owner()?;
+            let previous_paused = self.paused_features();
+            if paused {
+                self.paused |= features;
+            } else {
+                self.paused &= !features;
+                if let Some(pause) = &mut self.guardian_pause {
+                    pause.features &= !features;
+                }
+            }
+            self.env().emit_event(PauseUpdated {
+                previous_paused,
+                paused: self.paused_features(),
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x3805153D)]
+        pub fn guardian(&self) -> Option<AccountId> {
+            self.guardian
+        }
+
+        /// Sets, or with `None` removes, the guardian and how many blocks its
+        /// pauses last.
+        #[ink(message, selector = 0x5133ED53)]
+        pub fn set_guardian(
+            &mut self,
+            guardian: Option<AccountId>,
+            pause_blocks: BlockNumber,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            let previous_guardian = self.guardian;
+            let previous_pause_blocks = self.guardian_pause_blocks;
+            self.guardian = guardian;
+            self.guardian_pause_blocks = pause_blocks;
+            self.env().emit_event(GuardianUpdated {
+                previous_guardian,
+                guardian,
+                previous_pause_blocks,
+                pause_blocks,
+            });
+            Ok(())
+        }
+
+        /// The guardian's pause, while it runs.
+        #[ink(message, selector = 0x7F266E88)]
+        pub fn guardian_pause(&self) -> Option<GuardianPause> {
+            self.guardian_pause
+                .clone()
+                .filter(|pause| self.env().block_number() < pause.expires_at)
+        }
+
+        /// Pauses `features` right away, for `guardian_pause_blocks` blocks. A
+        /// pause while another one runs adds to its features but does not
+        /// extend it: only the owner can make a pause last.
+        #[ink(message, selector = 0xF9C14D3E)]
+        pub fn pause_as_guardian(&mut self, features: u32) -> Result<()> {
+            let caller = self.env().caller();
+            if self.guardian != Some(caller) {
+                return Err(Error::NotGuardian);
+            }
+            let pause = match self.guardian_pause() {
+                Some(running) => GuardianPause {
+                    features: running.features | features,
+                    ..running
+                },
+                None => GuardianPause {
+                    features,
+                    expires_at: self
+                        .env()
+                        .block_number()
+                        .saturating_add(self.guardian_pause_blocks),
+                },
+            };
+            self.env().emit_event(GuardianPaused {
+                guardian: caller,
+                features: pause.features,
+                expires_at: pause.expires_at,
+            });
+            self.guardian_pause = Some(pause);
+            Ok(())
+        }
+
+        /// Makes the running guardian pause an owner pause, which lasts until
+        /// the owner lifts it with `set_paused`.
+        #[ink(message, selector = 0xB6003234)]
+        pub fn ratify_guardian_pause(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            let features = self
+                .guardian_pause()
+                .ok_or(Error::NoGuardianPause)?
+                .features;
+            self.paused |= features;
+            self.guardian_pause = None;
+            self.env().emit_event(GuardianPauseRatified { features });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x1287C50C)]
+        pub fn recovery_config(&self) -> Option<RecoveryConfig> {
+            self.recovery_config.clone()
+        }
+
+        /// Lets `threshold` of `guardians` rotate the owner after `delay`. An
+        /// empty guardian list turns recovery off. Drops all votes and any
+        /// pending recovery.
+        #[ink(message, selector = 0x3B38A6EC)]
+        pub fn set_recovery(
+            &mut self,
+            guardians: Vec<AccountId>,
+            threshold: u32,
+            delay: Timestamp,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            if !guardians.is_empty() && (threshold == 0 || threshold as usize > guardians.len()) {
+                return Err(Error::InvalidThreshold);
+            }
+            self.clear_recovery();
+            self.env().emit_event(RecoveryConfigured {
+                guardians: guardians.clone(),
+                threshold,
+                delay,
+            });
+            self.recovery_config = if guardians.is_empty() {
+                None
+            } else {
+                Some(RecoveryConfig {
+                    guardians,
+                    threshold,
+                    delay,
+                })
+            };
+            Ok(())
+        }
+
+        /// Votes, as a recovery guardian, to make `new_owner` the owner. Once
+        /// `threshold` guardians voted for the same account the veto delay
+        /// starts; a guardian can change its vote until then.
+        #[ink(message, selector = 0xDCDE5F52)]
+        pub fn vote_recovery(&mut self, new_owner: AccountId) -> Result<()> {
+            let guardian = self.env().caller();
+            let config = self.recovery_config.clone().ok_or(Error::NoRecovery)?;
+            if !config.guardians.contains(&guardian) {
+                return Err(Error::NotRecoveryGuardian);
+            }
+            if self.pending_recovery.is_some() {
+                return Err(Error::RecoveryPending);
+            }
+            if new_owner == zero_address() {
+                return Err(Error::ZeroAddressNotAllowed);
+            }
+            self.recovery_votes.insert(guardian, &new_owner);
+            self.env().emit_event(RecoveryVoted {
+                guardian,
+                new_owner,
+            });
+            let votes = config
+                .guardians
+                .iter()
+                .filter(|&&guardian| self.recovery_votes.get(guardian) == Some(new_owner))
+                .count();
+            if votes >= config.threshold as usize {
+                let executable_at = self.env().block_timestamp().saturating_add(config.delay);
+                self.pending_recovery = Some(Recovery {
+                    new_owner,
+                    executable_at,
+                });
+                self.env().emit_event(RecoveryStarted {
+                    new_owner,
+                    executable_at,
+                });
+            }
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xA549ADE3)]
+        pub fn pending_recovery(&self) -> Option<Recovery> {
+            self.pending_recovery.clone()
+        }
+
+        /// Cancels the pending recovery and drops all votes, proving the owner
+        /// still holds its key.
+        #[ink(message, selector = 0x1684DBB7)]
+        pub fn veto_recovery(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            let recovery = self.pending_recovery.clone().ok_or(Error::NoRecovery)?;
+            self.clear_recovery();
+            self.env().emit_event(RecoveryVetoed {
+                new_owner: recovery.new_owner,
+            });
+            Ok(())
+        }
+
+        /// Hands the owner role to the new owner of the pending recovery once
+        /// its delay is over. Anyone may call it.
+        #[ink(message, selector = 0x5FA742D2)]
+        pub fn execute_recovery(&mut self) -> Result<()> {
+            let recovery = self.pending_recovery.clone().ok_or(Error::NoRecovery)?;
+            if self.env().block_timestamp() < recovery.executable_at {
+                return Err(Error::RecoveryDelayNotOver);
+            }
+            self.clear_recovery();
+            self.set_owner(recovery.new_owner)
+        }
+
+        fn clear_recovery(&mut self) {
+            if let Some(config) = &self.recovery_config {
+                for guardian in &config.guardians {
+                    self.recovery_votes.remove(guardian);
+                }
+            }
+            self.pending_recovery = None;
+        }
+
+        /// Takes `value` from the caller now and holds it until anyone
+        /// `execute`s the transfer to `to` at or after `execute_after`. Until
+        /// then the caller can `cancel` it and get the tokens back.
+        #[ink(message, selector = 0x04910B09)]
+        pub fn schedule_transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            execute_after: Timestamp,
+        ) -> Result<ScheduledTransferId> {
+            self.ensure_valid_recipient(to)?;
+            let from = self.env().caller();
+            self.ensure_transfer_allowed(from, to, value)?;
+            self.update(Some(from), Some(self.env().account_id()), value)?;
+            self.escrowed += value;
+            let id = self.next_scheduled_transfer_id;
+            self.next_scheduled_transfer_id += 1;
+            self.scheduled_transfers.insert(
+                id,
+                &ScheduledTransfer {
+                    from,
+                    to,
+                    value,
+                    execute_after,
+                },
+            );
+            self.env().emit_event(TransferScheduled {
+                id,
+                from,
+                to,
+                value,
+                execute_after,
+            });
+            Ok(id)
+        }
+
+        #[ink(message, selector = 0x481547E9)]
+        pub fn scheduled_transfer(&self, id: ScheduledTransferId) -> Option<ScheduledTransfer> {
+            self.scheduled_transfers.get(id)
+        }
+
+        /// Sends the tokens of scheduled transfer `id` on to its recipient once
+        /// it is due, less the transfer fee at that time. Anyone may call it.
+        #[ink(message, selector = 0xD00EC894)]
+        pub fn execute(&mut self, id: ScheduledTransferId) -> Result<()> {
+            let scheduled = self
+                .scheduled_transfer(id)
+                .ok_or(Error::ScheduledTransferNotFound)?;
+            if self.env().block_timestamp() < scheduled.execute_after {
+                return Err(Error::ScheduledTransferNotDue);
+            }
+            // The registry may have changed its mind since it was scheduled.
+            self.ensure_transfer_allowed(scheduled.from, scheduled.to, scheduled.value)?;
+            let fee = self.fee_between(scheduled.from, scheduled.to, scheduled.value);
+            self.release_scheduled(id, &scheduled, scheduled.to, fee)?;
+            self.env().emit_event(ScheduledTransferExecuted { id });
+            Ok(())
+        }
+
+        /// Returns the tokens of scheduled transfer `id` to the caller, its
+        /// sender.
+        #[ink(message, selector = 0x9796E9A7)]
+        pub fn cancel(&mut self, id: ScheduledTransferId) -> Result<()> {
+            let scheduled = self
+                .scheduled_transfer(id)
+                .ok_or(Error::ScheduledTransferNotFound)?;
+            if self.env().caller() != scheduled.from {
+                return Err(Error::NotTransferSender);
+            }
+            self.release_scheduled(id, &scheduled, scheduled.from, 0)?;
+            self.env().emit_event(ScheduledTransferCancelled { id });
+            Ok(())
+        }
+
+        /// Pays scheduled transfer `id` out to `to`, keeping back `fee` for the
+        /// fee policy.
+        fn release_scheduled(
+            &mut self,
+            id: ScheduledTransferId,
+            scheduled: &ScheduledTransfer,
+            to: AccountId,
+            fee: Balance,
+        ) -> Result<()> {
+            self.scheduled_transfers.remove(id);
+            self.escrowed -= scheduled.value;
+            let custody = self.env().account_id();
+            self.update(Some(custody), Some(to), scheduled.value - fee)?;
+            self.charge_fee(custody, fee)
+        }
+
+        #[ink(message, selector = 0x018946F2)]
+        pub fn inheritance(&self, holder: AccountId) -> Option<Inheritance> {
+            self.accounts.get(holder).and_then(|data| data.inheritance)
+        }
+
+        /// Opts in to the dead-man switch: `heir` may take the caller's whole
+        /// balance once the caller did not send a `heartbeat` for
+        /// `inactivity_period`. Setting it counts as a heartbeat.
+        #[ink(message, selector = 0xAB591AA5)]
+        pub fn set_heir(&mut self, heir: AccountId, inactivity_period: Timestamp) -> Result<()> {
+            self.ensure_valid_recipient(heir)?;
+            let holder = self.env().caller();
+            let mut data = self.accounts.get(holder).unwrap_or_default();
+            data.inheritance = Some(Inheritance {
+                heir,
+                inactivity_period,
+                last_heartbeat: self.env().block_timestamp(),
+            });
+            self.store_account(holder, data);
+            self.env().emit_event(HeirSet {
+                holder,
+                heir,
+                inactivity_period,
+            });
+            Ok(())
+        }
+
+        /// Shows the caller is still active, restarting its inactivity period.
+        #[ink(message, selector = 0xAC442764)]
+        pub fn heartbeat(&mut self) -> Result<()> {
+            let holder = self.env().caller();
+            let now = self.env().block_timestamp();
+            let mut data = self.accounts.get(holder).unwrap_or_default();
+            data.inheritance
+                .as_mut()
+                .ok_or(Error::NoInheritance)?
+                .last_heartbeat = now;
+            self.accounts.insert(holder, &data);
+            self.env().emit_event(Heartbeat { holder });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x7C887786)]
+        pub fn cancel_inheritance(&mut self) -> Result<()> {
+            let holder = self.env().caller();
+            let mut data = self.accounts.get(holder).unwrap_or_default();
+            data.inheritance.take().ok_or(Error::NoInheritance)?;
+            self.store_account(holder, data);
+            self.env().emit_event(InheritanceCancelled { holder });
+            Ok(())
+        }
+
+        /// Moves the whole balance of the inactive `holder` to the caller, its
+        /// heir, and ends the inheritance. Returns the amount inherited.
+        #[ink(message, selector = 0xAE4D34ED)]
+        pub fn claim_inheritance(&mut self, holder: AccountId) -> Result<Balance> {
+            let heir = self.env().caller();
+            let inheritance = self.inheritance(holder).ok_or(Error::NoInheritance)?;
+            if heir != inheritance.heir {
+                return Err(Error::NotHeir);
+            }
+            let inactive_since = inheritance
+                .last_heartbeat
+                .saturating_add(inheritance.inactivity_period);
+            if self.env().block_timestamp() < inactive_since {
+                return Err(Error::HolderStillActive);
+            }
+            if let Some(mut data) = self.accounts.get(holder) {
+                data.inheritance = None;
+                self.store_account(holder, data);
+            }
+            let amount = self.balance_of(holder);
+            self.update(Some(holder), Some(heir), amount)?;
+            self.env().emit_event(InheritanceClaimed {
+                holder,
+                heir,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        #[ink(message, selector = 0xC5BF4C67)]
+        pub fn roundup_of(&self, holder: AccountId) -> Option<RoundUp> {
+            self.round_ups.get(holder)
+        }
+
+        /// What round-ups of other accounts gave `charity` so far.
+        #[ink(message, selector = 0x352D6F57)]
+        pub fn roundup_received(&self, charity: AccountId) -> Balance {
+            self.round_ups_received.get(charity).unwrap_or(0)
+        }
+
+        /// Opts in to rounding every transfer the caller sends up to a
+        /// multiple of `unit`, giving the difference to `charity`. Replaces an
+        /// earlier setting and enables it, keeping the total donated.
+        #[ink(message, selector = 0x8F5E12AC)]
+        pub fn set_roundup(&mut self, charity: AccountId, unit: Balance) -> Result<()> {
+            let holder = self.env().caller();
+            self.ensure_valid_recipient(charity)?;
+            if charity == holder {
+                return Err(Error::InvalidRecipient);
+            }
+            if unit == 0 {
+                return Err(Error::InvalidAmount);
+            }
+            let donated = self
+                .roundup_of(holder)
+                .map_or(0, |round_up| round_up.donated);
+            self.round_ups.insert(
+                holder,
+                &RoundUp {
+                    charity,
+                    unit,
+                    enabled: true,
+                    donated,
+                },
+            );
+            self.env().emit_event(RoundUpSet {
+                holder,
+                charity,
+                unit,
+            });
+            Ok(())
+        }
+
+        /// Pauses or resumes the caller's round-up without forgetting it.
+        #[ink(message, selector = 0x2928BDA5)]
+        pub fn set_roundup_enabled(&mut self, enabled: bool) -> Result<()> {
+            let holder = self.env().caller();
+            let mut round_up = self.roundup_of(holder).ok_or(Error::NoRoundUp)?;
+            round_up.enabled = enabled;
+            self.round_ups.insert(holder, &round_up);
+            self.env().emit_event(RoundUpToggled { holder, enabled });
+            Ok(())
+        }
+
+        /// Gives `holder`'s charity what rounds `value` up to the next multiple
+        /// of its unit, or as much of it as `holder` has left.
+        fn round_up(&mut self, holder: AccountId, value: Balance) -> Result<()> {
+            let mut round_up = match self.roundup_of(holder) {
+                Some(round_up) if round_up.enabled => round_up,
+                _ => return Ok(()),
+            };
+            let remainder = value % round_up.unit;
+            if remainder == 0 {
+                return Ok(());
+            }
+            let amount = (round_up.unit - remainder).min(self.balance_of(holder));
+            if amount == 0 {
+                return Ok(());
+            }
+            let charity = round_up.charity;
+            self.update(Some(holder), Some(charity), amount)?;
+            round_up.donated = round_up.donated.saturating_add(amount);
+            self.round_ups.insert(holder, &round_up);
+            let received = self.roundup_received(charity).saturating_add(amount);
+            self.round_ups_received.insert(charity, &received);
+            self.env().emit_event(RoundedUp {
+                holder,
+                charity,
+                amount,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x86291FDB)]
+        pub fn sponsorship(&self) -> Option<Sponsorship> {
+            self.sponsorship.get().flatten()
+        }
+
+        /// Enables `sponsor_account` with the given terms or, with `None`,
+        /// disables it.
+        #[ink(message, selector = 0x645DCC8D)]
+        pub fn set_sponsorship(&mut self, sponsorship: Option<Sponsorship>) -> Result<()> {
+            self.ensure_owner()?;
+            let previous_sponsorship = self.sponsorship();
+            self.sponsorship.set(&sponsorship);
+            self.env().emit_event(SponsorshipUpdated {
+                previous_sponsorship,
+                sponsorship,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x093BB557)]
+        pub fn sponsor_of(&self, account: AccountId) -> Option<AccountId> {
+            self.sponsors.get(account)
+        }
+
+        #[ink(message, selector = 0x67415633)]
+        pub fn sponsored_count(&self, sponsor: AccountId) -> u32 {
+            self.sponsored_counts.get(sponsor).unwrap_or(0)
+        }
+
+        /// Onboards `new_account`: forwards the native currency sent along,
+        /// which should cover its existential deposit and first fees, and
+        /// moves the starter amount of tokens to it from the caller.
+        #[ink(message, payable, selector = 0x3C4307C1)]
+        pub fn sponsor_account(&mut self, new_account: AccountId) -> Result<()> {
+            let sponsor = self.env().caller();
+            let native = self.env().transferred_value();
+            let sponsorship = self.sponsorship().ok_or(Error::SponsorshipDisabled)?;
+            self.ensure_valid_recipient(new_account)?;
+            if new_account == sponsor
+                || self.sponsors.contains(new_account)
+                || self.balance_of(new_account) > 0
+            {
+                return Err(Error::AccountNotNew);
+            }
+            if self.sponsored_count(sponsor) >= sponsorship.max_accounts_per_sponsor {
+                return Err(Error::SponsorLimitReached);
+            }
+            let tokens = sponsorship.starter_amount;
+            if tokens > 0 {
+                self.update(Some(sponsor), Some(new_account), tokens)?;
+            }
+            self.sponsored_counts
+                .insert(sponsor, &(self.sponsored_count(sponsor) + 1));
+            self.sponsors.insert(new_account, &sponsor);
+            if native > 0 {
+                self.env()
+                    .transfer(new_account, native)
+                    .map_err(|_| Error::NativeTransferFailed)?;
+            }
+            self.env().emit_event(AccountSponsored {
+                sponsor,
+                account: new_account,
+                native,
+                tokens,
+            });
+            Ok(())
+        }
+
+        /// The savings pool with interest accrued up to the current block.
+        #[ink(message, selector = 0x3F83D645)]
+        pub fn savings_pool(&self) -> SavingsPool {
+            // Nothing was saved before the pool was first stored.
+            let mut pool = self.savings.get().unwrap_or(SavingsPool {
+                rate_per_block: 0,
+                index: SAVINGS_RATE_PRECISION,
+                last_accrual_block: self.env().block_number(),
+                scaled_total: 0,
+                reserve: 0,
+                held: 0,
+            });
+            let blocks = Balance::from(self.env().block_number() - pool.last_accrual_block);
+            if blocks == 0 || pool.rate_per_block == 0 || pool.scaled_total == 0 {
+                pool.last_accrual_block = self.env().block_number();
+                return pool;
+            }
+            let growth = pool
+                .index
+                .saturating_mul(pool.rate_per_block)
+                .saturating_mul(blocks)
+                / SAVINGS_RATE_PRECISION;
+            // Grow the index no further than the reserve can pay for.
+            let affordable =
+                pool.reserve.saturating_mul(SAVINGS_RATE_PRECISION) / pool.scaled_total;
+            let growth = growth.min(affordable);
+            let interest = pool.scaled_total.saturating_mul(growth) / SAVINGS_RATE_PRECISION;
+            pool.index += growth;
+            pool.reserve -= interest;
+            pool.last_accrual_block = self.env().block_number();
+            pool
+        }
+
+        /// `account`'s savings, interest included.
+        #[ink(message, selector = 0x1F75458A)]
+        pub fn savings_balance_of(&self, account: AccountId) -> Balance {
+            let scaled = self.savings_scaled.get(account).unwrap_or(0);
+            scaled.saturating_mul(self.savings_pool().index) / SAVINGS_RATE_PRECISION
+        }
+
+        /// Sets the interest savings earn per block, as a fraction of
+        /// `SAVINGS_RATE_PRECISION`. Interest up to now accrues at the old rate.
+        #[ink(message, selector = 0x51877E38)]
+        pub fn set_savings_rate(&mut self, rate_per_block: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let mut pool = self.savings_pool();
+            let previous_rate_per_block = pool.rate_per_block;
+            pool.rate_per_block = rate_per_block;
+            self.savings.set(&pool);
+            self.env().emit_event(SavingsRateSet {
+                previous_rate_per_block,
+                rate_per_block,
+            });
+            Ok(())
+        }
+
+        /// Moves `amount` of the caller's tokens into the reserve savings
+        /// interest is paid from. They can't be taken back.
+        #[ink(message, selector = 0xE7C5832B)]
+        pub fn fund_savings_reserve(&mut self, amount: Balance) -> Result<()> {
+            let from = self.env().caller();
+            let mut pool = self.savings_pool();
+            self.update(Some(from), Some(self.env().account_id()), amount)?;
+            pool.reserve += amount;
+            pool.held += amount;
+            self.savings.set(&pool);
+            self.env().emit_event(SavingsReserveFunded { from, amount });
+            Ok(())
+        }
+
+        /// Moves `amount` of the caller's tokens into savings.
+        #[ink(message, selector = 0x2467EB65)]
+        pub fn deposit_to_savings(&mut self, amount: Balance) -> Result<()> {
+            let account = self.env().caller();
+            let mut pool = self.savings_pool();
+            self.update(Some(account), Some(self.env().account_id()), amount)?;
+            // Rounding down credits the saver at most one unit less.
+            let scaled = amount
+                .checked_mul(SAVINGS_RATE_PRECISION)
+                .ok_or(Error::Overflow)?
+                / pool.index;
+            let saved = self.savings_scaled.get(account).unwrap_or(0);
+            self.savings_scaled.insert(account, &(saved + scaled));
+            pool.scaled_total += scaled;
+            pool.held += amount;
+            self.savings.set(&pool);
+            self.env().emit_event(SavingsDeposited { account, amount });
+            Ok(())
+        }
+
+        /// Moves `amount` of the caller's savings, interest included, back to
+        /// its balance.
+        #[ink(message, selector = 0xDFA62425)]
+        pub fn withdraw_from_savings(&mut self, amount: Balance) -> Result<()> {
+            let account = self.env().caller();
+            let mut pool = self.savings_pool();
+            let saved = self.savings_scaled.get(account).unwrap_or(0);
+            let available = saved.saturating_mul(pool.index) / SAVINGS_RATE_PRECISION;
+            if amount > available {
+                return Err(Error::InsufficientBalance {
+                    required: amount,
+                    available,
+                });
+            }
+            // Rounding up takes at most one unit more from the saver.
+            let scaled = amount
+                .saturating_mul(SAVINGS_RATE_PRECISION)
+                .div_ceil(pool.index)
+                .min(saved);
+            if saved == scaled {
+                self.savings_scaled.remove(account);
+            } else {
+                self.savings_scaled.insert(account, &(saved - scaled));
+            }
+            pool.scaled_total -= scaled;
+            pool.held -= amount;
+            self.savings.set(&pool);
+            self.update(Some(self.env().account_id()), Some(account), amount)?;
+            self.env().emit_event(SavingsWithdrawn { account, amount });
+            Ok(())
+        }
+
+        fn ensure_not_paused(&self, features: u32) -> Result<()> {
+            if self.is_paused(features) {
+                return Err(Error::Paused);
+            }
+            Ok(())
+        }
+
+        /// Pays the native currency sent along out to the holders, pro rata to
+        /// their balances at the end of the previous block. Anyone can
+        /// distribute, e.g. a contract forwarding fees. Tokens the contract
+        /// holds itself count towards the supply, so their share is never
+        /// paid out.
+        #[ink(message, payable, selector = 0x4ED1E925)]
+        pub fn distribute_native(&mut self) -> Result<DistributionId> {
+            let amount = self.env().transferred_value();
+            let snapshot_block = self.env().block_number().saturating_sub(1);
+            let total_supply = self.total_supply_at(snapshot_block);
+            if amount == 0 || total_supply == 0 {
+                return Err(Error::NothingToDistribute);
+            }
+            let id = self.native_distribution_count;
+            self.native_distributions.insert(
+                id,
+                &NativeDistribution {
+                    snapshot_block,
+                    amount,
+                    total_supply,
+                },
+            );
+            self.native_distribution_count += 1;
+            self.native_dividends_unclaimed += amount;
+            self.env().emit_event(NativeDistributed {
+                id,
+                from: self.env().caller(),
+                amount,
+                snapshot_block,
+            });
+            Ok(id)
+        }
+
+        #[ink(message, selector = 0xAEFAEC41)]
+        pub fn native_distribution(&self, id: DistributionId) -> Option<NativeDistribution> {
+            self.native_distributions.get(id)
+        }
+
+        /// What `holder` can claim of distribution `id`. The share is lost once
+        /// the claim period is over, or if `holder`'s balance history no
+        /// longer reaches back to the snapshot.
+        #[ink(message, selector = 0x397DAA3F)]
+        pub fn native_dividend(&self, id: DistributionId, holder: AccountId) -> Result<Balance> {
+            let distribution = self
+                .native_distributions
+                .get(id)
+                .ok_or(Error::DistributionNotFound)?;
+            if self.env().block_number() > native_dividend_deadline(&distribution) {
+                return Err(Error::ClaimPeriodOver);
+            }
+            if self.native_dividends_claimed.contains((id, holder)) {
+                return Ok(0);
+            }
+            let balance = self
+                .balance_at(holder, distribution.snapshot_block)
+                .ok_or(Error::SnapshotUnavailable)?;
+            // Split so that large amounts can't overflow.
+            let (amount, supply) = (distribution.amount, distribution.total_supply);
+            Ok(amount / supply * balance + amount % supply * balance / supply)
+        }
+
+        /// Sends the caller its share of distribution `id`.
+        #[ink(message, selector = 0x68A4CF45)]
+        pub fn claim_native_dividend(&mut self, id: DistributionId) -> Result<Balance> {
+            let holder = self.env().caller();
+            if self.native_dividends_claimed.contains((id, holder)) {
+                return Err(Error::AlreadyClaimed);
+            }
+            let amount = self.native_dividend(id, holder)?;
+            self.native_dividends_claimed.insert((id, holder), &());
+            self.native_dividends_unclaimed -= amount;
+            let paid = self.native_dividends_paid(id) + amount;
+            self.native_dividends_paid.insert(id, &paid);
+            self.env()
+                .transfer(holder, amount)
+                .map_err(|_| Error::NativeTransferFailed)?;
+            self.env()
+                .emit_event(NativeDividendClaimed { id, holder, amount });
+            Ok(amount)
+        }
+
+        #[ink(message, selector = 0x80F3ED84)]
+        pub fn native_dividends_paid(&self, id: DistributionId) -> Balance {
+            self.native_dividends_paid.get(id).unwrap_or(0)
+        }
+
+        /// Sends what is left of distribution `id` after its claim period to
+        /// `to`: shares nobody claimed, shares whose snapshot balance rolled out
+        /// of the balance history, and rounding remainders. Returns the amount.
+        #[ink(message, selector = 0xF942811B)]
+        pub fn reclaim_native_dividends(
+            &mut self,
+            id: DistributionId,
+            to: AccountId,
+        ) -> Result<Balance> {
+            self.ensure_owner()?;
+            let distribution = self
+                .native_distributions
+                .get(id)
+                .ok_or(Error::DistributionNotFound)?;
+            if self.env().block_number() <= native_dividend_deadline(&distribution) {
+                return Err(Error::ClaimPeriodNotOver);
+            }
+            let paid = self.native_dividends_paid(id);
+            if id < self.reclaimable_distributions_from.get_or_default()
+                || paid >= distribution.amount
+            {
+                return Err(Error::NothingToReclaim);
+            }
+            let amount = distribution.amount - paid;
+            self.native_dividends_paid.insert(id, &distribution.amount);
+            self.native_dividends_unclaimed -= amount;
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| Error::NativeTransferFailed)?;
+            self.env()
+                .emit_event(NativeDividendsReclaimed { id, to, amount });
+            Ok(amount)
+        }
+
+        /// `account`'s balance at the end of `block`, if the balance history
+        /// still goes back that far.
+        fn balance_at(&self, account: AccountId, block: BlockNumber) -> Option<Balance> {
+            let ring = match self.balance_checkpoint_rings.get(account) {
+                Some(ring) => ring,
+                None => return self.legacy_balance_at(account, block),
+            };
+            // Binary search for the last checkpoint at or before `block`.
+            let (mut low, mut high) = (0, ring.len);
+            while low < high {
+                let middle = low + (high - low) / 2;
+                match self.ring_checkpoint(account, ring, middle) {
+                    Some(checkpoint) if checkpoint.block <= block => low = middle + 1,
+                    _ => high = middle,
+                }
+            }
+            match low.checked_sub(1) {
+                Some(index) => self
+                    .ring_checkpoint(account, ring, index)
+                    .map(|checkpoint| checkpoint.value),
+                // Before the first checkpoint the balance was zero, unless
+                // older checkpoints were overwritten.
+                None if ring.len < MAX_BALANCE_CHECKPOINTS => Some(0),
+                None => None,
+            }
+        }
+
+        /// `balance_at` for a history `migrate` hasn't converted yet.
+        fn legacy_balance_at(&self, account: AccountId, block: BlockNumber) -> Option<Balance> {
+            let checkpoints = self.balance_checkpoints.get(account).unwrap_or_default();
+            match checkpoints
+                .iter()
+                .rev()
+                .find(|checkpoint| checkpoint.block <= block)
+            {
+                Some(checkpoint) => Some(checkpoint.value),
+                None if checkpoints.len() < MAX_BALANCE_CHECKPOINTS as usize => Some(0),
+                None => None,
+            }
+        }
+
+        #[ink(message, selector = 0xBEA08331)]
+        pub fn emission_schedule(&self) -> Option<EmissionSchedule> {
+            self.emission_schedule.clone()
+        }
+
+        /// Replaces the emission schedule, or with `None` ends emission. What
+        /// the old schedule accrued until now is minted first.
+        #[ink(message, selector = 0x0CF53459)]
+        pub fn set_emission_schedule(&mut self, schedule: Option<EmissionSchedule>) -> Result<()> {
+            self.ensure_owner()?;
+            if let Some(schedule) = &schedule {
+                if schedule.era_length == 0 {
+                    return Err(Error::InvalidPeriod);
+                }
+                self.ensure_valid_recipient(schedule.distributor)?;
+            }
+            if self.emission_schedule.is_some() {
+                self.mint_scheduled()?;
+            }
+            let previous_schedule = self.emission_schedule.clone();
+            self.emission_schedule = schedule.clone();
+            self.emitted = 0;
+            self.env().emit_event(EmissionScheduleUpdated {
+                previous_schedule,
+                schedule,
+            });
+            Ok(())
+        }
+
+        /// What `mint_scheduled` would mint now, not taking the cap into
+        /// account.
+        #[ink(message, selector = 0x0D6800D1)]
+        pub fn mintable_scheduled(&self) -> Balance {
+            self.emission_schedule.as_ref().map_or(0, |schedule| {
+                schedule
+                    .emitted_by(self.env().block_timestamp())
+                    .saturating_sub(self.emitted)
+            })
+        }
+
+        /// Mints what the emission schedule accrued since the last call to its
+        /// distributor and returns the amount. Anyone may call it: the amount
+        /// only depends on the block timestamp. Emission above the cap stays
+        /// accrued until the cap allows it.
+        #[ink(message, selector = 0xDCAFBB20)]
+        pub fn mint_scheduled(&mut self) -> Result<Balance> {
+            let distributor = self
+                .emission_schedule
+                .as_ref()
+                .ok_or(Error::NoEmissionSchedule)?
+                .distributor;
+            let headroom = self.cap.map_or(Balance::MAX, |cap| cap - self.total_supply);
+            let amount = core::cmp::min(self.mintable_scheduled(), headroom);
+            if amount > 0 {
+                self.update(None, Some(distributor), amount)?;
+                self.emitted += amount;
+            }
+            Ok(amount)
+        }
+    }
+
+    /// `bps` basis points of `amount`, rounded down.
+    fn bps_of(amount: Balance, bps: u16) -> Balance {
+        // Split so that large amounts can't overflow.
+        amount / 10_000 * Balance::from(bps) + amount % 10_000 * Balance::from(bps) / 10_000
+    }
+
+    /// Decodes all of `input` as a `T`.
+    fn decode_input<T: scale::Decode>(mut input: &[u8]) -> Result<T> {
+        let value = T::decode(&mut input).map_err(|_| Error::InvalidCallInput)?;
+        if !input.is_empty() {
+            return Err(Error::InvalidCallInput);
+        }
+        Ok(value)
+    }
+
+    /// Helpers for off-chain tests of this contract and of contracts built on
+    /// top of it.
+    #[cfg(feature = "std")]
+    pub mod test_utils {
+        use super::*;
+
+        pub type Accounts = ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment>;
+
+        type Event = <Erc20 as ink::reflect::ContractEventBase>::Type;
+
+        pub fn default_accounts() -> Accounts {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        /// Makes `caller` the caller of every following message.
+        pub fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        pub fn advance_block() {
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        }
+
+        pub fn block_number() -> BlockNumber {
+            ink::env::block_number::<ink::env::DefaultEnvironment>()
+        }
+
+        /// The account the contract runs under, distinct from the default
+        /// accounts.
+        pub fn contract_id() -> AccountId {
+            AccountId::from([0xff; 32])
+        }
+
+        /// Instantiates the contract with Alice as the caller, so she owns it
+        /// and holds the whole initial supply.
+        pub fn instantiate(total_supply: Balance) -> Erc20 {
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract_id());
+            set_caller(default_accounts().alice);
+            Erc20::new(total_supply)
+        }
+
+        /// Mints `amount` to `account` without emitting an event, so the
+        /// events a test asserts on are only the ones it caused.
+        pub fn seed_balance(erc20: &mut Erc20, account: AccountId, amount: Balance) {
+            let balance = erc20.balance_of(account) + amount;
+            erc20.set_balance(account, balance);
+            erc20.total_supply += amount;
+            erc20.checkpoint_total_supply();
+        }
+
+        /// Number of events emitted so far.
+        pub fn emitted_event_count() -> usize {
+            ink::env::test::recorded_events().count()
+        }
+
+        /// Asserts that the event emitted at `index` is a `Transfer` with the
+        /// given fields.
+        pub fn assert_transfer_event(
+            index: usize,
+            expected_from: Option<AccountId>,
+            expected_to: Option<AccountId>,
+            expected_value: Balance,
+        ) {
+            let event = ink::env::test::recorded_events()
+                .nth(index)
+                .expect("No event at this index");
+            let decoded_event = <Event as scale::Decode>::decode(&mut &event.data[..])
+                .expect("Encountered invalid contract event data buffer");
+            match decoded_event {
+                Event::Transfer(Transfer {
+                    from, to, value, ..
+                }) => {
+                    assert_eq!(from, expected_from, "Unexpected Transfer.from");
+                    assert_eq!(to, expected_to, "Unexpected Transfer.to");
+                    assert_eq!(value, expected_value, "Unexpected Transfer.value");
+                }
+                _ => panic!("Expected a Transfer event at index {}", index),
+            }
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        use super::test_utils::*;
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        #[ink::test]
+        fn create_contract_works() {
+            let erc20 = instantiate(1000);
+            assert_eq!(1000, erc20.total_supply());
+        }
+
+        #[ink::test]
+        fn get_good_balance() {
+            let accounts = default_accounts();
+            let erc20 = instantiate(1000);
+            assert_eq!(erc20.balance_of(accounts.alice), 1000);
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let to = accounts.bob;
+            assert_eq!(erc20.transfer(to, 100), Ok(()));
+            assert_eq!(erc20.balance_of(to), 100);
+            assert_eq!(erc20.balance_of(owner), 900);
+            assert_eq!(emitted_event_count(), 1);
+            assert_transfer_event(0, Some(owner), Some(to), 100);
+        }
+
+        #[ink::test]
+        fn transfer_failed_for_lower_balance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(100);
+            let to = accounts.bob;
+            assert_eq!(
+                erc20.transfer(to, 200),
+                Err(Error::InsufficientBalance {
+                    required: 200,
+                    available: 100
+                })
+            );
+            assert_eq!(emitted_event_count(), 0);
+        }
+
+        #[ink::test]
+        fn transfer_from_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let to = accounts.bob;
+            seed_balance(&mut erc20, to, 200);
+            set_caller(to);
+            erc20.approve(owner, 100).unwrap();
+            set_caller(owner);
+            assert_eq!(erc20.transfer_from(to, owner, 100), Ok(()));
+            assert_eq!(erc20.allowance(to, owner), 0);
+            assert_eq!(erc20.balance_of(to), 100);
+            assert_eq!(erc20.balance_of(owner), 1100);
+            assert_eq!(emitted_event_count(), 2);
+            assert_transfer_event(1, Some(to), Some(owner), 100);
+        }
+
+        #[ink::test]
+        fn transfer_from_failed_for_lower_balance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let to = accounts.bob;
+            seed_balance(&mut erc20, to, 100);
+            set_caller(to);
+            erc20.approve(owner, 200).unwrap();
+            set_caller(owner);
+            assert_eq!(
+                erc20.transfer_from(to, owner, 200),
+                Err(Error::InsufficientBalance {
+                    required: 200,
+                    available: 100
+                })
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_failed_for_insufficient_allowance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let spender = accounts.bob;
+            erc20.approve(spender, 50).unwrap();
+            set_caller(spender);
+            assert_eq!(
+                erc20.transfer_from(owner, spender, 100),
+                Err(Error::InsufficientAllowance {
+                    required: 100,
+                    available: 50
+                })
+            );
+            assert_eq!(erc20.balance_of(owner), 1000);
+        }
+
+        #[ink::test]
+        fn approve_with_expiry_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let spender = accounts.bob;
+            let now = ink::env::block_timestamp::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.approve_with_expiry(spender, 200, now), Ok(()));
+            assert_eq!(erc20.allowance(owner, spender), 200);
+            assert_eq!(erc20.allowance_expiry(owner, spender), Some(now));
+            set_caller(spender);
+            assert_eq!(erc20.transfer_from(owner, spender, 100), Ok(()));
+            assert_eq!(erc20.allowance(owner, spender), 100);
+        }
+
+        #[ink::test]
+        fn transfer_from_failed_for_expired_allowance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let spender = accounts.bob;
+            let now = ink::env::block_timestamp::<ink::env::DefaultEnvironment>();
+            erc20.approve_with_expiry(spender, 200, now).unwrap();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.allowance(owner, spender), 0);
+            set_caller(spender);
+            assert_eq!(
+                erc20.transfer_from(owner, spender, 100),
+                Err(Error::AllowanceExpired)
+            );
+            assert_eq!(erc20.balance_of(spender), 0);
+        }
+
+        #[ink::test]
+        fn recurring_allowance_is_capped_per_period() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let spender = accounts.bob;
+            assert_eq!(erc20.approve_recurring(spender, 100, 1_000_000), Ok(()));
+            set_caller(spender);
+            assert_eq!(erc20.transfer_from(owner, spender, 60), Ok(()));
+            assert_eq!(erc20.transfer_from(owner, spender, 40), Ok(()));
+            assert_eq!(
+                erc20.transfer_from(owner, spender, 1),
+                Err(Error::InsufficientAllowance {
+                    required: 1,
+                    available: 0
+                })
+            );
+            assert_eq!(
+                erc20
+                    .recurring_allowance(owner, spender)
+                    .map(|grant| grant.spent_in_period),
+                Some(100)
+            );
+            assert_eq!(erc20.balance_of(spender), 100);
+        }
+
+        #[ink::test]
+        fn recurring_allowance_refills_next_period() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let spender = accounts.bob;
+            erc20.approve_recurring(spender, 100, 1).unwrap();
+            set_caller(spender);
+            assert_eq!(erc20.transfer_from(owner, spender, 100), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.transfer_from(owner, spender, 100), Ok(()));
+            assert_eq!(erc20.balance_of(spender), 200);
+        }
+
+        #[ink::test]
+        fn approve_recurring_rejects_empty_period() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(
+                erc20.approve_recurring(accounts.bob, 100, 0),
+                Err(Error::InvalidPeriod)
+            );
+        }
+
+        #[ink::test]
+        fn approval_for_all_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let operator = accounts.bob;
+            assert_eq!(erc20.set_approval_for_all(operator, true), Ok(()));
+            assert!(erc20.is_approved_for_all(owner, operator));
+            set_caller(operator);
+            assert_eq!(erc20.transfer_from(owner, operator, 300), Ok(()));
+            assert_eq!(erc20.transfer_from(owner, operator, 300), Ok(()));
+            assert_eq!(erc20.balance_of(operator), 600);
+            set_caller(owner);
+            assert_eq!(erc20.set_approval_for_all(operator, false), Ok(()));
+            set_caller(operator);
+            assert_eq!(
+                erc20.transfer_from(owner, operator, 100),
+                Err(Error::InsufficientAllowance {
+                    required: 100,
+                    available: 0
+                })
+            );
+        }
+
+        #[ink::test]
+        fn active_approvals_track_grants() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            erc20.approve(accounts.bob, 100).unwrap();
+            erc20.approve_recurring(accounts.bob, 10, 1).unwrap();
+            erc20.set_approval_for_all(accounts.charlie, true).unwrap();
+            erc20.approve(accounts.django, 50).unwrap();
+            let spenders = |erc20: &Erc20| {
+                erc20
+                    .active_approvals(owner, 0)
+                    .into_iter()
+                    .map(|grants| grants.spender)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                spenders(&erc20),
+                vec![accounts.bob, accounts.charlie, accounts.django]
+            );
+            let bob = &erc20.active_approvals(owner, 0)[0];
+            assert_eq!(bob.allowance.as_ref().map(|grant| grant.amount), Some(100));
+            assert!(bob.recurring.is_some() && !bob.operator);
+            // Bob keeps his recurring allowance, charlie loses his only grant.
+            erc20.approve(accounts.bob, 0).unwrap();
+            erc20.set_approval_for_all(accounts.charlie, false).unwrap();
+            assert_eq!(spenders(&erc20), vec![accounts.bob, accounts.django]);
+            // Spending the whole allowance ends the grant too.
+            set_caller(accounts.django);
+            erc20.transfer_from(owner, accounts.django, 50).unwrap();
+            assert_eq!(spenders(&erc20), vec![accounts.bob]);
+            assert_eq!(erc20.active_approvals(owner, 1), vec![]);
+        }
+
+        #[ink::test]
+        fn revoke_all_allowances_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            erc20.approve(accounts.bob, 100).unwrap();
+            erc20.approve_recurring(accounts.charlie, 10, 1).unwrap();
+            erc20.set_approval_for_all(accounts.django, true).unwrap();
+            assert_eq!(erc20.revoke_all_allowances(), Ok(3));
+            assert_eq!(erc20.allowance(owner, accounts.bob), 0);
+            assert_eq!(erc20.recurring_allowance(owner, accounts.charlie), None);
+            assert!(!erc20.is_approved_for_all(owner, accounts.django));
+            assert_eq!(erc20.active_approvals(owner, 0), vec![]);
+            assert_eq!(erc20.revoke_all_allowances(), Ok(0));
+            // New grants are tracked from scratch.
+            erc20.approve(accounts.eve, 1).unwrap();
+            assert_eq!(erc20.active_approvals(owner, 0).len(), 1);
+        }
+
+        #[ink::test]
+        fn psp22_messages_forward() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.psp22_total_supply(), 1000);
+            assert_eq!(erc20.psp22_transfer(accounts.bob, 100, Vec::new()), Ok(()));
+            assert_eq!(erc20.psp22_balance_of(accounts.bob), 100);
+            assert_eq!(erc20.psp22_approve(accounts.charlie, 50), Ok(()));
+            assert_eq!(erc20.psp22_allowance(accounts.alice, accounts.charlie), 50);
+            set_caller(accounts.charlie);
+            assert_eq!(
+                erc20.psp22_transfer_from(accounts.alice, accounts.django, 50, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(accounts.django), 50);
+            assert_eq!(
+                erc20.psp22_transfer_from(accounts.alice, accounts.django, 1, Vec::new()),
+                Err(psp22::PSP22Error::InsufficientAllowance)
+            );
+        }
+
+        #[ink::test]
+        fn deprecated_aliases_forward() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let spender = accounts.bob;
+            assert_eq!(erc20.transer(spender, 100), Ok(()));
+            assert_eq!(erc20.balance_of(spender), 100);
+            erc20.approve(spender, 50).unwrap();
+            set_caller(spender);
+            assert_eq!(erc20.transer_from(owner, 50), Ok(()));
+            assert_eq!(erc20.balance_of(spender), 150);
+            assert_eq!(erc20.balance_of(owner), 850);
+            assert_transfer_event(0, Some(owner), Some(spender), 100);
+            assert_transfer_event(2, Some(owner), Some(spender), 50);
+        }
+
+        #[ink::test]
+        fn issue_failed_for_overflow() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(Balance::MAX);
+            assert_eq!(erc20.issue(accounts.bob, 1), Err(Error::Overflow));
+            assert_eq!(erc20.total_supply(), Balance::MAX);
+        }
+
+        #[ink::test]
+        fn zero_address_is_rejected() {
+            let mut erc20 = instantiate(1000);
+            assert_eq!(
+                erc20.approve(AccountId::from([0x0; 32]), 100),
+                Err(Error::ZeroAddressNotAllowed)
+            );
+            assert_eq!(
+                erc20.transfer_ownership(AccountId::from([0x0; 32])),
+                Err(Error::ZeroAddressNotAllowed)
+            );
+        }
+
+        #[ink::test]
+        fn errors_convert_to_psp22() {
+            assert_eq!(
+                psp22::PSP22Error::from(Error::InsufficientBalance {
+                    required: 2,
+                    available: 1
+                }),
+                psp22::PSP22Error::InsufficientBalance
+            );
+            assert_eq!(
+                psp22::PSP22Error::from(Error::AllowanceExpired),
+                psp22::PSP22Error::InsufficientAllowance
+            );
+            assert_eq!(
+                psp22::PSP22Error::from(Error::NotOwner),
+                psp22::PSP22Error::Custom("NotOwner".into())
+            );
+        }
+
+        #[ink::test]
+        fn invalid_recipients_are_rejected() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let zero = AccountId::from([0x0; 32]);
+            let contract = contract_id();
+            for to in [zero, contract].iter().copied() {
+                assert_eq!(erc20.transfer(to, 100), Err(Error::InvalidRecipient));
+                assert_eq!(erc20.issue(to, 100), Err(Error::InvalidRecipient));
+            }
+            erc20.approve(accounts.bob, 100).unwrap();
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, zero, 100),
+                Err(Error::InvalidRecipient)
+            );
+            assert_eq!(erc20.balance_of(accounts.alice), 1000);
+        }
+
+        #[ink::test]
+        fn transfer_to_self_can_be_allowed() {
+            let mut erc20 = instantiate(1000);
+            let contract = contract_id();
+            assert_eq!(erc20.set_transfer_to_self_allowed(true), Ok(()));
+            assert_eq!(erc20.transfer(contract, 100), Ok(()));
+            assert_eq!(erc20.balance_of(contract), 100);
+            assert_eq!(
+                erc20.transfer(AccountId::from([0x0; 32]), 100),
+                Err(Error::InvalidRecipient)
+            );
+        }
+
+        #[ink::test]
+        fn flash_fee_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.flash_fee(1_000_000), 0);
+            assert_eq!(erc20.set_flash_fee(9, Some(accounts.bob)), Ok(()));
+            assert_eq!(erc20.flash_fee(1_000_000), 900);
+            assert_eq!(erc20.flash_fee(Balance::MAX), Balance::MAX / 10_000 * 9 + 1);
+            assert_eq!(erc20.flash_fee_receiver(), Some(accounts.bob));
+            assert_eq!(erc20.set_flash_fee(10_001, None), Err(Error::InvalidFee));
+        }
+
+        #[ink::test]
+        fn set_flash_fee_failed_for_non_owner() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.set_flash_fee(9, None), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn flash_loan_failed_for_invalid_receiver() {
+            let mut erc20 = instantiate(1000);
+            assert_eq!(
+                erc20.flash_loan(AccountId::from([0x0; 32]), 100, Vec::new()),
+                Err(Error::InvalidRecipient)
+            );
+            assert_eq!(erc20.total_supply(), 1000);
+        }
+
+        #[ink::test]
+        fn mint_needs_minter_allowance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.mint(accounts.bob, 100), Err(Error::NotMinter));
+            assert_eq!(erc20.set_minter_allowance(accounts.charlie, 150), Ok(()));
+            assert!(erc20.is_minter(accounts.charlie));
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.mint(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+            assert_eq!(erc20.total_supply(), 1100);
+            assert_eq!(erc20.minter_allowance(accounts.charlie), 50);
+            assert_eq!(
+                erc20.mint(accounts.bob, 51),
+                Err(Error::MinterAllowanceExceeded)
+            );
+            assert_eq!(
+                erc20.set_minter_allowance(accounts.charlie, 0),
+                Err(Error::NotOwner)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.set_minter_allowance(accounts.charlie, 0), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.mint(accounts.bob, 50), Err(Error::NotMinter));
+        }
+
+        #[ink::test]
+        fn issue_batch_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let batch = vec![(accounts.bob, 100), (accounts.charlie, 200)];
+            assert_eq!(erc20.issue_batch(batch.clone()), Err(Error::NotMinter));
+            erc20.set_minter_allowance(accounts.alice, 299).unwrap();
+            assert_eq!(
+                erc20.issue_batch(batch.clone()),
+                Err(Error::MinterAllowanceExceeded)
+            );
+            erc20.set_minter_allowance(accounts.alice, 300).unwrap();
+            assert_eq!(erc20.issue_batch(batch), Ok(()));
+            assert_eq!(erc20.minter_allowance(accounts.alice), 0);
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+            assert_eq!(erc20.balance_of(accounts.charlie), 200);
+            assert_eq!(erc20.total_supply(), 1300);
+        }
+
+        #[ink::test]
+        fn issue_batch_respects_cap() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.set_minter_allowance(accounts.alice, 1000).unwrap();
+            assert_eq!(erc20.set_cap(Some(999)), Err(Error::CapExceeded));
+            assert_eq!(erc20.set_cap(Some(1250)), Ok(()));
+            assert_eq!(
+                erc20.issue_batch(vec![(accounts.bob, 200), (accounts.charlie, 100)]),
+                Err(Error::CapExceeded)
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(erc20.issue(accounts.bob, 251), Err(Error::CapExceeded));
+            assert_eq!(erc20.issue(accounts.bob, 250), Ok(()));
+            assert_eq!(erc20.cap(), Some(1250));
+        }
+
+        #[ink::test]
+        fn config_reflects_admin_settings() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let config = erc20.config();
+            assert_eq!(config.owner, accounts.alice);
+            assert_eq!(config.cap, None);
+            assert_eq!(config.paused, 0);
+            erc20.set_cap(Some(5000)).unwrap();
+            erc20.set_paused(PAUSE_MINTS, true).unwrap();
+            erc20.set_flash_fee(9, Some(accounts.eve)).unwrap();
+            erc20.set_rules_registry(Some(accounts.django)).unwrap();
+            erc20.set_guardian(Some(accounts.frank), 20).unwrap();
+            let config = erc20.config();
+            assert_eq!(config.cap, Some(5000));
+            assert_eq!(config.paused, PAUSE_MINTS);
+            assert_eq!(config.flash_fee_bps, 9);
+            assert_eq!(config.flash_fee_receiver, Some(accounts.eve));
+            assert_eq!(config.rules_registry, Some(accounts.django));
+            assert_eq!(config.guardian, Some(accounts.frank));
+            assert_eq!(config.guardian_pause_blocks, 20);
+        }
+
+        #[ink::test]
+        fn admin_events_carry_previous_values() {
+            type Event = <Erc20 as ink::reflect::ContractEventBase>::Type;
+            let last_event = || {
+                let event = ink::env::test::recorded_events().last().unwrap();
+                <Event as scale::Decode>::decode(&mut &event.data[..]).unwrap()
+            };
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.set_cap(Some(5000)).unwrap();
+            erc20.set_cap(None).unwrap();
+            assert!(matches!(
+                last_event(),
+                Event::CapUpdated(CapUpdated {
+                    previous_cap: Some(5000),
+                    cap: None,
+                })
+            ));
+            erc20.set_minter_allowance(accounts.bob, 100).unwrap();
+            erc20.set_minter_allowance(accounts.bob, 40).unwrap();
+            assert!(matches!(
+                last_event(),
+                Event::MinterAllowanceUpdated(MinterAllowanceUpdated {
+                    previous_amount: 100,
+                    amount: 40,
+                    ..
+                })
+            ));
+            erc20.set_paused(PAUSE_MINTS, true).unwrap();
+            erc20.set_paused(PAUSE_TRANSFERS, true).unwrap();
+            assert!(matches!(
+                last_event(),
+                Event::PauseUpdated(PauseUpdated {
+                    previous_paused: PAUSE_MINTS,
+                    paused,
+                }) if paused == PAUSE_MINTS | PAUSE_TRANSFERS
+            ));
+            erc20.set_rules_registry(Some(accounts.django)).unwrap();
+            erc20.set_rules_registry(None).unwrap();
+            assert!(matches!(
+                last_event(),
+                Event::RulesRegistryUpdated(RulesRegistryUpdated {
+                    previous_registry: Some(registry),
+                    registry: None,
+                }) if registry == accounts.django
+            ));
+        }
+
+        #[ink::test]
+        fn emission_halves_every_interval() {
+            let schedule = EmissionSchedule {
+                distributor: AccountId::from([0x7; 32]),
+                start: 100,
+                era_length: 10,
+                emission_per_era: 100,
+                halving_interval: 2,
+            };
+            assert_eq!(schedule.emitted_by(100), 0);
+            assert_eq!(schedule.emitted_by(105), 50);
+            assert_eq!(schedule.emitted_by(120), 200);
+            assert_eq!(schedule.emitted_by(130), 250);
+            assert_eq!(schedule.emitted_by(145), 312);
+            assert_eq!(schedule.emitted_by(Timestamp::MAX), 394);
+            let constant = EmissionSchedule {
+                halving_interval: 0,
+                ..schedule
+            };
+            assert_eq!(constant.emitted_by(1100), 10_000);
+        }
+
+        #[ink::test]
+        fn mint_scheduled_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.mint_scheduled(), Err(Error::NoEmissionSchedule));
+            let start = ink::env::block_timestamp::<ink::env::DefaultEnvironment>();
+            let schedule = EmissionSchedule {
+                distributor: accounts.charlie,
+                start,
+                era_length: 1,
+                emission_per_era: 10,
+                halving_interval: 0,
+            };
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.set_emission_schedule(Some(schedule.clone())),
+                Err(Error::NotOwner)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.set_emission_schedule(Some(schedule)), Ok(()));
+            assert_eq!(erc20.mint_scheduled(), Ok(0));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let now = ink::env::block_timestamp::<ink::env::DefaultEnvironment>();
+            let accrued = 10 * (now - start) as Balance;
+            assert_eq!(erc20.mintable_scheduled(), accrued);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.mint_scheduled(), Ok(accrued));
+            assert_eq!(erc20.mint_scheduled(), Ok(0));
+            assert_eq!(erc20.balance_of(accounts.charlie), accrued);
+            assert_eq!(erc20.total_supply(), 1000 + accrued);
+        }
+
+        #[ink::test]
+        fn emptied_balances_are_removed() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.transfer(accounts.bob, 0), Ok(()));
+            assert!(!erc20.accounts.contains(accounts.bob));
+            assert_eq!(erc20.transfer(accounts.bob, 1000), Ok(()));
+            assert!(!erc20.accounts.contains(accounts.alice));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.burn(1000), Ok(()));
+            assert!(!erc20.accounts.contains(accounts.bob));
+            assert_eq!(erc20.total_supply(), 0);
+        }
+
+        #[ink::test]
+        fn used_up_allowances_are_removed() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.approve(accounts.bob, 100).unwrap();
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 60),
+                Ok(())
+            );
+            assert!(erc20.allowance.contains((accounts.alice, accounts.bob)));
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 40),
+                Ok(())
+            );
+            assert!(!erc20.allowance.contains((accounts.alice, accounts.bob)));
+            set_caller(accounts.alice);
+            erc20.approve(accounts.charlie, 100).unwrap();
+            erc20.approve(accounts.charlie, 0).unwrap();
+            assert!(!erc20.allowance.contains((accounts.alice, accounts.charlie)));
+        }
+
+        #[ink::test]
+        fn mint_and_burn_emit_transfer_events() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.issue(accounts.bob, 100), Ok(()));
+            assert_transfer_event(0, None, Some(accounts.bob), 100);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.burn(40), Ok(()));
+            assert_transfer_event(1, Some(accounts.bob), None, 40);
+            assert_eq!(emitted_event_count(), 2);
+        }
+
+        #[ink::test]
+        fn paused_features_are_rejected() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.set_paused(PAUSE_MINTS, true), Err(Error::NotOwner));
+            set_caller(accounts.alice);
+            assert_eq!(erc20.set_paused(PAUSE_MINTS, true), Ok(()));
+            assert!(erc20.is_paused(PAUSE_MINTS));
+            assert!(!erc20.is_paused(PAUSE_TRANSFERS | PAUSE_BURNS));
+            assert_eq!(erc20.issue(accounts.bob, 100), Err(Error::Paused));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.burn(100), Ok(()));
+            assert_eq!(erc20.set_paused(PAUSE_ALL, true), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Err(Error::Paused));
+            assert_eq!(
+                erc20.transfer_and_call(accounts.bob, 100, Vec::new()),
+                Err(Error::Paused)
+            );
+            assert_eq!(erc20.set_paused(PAUSE_ALL, false), Ok(()));
+            assert_eq!(erc20.paused_features(), 0);
+            assert_eq!(erc20.issue(accounts.bob, 100), Ok(()));
+        }
+
+        #[ink::test]
+        fn guardian_pause_expires() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.pause_as_guardian(PAUSE_ALL), Err(Error::NotGuardian));
+            assert_eq!(erc20.set_guardian(Some(accounts.charlie), 1), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.pause_as_guardian(PAUSE_TRANSFERS), Ok(()));
+            set_caller(accounts.alice);
+            assert_eq!(erc20.transfer(accounts.bob, 100), Err(Error::Paused));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.guardian_pause(), None);
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.ratify_guardian_pause(), Err(Error::NoGuardianPause));
+        }
+
+        #[ink::test]
+        fn ratified_guardian_pause_lasts() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.set_guardian(Some(accounts.charlie), 1).unwrap();
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.pause_as_guardian(PAUSE_TRANSFERS), Ok(()));
+            assert_eq!(erc20.ratify_guardian_pause(), Err(Error::NotOwner));
+            set_caller(accounts.alice);
+            assert_eq!(erc20.ratify_guardian_pause(), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.transfer(accounts.bob, 100), Err(Error::Paused));
+            assert_eq!(erc20.set_paused(PAUSE_TRANSFERS, false), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+        }
+
+        fn recovery_guardians() -> Vec<AccountId> {
+            let accounts = default_accounts();
+            vec![accounts.bob, accounts.charlie, accounts.django]
+        }
+
+        #[ink::test]
+        fn recovery_rotates_owner_after_delay() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(
+                erc20.set_recovery(recovery_guardians(), 4, 0),
+                Err(Error::InvalidThreshold)
+            );
+            assert_eq!(erc20.set_recovery(recovery_guardians(), 2, 0), Ok(()));
+            set_caller(accounts.eve);
+            assert_eq!(
+                erc20.vote_recovery(accounts.eve),
+                Err(Error::NotRecoveryGuardian)
+            );
+            set_caller(accounts.bob);
+            assert_eq!(erc20.vote_recovery(accounts.eve), Ok(()));
+            assert_eq!(erc20.execute_recovery(), Err(Error::NoRecovery));
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.vote_recovery(accounts.frank), Ok(()));
+            assert_eq!(erc20.vote_recovery(accounts.eve), Ok(()));
+            assert_eq!(
+                erc20.pending_recovery().map(|recovery| recovery.new_owner),
+                Some(accounts.eve)
+            );
+            set_caller(accounts.django);
+            assert_eq!(
+                erc20.vote_recovery(accounts.frank),
+                Err(Error::RecoveryPending)
+            );
+            assert_eq!(erc20.execute_recovery(), Ok(()));
+            assert_eq!(erc20.owner(), accounts.eve);
+            assert_eq!(erc20.pending_recovery(), None);
+        }
+
+        #[ink::test]
+        fn owner_can_veto_recovery() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20
+                .set_recovery(recovery_guardians(), 1, 1_000_000)
+                .unwrap();
+            set_caller(accounts.bob);
+            assert_eq!(erc20.vote_recovery(accounts.eve), Ok(()));
+            assert_eq!(erc20.execute_recovery(), Err(Error::RecoveryDelayNotOver));
+            assert_eq!(erc20.veto_recovery(), Err(Error::NotOwner));
+            set_caller(accounts.alice);
+            assert_eq!(erc20.veto_recovery(), Ok(()));
+            assert_eq!(erc20.execute_recovery(), Err(Error::NoRecovery));
+            assert_eq!(erc20.owner(), accounts.alice);
+        }
+
+        #[ink::test]
+        fn scheduled_transfer_executes_when_due() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let now = ink::env::block_timestamp::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.schedule_transfer(accounts.bob, 100, now + 1), Ok(0));
+            assert_eq!(erc20.balance_of(accounts.alice), 900);
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.execute(0), Err(Error::ScheduledTransferNotDue));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.cancel(0), Err(Error::NotTransferSender));
+            assert_eq!(erc20.execute(0), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+            assert_eq!(erc20.execute(0), Err(Error::ScheduledTransferNotFound));
+            assert_eq!(erc20.escrowed, 0);
+        }
+
+        #[ink::test]
+        fn scheduled_transfer_can_be_cancelled() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20
+                .schedule_transfer(accounts.bob, 100, Timestamp::MAX)
+                .unwrap();
+            let contract = contract_id();
+            assert_eq!(erc20.balance_of(contract), 100);
+            assert_eq!(erc20.cancel(0), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 1000);
+            assert_eq!(erc20.balance_of(contract), 0);
+            assert_eq!(erc20.scheduled_transfer(0), None);
+        }
+
+        #[ink::test]
+        fn heir_claims_after_inactivity() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.heartbeat(), Err(Error::NoInheritance));
+            assert_eq!(erc20.set_heir(accounts.bob, 1), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.claim_inheritance(accounts.alice), Err(Error::NotHeir));
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.claim_inheritance(accounts.alice),
+                Err(Error::HolderStillActive)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.claim_inheritance(accounts.alice), Ok(1000));
+            assert_eq!(erc20.balance_of(accounts.bob), 1000);
+            assert_eq!(erc20.inheritance(accounts.alice), None);
+            assert!(!erc20.accounts.contains(accounts.alice));
+            assert_eq!(
+                erc20.claim_inheritance(accounts.alice),
+                Err(Error::NoInheritance)
+            );
+        }
+
+        #[ink::test]
+        fn heartbeat_and_cancel_stop_heir() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.set_heir(accounts.bob, 1).unwrap();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.heartbeat(), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.claim_inheritance(accounts.alice),
+                Err(Error::HolderStillActive)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.cancel_inheritance(), Ok(()));
+            assert_eq!(erc20.cancel_inheritance(), Err(Error::NoInheritance));
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.claim_inheritance(accounts.alice),
+                Err(Error::NoInheritance)
+            );
+        }
+
+        #[ink::test]
+        fn roundup_donates_the_difference() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.set_roundup(accounts.charlie, 100), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 130), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 130);
+            assert_eq!(erc20.balance_of(accounts.charlie), 70);
+            assert_eq!(erc20.balance_of(accounts.alice), 800);
+            // Multiples of the unit need no rounding.
+            assert_eq!(erc20.transfer(accounts.bob, 200), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.charlie), 70);
+            // Spenders' transfers are not rounded up.
+            erc20.approve(accounts.bob, 50).unwrap();
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 50),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(accounts.charlie), 70);
+            assert_eq!(erc20.roundup_of(accounts.alice).unwrap().donated, 70);
+            assert_eq!(erc20.roundup_received(accounts.charlie), 70);
+        }
+
+        #[ink::test]
+        fn roundup_can_be_paused_and_is_capped_by_balance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.set_roundup_enabled(false), Err(Error::NoRoundUp));
+            erc20.set_roundup(accounts.charlie, 100).unwrap();
+            assert_eq!(erc20.set_roundup_enabled(false), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.charlie), 0);
+            assert_eq!(erc20.set_roundup_enabled(true), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 960), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.charlie), 30);
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+            assert_eq!(erc20.roundup_of(accounts.alice).unwrap().donated, 30);
+        }
+
+        #[ink::test]
+        fn set_roundup_checks_its_arguments() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(
+                erc20.set_roundup(accounts.charlie, 0),
+                Err(Error::InvalidAmount)
+            );
+            assert_eq!(
+                erc20.set_roundup(accounts.alice, 100),
+                Err(Error::InvalidRecipient)
+            );
+            assert_eq!(erc20.roundup_of(accounts.alice), None);
+        }
+
+        #[ink::test]
+        fn sponsor_account_onboards() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let newcomer = AccountId::from([0x42; 32]);
+            assert_eq!(
+                erc20.set_sponsorship(Some(Sponsorship {
+                    starter_amount: 50,
+                    max_accounts_per_sponsor: 2,
+                })),
+                Ok(())
+            );
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id(), 100);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            assert_eq!(erc20.sponsor_account(newcomer), Ok(()));
+            assert_eq!(erc20.balance_of(newcomer), 50);
+            assert_eq!(erc20.balance_of(accounts.alice), 950);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(newcomer),
+                Ok(10)
+            );
+            assert_eq!(erc20.sponsor_of(newcomer), Some(accounts.alice));
+            assert_eq!(erc20.sponsored_count(accounts.alice), 1);
+            assert_eq!(erc20.sponsor_account(newcomer), Err(Error::AccountNotNew));
+            erc20.transfer(accounts.bob, 1).unwrap();
+            assert_eq!(
+                erc20.sponsor_account(accounts.bob),
+                Err(Error::AccountNotNew)
+            );
+        }
 
-    use ink_storage::collections::HashMap as StorageHashMap;
-    #[ink(storage)]
-    pub struct Erc20 {
-        total_supply: Balance,
-        balances: StorageHashMap<AccountId, Balance>,
-        allowance: StorageHashMap<(AccountId, AccountId), Balance>,
-    }
+        #[ink::test]
+        fn sponsor_account_is_limited() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(
+                erc20.sponsor_account(accounts.bob),
+                Err(Error::SponsorshipDisabled)
+            );
+            erc20
+                .set_sponsorship(Some(Sponsorship {
+                    starter_amount: 0,
+                    max_accounts_per_sponsor: 1,
+                }))
+                .unwrap();
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(erc20.sponsor_account(accounts.bob), Ok(()));
+            assert_eq!(
+                erc20.sponsor_account(accounts.charlie),
+                Err(Error::SponsorLimitReached)
+            );
+            set_caller(accounts.bob);
+            assert_eq!(erc20.set_sponsorship(None), Err(Error::NotOwner));
+        }
 
-    #[ink(event)]
-    pub struct Transfer {
-        #[ink(topic)]
-        from: AccountId,
-        #[ink(topic)]
-        to: AccountId,
-        value: Balance,
-    }
+        #[ink::test]
+        fn savings_accrue_interest() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.set_savings_rate(SAVINGS_RATE_PRECISION / 100), Ok(()));
+            assert_eq!(erc20.fund_savings_reserve(100), Ok(()));
+            assert_eq!(erc20.deposit_to_savings(500), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 400);
+            assert_eq!(erc20.savings_balance_of(accounts.alice), 500);
+            advance_block();
+            advance_block();
+            assert_eq!(erc20.savings_balance_of(accounts.alice), 510);
+            assert_eq!(erc20.savings_pool().reserve, 90);
+            assert_eq!(
+                erc20.withdraw_from_savings(511),
+                Err(Error::InsufficientBalance {
+                    required: 511,
+                    available: 510
+                })
+            );
+            assert_eq!(erc20.withdraw_from_savings(510), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 910);
+            assert_eq!(erc20.savings_balance_of(accounts.alice), 0);
+            assert_eq!(erc20.balance_of(contract_id()), 90);
+        }
 
-    #[derive(Debug, PartialEq, Eq, scale::Encode)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub enum Error {
-        InsufficientBallance,
-    }
-    pub type Result<T> = core::result::Result<T, Error>;
-    impl Erc20 {
-        /// Constructor that initializes the `bool` value to the given `init_value`.
-        #[ink(constructor)]
-        pub fn new(total_supply: Balance) -> Self {
-            let caller = Self::env().caller();
-            let mut balances = StorageHashMap::new();
-            balances.insert(caller, total_supply);
+        #[ink::test]
+        fn savings_interest_is_capped_by_reserve() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20
+                .set_savings_rate(SAVINGS_RATE_PRECISION / 100)
+                .unwrap();
+            erc20.fund_savings_reserve(5).unwrap();
+            erc20.deposit_to_savings(500).unwrap();
+            advance_block();
+            advance_block();
+            assert_eq!(erc20.savings_balance_of(accounts.alice), 505);
+            assert_eq!(erc20.savings_pool().reserve, 0);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.set_savings_rate(0), Err(Error::NotOwner));
+        }
 
-            Self {
-                total_supply,
-                balances,
-                allowance: StorageHashMap::new(),
-            }
+        #[ink::test]
+        fn sweep_leaves_savings() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.deposit_to_savings(100).unwrap();
+            seed_balance(&mut erc20, contract_id(), 7);
+            assert_eq!(erc20.sweep_token(contract_id(), accounts.bob), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 7);
+            assert_eq!(erc20.balance_of(contract_id()), 100);
         }
 
-        /// Constructor that initializes the `bool` value to `false`.
-        ///
-        /// Constructors can delegate to other constructors.
-        #[ink(constructor)]
-        pub fn default() -> Self {
-            Self::new(Default::default())
+        #[ink::test]
+        fn set_rules_registry_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.rules_registry(), None);
+            assert_eq!(erc20.set_rules_registry(Some(accounts.django)), Ok(()));
+            assert_eq!(erc20.rules_registry(), Some(accounts.django));
+            assert_eq!(erc20.set_rules_registry(None), Ok(()));
+            // Without a registry transfers go through unchecked.
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.set_rules_registry(Some(accounts.bob)),
+                Err(Error::NotOwner)
+            );
         }
 
-        #[ink(message)]
-        pub fn total_supply(&self) -> Balance {
-            self.total_supply
+        #[ink::test]
+        fn rules_registry_skips_the_contracts_own_balance() {
+            // Django is no contract, so asking it would fail the test.
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.set_rules_registry(Some(accounts.django)).unwrap();
+            assert_eq!(erc20.deposit_to_savings(100), Ok(()));
+            assert_eq!(erc20.withdraw_from_savings(100), Ok(()));
+            assert_eq!(erc20.burn(10), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 990);
         }
 
-        #[ink(message)]
-        pub fn balance_of(&self, owner: AccountId) -> Balance {
-            *self.balances.get(&owner).unwrap_or(&0)
+        #[ink::test]
+        fn metadata_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.token_uri(), "");
+            assert_eq!(erc20.logo_hash(), None);
+            assert_eq!(erc20.set_token_uri("ipfs://token.json".into()), Ok(()));
+            assert_eq!(erc20.set_logo_hash(Hash::from([0x7; 32])), Ok(()));
+            assert_eq!(erc20.token_uri(), "ipfs://token.json");
+            assert_eq!(erc20.logo_hash(), Some(Hash::from([0x7; 32])));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.set_token_uri(String::new()), Err(Error::NotOwner));
+            assert_eq!(
+                erc20.set_logo_hash(Hash::from([0x0; 32])),
+                Err(Error::NotOwner)
+            );
         }
 
-        #[ink(message)]
-        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
-            *self.allowance.get(&(owner, spender)).unwrap_or(&0)
+        #[ink::test]
+        fn display_amounts_work() {
+            let erc20 = instantiate(1000);
+            let unit = 10u128.pow(DECIMALS.into());
+            assert_eq!(erc20.to_display_amount(unit * 3 / 2), (1, unit / 2));
+            assert_eq!(erc20.from_display_amount(1, unit / 2), Ok(unit * 3 / 2));
+            assert_eq!(
+                erc20.from_display_amount(1, unit),
+                Err(Error::InvalidAmount)
+            );
+            assert_eq!(
+                erc20.from_display_amount(Balance::MAX, 0),
+                Err(Error::Overflow)
+            );
         }
 
-        #[ink(message)]
-        pub fn transer(&mut self, to: AccountId, value: Balance) -> Result<()> {
-            let who = Self::env().caller();
-            self.transfer_helper(who, to, value)
+        #[ink::test]
+        fn total_supply_at_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let start = block_number();
+            assert_eq!(erc20.issue(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.total_supply_at(start), 1100);
+            advance_block();
+            assert_eq!(erc20.burn(50), Ok(()));
+            advance_block();
+            advance_block();
+            assert_eq!(erc20.burn(50), Ok(()));
+            assert_eq!(erc20.total_supply_at(start), 1100);
+            assert_eq!(erc20.total_supply_at(start + 1), 1050);
+            assert_eq!(erc20.total_supply_at(start + 2), 1050);
+            assert_eq!(erc20.total_supply_at(start + 3), 1000);
+            assert_eq!(erc20.total_supply_at(start + 100), 1000);
+            // Transfers leave the supply alone.
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.supply_checkpoint_count, 3);
         }
 
-        fn transfer_helper(
-            &mut self,
-            from: AccountId,
-            to: AccountId,
-            value: Balance,
-        ) -> Result<()> {
-            let from_balance = self.balance_of(from);
-            if from_balance < value {
-                return Err(Error::InsufficientBallance);
+        #[ink::test]
+        fn balance_history_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let start = block_number();
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            advance_block();
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            let checkpoint = |block, value| Checkpoint { block, value };
+            assert_eq!(
+                erc20.balance_history(accounts.alice, 0, start + 1, 0, 10),
+                vec![checkpoint(start, 800), checkpoint(start + 1, 700)]
+            );
+            assert_eq!(
+                erc20.balance_history(accounts.bob, start + 1, start + 1, 0, 10),
+                vec![checkpoint(start + 1, 300)]
+            );
+            assert_eq!(
+                erc20.balance_history(accounts.bob, 0, start + 1, 1, 1),
+                vec![checkpoint(start + 1, 300)]
+            );
+            assert_eq!(
+                erc20.balance_history(accounts.charlie, 0, 10, 0, 10),
+                vec![]
+            );
+            assert_eq!(erc20.balance_of_at(accounts.alice, start), Some(800));
+            assert_eq!(erc20.balance_of_at(accounts.bob, start + 5), Some(300));
+            assert_eq!(erc20.balance_of_at(accounts.charlie, start), Some(0));
+        }
+
+        #[ink::test]
+        fn emptied_account_keeps_its_snapshots() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.transfer(accounts.bob, 250), Ok(()));
+            let snapshot = block_number();
+            advance_block();
+            // Bob sells everything after the snapshot.
+            set_caller(accounts.bob);
+            assert_eq!(erc20.transfer(accounts.charlie, 250), Ok(()));
+            assert_eq!(erc20.balance_of_at(accounts.bob, snapshot), Some(250));
+            assert_eq!(erc20.balance_of_at(accounts.bob, block_number()), Some(0));
+            assert_eq!(
+                erc20.balance_history(accounts.bob, 0, block_number(), 0, 10),
+                vec![
+                    Checkpoint {
+                        block: snapshot,
+                        value: 250
+                    },
+                    Checkpoint {
+                        block: block_number(),
+                        value: 0
+                    }
+                ]
+            );
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(400);
+            assert_eq!(erc20.distribute_native(), Ok(0));
+            assert_eq!(erc20.native_dividend(0, accounts.bob), Ok(100));
+            assert_eq!(erc20.native_dividend(0, accounts.charlie), Ok(0));
+        }
+
+        #[ink::test]
+        fn balance_history_is_bounded() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let start = block_number();
+            for _ in 0..MAX_BALANCE_CHECKPOINTS {
+                advance_block();
+                erc20.transfer(accounts.bob, 1).unwrap();
             }
-            self.balances.insert(from, from_balance - value);
-            let to_balance = self.balance_of(to);
-            self.balances.insert(to, to_balance + value);
+            let history = erc20.balance_history(accounts.alice, 0, block_number(), 0, 100);
+            assert_eq!(history.len(), MAX_BALANCE_CHECKPOINTS as usize);
+            assert_eq!(history[0].value, 999);
+            assert_eq!(history[history.len() - 1].value, 936);
+            assert_eq!(erc20.balance_of_at(accounts.alice, start), None);
+            assert_eq!(
+                erc20.balance_of_at(accounts.alice, block_number()),
+                Some(936)
+            );
+        }
 
-            self.env().emit_event(Transfer { from, to, value });
-            Ok(())
+        #[ink::test]
+        fn receipts_are_numbered() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.last_tx_id(), 0);
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.burn(40), Ok(()));
+            assert_eq!(erc20.last_tx_id(), 2);
+            let block = block_number();
+            assert_eq!(
+                erc20.get_receipt(1),
+                Some(Receipt {
+                    tx_id: 1,
+                    from: Some(accounts.alice),
+                    to: Some(accounts.bob),
+                    value: 100,
+                    block,
+                })
+            );
+            assert_eq!(
+                erc20.get_receipt(2),
+                Some(Receipt {
+                    tx_id: 2,
+                    from: Some(accounts.bob),
+                    to: None,
+                    value: 40,
+                    block,
+                })
+            );
+            assert_eq!(erc20.get_receipt(0), None);
+            assert_eq!(erc20.get_receipt(3), None);
         }
 
-        #[ink(message)]
-        pub fn transer_from(&mut self, from: AccountId, value: Balance) -> Result<()> {
-            let who = Self::env().caller();
-            self.transfer_helper(from, who, value)
+        #[ink::test]
+        fn old_receipts_are_overwritten() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            for _ in 0..MAX_RECEIPTS {
+                erc20.transfer(accounts.bob, 1).unwrap();
+            }
+            assert!(erc20.get_receipt(1).is_some());
+            erc20.transfer(accounts.bob, 1).unwrap();
+            assert_eq!(erc20.get_receipt(1), None);
+            assert_eq!(
+                erc20
+                    .get_receipt(MAX_RECEIPTS + 1)
+                    .map(|receipt| receipt.tx_id),
+                Some(MAX_RECEIPTS + 1)
+            );
         }
 
-        #[ink(message)]
-        pub fn burn(&mut self, value: Balance) {
-            let who = Self::env().caller();
-            let balance = self.balance_of(who);
-            if balance < value {
-                self.balances.insert(who, 0);
-            } else {
-                self.balances.insert(who, balance - value);
+        #[ink::test]
+        fn multicall_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let call = |selector: u32, input: Vec<u8>| Call {
+                selector: selector.to_be_bytes(),
+                input,
+            };
+            assert_eq!(
+                erc20.multicall(vec![
+                    call(0x681266A0, (accounts.bob, 100u128).encode()),
+                    call(0x84A15DA1, (accounts.bob, 300u128).encode()),
+                    call(0x0F755A56, accounts.bob.encode()),
+                    call(0x6A00165E, (accounts.alice, accounts.bob).encode()),
+                    call(0xDB6375A8, Vec::new()),
+                ]),
+                Ok(vec![
+                    Vec::new(),
+                    Vec::new(),
+                    300u128.encode(),
+                    100u128.encode(),
+                    1000u128.encode(),
+                ])
+            );
+        }
+
+        #[ink::test]
+        fn multicall_fails_on_bad_calls() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let call = |selector: u32, input: Vec<u8>| Call {
+                selector: selector.to_be_bytes(),
+                input,
+            };
+            assert_eq!(
+                erc20.multicall(vec![call(0x12345678, Vec::new())]),
+                Err(Error::UnknownSelector)
+            );
+            assert_eq!(
+                erc20.multicall(vec![call(0x0F755A56, vec![0x1])]),
+                Err(Error::InvalidCallInput)
+            );
+            assert_eq!(
+                erc20.multicall(vec![call(0x84A15DA1, (accounts.bob, 2000u128).encode())]),
+                Err(Error::InsufficientBalance {
+                    required: 2000,
+                    available: 1000,
+                })
+            );
+            let mut input = accounts.bob.encode();
+            input.push(0x0);
+            assert_eq!(
+                erc20.multicall(vec![call(0x0F755A56, input)]),
+                Err(Error::InvalidCallInput)
+            );
+        }
+
+        #[ink::test]
+        fn native_dividends_are_pro_rata() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.transfer(accounts.bob, 250), Ok(()));
+            advance_block();
+            // Transfers after the snapshot don't change the shares.
+            assert_eq!(erc20.transfer(accounts.bob, 250), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(400);
+            assert_eq!(erc20.distribute_native(), Ok(0));
+            assert_eq!(erc20.native_dividend(0, accounts.alice), Ok(300));
+            assert_eq!(erc20.native_dividend(0, accounts.bob), Ok(100));
+            assert_eq!(erc20.native_dividend(0, accounts.charlie), Ok(0));
+            assert_eq!(
+                erc20.native_dividend(1, accounts.bob),
+                Err(Error::DistributionNotFound)
+            );
+            assert_eq!(erc20.native_dividends_unclaimed, 400);
+            erc20
+                .native_dividends_claimed
+                .insert((0, accounts.bob), &());
+            assert_eq!(erc20.native_dividend(0, accounts.bob), Ok(0));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.claim_native_dividend(0), Err(Error::AlreadyClaimed));
+        }
+
+        #[ink::test]
+        fn expired_native_dividends_can_be_reclaimed() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.transfer(accounts.bob, 250), Ok(()));
+            advance_block();
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(400);
+            assert_eq!(erc20.distribute_native(), Ok(0));
+            // Bob's balance at the snapshot rolls out of his history.
+            set_caller(accounts.bob);
+            for _ in 0..MAX_BALANCE_CHECKPOINTS {
+                advance_block();
+                assert_eq!(erc20.transfer(accounts.charlie, 1), Ok(()));
+            }
+            assert_eq!(
+                erc20.native_dividend(0, accounts.bob),
+                Err(Error::SnapshotUnavailable)
+            );
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id(),
+                1_000_000,
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.claim_native_dividend(0), Ok(300));
+            assert_eq!(
+                erc20.reclaim_native_dividends(0, accounts.django),
+                Err(Error::ClaimPeriodNotOver)
+            );
+            let snapshot_block = erc20.native_distribution(0).unwrap().snapshot_block;
+            while block_number() <= snapshot_block + NATIVE_DIVIDEND_CLAIM_BLOCKS {
+                advance_block();
             }
+            set_caller(accounts.bob);
+            assert_eq!(erc20.claim_native_dividend(0), Err(Error::ClaimPeriodOver));
+            assert_eq!(
+                erc20.reclaim_native_dividends(0, accounts.django),
+                Err(Error::NotOwner)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.reclaim_native_dividends(0, accounts.django), Ok(100));
+            assert_eq!(erc20.native_dividends_unclaimed, 0);
+            assert_eq!(erc20.native_dividends_paid(0), 400);
+            assert_eq!(
+                erc20.reclaim_native_dividends(0, accounts.django),
+                Err(Error::NothingToReclaim)
+            );
         }
 
-        #[ink(message)]
-        pub fn issue(&mut self, to: AccountId, value: Balance) -> Result<()> {
-            let current_balance = self.balance_of(to);
-            self.balances.insert(to, current_balance + value);
-            Ok(())
+        #[ink::test]
+        fn distributions_before_migration_are_not_reclaimable() {
+            let accounts = default_accounts();
+            let mut erc20 = old_storage();
+            advance_block();
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(400);
+            assert_eq!(erc20.distribute_native(), Ok(0));
+            assert_eq!(erc20.migrate(), Ok(()));
+            while block_number() <= NATIVE_DIVIDEND_CLAIM_BLOCKS + 1 {
+                advance_block();
+            }
+            assert_eq!(
+                erc20.reclaim_native_dividends(0, accounts.django),
+                Err(Error::NothingToReclaim)
+            );
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+        #[ink::test]
+        fn distribute_native_needs_value() {
+            let mut erc20 = instantiate(1000);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            advance_block();
+            assert_eq!(erc20.distribute_native(), Err(Error::NothingToDistribute));
+        }
+
+        fn fee_policy(accounts: &Accounts) -> FeePolicy {
+            FeePolicy {
+                fee_bps: 1_000,
+                treasury: accounts.django,
+                treasury_bps: 5_000,
+                rewards: accounts.eve,
+                rewards_bps: 3_000,
+                burn_bps: 2_000,
+            }
+        }
 
-        use ink_lang as ink;
         #[ink::test]
-        fn create_contract_works() {
-            let erc20 = Erc20::new(1000);
-            assert_eq!(1000, erc20.total_supply());
+        fn transfer_fee_is_split() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.fee_policy = Some(fee_policy(&accounts));
+            assert_eq!(erc20.transfer_fee(500), 50);
+            assert_eq!(erc20.transfer(accounts.bob, 500), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 500);
+            assert_eq!(erc20.balance_of(accounts.bob), 450);
+            assert_eq!(erc20.balance_of(accounts.django), 25);
+            assert_eq!(erc20.balance_of(accounts.eve), 15);
+            assert_eq!(erc20.total_supply(), 990);
         }
 
         #[ink::test]
-        fn get_good_balance() {
-            let erc20 = Erc20::new(1000);
-            assert_eq!(erc20.balance_of(AccountId::from([0x1; 32])), 1000);
-            assert_eq!(erc20.balance_of(AccountId::from([0x2; 32])), 0);
+        fn fee_exempt_accounts_pay_no_fee() {
+            // Charlie stands in for an escrow contract pulling a deposit in
+            // and paying it out again.
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.fee_policy = Some(fee_policy(&accounts));
+            erc20.fee_exempt.insert(accounts.charlie, &());
+            assert_eq!(erc20.approve(accounts.charlie, 500), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.charlie, 500),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(accounts.charlie), 500);
+            assert_eq!(erc20.transfer(accounts.bob, 500), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert_eq!(erc20.total_supply(), 1000);
+            // Transfers between other accounts still pay.
+            set_caller(accounts.bob);
+            assert_eq!(erc20.transfer(accounts.alice, 100), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 590);
         }
 
         #[ink::test]
-        fn transfer_works() {
-            let mut erc20 = Erc20::new(1000);
-            let owner = AccountId::from([0x1; 32]);
-            let to = AccountId::from([0x2; 32]);
-            assert_eq!(erc20.transer(to, 100), Ok(()));
-            assert_eq!(erc20.balance_of(to), 100);
-            assert_eq!(erc20.balance_of(owner), 900);
+        fn fee_exemptions_wait_for_the_timelock() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            assert_eq!(
+                erc20.apply_fee_exemption(accounts.charlie),
+                Err(Error::NoPendingFeeExemption)
+            );
+            assert_eq!(erc20.queue_fee_exemption(accounts.charlie, true), Ok(()));
+            assert_eq!(
+                erc20.apply_fee_exemption(accounts.charlie),
+                Err(Error::FeeExemptionNotDue)
+            );
+            assert!(!erc20.is_fee_exempt(accounts.charlie));
+            let mut pending = erc20.pending_fee_exemption(accounts.charlie).unwrap();
+            pending.applicable_at = 0;
+            erc20
+                .pending_fee_exemptions
+                .insert(accounts.charlie, &pending);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.apply_fee_exemption(accounts.charlie), Ok(()));
+            assert!(erc20.is_fee_exempt(accounts.charlie));
+            assert_eq!(erc20.pending_fee_exemption(accounts.charlie), None);
+            assert_eq!(
+                erc20.queue_fee_exemption(accounts.charlie, false),
+                Err(Error::NotOwner)
+            );
         }
 
         #[ink::test]
-        fn transfer_failed_for_lower_balance() {
-            let mut erc20 = Erc20::new(100);
-            let to = AccountId::from([0x2; 32]);
-            assert_eq!(erc20.transer(to, 200), Err(Error::InsufficientBallance));
+        fn scheduled_transfers_pay_the_fee_on_execution() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.fee_policy = Some(fee_policy(&accounts));
+            let id = erc20.schedule_transfer(accounts.bob, 500, 0).unwrap();
+            assert_eq!(erc20.execute(id), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 450);
+            assert_eq!(erc20.balance_of(accounts.django), 25);
+            assert_eq!(erc20.balance_of(contract_id()), 0);
+            // Cancelling returns everything.
+            let id = erc20.schedule_transfer(accounts.bob, 100, 0).unwrap();
+            assert_eq!(erc20.cancel(id), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 500);
         }
+
         #[ink::test]
-        fn transfer_from_works() {
-            let mut erc20 = Erc20::new(1000);
-            let owner = AccountId::from([0x1; 32]);
-            let to = AccountId::from([0x2; 32]);
-            erc20.transer(to, 200).unwrap();
-            assert_eq!(erc20.transer_from(to, 100), Ok(()));
-            assert_eq!(erc20.balance_of(to), 100);
-            assert_eq!(erc20.balance_of(owner), 900);
+        fn fee_exempt_paths() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.fee_policy = Some(fee_policy(&accounts));
+            erc20
+                .set_sponsorship(Some(Sponsorship {
+                    starter_amount: 100,
+                    max_accounts_per_sponsor: 1,
+                }))
+                .unwrap();
+            assert_eq!(erc20.sponsor_account(accounts.frank), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.frank), 100);
+            // The round-up donation pays no fee on top of the transfer's.
+            assert_eq!(erc20.set_roundup(accounts.charlie, 100), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 50), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.charlie), 50);
+            assert_eq!(erc20.set_heir(accounts.bob, 0), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.claim_inheritance(accounts.alice), Ok(800));
+            assert_eq!(erc20.balance_of(accounts.bob), 845);
         }
 
         #[ink::test]
-        fn transfer_from_failed_for_lower_balance() {
-            let mut erc20 = Erc20::new(1000);
-            let to = AccountId::from([0x2; 32]);
-            erc20.transer(to, 100).unwrap();
+        fn fee_policy_changes_wait_for_the_timelock() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let policy = fee_policy(&accounts);
             assert_eq!(
-                erc20.transer_from(to, 200),
-                Err(Error::InsufficientBallance)
+                erc20.queue_fee_policy(Some(FeePolicy {
+                    burn_bps: 2_001,
+                    ..policy.clone()
+                })),
+                Err(Error::InvalidFee)
+            );
+            assert_eq!(erc20.apply_fee_policy(), Err(Error::NoPendingFeePolicy));
+            assert_eq!(erc20.queue_fee_policy(Some(policy.clone())), Ok(()));
+            assert_eq!(erc20.apply_fee_policy(), Err(Error::FeePolicyNotDue));
+            erc20.pending_fee_policy.as_mut().unwrap().applicable_at = 0;
+            assert_eq!(erc20.apply_fee_policy(), Ok(()));
+            assert_eq!(erc20.fee_policy(), Some(policy));
+            assert_eq!(erc20.pending_fee_policy(), None);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.queue_fee_policy(None), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn new_token_works() {
+            let accounts = default_accounts();
+            let erc20 = Erc20::new_token(
+                Some("Course Token".into()),
+                Some("CRS".into()),
+                1000,
+                accounts.bob,
             );
+            assert_eq!(erc20.token_name(), Some("Course Token".into()));
+            assert_eq!(erc20.token_symbol(), Some("CRS".into()));
+            assert_eq!(erc20.owner(), accounts.bob);
+            assert_eq!(erc20.balance_of(accounts.bob), 1000);
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+            assert_eq!(instantiate(1000).token_name(), None);
         }
 
         #[ink::test]
         fn burn_works() {
-            let mut erc20 = Erc20::new(1000);
-            let owner = AccountId::from([0x1; 32]);
-            erc20.burn(100);
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            erc20.burn(100).unwrap();
             assert_eq!(erc20.balance_of(owner), 900);
-            erc20.burn(1000);
+            assert_eq!(erc20.total_supply(), 900);
+            erc20.burn(1000).unwrap();
             assert_eq!(erc20.balance_of(owner), 0);
+            assert_eq!(erc20.total_supply(), 0);
         }
 
         #[ink::test]
         fn issue_works() {
-            let owner = AccountId::from([0x1; 32]);
-            let to = AccountId::from([0x2; 32]);
-            let mut erc20 = Erc20::new(1000);
+            let accounts = default_accounts();
+            let owner = accounts.alice;
+            let to = accounts.bob;
+            let mut erc20 = instantiate(1000);
             erc20.issue(to, 100).unwrap();
             assert_eq!(erc20.balance_of(owner), 1000);
             assert_eq!(erc20.balance_of(to), 100);
+            assert_eq!(erc20.total_supply(), 1100);
+        }
+
+        #[ink::test]
+        fn transfer_ownership_works() {
+            let accounts = default_accounts();
+            let owner = accounts.alice;
+            let new_owner = accounts.bob;
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.owner(), owner);
+            assert_eq!(erc20.transfer_ownership(new_owner), Ok(()));
+            assert_eq!(erc20.owner(), new_owner);
+            assert_eq!(erc20.transfer_ownership(owner), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn issue_failed_for_non_owner() {
+            let accounts = default_accounts();
+            let to = accounts.bob;
+            let mut erc20 = instantiate(1000);
+            erc20.transfer_ownership(to).unwrap();
+            assert_eq!(erc20.issue(to, 100), Err(Error::NotOwner));
+            assert_eq!(erc20.balance_of(to), 0);
+        }
+
+        #[ink::test]
+        fn upgrade_code_failed_for_non_owner() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.transfer_ownership(accounts.bob).unwrap();
+            assert_eq!(erc20.upgrade_code([0x7; 32]), Err(Error::NotOwner));
+            assert_eq!(erc20.version(), 1);
+        }
+
+        #[ink::test]
+        fn contract_info_works() {
+            let mut erc20 = instantiate(1000);
+            let info = erc20.contract_info();
+            assert_eq!(info.semver, env!("CARGO_PKG_VERSION"));
+            assert_eq!(info.version, 1);
+            assert_eq!(info.storage_version, STORAGE_VERSION);
+            assert_eq!(info.code_storage_version, STORAGE_VERSION);
+            erc20.storage_version = 1;
+            assert_eq!(erc20.contract_info().storage_version, 1);
+        }
+
+        /// Leaves storage as the previous version's code would have.
+        fn old_storage() -> Erc20 {
+            let mut erc20 = instantiate(1000);
+            let holders = erc20.holders(0, erc20.holder_count());
+            for holder in holders {
+                write_legacy_history(&mut erc20, holder);
+            }
+            erc20.storage_version = STORAGE_VERSION - 1;
+            erc20
+        }
+
+        /// Moves `account`'s balance history back to where version 4 kept it.
+        fn write_legacy_history(erc20: &mut Erc20, account: AccountId) {
+            let history = erc20.balance_history(account, 0, BlockNumber::MAX, 0, u32::MAX);
+            for slot in 0..MAX_BALANCE_CHECKPOINTS {
+                erc20.balance_checkpoint_slots.remove((account, slot));
+            }
+            erc20.balance_checkpoint_rings.remove(account);
+            erc20.balance_checkpoints.insert(account, &history);
+        }
+
+        #[ink::test]
+        fn new_contract_needs_no_migration() {
+            let mut erc20 = instantiate(1000);
+            assert_eq!(erc20.storage_version(), STORAGE_VERSION);
+            assert_eq!(erc20.migrate(), Err(Error::AlreadyMigrated));
+        }
+
+        #[ink::test]
+        fn migrate_works() {
+            let accounts = default_accounts();
+            let mut erc20 = old_storage();
+            let start = block_number();
+            assert_eq!(erc20.balance_of_at(accounts.alice, start), Some(1000));
+            assert_eq!(erc20.migrate(), Ok(()));
+            assert_eq!(erc20.storage_version(), STORAGE_VERSION);
+            assert_eq!(erc20.balance_checkpoints.get(accounts.alice), None);
+            assert_eq!(
+                erc20.balance_history(accounts.alice, 0, start, 0, 10),
+                vec![Checkpoint {
+                    block: start,
+                    value: 1000
+                }]
+            );
+            assert_eq!(erc20.migrate(), Err(Error::AlreadyMigrated));
+        }
+
+        #[ink::test]
+        fn migrate_converts_holders_in_batches() {
+            let mut erc20 = old_storage();
+            for seed in 0..MIGRATION_BATCH_SIZE as u8 {
+                let holder = AccountId::from([0x80 + seed; 32]);
+                seed_balance(&mut erc20, holder, 1);
+                write_legacy_history(&mut erc20, holder);
+            }
+            assert_eq!(erc20.migrate(), Ok(()));
+            assert_eq!(erc20.storage_version(), STORAGE_VERSION - 1);
+            // The first holder, the owner, is left for the next batch.
+            assert_eq!(
+                erc20.balance_checkpoints.get(AccountId::from([0x80; 32])),
+                None
+            );
+            let owner = default_accounts().alice;
+            assert!(erc20.balance_checkpoints.get(owner).is_some());
+            assert_eq!(erc20.balance_of_at(owner, block_number()), Some(1000));
+            assert_eq!(erc20.migrate(), Ok(()));
+            assert_eq!(erc20.storage_version(), STORAGE_VERSION);
+            assert_eq!(erc20.balance_checkpoints.get(owner), None);
+            assert_eq!(erc20.balance_of_at(owner, block_number()), Some(1000));
+        }
+
+        #[ink::test]
+        fn unconverted_history_is_converted_on_change() {
+            let accounts = default_accounts();
+            let mut erc20 = old_storage();
+            let start = block_number();
+            advance_block();
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.balance_checkpoints.get(accounts.alice), None);
+            assert_eq!(erc20.balance_of_at(accounts.alice, start), Some(1000));
+            assert_eq!(
+                erc20.balance_of_at(accounts.alice, block_number()),
+                Some(900)
+            );
+        }
+
+        #[ink::test]
+        fn migrate_failed_for_non_owner() {
+            let accounts = default_accounts();
+            let mut erc20 = old_storage();
+            erc20.transfer_ownership(accounts.bob).unwrap();
+            assert_eq!(erc20.migrate(), Err(Error::NotOwner));
+            assert_eq!(erc20.storage_version(), STORAGE_VERSION - 1);
+        }
+
+        #[ink::test]
+        fn kill_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let beneficiary = accounts.bob;
+            let contract = contract_id();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract, 100);
+            let should_terminate = move || {
+                erc20.kill(beneficiary).unwrap();
+            };
+            ink::env::test::assert_contract_termination::<ink::env::DefaultEnvironment, _>(
+                should_terminate,
+                beneficiary,
+                100,
+            );
+        }
+
+        #[ink::test]
+        fn kill_failed_for_non_owner() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            erc20.transfer_ownership(accounts.bob).unwrap();
+            assert_eq!(erc20.kill(accounts.bob), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn sweep_own_token_works() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let contract = contract_id();
+            let to = accounts.bob;
+            seed_balance(&mut erc20, contract, 300);
+            assert_eq!(erc20.sweep_token(contract, to), Ok(()));
+            assert_eq!(erc20.balance_of(contract), 0);
+            assert_eq!(erc20.balance_of(to), 300);
+            assert_transfer_event(0, Some(contract), Some(to), 300);
+        }
+
+        #[ink::test]
+        fn sweep_failed_for_non_owner() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let to = accounts.bob;
+            erc20.transfer_ownership(to).unwrap();
+            assert_eq!(
+                erc20.sweep_token(AccountId::from([0x9; 32]), to),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(erc20.sweep_native(to), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn transfer_and_call_failed_for_lower_balance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(100);
+            let to = accounts.bob;
+            assert_eq!(
+                erc20.transfer_and_call(to, 200, Vec::new()),
+                Err(Error::InsufficientBalance {
+                    required: 200,
+                    available: 100
+                })
+            );
+            assert_eq!(erc20.balance_of(to), 0);
+        }
+
+        #[ink::test]
+        fn safe_transfer_failed_for_lower_balance() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(100);
+            let to = accounts.bob;
+            assert_eq!(
+                erc20.safe_transfer(to, 200),
+                Err(Error::InsufficientBalance {
+                    required: 200,
+                    available: 100
+                })
+            );
+        }
+
+        #[ink::test]
+        fn holders_follow_balances() {
+            let accounts = default_accounts();
+            let mut erc20 = instantiate(1000);
+            let owner = accounts.alice;
+            let first = accounts.bob;
+            let second = accounts.charlie;
+            assert_eq!(erc20.holder_count(), 1);
+            erc20.transfer(first, 100).unwrap();
+            erc20.transfer(second, 100).unwrap();
+            assert_eq!(erc20.holders(0, 10), vec![owner, first, second]);
+            erc20.transfer(owner, 0).unwrap();
+            assert_eq!(erc20.holder_count(), 3);
+            erc20.burn(800).unwrap();
+            assert_eq!(erc20.holders(0, 10), vec![second, first]);
+        }
+
+        #[ink::test]
+        fn holders_are_paginated() {
+            let mut erc20 = instantiate(1000);
+            for i in 2..7 {
+                erc20.transfer(AccountId::from([i; 32]), 1).unwrap();
+            }
+            assert_eq!(erc20.holder_count(), 6);
+            assert_eq!(erc20.holders(0, 4).len(), 4);
+            assert_eq!(
+                erc20.holders(1, 4),
+                vec![AccountId::from([0x5; 32]), AccountId::from([0x6; 32])]
+            );
+            assert!(erc20.holders(2, 4).is_empty());
         }
     }
 }
@@ -0,0 +1,581 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Lottery paid in the course `Erc20`.
+///
+/// The owner starts a round, players buy tickets until the round's sales end
+/// and then one ticket is drawn. Its holder gets the pot minus the owner's
+/// fee.
+///
+/// The winner only depends on data fixed when sales end, so nobody can pick
+/// the block of the draw to choose the outcome. With `Randomness::Chain` that
+/// data is a hash chained through every purchase with its block, which the
+/// last buyers and block producers can still influence: contracts get no
+/// random seed from the chain. When that matters use
+/// `Randomness::CommitReveal`: the owner commits to the hash of a secret seed
+/// when starting the round and reveals the seed to draw, which is mixed with
+/// the hash of the purchases. The owner can't pick a seed after seeing the
+/// tickets, and the seed alone doesn't tell which ticket wins. An owner who
+/// also plays still knows more than the other players, though: with the seed
+/// in hand they can try out the last purchases until one of them wins, so the
+/// players have to trust the owner not to. The owner can also refuse to
+/// reveal. If the seed isn't revealed within `reveal_period` after sales end,
+/// anyone can cancel the round and the players get their tickets refunded.
+#[ink::contract]
+mod lottery {
+    use erc20::Erc20Ref;
+    use ink::env::hash::Blake2x256;
+    use ink::storage::Mapping;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Randomness {
+        Chain,
+        CommitReveal,
+    }
+
+    #[ink(storage)]
+    pub struct Lottery {
+        token: AccountId,
+        owner: AccountId,
+        ticket_price: Balance,
+        /// Owner's cut of the pot, in basis points.
+        fee_bps: u16,
+        randomness: Randomness,
+        round: u32,
+        /// Tickets can be bought until this timestamp. `None` between rounds.
+        sales_end: Option<Timestamp>,
+        /// Hash of the owner's seed under `Randomness::CommitReveal`.
+        commitment: Option<Hash>,
+        /// One entry per ticket sold this round, by index. Entries from
+        /// earlier rounds past `ticket_count` are stale.
+        tickets: Mapping<u32, AccountId>,
+        ticket_count: u32,
+        /// How long after sales end the owner has to reveal the seed.
+        reveal_period: Timestamp,
+        /// Hash of the round's purchases so far, the seed of a chain draw.
+        entropy: Hash,
+        /// Tickets each player bought, keyed by `(round, player)`.
+        tickets_bought: Mapping<(u32, AccountId), u32>,
+        /// Rounds whose seed wasn't revealed in time, so players get refunds.
+        cancelled_rounds: Mapping<u32, ()>,
+    }
+
+    #[ink(event)]
+    pub struct RoundStarted {
+        #[ink(topic)]
+        round: u32,
+        sales_end: Timestamp,
+        commitment: Option<Hash>,
+    }
+
+    #[ink(event)]
+    pub struct TicketsBought {
+        #[ink(topic)]
+        round: u32,
+        #[ink(topic)]
+        player: AccountId,
+        count: u32,
+    }
+
+    #[ink(event)]
+    pub struct Drawn {
+        #[ink(topic)]
+        round: u32,
+        #[ink(topic)]
+        winner: Option<AccountId>,
+        prize: Balance,
+        fee: Balance,
+    }
+
+    #[ink(event)]
+    pub struct RoundCancelled {
+        #[ink(topic)]
+        round: u32,
+    }
+
+    #[ink(event)]
+    pub struct Refunded {
+        #[ink(topic)]
+        round: u32,
+        #[ink(topic)]
+        player: AccountId,
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        RoundOpen,
+        NoRound,
+        SalesEnded,
+        SalesNotEnded,
+        /// A commit-reveal round needs a commitment, a chain one must not have
+        /// one.
+        CommitmentMismatch,
+        /// The revealed seed does not hash to the commitment.
+        InvalidSeed,
+        WrongRandomness,
+        /// The owner can't reveal any more, the round can only be cancelled.
+        RevealPeriodOver,
+        /// The owner may still reveal.
+        RevealPeriodNotOver,
+        RoundNotCancelled,
+        NothingToRefund,
+        ZeroTickets,
+        Overflow,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Lottery {
+        /// `reveal_period` only matters under `Randomness::CommitReveal`.
+        #[ink(constructor)]
+        pub fn new(
+            token: AccountId,
+            ticket_price: Balance,
+            fee_bps: u16,
+            randomness: Randomness,
+            reveal_period: Timestamp,
+        ) -> Self {
+            assert!(fee_bps <= 10_000, "fee exceeds the pot");
+            Self {
+                token,
+                owner: Self::env().caller(),
+                ticket_price,
+                fee_bps,
+                randomness,
+                round: 0,
+                sales_end: None,
+                commitment: None,
+                tickets: Mapping::default(),
+                ticket_count: 0,
+                reveal_period,
+                entropy: Hash::default(),
+                tickets_bought: Mapping::default(),
+                cancelled_rounds: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn round(&self) -> u32 {
+            self.round
+        }
+
+        #[ink(message)]
+        pub fn sales_end(&self) -> Option<Timestamp> {
+            self.sales_end
+        }
+
+        #[ink(message)]
+        pub fn ticket_count(&self) -> u32 {
+            self.ticket_count
+        }
+
+        #[ink(message)]
+        pub fn pot(&self) -> Balance {
+            self.ticket_price * Balance::from(self.ticket_count)
+        }
+
+        #[ink(message)]
+        pub fn reveal_period(&self) -> Timestamp {
+            self.reveal_period
+        }
+
+        /// Tickets `player` bought in `round` and, if it was cancelled, has
+        /// not been refunded for yet.
+        #[ink(message)]
+        pub fn tickets_of(&self, round: u32, player: AccountId) -> u32 {
+            self.tickets_bought.get((round, player)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn is_cancelled(&self, round: u32) -> bool {
+            self.cancelled_rounds.contains(round)
+        }
+
+        /// Opens the next round. `commitment` is the Blake2x256 hash of the
+        /// seed that will be revealed to draw it, under
+        /// `Randomness::CommitReveal` only.
+        #[ink(message)]
+        pub fn start_round(
+            &mut self,
+            sales_end: Timestamp,
+            commitment: Option<Hash>,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            if self.sales_end.is_some() {
+                return Err(Error::RoundOpen);
+            }
+            if commitment.is_some() != (self.randomness == Randomness::CommitReveal) {
+                return Err(Error::CommitmentMismatch);
+            }
+            self.round += 1;
+            self.sales_end = Some(sales_end);
+            self.commitment = commitment;
+            self.entropy = Hash::default();
+            ink::codegen::EmitEvent::<Lottery>::emit_event(
+                self.env(),
+                RoundStarted {
+                    round: self.round,
+                    sales_end,
+                    commitment,
+                },
+            );
+            Ok(())
+        }
+
+        /// Buys `count` tickets for the caller, who must have approved the
+        /// lottery for their price.
+        #[ink(message)]
+        pub fn buy_tickets(&mut self, count: u32) -> Result<()> {
+            let sales_end = self.sales_end.ok_or(Error::NoRound)?;
+            if self.env().block_timestamp() >= sales_end {
+                return Err(Error::SalesEnded);
+            }
+            if count == 0 {
+                return Err(Error::ZeroTickets);
+            }
+            let price = self
+                .ticket_price
+                .checked_mul(Balance::from(count))
+                .ok_or(Error::Overflow)?;
+            let ticket_count = self
+                .ticket_count
+                .checked_add(count)
+                .ok_or(Error::Overflow)?;
+            let player = self.env().caller();
+            self.token_contract()
+                .transfer_from(player, self.env().account_id(), price)?;
+            for ticket in self.ticket_count..ticket_count {
+                self.tickets.insert(ticket, &player);
+            }
+            self.ticket_count = ticket_count;
+            let bought = self.tickets_of(self.round, player) + count;
+            self.tickets_bought.insert((self.round, player), &bought);
+            self.entropy = Hash::from(self.env().hash_encoded::<Blake2x256, _>(&(
+                self.entropy,
+                player,
+                count,
+                self.env().block_number(),
+                self.env().block_timestamp(),
+            )));
+            ink::codegen::EmitEvent::<Lottery>::emit_event(
+                self.env(),
+                TicketsBought {
+                    round: self.round,
+                    player,
+                    count,
+                },
+            );
+            Ok(())
+        }
+
+        /// Draws the round from its purchases. Anyone may call this once
+        /// sales have ended.
+        #[ink(message)]
+        pub fn draw(&mut self) -> Result<()> {
+            if self.randomness != Randomness::Chain {
+                return Err(Error::WrongRandomness);
+            }
+            self.ensure_sales_ended()?;
+            self.settle(&[])
+        }
+
+        /// Draws a commit-reveal round with the seed committed to in
+        /// `start_round`.
+        #[ink(message)]
+        pub fn reveal_and_draw(&mut self, seed: [u8; 32]) -> Result<()> {
+            self.ensure_owner()?;
+            if self.randomness != Randomness::CommitReveal {
+                return Err(Error::WrongRandomness);
+            }
+            let sales_end = self.ensure_sales_ended()?;
+            if self.env().block_timestamp() >= sales_end.saturating_add(self.reveal_period) {
+                return Err(Error::RevealPeriodOver);
+            }
+            if self.commitment != Some(Hash::from(self.env().hash_bytes::<Blake2x256>(&seed))) {
+                return Err(Error::InvalidSeed);
+            }
+            self.settle(&seed)
+        }
+
+        /// Closes a commit-reveal round whose seed the owner didn't reveal in
+        /// time, so its players can `refund` their tickets. Anyone may call it.
+        #[ink(message)]
+        pub fn cancel_round(&mut self) -> Result<()> {
+            if self.randomness != Randomness::CommitReveal {
+                return Err(Error::WrongRandomness);
+            }
+            let sales_end = self.ensure_sales_ended()?;
+            if self.env().block_timestamp() < sales_end.saturating_add(self.reveal_period) {
+                return Err(Error::RevealPeriodNotOver);
+            }
+            self.cancelled_rounds.insert(self.round, &());
+            self.close_round();
+            ink::codegen::EmitEvent::<Lottery>::emit_event(
+                self.env(),
+                RoundCancelled { round: self.round },
+            );
+            Ok(())
+        }
+
+        /// Pays the caller back the tickets it bought in the cancelled `round`
+        /// and returns the amount.
+        #[ink(message)]
+        pub fn refund(&mut self, round: u32) -> Result<Balance> {
+            if !self.is_cancelled(round) {
+                return Err(Error::RoundNotCancelled);
+            }
+            let player = self.env().caller();
+            let count = self
+                .tickets_bought
+                .take((round, player))
+                .ok_or(Error::NothingToRefund)?;
+            let amount = self.ticket_price * Balance::from(count);
+            self.token_contract().transfer(player, amount)?;
+            ink::codegen::EmitEvent::<Lottery>::emit_event(
+                self.env(),
+                Refunded {
+                    round,
+                    player,
+                    amount,
+                },
+            );
+            Ok(amount)
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        /// The end of the open round's sales, once they are over.
+        fn ensure_sales_ended(&self) -> Result<Timestamp> {
+            let sales_end = self.sales_end.ok_or(Error::NoRound)?;
+            if self.env().block_timestamp() < sales_end {
+                return Err(Error::SalesNotEnded);
+            }
+            Ok(sales_end)
+        }
+
+        /// The ticket `seed` draws in the open round, mixed with the hash of
+        /// its purchases. Nothing about the block of the draw goes in.
+        fn drawn_ticket(&self, seed: &[u8]) -> u32 {
+            let mixed = self
+                .env()
+                .hash_encoded::<Blake2x256, _>(&(seed, self.entropy, self.round));
+            winning_ticket(&mixed, self.ticket_count)
+        }
+
+        /// Picks the winner from `seed`, pays out and closes the round.
+        fn settle(&mut self, seed: &[u8]) -> Result<()> {
+            let pot = self.pot();
+            let fee = pot * Balance::from(self.fee_bps) / 10_000;
+            let prize = pot - fee;
+            let winner = if self.ticket_count == 0 {
+                None
+            } else {
+                self.tickets.get(self.drawn_ticket(seed))
+            };
+            if let Some(winner) = winner {
+                self.token_contract().transfer(winner, prize)?;
+                if fee > 0 {
+                    self.token_contract().transfer(self.owner, fee)?;
+                }
+            }
+            self.close_round();
+            ink::codegen::EmitEvent::<Lottery>::emit_event(
+                self.env(),
+                Drawn {
+                    round: self.round,
+                    winner,
+                    prize,
+                    fee,
+                },
+            );
+            Ok(())
+        }
+
+        fn close_round(&mut self) {
+            self.ticket_count = 0;
+            self.sales_end = None;
+            self.commitment = None;
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    /// Index of the winning ticket out of `ticket_count` for a random `seed`.
+    fn winning_ticket(seed: &[u8; 32], ticket_count: u32) -> u32 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&seed[..8]);
+        (u64::from_le_bytes(bytes) % u64::from(ticket_count)) as u32
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn commitment(seed: [u8; 32]) -> Hash {
+            let mut output = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&seed, &mut output);
+            Hash::from(output)
+        }
+
+        const REVEAL_PERIOD: Timestamp = 1_000;
+
+        fn now() -> Timestamp {
+            ink::env::block_timestamp::<ink::env::DefaultEnvironment>()
+        }
+
+        fn set_now(timestamp: Timestamp) {
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+        }
+
+        fn commit_reveal_round(seed: [u8; 32]) -> Lottery {
+            let mut lottery = Lottery::new(
+                AccountId::from([0x9; 32]),
+                10,
+                500,
+                Randomness::CommitReveal,
+                REVEAL_PERIOD,
+            );
+            lottery.start_round(now(), Some(commitment(seed))).unwrap();
+            lottery
+        }
+
+        #[ink::test]
+        fn winning_ticket_is_in_range() {
+            assert_eq!(winning_ticket(&[0xff; 32], 1), 0);
+            assert_eq!(winning_ticket(&[0x0; 32], 7), 0);
+            let mut seed = [0x0; 32];
+            seed[0] = 10;
+            assert_eq!(winning_ticket(&seed, 7), 3);
+        }
+
+        #[ink::test]
+        fn start_round_checks_commitment() {
+            let token = AccountId::from([0x9; 32]);
+            let mut chain = Lottery::new(token, 10, 500, Randomness::Chain, 0);
+            assert_eq!(
+                chain.start_round(now() + 10, Some(commitment([0x1; 32]))),
+                Err(Error::CommitmentMismatch)
+            );
+            assert_eq!(chain.start_round(now() + 10, None), Ok(()));
+            assert_eq!(chain.start_round(now() + 10, None), Err(Error::RoundOpen));
+            assert_eq!(chain.round(), 1);
+
+            let mut commit_reveal =
+                Lottery::new(token, 10, 500, Randomness::CommitReveal, REVEAL_PERIOD);
+            assert_eq!(
+                commit_reveal.start_round(now() + 10, None),
+                Err(Error::CommitmentMismatch)
+            );
+        }
+
+        #[ink::test]
+        fn draw_waits_for_sales_end() {
+            let mut lottery =
+                Lottery::new(AccountId::from([0x9; 32]), 10, 500, Randomness::Chain, 0);
+            assert_eq!(lottery.draw(), Err(Error::NoRound));
+            lottery.start_round(now() + 10, None).unwrap();
+            assert_eq!(lottery.draw(), Err(Error::SalesNotEnded));
+            assert_eq!(
+                lottery.reveal_and_draw([0x1; 32]),
+                Err(Error::WrongRandomness)
+            );
+        }
+
+        #[ink::test]
+        fn draw_without_tickets_closes_round() {
+            let mut lottery =
+                Lottery::new(AccountId::from([0x9; 32]), 10, 500, Randomness::Chain, 0);
+            lottery.start_round(now(), None).unwrap();
+            assert_eq!(lottery.buy_tickets(1), Err(Error::SalesEnded));
+            assert_eq!(lottery.draw(), Ok(()));
+            assert_eq!(lottery.sales_end(), None);
+        }
+
+        #[ink::test]
+        fn reveal_checks_seed() {
+            let seed = [0x1; 32];
+            let mut lottery = Lottery::new(
+                AccountId::from([0x9; 32]),
+                10,
+                500,
+                Randomness::CommitReveal,
+                REVEAL_PERIOD,
+            );
+            lottery.start_round(now(), Some(commitment(seed))).unwrap();
+            assert_eq!(lottery.reveal_and_draw([0x2; 32]), Err(Error::InvalidSeed));
+            assert_eq!(lottery.reveal_and_draw(seed), Ok(()));
+            assert_eq!(lottery.round(), 1);
+            assert_eq!(lottery.sales_end(), None);
+        }
+
+        #[ink::test]
+        fn drawn_ticket_ignores_the_block() {
+            let mut lottery = commit_reveal_round([0x1; 32]);
+            lottery.ticket_count = 1_000;
+            let ticket = lottery.drawn_ticket(&[0x1; 32]);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            set_now(now() + 12_345);
+            assert_eq!(lottery.drawn_ticket(&[0x1; 32]), ticket);
+            assert_ne!(lottery.drawn_ticket(&[0x2; 32]), ticket);
+        }
+
+        #[ink::test]
+        fn drawn_ticket_depends_on_the_purchases() {
+            let seed = [0x1; 32];
+            let mut lottery = commit_reveal_round(seed);
+            lottery.ticket_count = 1_000;
+            let ticket = lottery.drawn_ticket(&seed);
+            // Another purchase changes the entropy, and with it the winner,
+            // even with the same number of tickets sold.
+            lottery.entropy = Hash::from([0x5; 32]);
+            assert_ne!(lottery.drawn_ticket(&seed), ticket);
+        }
+
+        #[ink::test]
+        fn unrevealed_round_can_be_cancelled() {
+            let seed = [0x1; 32];
+            let mut lottery = commit_reveal_round(seed);
+            let player = AccountId::from([0x3; 32]);
+            lottery.tickets_bought.insert((1, player), &2);
+            let sales_end = lottery.sales_end().unwrap();
+            assert_eq!(lottery.cancel_round(), Err(Error::RevealPeriodNotOver));
+            assert_eq!(lottery.refund(1), Err(Error::RoundNotCancelled));
+            set_now(sales_end + REVEAL_PERIOD);
+            assert_eq!(lottery.reveal_and_draw(seed), Err(Error::RevealPeriodOver));
+            assert_eq!(lottery.cancel_round(), Ok(()));
+            assert!(lottery.is_cancelled(1));
+            assert_eq!(lottery.sales_end(), None);
+            assert_eq!(lottery.tickets_of(1, player), 2);
+            // The caller bought no tickets.
+            assert_eq!(lottery.refund(1), Err(Error::NothingToRefund));
+            assert_eq!(lottery.cancel_round(), Err(Error::NoRound));
+        }
+
+        #[ink::test]
+        fn chain_rounds_cannot_be_cancelled() {
+            let mut lottery =
+                Lottery::new(AccountId::from([0x9; 32]), 10, 500, Randomness::Chain, 0);
+            lottery.start_round(now(), None).unwrap();
+            assert_eq!(lottery.cancel_round(), Err(Error::WrongRandomness));
+        }
+    }
+}
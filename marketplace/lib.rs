@@ -0,0 +1,444 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Sells `Erc721` tokens for the course `Erc20`.
+///
+/// Sellers list a token with a buy-now price and the lowest bid they would
+/// consider. Listing moves the NFT into the marketplace, which must be
+/// approved for it, so a listed token can't change hands elsewhere. Anyone can
+/// buy at the listed price, or bid: bids are held by the marketplace until
+/// they are outbid, withdrawn, accepted by the seller or the listing ends.
+///
+/// Every sale pays `fee_bps` of the price to the treasury through
+/// `transfer_and_call`, so the treasury has to accept tokens the way the
+/// workspace's `treasury` does, and `royalty_bps` to whoever minted the NFT
+/// unless they are the seller. The seller gets the rest.
+#[ink::contract]
+mod marketplace {
+    use erc20::Erc20Ref;
+    use erc721::{Erc721Ref, TokenId};
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    /// An NFT contract and a token of it.
+    pub type Item = (AccountId, TokenId);
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Listing {
+        pub seller: AccountId,
+        /// Buys the token outright.
+        pub price: Balance,
+        pub min_bid: Balance,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Bid {
+        pub bidder: AccountId,
+        pub amount: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct Marketplace {
+        token: AccountId,
+        treasury: AccountId,
+        fee_bps: u16,
+        royalty_bps: u16,
+        listings: Mapping<Item, Listing>,
+        /// The highest bid on each listing.
+        bids: Mapping<Item, Bid>,
+    }
+
+    #[ink(event)]
+    pub struct Listed {
+        #[ink(topic)]
+        nft: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+        seller: AccountId,
+        price: Balance,
+        min_bid: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Cancelled {
+        #[ink(topic)]
+        nft: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    #[ink(event)]
+    pub struct BidPlaced {
+        #[ink(topic)]
+        nft: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        bidder: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BidWithdrawn {
+        #[ink(topic)]
+        nft: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        bidder: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Sold {
+        #[ink(topic)]
+        nft: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+        seller: AccountId,
+        #[ink(topic)]
+        buyer: AccountId,
+        price: Balance,
+        fee: Balance,
+        royalty: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotListed,
+        AlreadyListed,
+        NotSeller,
+        ZeroPrice,
+        /// The bid is below the listing's minimum or not above the highest
+        /// bid.
+        BidTooLow,
+        NoBid,
+        NotBidder,
+        TokenError(erc20::Error),
+        NftError(erc721::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl From<erc721::Error> for Error {
+        fn from(error: erc721::Error) -> Self {
+            Error::NftError(error)
+        }
+    }
+
+    impl Marketplace {
+        #[ink(constructor)]
+        pub fn new(token: AccountId, treasury: AccountId, fee_bps: u16, royalty_bps: u16) -> Self {
+            assert!(
+                u32::from(fee_bps) + u32::from(royalty_bps) <= 10_000,
+                "fees exceed the price"
+            );
+            Self {
+                token,
+                treasury,
+                fee_bps,
+                royalty_bps,
+                listings: Mapping::default(),
+                bids: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn listing(&self, nft: AccountId, id: TokenId) -> Option<Listing> {
+            self.listings.get((nft, id))
+        }
+
+        #[ink(message)]
+        pub fn highest_bid(&self, nft: AccountId, id: TokenId) -> Option<Bid> {
+            self.bids.get((nft, id))
+        }
+
+        /// Lists the caller's token `id` of `nft`, moving it into the
+        /// marketplace.
+        #[ink(message)]
+        pub fn list(
+            &mut self,
+            nft: AccountId,
+            id: TokenId,
+            price: Balance,
+            min_bid: Balance,
+        ) -> Result<()> {
+            let seller = self.env().caller();
+            if price == 0 {
+                return Err(Error::ZeroPrice);
+            }
+            if self.listings.contains((nft, id)) {
+                return Err(Error::AlreadyListed);
+            }
+            self.listings.insert(
+                (nft, id),
+                &Listing {
+                    seller,
+                    price,
+                    min_bid,
+                },
+            );
+            nft_contract(nft).transfer_from(seller, self.env().account_id(), id)?;
+            ink::codegen::EmitEvent::<Marketplace>::emit_event(
+                self.env(),
+                Listed {
+                    nft,
+                    id,
+                    seller,
+                    price,
+                    min_bid,
+                },
+            );
+            Ok(())
+        }
+
+        /// Ends the caller's listing, refunding the highest bid and returning
+        /// the token.
+        #[ink(message)]
+        pub fn cancel(&mut self, nft: AccountId, id: TokenId) -> Result<()> {
+            let listing = self.seller_listing((nft, id))?;
+            self.listings.remove((nft, id));
+            self.refund_bid((nft, id))?;
+            nft_contract(nft).transfer(listing.seller, id)?;
+            ink::codegen::EmitEvent::<Marketplace>::emit_event(self.env(), Cancelled { nft, id });
+            Ok(())
+        }
+
+        /// Buys the token at its listed price, which the caller must have
+        /// approved the marketplace for.
+        #[ink(message)]
+        pub fn buy(&mut self, nft: AccountId, id: TokenId) -> Result<()> {
+            let buyer = self.env().caller();
+            let listing = self.listings.get((nft, id)).ok_or(Error::NotListed)?;
+            self.listings.remove((nft, id));
+            self.refund_bid((nft, id))?;
+            self.token_contract()
+                .transfer_from(buyer, self.env().account_id(), listing.price)?;
+            self.settle((nft, id), listing.seller, buyer, listing.price)
+        }
+
+        /// Bids `amount`, which the caller must have approved the marketplace
+        /// for, and refunds the bid it replaces.
+        #[ink(message)]
+        pub fn bid(&mut self, nft: AccountId, id: TokenId, amount: Balance) -> Result<()> {
+            let bidder = self.env().caller();
+            let listing = self.listings.get((nft, id)).ok_or(Error::NotListed)?;
+            let highest = self.bids.get((nft, id)).map_or(0, |bid| bid.amount);
+            if amount == 0 || amount < listing.min_bid || amount <= highest {
+                return Err(Error::BidTooLow);
+            }
+            self.refund_bid((nft, id))?;
+            self.bids.insert((nft, id), &Bid { bidder, amount });
+            self.token_contract()
+                .transfer_from(bidder, self.env().account_id(), amount)?;
+            ink::codegen::EmitEvent::<Marketplace>::emit_event(
+                self.env(),
+                BidPlaced {
+                    nft,
+                    id,
+                    bidder,
+                    amount,
+                },
+            );
+            Ok(())
+        }
+
+        /// Takes back the caller's bid, as long as it is the highest one.
+        #[ink(message)]
+        pub fn withdraw_bid(&mut self, nft: AccountId, id: TokenId) -> Result<()> {
+            let bid = self.bids.get((nft, id)).ok_or(Error::NoBid)?;
+            if bid.bidder != self.env().caller() {
+                return Err(Error::NotBidder);
+            }
+            self.refund_bid((nft, id))
+        }
+
+        /// Sells the token to the highest bidder for their bid.
+        #[ink(message)]
+        pub fn accept_bid(&mut self, nft: AccountId, id: TokenId) -> Result<()> {
+            let listing = self.seller_listing((nft, id))?;
+            let bid = self.bids.take((nft, id)).ok_or(Error::NoBid)?;
+            self.listings.remove((nft, id));
+            self.settle((nft, id), listing.seller, bid.bidder, bid.amount)
+        }
+
+        /// Splits `price` into the treasury's fee, the creator's royalty and
+        /// the seller's proceeds.
+        fn split(&self, price: Balance, pays_royalty: bool) -> (Balance, Balance, Balance) {
+            let fee = price * Balance::from(self.fee_bps) / 10_000;
+            let royalty = if pays_royalty {
+                price * Balance::from(self.royalty_bps) / 10_000
+            } else {
+                0
+            };
+            (fee, royalty, price - fee - royalty)
+        }
+
+        /// Pays out `price`, which the marketplace holds, and hands the token
+        /// to `buyer`.
+        fn settle(
+            &mut self,
+            (nft, id): Item,
+            seller: AccountId,
+            buyer: AccountId,
+            price: Balance,
+        ) -> Result<()> {
+            let mut nft_contract = nft_contract(nft);
+            let creator = nft_contract
+                .creator_of(id)
+                .filter(|&creator| creator != seller);
+            let (fee, royalty, proceeds) = self.split(price, creator.is_some());
+            let mut token = self.token_contract();
+            if fee > 0 {
+                token.transfer_and_call(self.treasury, fee, Vec::new())?;
+            }
+            if let Some(creator) = creator.filter(|_| royalty > 0) {
+                token.transfer(creator, royalty)?;
+            }
+            if proceeds > 0 {
+                token.transfer(seller, proceeds)?;
+            }
+            nft_contract.transfer(buyer, id)?;
+            ink::codegen::EmitEvent::<Marketplace>::emit_event(
+                self.env(),
+                Sold {
+                    nft,
+                    id,
+                    seller,
+                    buyer,
+                    price,
+                    fee,
+                    royalty,
+                },
+            );
+            Ok(())
+        }
+
+        /// Returns the highest bid on `item`, if any, to its bidder.
+        fn refund_bid(&mut self, (nft, id): Item) -> Result<()> {
+            if let Some(Bid { bidder, amount }) = self.bids.take((nft, id)) {
+                self.token_contract().transfer(bidder, amount)?;
+                ink::codegen::EmitEvent::<Marketplace>::emit_event(
+                    self.env(),
+                    BidWithdrawn {
+                        nft,
+                        id,
+                        bidder,
+                        amount,
+                    },
+                );
+            }
+            Ok(())
+        }
+
+        fn seller_listing(&self, item: Item) -> Result<Listing> {
+            let listing = self.listings.get(item).ok_or(Error::NotListed)?;
+            if listing.seller != self.env().caller() {
+                return Err(Error::NotSeller);
+            }
+            Ok(listing)
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    fn nft_contract(nft: AccountId) -> Erc721Ref {
+        ink::env::call::FromAccountId::from_account_id(nft)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const NFT: [u8; 32] = [0x7; 32];
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        /// A marketplace taking 2.5% for the treasury and 5% for creators,
+        /// with token 1 listed by bob for 1000 and bids from 100.
+        fn marketplace() -> Marketplace {
+            let mut marketplace = Marketplace::new(
+                AccountId::from([0x9; 32]),
+                AccountId::from([0x8; 32]),
+                250,
+                500,
+            );
+            marketplace.listings.insert(
+                (AccountId::from(NFT), 1),
+                &Listing {
+                    seller: accounts().bob,
+                    price: 1000,
+                    min_bid: 100,
+                },
+            );
+            marketplace
+        }
+
+        #[ink::test]
+        fn price_is_split() {
+            let marketplace = marketplace();
+            assert_eq!(marketplace.split(1000, true), (25, 50, 925));
+            assert_eq!(marketplace.split(1000, false), (25, 0, 975));
+            assert_eq!(marketplace.split(39, true), (0, 1, 38));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "fees exceed the price")]
+        fn fees_are_capped() {
+            Marketplace::new(
+                AccountId::from([0x9; 32]),
+                AccountId::from([0x8; 32]),
+                5_000,
+                5_001,
+            );
+        }
+
+        #[ink::test]
+        fn bids_must_beat_the_highest() {
+            let mut marketplace = marketplace();
+            let nft = AccountId::from(NFT);
+            assert_eq!(marketplace.bid(nft, 2, 500), Err(Error::NotListed));
+            assert_eq!(marketplace.bid(nft, 1, 99), Err(Error::BidTooLow));
+            marketplace.bids.insert(
+                (nft, 1),
+                &Bid {
+                    bidder: accounts().charlie,
+                    amount: 300,
+                },
+            );
+            assert_eq!(marketplace.bid(nft, 1, 300), Err(Error::BidTooLow));
+            assert_eq!(marketplace.withdraw_bid(nft, 1), Err(Error::NotBidder));
+            assert_eq!(marketplace.withdraw_bid(nft, 2), Err(Error::NoBid));
+        }
+
+        #[ink::test]
+        fn only_the_seller_ends_a_listing() {
+            let mut marketplace = marketplace();
+            let nft = AccountId::from(NFT);
+            assert_eq!(marketplace.list(nft, 1, 0, 0), Err(Error::ZeroPrice));
+            assert_eq!(marketplace.list(nft, 1, 10, 0), Err(Error::AlreadyListed));
+            assert_eq!(marketplace.cancel(nft, 1), Err(Error::NotSeller));
+            assert_eq!(marketplace.accept_bid(nft, 1), Err(Error::NotSeller));
+            assert_eq!(marketplace.buy(nft, 2), Err(Error::NotListed));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts().bob);
+            assert_eq!(marketplace.accept_bid(nft, 1), Err(Error::NoBid));
+        }
+    }
+}
@@ -0,0 +1,244 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Programmable counterparty for the token's callbacks, used by the
+/// integration tests in `tests/callbacks.rs`.
+///
+/// It implements `TokenReceiver` and `FlashBorrower` and answers both as its
+/// `Behavior` says: accept, reject, or call back into the token before
+/// accepting. A flash loan is repaid by approving the token for `amount + fee`,
+/// so with a fee the mock has to hold the fee beforehand. The token is always
+/// the caller of a callback, so the mock needs no configuration to find it.
+#[ink::contract]
+mod mock_receiver {
+    use erc20::{Erc20Ref, Error};
+    use ink::prelude::vec::Vec;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Behavior {
+        Accept,
+        Reject,
+        /// Calls back into the token first, then accepts. On `on_flash_loan` it
+        /// takes out another flash loan. On `on_token_received` it sends the
+        /// tokens back with `transfer_and_call`, but the token doesn't allow
+        /// reentry there, so the runtime traps the mock and the transfer fails.
+        Reenter,
+    }
+
+    /// What became of the call back into the token in `Behavior::Reenter`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Reentry {
+        Succeeded,
+        /// The token's reentrancy guard refused the call.
+        Blocked,
+        /// The token refused the call for another reason.
+        Failed,
+    }
+
+    #[ink(storage)]
+    pub struct MockReceiver {
+        behavior: Behavior,
+        /// Total accepted through `on_token_received`.
+        received: Balance,
+        /// Number of flash loans accepted.
+        loans: u32,
+        last_reentry: Option<Reentry>,
+    }
+
+    #[ink(event)]
+    pub struct Received {
+        #[ink(topic)]
+        token: AccountId,
+        operator: AccountId,
+        from: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Borrowed {
+        #[ink(topic)]
+        token: AccountId,
+        initiator: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
+    impl MockReceiver {
+        #[ink(constructor)]
+        pub fn new(behavior: Behavior) -> Self {
+            Self {
+                behavior,
+                received: 0,
+                loans: 0,
+                last_reentry: None,
+            }
+        }
+
+        #[ink(message)]
+        pub fn behavior(&self) -> Behavior {
+            self.behavior
+        }
+
+        #[ink(message)]
+        pub fn set_behavior(&mut self, behavior: Behavior) {
+            self.behavior = behavior;
+        }
+
+        #[ink(message)]
+        pub fn received(&self) -> Balance {
+            self.received
+        }
+
+        #[ink(message)]
+        pub fn loans(&self) -> u32 {
+            self.loans
+        }
+
+        /// Outcome of the latest call back into the token.
+        #[ink(message)]
+        pub fn last_reentry(&self) -> Option<Reentry> {
+            self.last_reentry
+        }
+
+        fn record_reentry(&mut self, result: Result<(), Error>) {
+            self.last_reentry = Some(match result {
+                Ok(()) => Reentry::Succeeded,
+                Err(Error::ReentrancyDetected) => Reentry::Blocked,
+                Err(_) => Reentry::Failed,
+            });
+        }
+    }
+
+    impl psp22::TokenReceiver for MockReceiver {
+        #[ink(message)]
+        fn on_token_received(
+            &mut self,
+            operator: AccountId,
+            from: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> bool {
+            let token = self.env().caller();
+            match self.behavior {
+                Behavior::Accept => {}
+                Behavior::Reject => return false,
+                Behavior::Reenter => {
+                    let mut erc20: Erc20Ref = ink::env::call::FromAccountId::from_account_id(token);
+                    let result = erc20.transfer_and_call(from, value, Vec::new());
+                    self.record_reentry(result);
+                }
+            }
+            self.received += value;
+            ink::codegen::EmitEvent::<MockReceiver>::emit_event(
+                self.env(),
+                Received {
+                    token,
+                    operator,
+                    from,
+                    value,
+                },
+            );
+            true
+        }
+    }
+
+    impl psp22::FlashBorrower for MockReceiver {
+        #[ink(message)]
+        fn on_flash_loan(
+            &mut self,
+            initiator: AccountId,
+            amount: Balance,
+            fee: Balance,
+            data: Vec<u8>,
+        ) -> bool {
+            let token = self.env().caller();
+            let mut erc20: Erc20Ref = ink::env::call::FromAccountId::from_account_id(token);
+            match self.behavior {
+                Behavior::Accept => {}
+                Behavior::Reject => return false,
+                Behavior::Reenter => {
+                    let result = erc20.flash_loan(self.env().account_id(), amount, data);
+                    self.record_reentry(result);
+                }
+            }
+            let repayment = match amount.checked_add(fee) {
+                Some(repayment) => repayment,
+                None => return false,
+            };
+            if erc20.approve(token, repayment).is_err() {
+                return false;
+            }
+            self.loans += 1;
+            ink::codegen::EmitEvent::<MockReceiver>::emit_event(
+                self.env(),
+                Borrowed {
+                    token,
+                    initiator,
+                    amount,
+                    fee,
+                },
+            );
+            true
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use psp22::{FlashBorrower, TokenReceiver};
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        #[ink::test]
+        fn accepting_receiver_counts_tokens() {
+            let accounts = accounts();
+            let mut receiver = MockReceiver::new(Behavior::Accept);
+            assert!(receiver.on_token_received(accounts.bob, accounts.bob, 100, Vec::new()));
+            assert!(receiver.on_token_received(accounts.bob, accounts.charlie, 50, Vec::new()));
+            assert_eq!(receiver.received(), 150);
+            assert_eq!(ink::env::test::recorded_events().count(), 2);
+        }
+
+        #[ink::test]
+        fn rejecting_receiver_refuses_both_callbacks() {
+            let accounts = accounts();
+            let mut receiver = MockReceiver::new(Behavior::Reject);
+            assert!(!receiver.on_token_received(accounts.bob, accounts.bob, 100, Vec::new()));
+            assert!(!receiver.on_flash_loan(accounts.bob, 1000, 9, Vec::new()));
+            assert_eq!(receiver.received(), 0);
+            assert_eq!(receiver.loans(), 0);
+            assert_eq!(ink::env::test::recorded_events().count(), 0);
+        }
+
+        #[ink::test]
+        fn behavior_can_be_changed() {
+            let accounts = accounts();
+            let mut receiver = MockReceiver::new(Behavior::Reject);
+            receiver.set_behavior(Behavior::Accept);
+            assert_eq!(receiver.behavior(), Behavior::Accept);
+            assert!(receiver.on_token_received(accounts.bob, accounts.bob, 100, Vec::new()));
+        }
+
+        #[ink::test]
+        fn reentry_outcome_is_recorded() {
+            let mut receiver = MockReceiver::new(Behavior::Reenter);
+            assert_eq!(receiver.last_reentry(), None);
+            receiver.record_reentry(Err(Error::ReentrancyDetected));
+            assert_eq!(receiver.last_reentry(), Some(Reentry::Blocked));
+            receiver.record_reentry(Err(Error::TransferRejected));
+            assert_eq!(receiver.last_reentry(), Some(Reentry::Failed));
+            receiver.record_reentry(Ok(()));
+            assert_eq!(receiver.last_reentry(), Some(Reentry::Succeeded));
+        }
+    }
+}
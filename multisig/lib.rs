@@ -0,0 +1,534 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// An M-of-N multisig wallet that can call any message of another contract.
+///
+/// To put the `Erc20` mint behind the wallet, instantiate the wallet with the
+/// owner set and threshold, then call `transfer_ownership(<wallet address>)`
+/// on the token. From then on a mint is a transaction with
+/// `callee = <token address>`, `selector = <selector of issue>` and
+/// `input = (to, value).encode()` that only runs once `requirement` owners
+/// have confirmed it.
+///
+/// Owners and the requirement change through transactions the wallet sends to
+/// itself, e.g. `callee = <wallet address>`, `selector = <selector of
+/// add_owner>` and `input = owner.encode()`.
+#[ink::contract]
+mod multisig {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::CallFlags;
+    use ink::prelude::{vec, vec::Vec};
+    use ink::storage::Mapping;
+    use scale::Output;
+
+    pub type TransactionId = u32;
+
+    /// Raw argument bytes that are appended to the selector unchanged.
+    struct CallInput<'a>(&'a [u8]);
+
+    impl<'a> scale::Encode for CallInput<'a> {
+        fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    /// Return value of the called message, left encoded.
+    struct CallOutput(Vec<u8>);
+
+    impl scale::Decode for CallOutput {
+        fn decode<I: scale::Input>(input: &mut I) -> core::result::Result<Self, scale::Error> {
+            let mut output = vec![0; input.remaining_len()?.unwrap_or(0)];
+            input.read(&mut output)?;
+            Ok(Self(output))
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Transaction {
+        pub callee: AccountId,
+        pub selector: [u8; 4],
+        /// SCALE encoded arguments of the called message.
+        pub input: Vec<u8>,
+        pub transferred_value: Balance,
+        pub gas_limit: u64,
+    }
+
+    #[ink(storage)]
+    pub struct Multisig {
+        /// The owners by index, in the order they were added.
+        owners: Mapping<u32, AccountId>,
+        owner_count: u32,
+        is_owner: Mapping<AccountId, ()>,
+        requirement: u32,
+        transactions: Mapping<TransactionId, Transaction>,
+        /// Ids of the transactions not executed yet.
+        pending: Vec<TransactionId>,
+        next_transaction_id: TransactionId,
+        confirmations: Mapping<(TransactionId, AccountId), ()>,
+        confirmation_count: Mapping<TransactionId, u32>,
+    }
+
+    #[ink(event)]
+    pub struct Submission {
+        #[ink(topic)]
+        transaction: TransactionId,
+    }
+
+    #[ink(event)]
+    pub struct Confirmation {
+        #[ink(topic)]
+        transaction: TransactionId,
+        #[ink(topic)]
+        from: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Revocation {
+        #[ink(topic)]
+        transaction: TransactionId,
+        #[ink(topic)]
+        from: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Execution {
+        #[ink(topic)]
+        transaction: TransactionId,
+        success: bool,
+        /// SCALE encoded return value of the called message, empty if the
+        /// call trapped.
+        output: Vec<u8>,
+    }
+
+    #[ink(event)]
+    pub struct OwnerAddition {
+        #[ink(topic)]
+        owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct OwnerRemoval {
+        #[ink(topic)]
+        owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RequirementChange {
+        new_requirement: u32,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        NotWallet,
+        AlreadyOwner,
+        InvalidRequirement,
+        TransactionNotFound,
+        AlreadyConfirmed,
+        NotConfirmed,
+        NotEnoughConfirmations,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl Multisig {
+        /// Creates a wallet where any `requirement` of `owners` can execute a transaction.
+        #[ink(constructor)]
+        pub fn new(requirement: u32, owners: Vec<AccountId>) -> Self {
+            let mut wallet = Self {
+                owners: Mapping::default(),
+                owner_count: 0,
+                is_owner: Mapping::default(),
+                requirement,
+                transactions: Mapping::default(),
+                pending: Vec::new(),
+                next_transaction_id: 0,
+                confirmations: Mapping::default(),
+                confirmation_count: Mapping::default(),
+            };
+            for owner in owners {
+                if !wallet.is_owner.contains(owner) {
+                    wallet.push_owner(owner);
+                }
+            }
+            assert!(
+                Self::valid_requirement(wallet.owner_count, requirement),
+                "invalid requirement"
+            );
+            wallet
+        }
+
+        #[ink(message)]
+        pub fn owners(&self) -> Vec<AccountId> {
+            (0..self.owner_count)
+                .filter_map(|index| self.owners.get(index))
+                .collect()
+        }
+
+        #[ink(message)]
+        pub fn requirement(&self) -> u32 {
+            self.requirement
+        }
+
+        #[ink(message)]
+        pub fn transaction(&self, id: TransactionId) -> Option<Transaction> {
+            self.transactions.get(id)
+        }
+
+        #[ink(message)]
+        pub fn confirmations(&self, id: TransactionId) -> u32 {
+            self.confirmation_count.get(id).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn is_confirmed_by(&self, id: TransactionId, owner: AccountId) -> bool {
+            self.confirmations.contains((id, owner))
+        }
+
+        /// Proposes a call and confirms it on behalf of the submitting owner.
+        #[ink(message)]
+        pub fn submit_transaction(&mut self, transaction: Transaction) -> Result<TransactionId> {
+            self.ensure_owner()?;
+            let id = self.next_transaction_id;
+            self.next_transaction_id = id.checked_add(1).expect("transaction id overflow");
+            self.transactions.insert(id, &transaction);
+            self.pending.push(id);
+            self.env().emit_event(Submission { transaction: id });
+            self.confirm(id)?;
+            Ok(id)
+        }
+
+        #[ink(message)]
+        pub fn confirm(&mut self, id: TransactionId) -> Result<()> {
+            let caller = self.ensure_owner()?;
+            if !self.transactions.contains(id) {
+                return Err(Error::TransactionNotFound);
+            }
+            if self.confirmations.contains((id, caller)) {
+                return Err(Error::AlreadyConfirmed);
+            }
+            self.confirmations.insert((id, caller), &());
+            self.confirmation_count
+                .insert(id, &(self.confirmations(id) + 1));
+            self.env().emit_event(Confirmation {
+                transaction: id,
+                from: caller,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn revoke_confirmation(&mut self, id: TransactionId) -> Result<()> {
+            let caller = self.ensure_owner()?;
+            if self.confirmations.take((id, caller)).is_none() {
+                return Err(Error::NotConfirmed);
+            }
+            self.confirmation_count
+                .insert(id, &(self.confirmations(id) - 1));
+            self.env().emit_event(Revocation {
+                transaction: id,
+                from: caller,
+            });
+            Ok(())
+        }
+
+        /// Fires a transaction once it has at least `requirement` confirmations
+        /// and returns whether the call succeeded. A failed call still uses up
+        /// the transaction, and the `Execution` event records the failure.
+        ///
+        /// The called message is expected to return `()` or a `Result`, as the
+        /// `Erc20`'s messages do. An `Err` counts as a failure.
+        #[ink(message, payable)]
+        pub fn execute(&mut self, id: TransactionId) -> Result<bool> {
+            self.ensure_owner()?;
+            if !self.transactions.contains(id) {
+                return Err(Error::TransactionNotFound);
+            }
+            if self.confirmations(id) < self.requirement {
+                return Err(Error::NotEnoughConfirmations);
+            }
+            let transaction = self.take_transaction(id);
+            // Only a call to the wallet itself may re-enter it, to manage the
+            // owners. It runs on the stored fields and changes them.
+            let to_self = transaction.callee == self.env().account_id();
+            if to_self {
+                self.flush();
+            }
+            let result = build_call::<ink::env::DefaultEnvironment>()
+                .call(transaction.callee)
+                .call_flags(CallFlags::default().set_allow_reentry(to_self))
+                .gas_limit(transaction.gas_limit)
+                .transferred_value(transaction.transferred_value)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(transaction.selector))
+                        .push_arg(CallInput(&transaction.input)),
+                )
+                .returns::<CallOutput>()
+                .try_invoke();
+            if to_self {
+                self.reload();
+            }
+            let output = match result {
+                Ok(Ok(CallOutput(output))) => Some(output),
+                _ => None,
+            };
+            let success = output.as_deref().is_some_and(returned_ok);
+            self.env().emit_event(Execution {
+                transaction: id,
+                success,
+                output: output.unwrap_or_default(),
+            });
+            Ok(success)
+        }
+
+        /// Only callable by the wallet itself through an executed transaction.
+        #[ink(message)]
+        pub fn add_owner(&mut self, owner: AccountId) -> Result<()> {
+            self.ensure_from_wallet()?;
+            if self.is_owner.contains(owner) {
+                return Err(Error::AlreadyOwner);
+            }
+            self.push_owner(owner);
+            self.env().emit_event(OwnerAddition { owner });
+            Ok(())
+        }
+
+        /// Only callable by the wallet itself through an executed transaction.
+        #[ink(message)]
+        pub fn remove_owner(&mut self, owner: AccountId) -> Result<()> {
+            self.ensure_from_wallet()?;
+            let index = (0..self.owner_count)
+                .find(|&index| self.owners.get(index) == Some(owner))
+                .ok_or(Error::NotOwner)?;
+            let remaining = self.owner_count - 1;
+            if !Self::valid_requirement(remaining, self.requirement.min(remaining)) {
+                return Err(Error::InvalidRequirement);
+            }
+            // Move the last owner into the freed index.
+            let last = self.owners.take(remaining).expect("owners are contiguous");
+            if index != remaining {
+                self.owners.insert(index, &last);
+            }
+            self.owner_count = remaining;
+            self.is_owner.remove(owner);
+            self.clear_confirmations_of(owner);
+            if self.requirement > remaining {
+                self.requirement = remaining;
+                self.env().emit_event(RequirementChange {
+                    new_requirement: remaining,
+                });
+            }
+            self.env().emit_event(OwnerRemoval { owner });
+            Ok(())
+        }
+
+        /// Only callable by the wallet itself through an executed transaction.
+        #[ink(message)]
+        pub fn change_requirement(&mut self, new_requirement: u32) -> Result<()> {
+            self.ensure_from_wallet()?;
+            if !Self::valid_requirement(self.owner_count, new_requirement) {
+                return Err(Error::InvalidRequirement);
+            }
+            self.requirement = new_requirement;
+            self.env().emit_event(RequirementChange { new_requirement });
+            Ok(())
+        }
+
+        fn push_owner(&mut self, owner: AccountId) {
+            self.owners.insert(self.owner_count, &owner);
+            self.owner_count += 1;
+            self.is_owner.insert(owner, &());
+        }
+
+        fn valid_requirement(owners: u32, requirement: u32) -> bool {
+            0 < requirement && requirement <= owners
+        }
+
+        fn ensure_owner(&self) -> Result<AccountId> {
+            let caller = self.env().caller();
+            if !self.is_owner.contains(caller) {
+                return Err(Error::NotOwner);
+            }
+            Ok(caller)
+        }
+
+        fn ensure_from_wallet(&self) -> Result<()> {
+            if self.env().caller() != self.env().account_id() {
+                return Err(Error::NotWallet);
+            }
+            Ok(())
+        }
+
+        /// Writes the fields to storage before a call that re-enters the
+        /// wallet, which would otherwise see them as of before this message.
+        fn flush(&self) {
+            ink::env::set_contract_storage(&<Self as ink::storage::traits::StorageKey>::KEY, self);
+        }
+
+        /// Reads the fields back after such a call, picking up its changes.
+        fn reload(&mut self) {
+            *self =
+                ink::env::get_contract_storage(&<Self as ink::storage::traits::StorageKey>::KEY)
+                    .ok()
+                    .flatten()
+                    .expect("the wallet is stored");
+        }
+
+        fn take_transaction(&mut self, id: TransactionId) -> Transaction {
+            for owner in self.owners() {
+                self.confirmations.remove((id, owner));
+            }
+            self.confirmation_count.remove(id);
+            self.pending.retain(|&pending| pending != id);
+            self.transactions.take(id).expect("checked by caller")
+        }
+
+        fn clear_confirmations_of(&mut self, owner: AccountId) {
+            for &id in &self.pending {
+                if self.confirmations.take((id, owner)).is_some() {
+                    self.confirmation_count
+                        .insert(id, &(self.confirmations(id) - 1));
+                }
+            }
+        }
+    }
+
+    /// Whether an encoded return value is `()` or `Ok`, whose variant index
+    /// is 0.
+    fn returned_ok(output: &[u8]) -> bool {
+        output.first().is_none_or(|&variant| variant == 0)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        type Accounts = ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment>;
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        fn default_accounts() -> Accounts {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn build_wallet(accounts: &Accounts) -> Multisig {
+            // The wallet's own address, which tests otherwise share with alice.
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(AccountId::from([0xff; 32]));
+            Multisig::new(2, vec![accounts.alice, accounts.bob, accounts.eve])
+        }
+
+        fn issue_transaction() -> Transaction {
+            Transaction {
+                callee: AccountId::from([0x9; 32]),
+                selector: [0xde, 0xad, 0xbe, 0xef],
+                input: scale::Encode::encode(&(AccountId::from([0x2; 32]), 100u128)),
+                transferred_value: 0,
+                gas_limit: 1000000,
+            }
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let accounts = default_accounts();
+            let wallet = build_wallet(&accounts);
+            assert_eq!(wallet.requirement(), 2);
+            assert_eq!(
+                wallet.owners(),
+                vec![accounts.alice, accounts.bob, accounts.eve]
+            );
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid requirement")]
+        fn new_fails_for_invalid_requirement() {
+            let accounts = default_accounts();
+            Multisig::new(3, vec![accounts.alice, accounts.bob]);
+        }
+
+        #[ink::test]
+        fn submit_confirms_for_submitter() {
+            let accounts = default_accounts();
+            let mut wallet = build_wallet(&accounts);
+            let id = wallet.submit_transaction(issue_transaction()).unwrap();
+            assert_eq!(wallet.transaction(id), Some(issue_transaction()));
+            assert_eq!(wallet.confirmations(id), 1);
+            assert!(wallet.is_confirmed_by(id, accounts.alice));
+        }
+
+        #[ink::test]
+        fn submit_fails_for_non_owner() {
+            let accounts = default_accounts();
+            let mut wallet = build_wallet(&accounts);
+            set_caller(accounts.django);
+            assert_eq!(
+                wallet.submit_transaction(issue_transaction()),
+                Err(Error::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn confirm_and_revoke_work() {
+            let accounts = default_accounts();
+            let mut wallet = build_wallet(&accounts);
+            let id = wallet.submit_transaction(issue_transaction()).unwrap();
+            assert_eq!(wallet.confirm(id), Err(Error::AlreadyConfirmed));
+            set_caller(accounts.bob);
+            assert_eq!(wallet.confirm(id), Ok(()));
+            assert_eq!(wallet.confirmations(id), 2);
+            assert_eq!(wallet.revoke_confirmation(id), Ok(()));
+            assert_eq!(wallet.revoke_confirmation(id), Err(Error::NotConfirmed));
+            assert_eq!(wallet.confirmations(id), 1);
+        }
+
+        #[ink::test]
+        fn execute_requires_threshold() {
+            let accounts = default_accounts();
+            let mut wallet = build_wallet(&accounts);
+            let id = wallet.submit_transaction(issue_transaction()).unwrap();
+            assert_eq!(wallet.execute(id), Err(Error::NotEnoughConfirmations));
+            assert_eq!(wallet.execute(id + 1), Err(Error::TransactionNotFound));
+        }
+
+        #[ink::test]
+        fn call_output_is_read_as_result() {
+            let encoded = scale::Encode::encode(&Err::<(), u8>(3));
+            let CallOutput(output) = scale::Decode::decode(&mut &encoded[..]).unwrap();
+            assert_eq!(output, encoded);
+            assert!(!returned_ok(&output));
+            assert!(returned_ok(&scale::Encode::encode(&Ok::<u128, u8>(7))));
+            assert!(returned_ok(&scale::Encode::encode(&())));
+        }
+
+        #[ink::test]
+        fn owner_management_only_from_wallet() {
+            let accounts = default_accounts();
+            let mut wallet = build_wallet(&accounts);
+            assert_eq!(wallet.add_owner(accounts.django), Err(Error::NotWallet));
+            assert_eq!(wallet.remove_owner(accounts.bob), Err(Error::NotWallet));
+            assert_eq!(wallet.change_requirement(1), Err(Error::NotWallet));
+        }
+
+        /// Runs the owner management messages as the wallet's own call would;
+        /// `tests/multisig.rs` sends them through `execute`.
+        #[ink::test]
+        fn owner_management_works() {
+            let accounts = default_accounts();
+            let mut wallet = build_wallet(&accounts);
+            let id = wallet.submit_transaction(issue_transaction()).unwrap();
+            set_caller(ink::env::account_id::<ink::env::DefaultEnvironment>());
+            assert_eq!(wallet.add_owner(accounts.django), Ok(()));
+            assert_eq!(wallet.add_owner(accounts.django), Err(Error::AlreadyOwner));
+            assert_eq!(wallet.change_requirement(5), Err(Error::InvalidRequirement));
+            assert_eq!(wallet.change_requirement(4), Ok(()));
+            assert_eq!(wallet.remove_owner(accounts.alice), Ok(()));
+            assert_eq!(wallet.requirement(), 3);
+            assert_eq!(wallet.confirmations(id), 0);
+            assert_eq!(wallet.owners().len(), 3);
+        }
+    }
+}
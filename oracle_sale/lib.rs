@@ -0,0 +1,239 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Sells the course `Erc20` for native currency at a fixed USD price.
+///
+/// The native currency's USD price comes from the runtime's oracle pallet
+/// through the chain extension in `chain_extensions::oracle`, so the contract
+/// only runs on a chain that provides it. Reports older than `max_price_age`
+/// are refused.
+///
+/// The owner funds the sale by transferring tokens to the contract and
+/// collects the proceeds with `withdraw`. Both assets are assumed to have the
+/// same number of decimals.
+#[ink::contract(env = chain_extensions::oracle::OracleEnvironment)]
+mod oracle_sale {
+    use chain_extensions::oracle::{OracleError, PairId, Price};
+    use erc20::Erc20Ref;
+
+    #[ink(storage)]
+    pub struct OracleSale {
+        token: AccountId,
+        owner: AccountId,
+        /// Oracle feed of the native currency in USD.
+        pair: PairId,
+        /// USD price of one token, with `PRICE_DECIMALS` decimals.
+        token_price: Price,
+        max_price_age: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Sold {
+        #[ink(topic)]
+        buyer: AccountId,
+        paid: Balance,
+        tokens: Balance,
+        native_price: Price,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        OracleError(OracleError),
+        StalePrice,
+        /// The payment buys less than one token unit.
+        PaymentTooSmall,
+        Overflow,
+        WithdrawFailed,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<OracleError> for Error {
+        fn from(error: OracleError) -> Self {
+            Error::OracleError(error)
+        }
+    }
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl OracleSale {
+        #[ink(constructor)]
+        pub fn new(
+            token: AccountId,
+            pair: PairId,
+            token_price: Price,
+            max_price_age: Timestamp,
+        ) -> Self {
+            assert!(token_price > 0, "token price must not be zero");
+            Self {
+                token,
+                owner: Self::env().caller(),
+                pair,
+                token_price,
+                max_price_age,
+            }
+        }
+
+        #[ink(message)]
+        pub fn token_price(&self) -> Price {
+            self.token_price
+        }
+
+        /// Current USD price of the native currency from the oracle.
+        #[ink(message)]
+        pub fn native_price(&self) -> Result<Price> {
+            let (price, reported_at) = self.env().extension().latest_price(self.pair)?;
+            if self.env().block_timestamp().saturating_sub(reported_at) > self.max_price_age {
+                return Err(Error::StalePrice);
+            }
+            Ok(price)
+        }
+
+        /// Tokens that `payment` in native currency buys at the current price.
+        #[ink(message)]
+        pub fn quote(&self, payment: Balance) -> Result<Balance> {
+            let tokens = payment
+                .checked_mul(self.native_price()?)
+                .ok_or(Error::Overflow)?
+                / self.token_price;
+            if tokens == 0 {
+                return Err(Error::PaymentTooSmall);
+            }
+            Ok(tokens)
+        }
+
+        /// Sells the caller tokens for the native currency sent along.
+        #[ink(message, payable)]
+        pub fn buy(&mut self) -> Result<Balance> {
+            let buyer = self.env().caller();
+            let paid = self.env().transferred_value();
+            let native_price = self.native_price()?;
+            let tokens = self.quote(paid)?;
+            let mut token: Erc20Ref = ink::env::call::FromAccountId::from_account_id(self.token);
+            token.transfer(buyer, tokens)?;
+            ink::codegen::EmitEvent::<OracleSale>::emit_event(
+                self.env(),
+                Sold {
+                    buyer,
+                    paid,
+                    tokens,
+                    native_price,
+                },
+            );
+            Ok(tokens)
+        }
+
+        /// Changes the USD price of one token.
+        #[ink(message)]
+        pub fn set_token_price(&mut self, token_price: Price) -> Result<()> {
+            self.ensure_owner()?;
+            assert!(token_price > 0, "token price must not be zero");
+            self.token_price = token_price;
+            Ok(())
+        }
+
+        /// Sends the collected native currency to the owner.
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            let amount = self
+                .env()
+                .balance()
+                .saturating_sub(self.env().minimum_balance());
+            self.env()
+                .transfer(self.owner, amount)
+                .map_err(|_| Error::WithdrawFailed)
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use chain_extensions::oracle::Timestamp as OracleTimestamp;
+        use scale::Encode;
+
+        const DOT_USD: PairId = *b"DOT/USD\0";
+
+        /// Reports `price` as of `reported_at`, or fails with `status`.
+        struct MockOracle {
+            price: Price,
+            reported_at: OracleTimestamp,
+            status: u32,
+        }
+
+        impl ink::env::test::ChainExtension for MockOracle {
+            fn func_id(&self) -> u32 {
+                0x1101
+            }
+
+            fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+                // The off-chain engine hands over the input encoded once more.
+                assert_eq!(input, &DOT_USD.encode().encode()[..]);
+                (self.price, self.reported_at).encode_to(output);
+                self.status
+            }
+        }
+
+        fn register_oracle(price: Price, reported_at: OracleTimestamp, status: u32) {
+            ink::env::test::register_chain_extension(MockOracle {
+                price,
+                reported_at,
+                status,
+            });
+        }
+
+        fn now() -> Timestamp {
+            ink::env::block_timestamp::<ink::env::DefaultEnvironment>()
+        }
+
+        #[ink::test]
+        fn quote_uses_oracle_price() {
+            // 1 token costs 0.5 USD, 1 DOT is worth 5 USD.
+            let sale = OracleSale::new(AccountId::from([0x9; 32]), DOT_USD, 50_000_000, 60_000);
+            register_oracle(500_000_000, now(), 0);
+            assert_eq!(sale.native_price(), Ok(500_000_000));
+            assert_eq!(sale.quote(3), Ok(30));
+            assert_eq!(sale.quote(0), Err(Error::PaymentTooSmall));
+        }
+
+        #[ink::test]
+        fn stale_price_is_refused() {
+            let sale = OracleSale::new(AccountId::from([0x9; 32]), DOT_USD, 50_000_000, 0);
+            register_oracle(500_000_000, now(), 0);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(sale.native_price(), Err(Error::StalePrice));
+        }
+
+        #[ink::test]
+        fn oracle_errors_are_mapped() {
+            let sale = OracleSale::new(AccountId::from([0x9; 32]), DOT_USD, 50_000_000, 60_000);
+            register_oracle(0, 0, 1);
+            assert_eq!(
+                sale.native_price(),
+                Err(Error::OracleError(OracleError::UnknownPair))
+            );
+        }
+
+        #[ink::test]
+        fn only_owner_sets_price() {
+            let mut sale = OracleSale::new(AccountId::from([0x9; 32]), DOT_USD, 50_000_000, 0);
+            assert_eq!(sale.set_token_price(25_000_000), Ok(()));
+            assert_eq!(sale.token_price(), 25_000_000);
+            sale.owner = AccountId::from([0x2; 32]);
+            assert_eq!(sale.set_token_price(1), Err(Error::NotOwner));
+        }
+    }
+}
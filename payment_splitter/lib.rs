@@ -0,0 +1,285 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Splits everything the contract receives, in native currency and in the
+/// `Erc20`, between a fixed set of payees according to their shares. Payees
+/// pull their portion with `release` / `release_token`.
+#[ink::contract]
+mod payment_splitter {
+    use erc20::Erc20Ref;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    #[ink(storage)]
+    pub struct PaymentSplitter {
+        token: AccountId,
+        payees: Vec<AccountId>,
+        shares: Mapping<AccountId, Balance>,
+        total_shares: Balance,
+        released: Mapping<AccountId, Balance>,
+        total_released: Balance,
+        token_released: Mapping<AccountId, Balance>,
+        total_token_released: Balance,
+    }
+
+    #[ink(event)]
+    pub struct PayeeAdded {
+        #[ink(topic)]
+        account: AccountId,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct PaymentReleased {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TokenPaymentReleased {
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NoShares,
+        NothingDue,
+        TransferFailed,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl PaymentSplitter {
+        #[ink(constructor)]
+        pub fn new(token: AccountId, payees: Vec<AccountId>, shares: Vec<Balance>) -> Self {
+            assert_eq!(
+                payees.len(),
+                shares.len(),
+                "payees and shares length mismatch"
+            );
+            assert!(!payees.is_empty(), "no payees");
+            let mut splitter = Self {
+                token,
+                payees: Vec::new(),
+                shares: Mapping::default(),
+                total_shares: 0,
+                released: Mapping::default(),
+                total_released: 0,
+                token_released: Mapping::default(),
+                total_token_released: 0,
+            };
+            for (account, shares) in payees.into_iter().zip(shares) {
+                splitter.add_payee(account, shares);
+            }
+            splitter
+        }
+
+        #[ink(message)]
+        pub fn token(&self) -> AccountId {
+            self.token
+        }
+
+        #[ink(message)]
+        pub fn payees(&self) -> Vec<AccountId> {
+            self.payees.clone()
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        #[ink(message)]
+        pub fn shares(&self, account: AccountId) -> Balance {
+            self.shares.get(account).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn total_released(&self) -> Balance {
+            self.total_released
+        }
+
+        #[ink(message)]
+        pub fn released(&self, account: AccountId) -> Balance {
+            self.released.get(account).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn total_token_released(&self) -> Balance {
+            self.total_token_released
+        }
+
+        #[ink(message)]
+        pub fn token_released(&self, account: AccountId) -> Balance {
+            self.token_released.get(account).unwrap_or(0)
+        }
+
+        /// Pays the caller their share of all native currency received so far.
+        /// The existential deposit stays behind to keep the contract alive.
+        #[ink(message)]
+        pub fn release(&mut self) -> Result<Balance> {
+            let payee = self.env().caller();
+            let total_received = self
+                .env()
+                .balance()
+                .saturating_sub(self.env().minimum_balance())
+                + self.total_released;
+            let amount = self.pending_payment(payee, total_received, self.released(payee))?;
+            self.released
+                .insert(payee, &(self.released(payee) + amount));
+            self.total_released += amount;
+            self.env()
+                .transfer(payee, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            ink::codegen::EmitEvent::<PaymentSplitter>::emit_event(
+                self.env(),
+                PaymentReleased { to: payee, amount },
+            );
+            Ok(amount)
+        }
+
+        /// Pays the caller their share of all `Erc20` tokens received so far.
+        #[ink(message)]
+        pub fn release_token(&mut self) -> Result<Balance> {
+            let payee = self.env().caller();
+            let mut token: Erc20Ref = ink::env::call::FromAccountId::from_account_id(self.token);
+            let total_received =
+                token.balance_of(self.env().account_id()) + self.total_token_released;
+            let amount = self.pending_payment(payee, total_received, self.token_released(payee))?;
+            self.token_released
+                .insert(payee, &(self.token_released(payee) + amount));
+            self.total_token_released += amount;
+            token.transfer(payee, amount)?;
+            ink::codegen::EmitEvent::<PaymentSplitter>::emit_event(
+                self.env(),
+                TokenPaymentReleased {
+                    token: self.token,
+                    to: payee,
+                    amount,
+                },
+            );
+            Ok(amount)
+        }
+
+        fn add_payee(&mut self, account: AccountId, shares: Balance) {
+            assert!(shares > 0, "payee has no shares");
+            assert!(
+                self.shares.insert(account, &shares).is_none(),
+                "payee added twice"
+            );
+            self.payees.push(account);
+            self.total_shares += shares;
+            ink::codegen::EmitEvent::<PaymentSplitter>::emit_event(
+                self.env(),
+                PayeeAdded { account, shares },
+            );
+        }
+
+        fn pending_payment(
+            &self,
+            account: AccountId,
+            total_received: Balance,
+            already_released: Balance,
+        ) -> Result<Balance> {
+            let shares = self.shares(account);
+            if shares == 0 {
+                return Err(Error::NoShares);
+            }
+            let due = total_received.saturating_mul(shares) / self.total_shares - already_released;
+            if due == 0 {
+                return Err(Error::NothingDue);
+            }
+            Ok(due)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn build_splitter() -> PaymentSplitter {
+            PaymentSplitter::new(
+                AccountId::from([0x9; 32]),
+                vec![AccountId::from([0x1; 32]), AccountId::from([0x2; 32])],
+                vec![1, 3],
+            )
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let splitter = build_splitter();
+            assert_eq!(splitter.token(), AccountId::from([0x9; 32]));
+            assert_eq!(splitter.total_shares(), 4);
+            assert_eq!(splitter.shares(AccountId::from([0x2; 32])), 3);
+            assert_eq!(splitter.payees().len(), 2);
+            assert_eq!(splitter.total_released(), 0);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "payee added twice")]
+        fn new_fails_for_duplicate_payee() {
+            PaymentSplitter::new(
+                AccountId::from([0x9; 32]),
+                vec![AccountId::from([0x1; 32]), AccountId::from([0x1; 32])],
+                vec![1, 3],
+            );
+        }
+
+        #[ink::test]
+        fn pending_payment_is_pro_rata() {
+            let splitter = build_splitter();
+            let first = AccountId::from([0x1; 32]);
+            let second = AccountId::from([0x2; 32]);
+            assert_eq!(splitter.pending_payment(first, 1000, 0), Ok(250));
+            assert_eq!(splitter.pending_payment(second, 1000, 0), Ok(750));
+            assert_eq!(splitter.pending_payment(second, 2000, 750), Ok(750));
+            assert_eq!(
+                splitter.pending_payment(first, 1000, 250),
+                Err(Error::NothingDue)
+            );
+        }
+
+        #[ink::test]
+        fn every_payee_can_release_in_full() {
+            let contract = AccountId::from([0xff; 32]);
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract);
+            let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract,
+                minimum_balance + 1000,
+            );
+            let mut splitter = build_splitter();
+            let second = AccountId::from([0x2; 32]);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(second);
+            assert_eq!(splitter.release(), Ok(750));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(AccountId::from([0x1; 32]));
+            assert_eq!(splitter.release(), Ok(250));
+            assert_eq!(splitter.release(), Err(Error::NothingDue));
+            assert_eq!(splitter.total_released(), 1000);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(contract),
+                Ok(minimum_balance)
+            );
+        }
+
+        #[ink::test]
+        fn non_payee_cannot_release() {
+            let splitter = build_splitter();
+            assert_eq!(
+                splitter.pending_payment(AccountId::from([0x3; 32]), 1000, 0),
+                Err(Error::NoShares)
+            );
+        }
+    }
+}
@@ -0,0 +1,367 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Pays salaries in the course `Erc20` out of a pool the employer funds.
+///
+/// Each employee earns `salary` tokens per `period`, accruing by the
+/// millisecond, and pulls what accrued with `claim_salary`. When the pool runs
+/// short an employee gets what is there and the rest stays owed. Adjusting a
+/// salary or terminating an employee first books what accrued at the old rate,
+/// so no pay is lost; a terminated employee can still claim it.
+#[ink::contract]
+mod payroll {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Employee {
+        /// Tokens earned per period; zero once terminated.
+        pub salary: Balance,
+        /// Pay accrues from this timestamp on.
+        pub accrues_since: Timestamp,
+        /// Pay booked but not paid yet.
+        pub owed: Balance,
+    }
+
+    impl Employee {
+        /// Everything owed to the employee at `now`.
+        pub fn accrued(&self, period: Timestamp, now: Timestamp) -> Balance {
+            let elapsed = Balance::from(now.saturating_sub(self.accrues_since));
+            let period = Balance::from(period);
+            // Split so that large salaries can't overflow.
+            let earned = self.salary / period * elapsed + self.salary % period * elapsed / period;
+            self.owed.saturating_add(earned)
+        }
+    }
+
+    #[ink(storage)]
+    pub struct Payroll {
+        token: AccountId,
+        employer: AccountId,
+        period: Timestamp,
+        employees: Mapping<AccountId, Employee>,
+        /// Tokens funded and not paid out or withdrawn yet.
+        pool: Balance,
+        /// Sum of the `owed` of all employees.
+        total_owed: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Funded {
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SalarySet {
+        #[ink(topic)]
+        employee: AccountId,
+        salary: Balance,
+    }
+
+    #[ink(event)]
+    pub struct EmployeeTerminated {
+        #[ink(topic)]
+        employee: AccountId,
+        final_pay: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SalaryClaimed {
+        #[ink(topic)]
+        employee: AccountId,
+        amount: Balance,
+        still_owed: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotEmployer,
+        NotEmployee,
+        AlreadyEmployed,
+        /// The withdrawal would take pay already owed out of the pool.
+        InsufficientFunds,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Payroll {
+        /// The caller becomes the employer. `period` is how long it takes to
+        /// earn one salary, in milliseconds.
+        #[ink(constructor)]
+        pub fn new(token: AccountId, period: Timestamp) -> Self {
+            assert!(period > 0, "period must not be zero");
+            Self {
+                token,
+                employer: Self::env().caller(),
+                period,
+                employees: Mapping::default(),
+                pool: 0,
+                total_owed: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn employee(&self, employee: AccountId) -> Option<Employee> {
+            self.employees.get(employee)
+        }
+
+        #[ink(message)]
+        pub fn pool(&self) -> Balance {
+            self.pool
+        }
+
+        /// What `employee` could claim now if the pool covers it.
+        #[ink(message)]
+        pub fn accrued(&self, employee: AccountId) -> Balance {
+            self.employees.get(employee).map_or(0, |employee| {
+                employee.accrued(self.period, self.env().block_timestamp())
+            })
+        }
+
+        /// Adds `amount` of the employer's tokens to the pool. The employer must
+        /// have approved this contract for `amount`.
+        #[ink(message)]
+        pub fn fund(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_employer()?;
+            self.token_contract()
+                .transfer_from(self.employer, self.env().account_id(), amount)?;
+            self.pool += amount;
+            ink::codegen::EmitEvent::<Payroll>::emit_event(self.env(), Funded { amount });
+            Ok(())
+        }
+
+        /// Takes `amount` back out of the pool, leaving what is owed already.
+        #[ink(message)]
+        pub fn withdraw(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_employer()?;
+            if amount > self.pool.saturating_sub(self.total_owed) {
+                return Err(Error::InsufficientFunds);
+            }
+            self.pool -= amount;
+            self.token_contract().transfer(self.employer, amount)?;
+            Ok(())
+        }
+
+        /// Starts paying `employee` `salary` per period from now on.
+        #[ink(message)]
+        pub fn register_employee(&mut self, employee: AccountId, salary: Balance) -> Result<()> {
+            self.ensure_employer()?;
+            if self.employees.contains(employee) {
+                return Err(Error::AlreadyEmployed);
+            }
+            self.employees.insert(
+                employee,
+                &Employee {
+                    salary,
+                    accrues_since: self.env().block_timestamp(),
+                    owed: 0,
+                },
+            );
+            ink::codegen::EmitEvent::<Payroll>::emit_event(
+                self.env(),
+                SalarySet { employee, salary },
+            );
+            Ok(())
+        }
+
+        /// Pays `employee` `salary` per period from now on. What accrued at the
+        /// old salary stays owed.
+        #[ink(message)]
+        pub fn adjust_salary(&mut self, employee: AccountId, salary: Balance) -> Result<()> {
+            self.ensure_employer()?;
+            self.book_accrued(employee)?;
+            if let Some(mut record) = self.employees.get(employee) {
+                record.salary = salary;
+                self.employees.insert(employee, &record);
+            }
+            ink::codegen::EmitEvent::<Payroll>::emit_event(
+                self.env(),
+                SalarySet { employee, salary },
+            );
+            Ok(())
+        }
+
+        /// Stops `employee`'s pay from accruing. What accrued until now stays
+        /// owed and can still be claimed.
+        #[ink(message)]
+        pub fn terminate(&mut self, employee: AccountId) -> Result<()> {
+            self.ensure_employer()?;
+            let final_pay = self.book_accrued(employee)?;
+            if final_pay == 0 {
+                self.employees.remove(employee);
+            } else if let Some(mut record) = self.employees.get(employee) {
+                record.salary = 0;
+                self.employees.insert(employee, &record);
+            }
+            ink::codegen::EmitEvent::<Payroll>::emit_event(
+                self.env(),
+                EmployeeTerminated {
+                    employee,
+                    final_pay,
+                },
+            );
+            Ok(())
+        }
+
+        /// Pays the caller what accrued, as far as the pool covers it, and
+        /// returns the amount paid.
+        #[ink(message)]
+        pub fn claim_salary(&mut self) -> Result<Balance> {
+            let employee = self.env().caller();
+            let owed = self.book_accrued(employee)?;
+            let amount = owed.min(self.pool);
+            let still_owed = owed - amount;
+            self.pool -= amount;
+            self.total_owed -= amount;
+            let mut record = self.employees.get(employee).ok_or(Error::NotEmployee)?;
+            record.owed = still_owed;
+            if record.salary == 0 && still_owed == 0 {
+                self.employees.remove(employee);
+            } else {
+                self.employees.insert(employee, &record);
+            }
+            if amount > 0 {
+                self.token_contract().transfer(employee, amount)?;
+            }
+            ink::codegen::EmitEvent::<Payroll>::emit_event(
+                self.env(),
+                SalaryClaimed {
+                    employee,
+                    amount,
+                    still_owed,
+                },
+            );
+            Ok(amount)
+        }
+
+        /// Moves what accrued to `employee` since the last booking into its
+        /// `owed` and returns the new total owed.
+        fn book_accrued(&mut self, employee: AccountId) -> Result<Balance> {
+            let now = self.env().block_timestamp();
+            let period = self.period;
+            let mut record = self.employees.get(employee).ok_or(Error::NotEmployee)?;
+            let owed = record.accrued(period, now);
+            self.total_owed += owed - record.owed;
+            record.owed = owed;
+            record.accrues_since = now;
+            self.employees.insert(employee, &record);
+            Ok(owed)
+        }
+
+        fn ensure_employer(&self) -> Result<()> {
+            if self.env().caller() != self.employer {
+                return Err(Error::NotEmployer);
+            }
+            Ok(())
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn now() -> Timestamp {
+            ink::env::block_timestamp::<ink::env::DefaultEnvironment>()
+        }
+
+        fn advance_block() {
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        }
+
+        fn payroll() -> Payroll {
+            Payroll::new(AccountId::from([0x9; 32]), 10)
+        }
+
+        #[ink::test]
+        fn pay_accrues_pro_rata() {
+            let employee = Employee {
+                salary: 100,
+                accrues_since: 1000,
+                owed: 7,
+            };
+            assert_eq!(employee.accrued(10, 900), 7);
+            assert_eq!(employee.accrued(10, 1000), 7);
+            assert_eq!(employee.accrued(10, 1005), 57);
+            assert_eq!(employee.accrued(10, 1020), 207);
+        }
+
+        #[ink::test]
+        fn adjusting_keeps_accrued_pay() {
+            let mut payroll = payroll();
+            let employee = AccountId::from([0x2; 32]);
+            let start = now();
+            assert_eq!(payroll.register_employee(employee, 100), Ok(()));
+            assert_eq!(
+                payroll.register_employee(employee, 100),
+                Err(Error::AlreadyEmployed)
+            );
+            advance_block();
+            let earned = 10 * Balance::from(now() - start);
+            assert_eq!(payroll.adjust_salary(employee, 200), Ok(()));
+            assert_eq!(
+                payroll.employee(employee),
+                Some(Employee {
+                    salary: 200,
+                    accrues_since: now(),
+                    owed: earned,
+                })
+            );
+            assert_eq!(payroll.total_owed, earned);
+        }
+
+        #[ink::test]
+        fn terminated_employee_keeps_final_pay() {
+            let mut payroll = payroll();
+            let employee = AccountId::from([0x1; 32]);
+            let start = now();
+            payroll.register_employee(employee, 100).unwrap();
+            advance_block();
+            let final_pay = 10 * Balance::from(now() - start);
+            assert_eq!(payroll.terminate(employee), Ok(()));
+            advance_block();
+            assert_eq!(payroll.accrued(employee), final_pay);
+            // The pool is empty, so nothing is paid and everything stays owed.
+            assert_eq!(payroll.claim_salary(), Ok(0));
+            assert_eq!(payroll.accrued(employee), final_pay);
+            assert_eq!(payroll.withdraw(1), Err(Error::InsufficientFunds));
+        }
+
+        #[ink::test]
+        fn terminating_without_pay_owed_removes_employee() {
+            let mut payroll = payroll();
+            let employee = AccountId::from([0x2; 32]);
+            payroll.register_employee(employee, 100).unwrap();
+            assert_eq!(payroll.terminate(employee), Ok(()));
+            assert_eq!(payroll.employee(employee), None);
+            assert_eq!(payroll.terminate(employee), Err(Error::NotEmployee));
+        }
+
+        #[ink::test]
+        fn only_employer_manages_payroll() {
+            let mut payroll = payroll();
+            let employee = AccountId::from([0x2; 32]);
+            payroll.employer = AccountId::from([0x3; 32]);
+            assert_eq!(
+                payroll.register_employee(employee, 100),
+                Err(Error::NotEmployer)
+            );
+            assert_eq!(payroll.adjust_salary(employee, 1), Err(Error::NotEmployer));
+            assert_eq!(payroll.terminate(employee), Err(Error::NotEmployer));
+            assert_eq!(payroll.withdraw(0), Err(Error::NotEmployer));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Forwards every call it does not handle itself to the code stored under
+/// `code_hash` via delegate call.
+///
+/// The delegated code runs against the proxy's own storage and address, so
+/// callers keep talking to the same account while the admin swaps the code
+/// underneath with `set_code_hash`. The proxy keeps its own fields under a
+/// hashed storage key so they never collide with the storage of the code it
+/// delegates to. ink! writes the storage struct to the root key after every
+/// `&mut self` message, and that key belongs to the delegated code, so the
+/// proxy's messages all take `&self` and store the fields themselves.
+///
+/// Delegated code never runs its constructor, so the proxy can only front
+/// code whose messages cope with empty storage. That rules out contracts that
+/// read their root storage struct in every message, the course `Erc20` among
+/// them; it upgrades in place with its own `upgrade_code` instead.
+#[ink::contract]
+mod proxy {
+    use ink::env::call::build_call;
+
+    const PROXY_FIELDS_STORAGE_KEY: [u8; 32] = ink::blake2x256!("ProxyFields");
+
+    /// Stored packed under `PROXY_FIELDS_STORAGE_KEY` instead of the root key
+    /// the delegated contract uses.
+    #[derive(Debug, scale::Encode, scale::Decode)]
+    pub struct ProxyFields {
+        code_hash: Hash,
+        admin: AccountId,
+    }
+
+    #[ink(storage)]
+    pub struct Proxy {}
+
+    #[ink(event)]
+    pub struct Upgraded {
+        #[ink(topic)]
+        code_hash: Hash,
+    }
+
+    #[ink(event)]
+    pub struct AdminChanged {
+        #[ink(topic)]
+        previous_admin: AccountId,
+        #[ink(topic)]
+        new_admin: AccountId,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotAdmin,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl Proxy {
+        #[ink(constructor)]
+        pub fn new(code_hash: Hash) -> Self {
+            let proxy = Self {};
+            proxy.set_fields(&ProxyFields {
+                code_hash,
+                admin: Self::env().caller(),
+            });
+            proxy
+        }
+
+        #[ink(message)]
+        pub fn proxy_code_hash(&self) -> Hash {
+            self.fields().code_hash
+        }
+
+        #[ink(message)]
+        pub fn proxy_admin(&self) -> AccountId {
+            self.fields().admin
+        }
+
+        /// Points the proxy at new implementation code. The new code must keep
+        /// the storage layout of the previous one.
+        #[ink(message)]
+        pub fn set_code_hash(&self, code_hash: Hash) -> Result<()> {
+            let mut fields = self.admin_fields()?;
+            fields.code_hash = code_hash;
+            self.set_fields(&fields);
+            self.env().emit_event(Upgraded { code_hash });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn change_admin(&self, new_admin: AccountId) -> Result<()> {
+            let mut fields = self.admin_fields()?;
+            let previous_admin = fields.admin;
+            fields.admin = new_admin;
+            self.set_fields(&fields);
+            self.env().emit_event(AdminChanged {
+                previous_admin,
+                new_admin,
+            });
+            Ok(())
+        }
+
+        /// Delegates any other selector, with its input and value, to the
+        /// implementation code. Never returns; the output of the delegated
+        /// call is handed back to the caller as is.
+        #[ink(message, payable, selector = _)]
+        pub fn forward(&self) {
+            let result = build_call::<ink::env::DefaultEnvironment>()
+                .delegate(self.fields().code_hash)
+                .call_flags(
+                    ink::env::CallFlags::default()
+                        .set_forward_input(true)
+                        .set_tail_call(true),
+                )
+                .try_invoke();
+            // Tail calls never return to the proxy, so the call failed.
+            panic!("delegate call failed: {:?}", result);
+        }
+
+        fn fields(&self) -> ProxyFields {
+            ink::env::get_contract_storage(&PROXY_FIELDS_STORAGE_KEY)
+                .ok()
+                .flatten()
+                .expect("the constructor stores the fields")
+        }
+
+        fn set_fields(&self, fields: &ProxyFields) {
+            ink::env::set_contract_storage(&PROXY_FIELDS_STORAGE_KEY, fields);
+        }
+
+        /// The fields, if the caller is the admin.
+        fn admin_fields(&self) -> Result<ProxyFields> {
+            let fields = self.fields();
+            if self.env().caller() != fields.admin {
+                return Err(Error::NotAdmin);
+            }
+            Ok(fields)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_works() {
+            let proxy = Proxy::new(Hash::from([0x7; 32]));
+            assert_eq!(proxy.proxy_code_hash(), Hash::from([0x7; 32]));
+            assert_eq!(proxy.proxy_admin(), AccountId::from([0x1; 32]));
+        }
+
+        #[ink::test]
+        fn set_code_hash_works() {
+            let proxy = Proxy::new(Hash::from([0x7; 32]));
+            assert_eq!(proxy.set_code_hash(Hash::from([0x8; 32])), Ok(()));
+            assert_eq!(proxy.proxy_code_hash(), Hash::from([0x8; 32]));
+        }
+
+        #[ink::test]
+        fn only_admin_can_change_proxy() {
+            let proxy = Proxy::new(Hash::from([0x7; 32]));
+            let new_admin = AccountId::from([0x2; 32]);
+            assert_eq!(proxy.change_admin(new_admin), Ok(()));
+            assert_eq!(proxy.proxy_admin(), new_admin);
+            assert_eq!(
+                proxy.set_code_hash(Hash::from([0x8; 32])),
+                Err(Error::NotAdmin)
+            );
+            assert_eq!(proxy.change_admin(new_admin), Err(Error::NotAdmin));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Client side of the PSP22 fungible token standard.
+//!
+//! Contracts in this workspace use these helpers to talk to any PSP22
+//! compatible token without depending on its crate.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink::env::{
+    call::{build_call, ExecutionInput, Selector},
+    CallFlags, DefaultEnvironment, Environment,
+};
+use ink::prelude::{string::String, vec::Vec};
+use ink::primitives::AccountId;
+
+pub type Balance = <DefaultEnvironment as Environment>::Balance;
+
+pub const TOTAL_SUPPLY: [u8; 4] = [0x16, 0x2d, 0xf8, 0xc2];
+pub const BALANCE_OF: [u8; 4] = [0x65, 0x68, 0x38, 0x2f];
+pub const ALLOWANCE: [u8; 4] = [0x4d, 0x47, 0xd9, 0x21];
+pub const TRANSFER: [u8; 4] = [0xdb, 0x20, 0xf9, 0xf5];
+pub const TRANSFER_FROM: [u8; 4] = [0x54, 0xb3, 0xc7, 0x6e];
+pub const APPROVE: [u8; 4] = [0xb2, 0x0f, 0x1b, 0xbd];
+
+/// Selector of `TokenReceiver::on_token_received`.
+pub const ON_TOKEN_RECEIVED: [u8; 4] = [0xe1, 0x93, 0x61, 0x55];
+/// Selector of `FlashBorrower::on_flash_loan`.
+pub const ON_FLASH_LOAN: [u8; 4] = [0x38, 0x09, 0x6f, 0xfe];
+/// Selector of `TransferRules::can_transfer`.
+pub const CAN_TRANSFER: [u8; 4] = [0x21, 0x70, 0xf0, 0x62];
+
+/// Implemented by contracts that want to hold tokens sent with
+/// `transfer_and_call` or `safe_transfer`.
+#[ink::trait_definition]
+pub trait TokenReceiver {
+    /// Called by the token after `value` tokens moved from `from` to this
+    /// contract on behalf of `operator`. Returning `false` reverts the transfer.
+    #[ink(message)]
+    fn on_token_received(
+        &mut self,
+        operator: AccountId,
+        from: AccountId,
+        value: Balance,
+        data: Vec<u8>,
+    ) -> bool;
+}
+
+/// Implemented by contracts that take flash loans from a token.
+#[ink::trait_definition]
+pub trait FlashBorrower {
+    /// Called by the token after minting `amount` to this contract for
+    /// `initiator`. Before returning `true` the borrower has to approve the
+    /// token contract to take back `amount + fee`, otherwise the loan reverts.
+    #[ink(message)]
+    fn on_flash_loan(
+        &mut self,
+        initiator: AccountId,
+        amount: Balance,
+        fee: Balance,
+        data: Vec<u8>,
+    ) -> bool;
+}
+
+/// Implemented by compliance contracts a token consults before every transfer,
+/// so the rules can change without upgrading the token.
+#[ink::trait_definition]
+pub trait TransferRules {
+    /// Whether `from` may transfer `value` tokens to `to`.
+    #[ink(message)]
+    fn can_transfer(&self, from: AccountId, to: AccountId, value: Balance) -> bool;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PSP22Error {
+    Custom(String),
+    InsufficientBalance,
+    InsufficientAllowance,
+    ZeroRecipientAddress,
+    ZeroSenderAddress,
+    SafeTransferCheckFailed(String),
+}
+
+impl From<ink::env::Error> for PSP22Error {
+    fn from(_: ink::env::Error) -> Self {
+        PSP22Error::Custom(String::from("CallFailed"))
+    }
+}
+
+impl From<ink::LangError> for PSP22Error {
+    fn from(_: ink::LangError) -> Self {
+        PSP22Error::Custom(String::from("CallFailed"))
+    }
+}
+
+pub fn balance_of(token: AccountId, owner: AccountId) -> Result<Balance, PSP22Error> {
+    let balance = build_call::<DefaultEnvironment>()
+        .call(token)
+        .exec_input(ExecutionInput::new(Selector::new(BALANCE_OF)).push_arg(owner))
+        .returns::<Balance>()
+        .try_invoke()??;
+    Ok(balance)
+}
+
+pub fn transfer(token: AccountId, to: AccountId, value: Balance) -> Result<(), PSP22Error> {
+    build_call::<DefaultEnvironment>()
+        .call(token)
+        .exec_input(
+            ExecutionInput::new(Selector::new(TRANSFER))
+                .push_arg(to)
+                .push_arg(value)
+                .push_arg(Vec::<u8>::new()),
+        )
+        .returns::<Result<(), PSP22Error>>()
+        .try_invoke()??
+}
+
+pub fn transfer_from(
+    token: AccountId,
+    from: AccountId,
+    to: AccountId,
+    value: Balance,
+) -> Result<(), PSP22Error> {
+    build_call::<DefaultEnvironment>()
+        .call(token)
+        .exec_input(
+            ExecutionInput::new(Selector::new(TRANSFER_FROM))
+                .push_arg(from)
+                .push_arg(to)
+                .push_arg(value)
+                .push_arg(Vec::<u8>::new()),
+        )
+        .returns::<Result<(), PSP22Error>>()
+        .try_invoke()??
+}
+
+/// Notifies `receiver` that it was sent `value` tokens. Returns whether the
+/// receiver accepted them; a failed call counts as a rejection.
+pub fn on_token_received(
+    receiver: AccountId,
+    operator: AccountId,
+    from: AccountId,
+    value: Balance,
+    data: Vec<u8>,
+) -> bool {
+    build_call::<DefaultEnvironment>()
+        .call(receiver)
+        .exec_input(
+            ExecutionInput::new(Selector::new(ON_TOKEN_RECEIVED))
+                .push_arg(operator)
+                .push_arg(from)
+                .push_arg(value)
+                .push_arg(data),
+        )
+        .returns::<bool>()
+        .try_invoke()
+        == Ok(Ok(true))
+}
+
+/// Calls `FlashBorrower::on_flash_loan` on `receiver`. The receiver may call
+/// back into the token to use and repay the loan. Any failure counts as `false`.
+pub fn on_flash_loan(
+    receiver: AccountId,
+    initiator: AccountId,
+    amount: Balance,
+    fee: Balance,
+    data: Vec<u8>,
+) -> bool {
+    build_call::<DefaultEnvironment>()
+        .call(receiver)
+        .call_flags(CallFlags::default().set_allow_reentry(true))
+        .exec_input(
+            ExecutionInput::new(Selector::new(ON_FLASH_LOAN))
+                .push_arg(initiator)
+                .push_arg(amount)
+                .push_arg(fee)
+                .push_arg(data),
+        )
+        .returns::<bool>()
+        .try_invoke()
+        == Ok(Ok(true))
+}
+
+/// Asks the `rules` contract whether the transfer is allowed. A failed call
+/// counts as a rejection.
+pub fn can_transfer(rules: AccountId, from: AccountId, to: AccountId, value: Balance) -> bool {
+    build_call::<DefaultEnvironment>()
+        .call(rules)
+        .exec_input(
+            ExecutionInput::new(Selector::new(CAN_TRANSFER))
+                .push_arg(from)
+                .push_arg(to)
+                .push_arg(value),
+        )
+        .returns::<bool>()
+        .try_invoke()
+        == Ok(Ok(true))
+}
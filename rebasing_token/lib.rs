@@ -0,0 +1,360 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::rebasing_token::{Error, RebasingToken, RebasingTokenRef};
+
+/// A PSP22 token with an elastic supply.
+///
+/// Balances are stored as shares of the total supply. When the owner rebases
+/// the supply up or down by `delta`, every balance changes by the same
+/// proportion without touching any account, like staking tokens that pass on
+/// their rewards. `shares_of` and `shares_to_balance` expose the shares;
+/// integrations that can't handle moving balances should hold shares through
+/// a wrapper instead.
+///
+/// Amounts are converted to shares rounding down, so a transfer can move a raw
+/// unit less than asked for.
+#[ink::contract]
+pub mod rebasing_token {
+    use ink::prelude::{format, vec::Vec};
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+
+    #[ink(storage)]
+    pub struct RebasingToken {
+        owner: AccountId,
+        total_supply: Balance,
+        total_shares: Balance,
+        shares: Mapping<AccountId, Balance>,
+        /// Allowances are in tokens, not shares, as PSP22 expects.
+        allowances: Mapping<(AccountId, AccountId), Balance>,
+    }
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Rebased {
+        delta: i128,
+        total_supply: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        InsufficientBalance,
+        InsufficientAllowance,
+        /// The rebase would leave shares worth nothing, or overflow.
+        InvalidRebase,
+        Overflow,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<Error> for PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::InsufficientBalance => PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance => PSP22Error::InsufficientAllowance,
+                error => PSP22Error::Custom(format!("{:?}", error)),
+            }
+        }
+    }
+
+    impl RebasingToken {
+        /// Mints `initial_supply` to the caller, at one share per token.
+        #[ink(constructor)]
+        pub fn new(initial_supply: Balance) -> Self {
+            let caller = Self::env().caller();
+            let mut shares = Mapping::default();
+            if initial_supply > 0 {
+                shares.insert(caller, &initial_supply);
+            }
+            Self {
+                owner: caller,
+                total_supply: initial_supply,
+                total_shares: initial_supply,
+                shares,
+                allowances: Mapping::default(),
+            }
+        }
+
+        /// PSP22::total_supply
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        /// PSP22::balance_of
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.shares_to_balance(self.shares_of(owner))
+        }
+
+        /// PSP22::allowance
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// PSP22::transfer
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let from = self.env().caller();
+            let shares = self.balance_to_shares(value);
+            Ok(self.move_shares(from, to, shares)?)
+        }
+
+        /// PSP22::transfer_from
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let spender = self.env().caller();
+            let allowance = self.allowance(from, spender);
+            if allowance < value {
+                return Err(Error::InsufficientAllowance.into());
+            }
+            self.allowances
+                .insert((from, spender), &(allowance - value));
+            let shares = self.balance_to_shares(value);
+            Ok(self.move_shares(from, to, shares)?)
+        }
+
+        /// PSP22::approve
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        #[ink(message)]
+        pub fn shares_of(&self, owner: AccountId) -> Balance {
+            self.shares.get(owner).unwrap_or(0)
+        }
+
+        /// What `shares` are worth in tokens now, rounded down.
+        #[ink(message)]
+        pub fn shares_to_balance(&self, shares: Balance) -> Balance {
+            if self.total_shares == 0 {
+                return shares;
+            }
+            mul_div(shares, self.total_supply, self.total_shares).unwrap_or(Balance::MAX)
+        }
+
+        /// How many shares `value` tokens are now, rounded down.
+        #[ink(message)]
+        pub fn balance_to_shares(&self, value: Balance) -> Balance {
+            if self.total_supply == 0 {
+                return value;
+            }
+            mul_div(value, self.total_shares, self.total_supply).unwrap_or(Balance::MAX)
+        }
+
+        /// Moves exactly `shares` of the caller's shares to `to`, for
+        /// integrations that account in shares.
+        #[ink(message)]
+        pub fn transfer_shares(&mut self, to: AccountId, shares: Balance) -> Result<()> {
+            let from = self.env().caller();
+            self.move_shares(from, to, shares)
+        }
+
+        /// Changes the total supply by `delta`, scaling every balance by the
+        /// same proportion.
+        #[ink(message)]
+        pub fn rebase(&mut self, delta: i128) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            let magnitude = delta.unsigned_abs();
+            let total_supply = if delta >= 0 {
+                self.total_supply.checked_add(magnitude)
+            } else {
+                self.total_supply.checked_sub(magnitude)
+            }
+            .ok_or(Error::InvalidRebase)?;
+            if total_supply == 0 && self.total_shares > 0 {
+                return Err(Error::InvalidRebase);
+            }
+            self.total_supply = total_supply;
+            self.env().emit_event(Rebased {
+                delta,
+                total_supply,
+            });
+            Ok(())
+        }
+
+        fn move_shares(&mut self, from: AccountId, to: AccountId, shares: Balance) -> Result<()> {
+            let from_shares = self.shares_of(from);
+            if from_shares < shares {
+                return Err(Error::InsufficientBalance);
+            }
+            self.shares.insert(from, &(from_shares - shares));
+            let to_shares = self
+                .shares_of(to)
+                .checked_add(shares)
+                .ok_or(Error::Overflow)?;
+            self.shares.insert(to, &to_shares);
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: self.shares_to_balance(shares),
+                shares,
+            });
+            Ok(())
+        }
+    }
+
+    /// `a * b / d` rounded down, without overflowing in between. `None` if `d`
+    /// is zero or the result does not fit a `Balance`.
+    pub fn mul_div(a: Balance, b: Balance, d: Balance) -> Option<Balance> {
+        if d == 0 {
+            return None;
+        }
+        // Multiply into a 256-bit `(high, low)` pair from 64-bit halves.
+        let half = |x: Balance| (x >> 64, x & Balance::from(u64::MAX));
+        let ((a_high, a_low), (b_high, b_low)) = (half(a), half(b));
+        let (middle, middle_carry) = (a_high * b_low).overflowing_add(a_low * b_high);
+        let (low, low_carry) = (a_low * b_low).overflowing_add(middle << 64);
+        let high = a_high * b_high
+            + (middle >> 64)
+            + (Balance::from(middle_carry) << 64)
+            + Balance::from(low_carry);
+        if high >= d {
+            return None;
+        }
+        // Long division by `d`, one bit at a time.
+        let (mut remainder, mut quotient) = (high, 0);
+        for bit in (0..128).rev() {
+            let overflow = remainder >> 127;
+            remainder = (remainder << 1) | ((low >> bit) & 1);
+            quotient <<= 1;
+            if overflow == 1 || remainder >= d {
+                remainder = remainder.wrapping_sub(d);
+                quotient |= 1;
+            }
+        }
+        Some(quotient)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        #[ink::test]
+        fn mul_div_works() {
+            assert_eq!(mul_div(6, 7, 4), Some(10));
+            assert_eq!(
+                mul_div(Balance::MAX, Balance::MAX, Balance::MAX),
+                Some(Balance::MAX)
+            );
+            assert_eq!(mul_div(Balance::MAX, 3, 6), Some(Balance::MAX / 2));
+            assert_eq!(mul_div(Balance::MAX, 2, 1), None);
+            assert_eq!(mul_div(1, 1, 0), None);
+        }
+
+        #[ink::test]
+        fn rebase_scales_balances() {
+            let accounts = accounts();
+            let mut token = RebasingToken::new(1000);
+            assert_eq!(token.transfer(accounts.bob, 250, Vec::new()), Ok(()));
+            assert_eq!(token.rebase(1000), Ok(()));
+            assert_eq!(token.total_supply(), 2000);
+            assert_eq!(token.balance_of(accounts.alice), 1500);
+            assert_eq!(token.balance_of(accounts.bob), 500);
+            assert_eq!(token.shares_of(accounts.bob), 250);
+            assert_eq!(token.rebase(-1600), Ok(()));
+            assert_eq!(token.balance_of(accounts.bob), 100);
+            assert_eq!(token.rebase(-400), Err(Error::InvalidRebase));
+        }
+
+        #[ink::test]
+        fn transfers_move_shares() {
+            let accounts = accounts();
+            let mut token = RebasingToken::new(1000);
+            token.rebase(500).unwrap();
+            // 300 tokens are 200 shares at 1.5 tokens per share.
+            assert_eq!(token.transfer(accounts.bob, 300, Vec::new()), Ok(()));
+            assert_eq!(token.shares_of(accounts.bob), 200);
+            assert_eq!(token.transfer_shares(accounts.bob, 100), Ok(()));
+            assert_eq!(token.balance_of(accounts.bob), 450);
+            assert_eq!(
+                token.transfer(accounts.bob, 2000, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_spends_allowance() {
+            let accounts = accounts();
+            let mut token = RebasingToken::new(1000);
+            assert_eq!(token.approve(accounts.alice, 100), Ok(()));
+            assert_eq!(
+                token.transfer_from(accounts.alice, accounts.bob, 101, Vec::new()),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                token.transfer_from(accounts.alice, accounts.bob, 100, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(token.allowance(accounts.alice, accounts.alice), 0);
+            assert_eq!(token.balance_of(accounts.bob), 100);
+        }
+
+        #[ink::test]
+        fn only_owner_rebases() {
+            let accounts = accounts();
+            let mut token = RebasingToken::new(1000);
+            token.owner = accounts.bob;
+            assert_eq!(token.rebase(1), Err(Error::NotOwner));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Guard against re-entering a contract while it waits on a cross-contract call.
+//!
+//! ink! writes the fields of a contract back to storage only when a message
+//! returns, so an `entered: bool` field would still read `false` in a call that
+//! comes back in. The guard therefore keeps its flag under its own storage key
+//! and writes it immediately.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Storage key of the `entered` flag, chosen not to collide with contract fields.
+const ENTERED_KEY: [u8; 32] = *b"reentrancy_guard::entered_flag::";
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ReentrancyDetected;
+
+pub fn entered() -> bool {
+    ink::env::get_contract_storage::<_, bool>(&ENTERED_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+/// Runs `f` with the guard set, failing with `ReentrancyDetected` if the guard
+/// is already set by an outer call of the same contract.
+pub fn non_reentrant<T, E, F>(f: F) -> Result<T, E>
+where
+    F: FnOnce() -> Result<T, E>,
+    E: From<ReentrancyDetected>,
+{
+    if entered() {
+        return Err(ReentrancyDetected.into());
+    }
+    ink::env::set_contract_storage(&ENTERED_KEY, &true);
+    let result = f();
+    ink::env::clear_contract_storage(&ENTERED_KEY);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ink::env::DefaultEnvironment;
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Reentrancy,
+        Inner,
+    }
+
+    impl From<ReentrancyDetected> for Error {
+        fn from(_: ReentrancyDetected) -> Self {
+            Error::Reentrancy
+        }
+    }
+
+    #[test]
+    fn guard_is_set_only_inside() {
+        ink::env::test::run_test::<DefaultEnvironment, _>(|_| {
+            assert!(!entered());
+            let result: Result<bool, Error> = non_reentrant(|| Ok(entered()));
+            assert_eq!(result, Ok(true));
+            assert!(!entered());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn nested_entry_is_rejected() {
+        ink::env::test::run_test::<DefaultEnvironment, _>(|_| {
+            let result: Result<(), Error> = non_reentrant(|| non_reentrant(|| Ok(())));
+            assert_eq!(result, Err(Error::Reentrancy));
+            assert!(!entered());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn guard_is_released_on_error() {
+        ink::env::test::run_test::<DefaultEnvironment, _>(|_| {
+            let result: Result<(), Error> = non_reentrant(|| Err(Error::Inner));
+            assert_eq!(result, Err(Error::Inner));
+            assert!(!entered());
+            Ok(())
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Settles orders that makers signed off-chain, as a minimal order book.
+///
+/// An `Order` offers `sell_amount` of `sell_token` for `buy_amount` of
+/// `buy_token`. A taker fills any part of it with `fill`, which moves both
+/// sides with PSP22 `transfer_from` in the same transaction, so both the maker
+/// and the taker have to approve this contract on the token they give. Fills
+/// pay the maker's price, rounded up in the maker's favour. Makers cancel an
+/// order by its nonce. Signatures are checked by the runtime.
+#[ink::contract(env = chain_extensions::signatures::SignaturesEnvironment)]
+mod settlement {
+    use chain_extensions::signatures::{Signature, SignaturesError};
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+    use scale::Encode;
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Order {
+        pub maker: AccountId,
+        pub sell_token: AccountId,
+        pub sell_amount: Balance,
+        pub buy_token: AccountId,
+        pub buy_amount: Balance,
+        /// The order can't be filled after this timestamp.
+        pub expiry: Timestamp,
+        /// Identifies the order among the maker's, for fills and cancelling.
+        pub nonce: u64,
+    }
+
+    #[ink(storage)]
+    pub struct Settlement {
+        /// How much of each order was sold, keyed by `(maker, nonce)`.
+        filled: Mapping<(AccountId, u64), Balance>,
+        cancelled: Mapping<(AccountId, u64), ()>,
+    }
+
+    #[ink(event)]
+    pub struct OrderFilled {
+        #[ink(topic)]
+        maker: AccountId,
+        #[ink(topic)]
+        taker: AccountId,
+        nonce: u64,
+        sold: Balance,
+        paid: Balance,
+    }
+
+    #[ink(event)]
+    pub struct OrderCancelled {
+        #[ink(topic)]
+        maker: AccountId,
+        nonce: u64,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        OrderExpired,
+        OrderCancelled,
+        /// The fill is zero or more than what is left of the order.
+        InvalidFill,
+        InvalidSignature,
+        Overflow,
+        SignaturesError(SignaturesError),
+        TokenError(PSP22Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<SignaturesError> for Error {
+        fn from(error: SignaturesError) -> Self {
+            Error::SignaturesError(error)
+        }
+    }
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Settlement {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                filled: Mapping::default(),
+                cancelled: Mapping::default(),
+            }
+        }
+
+        /// How much of the order `nonce` of `maker` was sold so far.
+        #[ink(message)]
+        pub fn filled(&self, maker: AccountId, nonce: u64) -> Balance {
+            self.filled.get((maker, nonce)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn is_cancelled(&self, maker: AccountId, nonce: u64) -> bool {
+            self.cancelled.contains((maker, nonce))
+        }
+
+        /// Buys `amount` of what `order` sells, paying the order's price.
+        /// `signature` is the maker's signature of the SCALE encoding of
+        /// `(settlement, order)`, where `settlement` is this contract. Returns
+        /// what the caller paid.
+        #[ink(message)]
+        pub fn fill(
+            &mut self,
+            order: Order,
+            signature: Signature,
+            amount: Balance,
+        ) -> Result<Balance> {
+            let taker = self.env().caller();
+            if self.env().block_timestamp() > order.expiry {
+                return Err(Error::OrderExpired);
+            }
+            if self.is_cancelled(order.maker, order.nonce) {
+                return Err(Error::OrderCancelled);
+            }
+            let filled = self.filled(order.maker, order.nonce);
+            if amount == 0 || amount > order.sell_amount.saturating_sub(filled) {
+                return Err(Error::InvalidFill);
+            }
+            let price = price_of(amount, order.buy_amount, order.sell_amount)?;
+            let message = (self.env().account_id(), &order).encode();
+            if !self
+                .env()
+                .extension()
+                .verify(signature, message, order.maker)?
+            {
+                return Err(Error::InvalidSignature);
+            }
+            self.filled
+                .insert((order.maker, order.nonce), &(filled + amount));
+            psp22::transfer_from(order.sell_token, order.maker, taker, amount)?;
+            psp22::transfer_from(order.buy_token, taker, order.maker, price)?;
+            self.env().emit_event(OrderFilled {
+                maker: order.maker,
+                taker,
+                nonce: order.nonce,
+                sold: amount,
+                paid: price,
+            });
+            Ok(price)
+        }
+
+        /// Stops the caller's order `nonce` from being filled any further.
+        #[ink(message)]
+        pub fn cancel(&mut self, nonce: u64) -> Result<()> {
+            let maker = self.env().caller();
+            self.cancelled.insert((maker, nonce), &());
+            self.env().emit_event(OrderCancelled { maker, nonce });
+            Ok(())
+        }
+    }
+
+    /// What buying `amount` costs at `buy_amount` per `sell_amount`, rounded
+    /// up.
+    fn price_of(amount: Balance, buy_amount: Balance, sell_amount: Balance) -> Result<Balance> {
+        let product = amount.checked_mul(buy_amount).ok_or(Error::Overflow)?;
+        Ok(product / sell_amount + Balance::from(product % sell_amount != 0))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn order(expiry: Timestamp) -> Order {
+            Order {
+                maker: accounts().bob,
+                sell_token: AccountId::from([0x8; 32]),
+                sell_amount: 300,
+                buy_token: AccountId::from([0x9; 32]),
+                buy_amount: 100,
+                expiry,
+                nonce: 7,
+            }
+        }
+
+        #[ink::test]
+        fn price_rounds_up() {
+            assert_eq!(price_of(300, 100, 300), Ok(100));
+            assert_eq!(price_of(3, 100, 300), Ok(1));
+            assert_eq!(price_of(1, 100, 300), Ok(1));
+            assert_eq!(price_of(Balance::MAX, 2, 1), Err(Error::Overflow));
+        }
+
+        #[ink::test]
+        fn fill_checks_order() {
+            let mut settlement = Settlement::new();
+            let order = order(Timestamp::MAX);
+            assert_eq!(
+                settlement.fill(order.clone(), [0; 64], 0),
+                Err(Error::InvalidFill)
+            );
+            assert_eq!(
+                settlement.fill(order.clone(), [0; 64], 301),
+                Err(Error::InvalidFill)
+            );
+            settlement.filled.insert((order.maker, order.nonce), &200);
+            assert_eq!(
+                settlement.fill(order.clone(), [0; 64], 101),
+                Err(Error::InvalidFill)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                settlement.fill(self::order(0), [0; 64], 1),
+                Err(Error::OrderExpired)
+            );
+        }
+
+        #[ink::test]
+        fn cancelled_orders_cannot_be_filled() {
+            let accounts = accounts();
+            let mut settlement = Settlement::new();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(settlement.cancel(7), Ok(()));
+            assert!(settlement.is_cancelled(accounts.bob, 7));
+            assert_eq!(
+                settlement.fill(order(Timestamp::MAX), [0; 64], 1),
+                Err(Error::OrderCancelled)
+            );
+        }
+    }
+}
@@ -0,0 +1,341 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Streams `Erc20` tokens from a sender to a recipient continuously.
+///
+/// The sender escrows a deposit that accrues to the recipient linearly from
+/// `start` to `stop`, by the millisecond. The recipient withdraws whatever has
+/// accrued at any time. Either side may cancel, which pays the recipient what
+/// accrued so far and returns the rest to the sender.
+#[ink::contract]
+mod stream {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    pub type StreamId = u64;
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Stream {
+        pub sender: AccountId,
+        pub recipient: AccountId,
+        pub deposit: Balance,
+        pub start: Timestamp,
+        pub stop: Timestamp,
+        /// Already paid to the recipient.
+        pub withdrawn: Balance,
+    }
+
+    impl Stream {
+        /// Part of the deposit that has accrued to the recipient at `now`.
+        pub fn accrued(&self, now: Timestamp) -> Balance {
+            if now <= self.start {
+                return 0;
+            }
+            if now >= self.stop {
+                return self.deposit;
+            }
+            let duration = Balance::from(self.stop - self.start);
+            let elapsed = Balance::from(now - self.start);
+            // Split so that large deposits can't overflow.
+            self.deposit / duration * elapsed + self.deposit % duration * elapsed / duration
+        }
+    }
+
+    #[ink(storage)]
+    pub struct StreamPayments {
+        token: AccountId,
+        streams: Mapping<StreamId, Stream>,
+        next_stream_id: StreamId,
+    }
+
+    #[ink(event)]
+    pub struct StreamCreated {
+        #[ink(topic)]
+        stream_id: StreamId,
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        recipient: AccountId,
+        deposit: Balance,
+        start: Timestamp,
+        stop: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        stream_id: StreamId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct StreamCancelled {
+        #[ink(topic)]
+        stream_id: StreamId,
+        recipient_amount: Balance,
+        sender_amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// `start` is in the past or not before `stop`.
+        InvalidSchedule,
+        ZeroDeposit,
+        StreamNotFound,
+        NotRecipient,
+        NotParty,
+        InsufficientAccrued,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl StreamPayments {
+        #[ink(constructor)]
+        pub fn new(token: AccountId) -> Self {
+            Self {
+                token,
+                streams: Mapping::default(),
+                next_stream_id: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn token(&self) -> AccountId {
+            self.token
+        }
+
+        #[ink(message)]
+        pub fn stream(&self, stream_id: StreamId) -> Option<Stream> {
+            self.streams.get(stream_id)
+        }
+
+        /// What `who` would get if the stream were cancelled now: for the
+        /// recipient the accrued but not withdrawn part, for the sender the part
+        /// not accrued yet.
+        #[ink(message)]
+        pub fn balance_of(&self, stream_id: StreamId, who: AccountId) -> Balance {
+            let stream = match self.streams.get(stream_id) {
+                Some(stream) => stream,
+                None => return 0,
+            };
+            let accrued = stream.accrued(self.env().block_timestamp());
+            if who == stream.recipient {
+                accrued - stream.withdrawn
+            } else if who == stream.sender {
+                stream.deposit - accrued
+            } else {
+                0
+            }
+        }
+
+        /// Escrows `deposit` tokens of the caller to stream them to `recipient`
+        /// between `start` and `stop`. The caller must have approved this
+        /// contract for `deposit`.
+        #[ink(message)]
+        pub fn create_stream(
+            &mut self,
+            recipient: AccountId,
+            deposit: Balance,
+            start: Timestamp,
+            stop: Timestamp,
+        ) -> Result<StreamId> {
+            if start < self.env().block_timestamp() || start >= stop {
+                return Err(Error::InvalidSchedule);
+            }
+            if deposit == 0 {
+                return Err(Error::ZeroDeposit);
+            }
+            let sender = self.env().caller();
+            self.token_contract()
+                .transfer_from(sender, self.env().account_id(), deposit)?;
+            let stream_id = self.next_stream_id;
+            self.next_stream_id += 1;
+            self.streams.insert(
+                stream_id,
+                &Stream {
+                    sender,
+                    recipient,
+                    deposit,
+                    start,
+                    stop,
+                    withdrawn: 0,
+                },
+            );
+            ink::codegen::EmitEvent::<StreamPayments>::emit_event(
+                self.env(),
+                StreamCreated {
+                    stream_id,
+                    sender,
+                    recipient,
+                    deposit,
+                    start,
+                    stop,
+                },
+            );
+            Ok(stream_id)
+        }
+
+        /// Pays `amount` of what has accrued to the recipient out to them.
+        #[ink(message)]
+        pub fn withdraw_from_stream(&mut self, stream_id: StreamId, amount: Balance) -> Result<()> {
+            let recipient = self.env().caller();
+            let available = self.balance_of(stream_id, recipient);
+            let mut stream = self.streams.get(stream_id).ok_or(Error::StreamNotFound)?;
+            if recipient != stream.recipient {
+                return Err(Error::NotRecipient);
+            }
+            if amount > available {
+                return Err(Error::InsufficientAccrued);
+            }
+            stream.withdrawn += amount;
+            if stream.withdrawn == stream.deposit {
+                self.streams.remove(stream_id);
+            } else {
+                self.streams.insert(stream_id, &stream);
+            }
+            self.token_contract().transfer(recipient, amount)?;
+            ink::codegen::EmitEvent::<StreamPayments>::emit_event(
+                self.env(),
+                Withdrawn { stream_id, amount },
+            );
+            Ok(())
+        }
+
+        /// Ends the stream, paying the recipient what accrued and the sender
+        /// the rest. Either of them may cancel.
+        #[ink(message)]
+        pub fn cancel_stream(&mut self, stream_id: StreamId) -> Result<()> {
+            let stream = self.stream(stream_id).ok_or(Error::StreamNotFound)?;
+            let caller = self.env().caller();
+            if caller != stream.sender && caller != stream.recipient {
+                return Err(Error::NotParty);
+            }
+            let recipient_amount = self.balance_of(stream_id, stream.recipient);
+            let sender_amount = self.balance_of(stream_id, stream.sender);
+            self.streams.remove(stream_id);
+            if recipient_amount > 0 {
+                self.token_contract()
+                    .transfer(stream.recipient, recipient_amount)?;
+            }
+            if sender_amount > 0 {
+                self.token_contract()
+                    .transfer(stream.sender, sender_amount)?;
+            }
+            ink::codegen::EmitEvent::<StreamPayments>::emit_event(
+                self.env(),
+                StreamCancelled {
+                    stream_id,
+                    recipient_amount,
+                    sender_amount,
+                },
+            );
+            Ok(())
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn stream(deposit: Balance, start: Timestamp, stop: Timestamp) -> Stream {
+            Stream {
+                sender: AccountId::from([0x1; 32]),
+                recipient: AccountId::from([0x2; 32]),
+                deposit,
+                start,
+                stop,
+                withdrawn: 0,
+            }
+        }
+
+        fn now() -> Timestamp {
+            ink::env::block_timestamp::<ink::env::DefaultEnvironment>()
+        }
+
+        #[ink::test]
+        fn accrued_is_linear() {
+            let stream = stream(1000, 100, 200);
+            assert_eq!(stream.accrued(50), 0);
+            assert_eq!(stream.accrued(100), 0);
+            assert_eq!(stream.accrued(125), 250);
+            assert_eq!(stream.accrued(199), 990);
+            assert_eq!(stream.accrued(300), 1000);
+        }
+
+        #[ink::test]
+        fn accrued_does_not_overflow() {
+            let stream = stream(Balance::MAX, 0, Timestamp::MAX - 1);
+            assert_eq!(stream.accrued(Timestamp::MAX / 2), Balance::MAX / 2);
+        }
+
+        #[ink::test]
+        fn create_stream_checks_schedule() {
+            let mut payments = StreamPayments::new(AccountId::from([0x9; 32]));
+            let recipient = AccountId::from([0x2; 32]);
+            assert_eq!(
+                payments.create_stream(recipient, 100, now() + 10, now() + 10),
+                Err(Error::InvalidSchedule)
+            );
+            assert_eq!(
+                payments.create_stream(recipient, 0, now(), now() + 10),
+                Err(Error::ZeroDeposit)
+            );
+        }
+
+        #[ink::test]
+        fn balances_split_the_deposit() {
+            let mut payments = StreamPayments::new(AccountId::from([0x9; 32]));
+            let start = now();
+            payments.streams.insert(0, &stream(1000, start, start + 4));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let elapsed = Balance::from(now() - start).min(4);
+            let accrued = 1000 * elapsed / 4;
+            assert_eq!(payments.balance_of(0, AccountId::from([0x2; 32])), accrued);
+            assert_eq!(
+                payments.balance_of(0, AccountId::from([0x1; 32])),
+                1000 - accrued
+            );
+            assert_eq!(payments.balance_of(0, AccountId::from([0x3; 32])), 0);
+        }
+
+        #[ink::test]
+        fn withdraw_checks_recipient_and_amount() {
+            let mut payments = StreamPayments::new(AccountId::from([0x9; 32]));
+            payments
+                .streams
+                .insert(0, &stream(1000, now() + 10, now() + 20));
+            assert_eq!(
+                payments.withdraw_from_stream(0, 1),
+                Err(Error::NotRecipient)
+            );
+            assert_eq!(
+                payments.withdraw_from_stream(1, 1),
+                Err(Error::StreamNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn cancel_needs_party() {
+            let mut payments = StreamPayments::new(AccountId::from([0x9; 32]));
+            let mut other = stream(1000, now() + 10, now() + 20);
+            other.sender = AccountId::from([0x3; 32]);
+            payments.streams.insert(0, &other);
+            assert_eq!(payments.cancel_stream(0), Err(Error::NotParty));
+            assert_eq!(payments.cancel_stream(1), Err(Error::StreamNotFound));
+        }
+    }
+}
@@ -0,0 +1,394 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Recurring billing on top of `Erc20` recurring allowances.
+///
+/// A merchant registers a plan charging `amount` of a token every `period`.
+/// To subscribe, the subscriber calls `approve_recurring(subscription
+/// contract, amount, period)` on the token and then `subscribe`. From then on
+/// anyone, typically the merchant's keeper, calls `process_due` to pull each
+/// period's payment once it is due.
+///
+/// A payment that can't be pulled can be retried until the plan's grace
+/// period after the due date has passed; the next `process_due` after that
+/// lapses the subscription.
+#[ink::contract]
+mod subscription {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    pub type PlanId = u32;
+    pub type SubscriptionId = u64;
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Plan {
+        pub merchant: AccountId,
+        pub token: AccountId,
+        pub amount: Balance,
+        pub period: Timestamp,
+        /// How long after a due date a failed payment may still be retried.
+        pub grace_period: Timestamp,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Status {
+        Active,
+        Cancelled,
+        Lapsed,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Subscription {
+        pub plan_id: PlanId,
+        pub subscriber: AccountId,
+        /// When the next payment is due.
+        pub next_due: Timestamp,
+        pub status: Status,
+    }
+
+    #[ink(storage)]
+    pub struct Subscriptions {
+        plans: Mapping<PlanId, Plan>,
+        next_plan_id: PlanId,
+        subscriptions: Mapping<SubscriptionId, Subscription>,
+        next_subscription_id: SubscriptionId,
+    }
+
+    #[ink(event)]
+    pub struct PlanRegistered {
+        #[ink(topic)]
+        plan_id: PlanId,
+        #[ink(topic)]
+        merchant: AccountId,
+        token: AccountId,
+        amount: Balance,
+        period: Timestamp,
+        grace_period: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Subscribed {
+        #[ink(topic)]
+        subscription_id: SubscriptionId,
+        #[ink(topic)]
+        plan_id: PlanId,
+        #[ink(topic)]
+        subscriber: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Charged {
+        #[ink(topic)]
+        subscription_id: SubscriptionId,
+        amount: Balance,
+        next_due: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Ended {
+        #[ink(topic)]
+        subscription_id: SubscriptionId,
+        status: Status,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        InvalidPlan,
+        PlanNotFound,
+        SubscriptionNotFound,
+        NotActive,
+        NotDue,
+        /// The subscriber lacks the balance or allowance for the payment. It
+        /// can be retried until the grace period is over.
+        PaymentFailed,
+        NotParty,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Subscriptions {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                plans: Mapping::default(),
+                next_plan_id: 0,
+                subscriptions: Mapping::default(),
+                next_subscription_id: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn plan(&self, plan_id: PlanId) -> Option<Plan> {
+            self.plans.get(plan_id)
+        }
+
+        #[ink(message)]
+        pub fn subscription(&self, subscription_id: SubscriptionId) -> Option<Subscription> {
+            self.subscriptions.get(subscription_id)
+        }
+
+        /// Registers a plan paying the caller `amount` of `token` every
+        /// `period`.
+        #[ink(message)]
+        pub fn register_plan(
+            &mut self,
+            token: AccountId,
+            amount: Balance,
+            period: Timestamp,
+            grace_period: Timestamp,
+        ) -> Result<PlanId> {
+            if amount == 0 || period == 0 || grace_period >= period {
+                return Err(Error::InvalidPlan);
+            }
+            let merchant = self.env().caller();
+            let plan_id = self.next_plan_id;
+            self.next_plan_id += 1;
+            self.plans.insert(
+                plan_id,
+                &Plan {
+                    merchant,
+                    token,
+                    amount,
+                    period,
+                    grace_period,
+                },
+            );
+            ink::codegen::EmitEvent::<Subscriptions>::emit_event(
+                self.env(),
+                PlanRegistered {
+                    plan_id,
+                    merchant,
+                    token,
+                    amount,
+                    period,
+                    grace_period,
+                },
+            );
+            Ok(plan_id)
+        }
+
+        /// Subscribes the caller to `plan_id`. The first payment is due now.
+        #[ink(message)]
+        pub fn subscribe(&mut self, plan_id: PlanId) -> Result<SubscriptionId> {
+            if !self.plans.contains(plan_id) {
+                return Err(Error::PlanNotFound);
+            }
+            let subscriber = self.env().caller();
+            let subscription_id = self.next_subscription_id;
+            self.next_subscription_id += 1;
+            self.subscriptions.insert(
+                subscription_id,
+                &Subscription {
+                    plan_id,
+                    subscriber,
+                    next_due: self.env().block_timestamp(),
+                    status: Status::Active,
+                },
+            );
+            ink::codegen::EmitEvent::<Subscriptions>::emit_event(
+                self.env(),
+                Subscribed {
+                    subscription_id,
+                    plan_id,
+                    subscriber,
+                },
+            );
+            Ok(subscription_id)
+        }
+
+        /// Pulls the payment due for `subscription_id` from the subscriber to
+        /// the merchant, or lapses the subscription if it could not be paid
+        /// within the grace period.
+        #[ink(message)]
+        pub fn process_due(&mut self, subscription_id: SubscriptionId) -> Result<()> {
+            let subscription = self
+                .subscription(subscription_id)
+                .ok_or(Error::SubscriptionNotFound)?;
+            if subscription.status != Status::Active {
+                return Err(Error::NotActive);
+            }
+            let now = self.env().block_timestamp();
+            if now < subscription.next_due {
+                return Err(Error::NotDue);
+            }
+            let plan = self.plan(subscription.plan_id).ok_or(Error::PlanNotFound)?;
+            let mut token: Erc20Ref = ink::env::call::FromAccountId::from_account_id(plan.token);
+            if !self.can_pull(&token, subscription.subscriber, plan.amount) {
+                if now < subscription.next_due + plan.grace_period {
+                    return Err(Error::PaymentFailed);
+                }
+                return self.end(subscription_id, Status::Lapsed);
+            }
+            token.transfer_from(subscription.subscriber, plan.merchant, plan.amount)?;
+            let next_due = subscription.next_due + plan.period;
+            self.subscriptions.insert(
+                subscription_id,
+                &Subscription {
+                    next_due,
+                    ..subscription
+                },
+            );
+            ink::codegen::EmitEvent::<Subscriptions>::emit_event(
+                self.env(),
+                Charged {
+                    subscription_id,
+                    amount: plan.amount,
+                    next_due,
+                },
+            );
+            Ok(())
+        }
+
+        /// Stops future payments. The subscriber or the merchant may cancel.
+        #[ink(message)]
+        pub fn cancel(&mut self, subscription_id: SubscriptionId) -> Result<()> {
+            let subscription = self
+                .subscription(subscription_id)
+                .ok_or(Error::SubscriptionNotFound)?;
+            if subscription.status != Status::Active {
+                return Err(Error::NotActive);
+            }
+            let caller = self.env().caller();
+            let merchant = self.plan(subscription.plan_id).map(|plan| plan.merchant);
+            if caller != subscription.subscriber && Some(caller) != merchant {
+                return Err(Error::NotParty);
+            }
+            self.end(subscription_id, Status::Cancelled)
+        }
+
+        fn end(&mut self, subscription_id: SubscriptionId, status: Status) -> Result<()> {
+            let mut subscription = self
+                .subscriptions
+                .get(subscription_id)
+                .ok_or(Error::SubscriptionNotFound)?;
+            subscription.status = status;
+            self.subscriptions.insert(subscription_id, &subscription);
+            ink::codegen::EmitEvent::<Subscriptions>::emit_event(
+                self.env(),
+                Ended {
+                    subscription_id,
+                    status,
+                },
+            );
+            Ok(())
+        }
+
+        /// Whether `transfer_from` of `amount` from `subscriber` would succeed.
+        /// A failing cross-contract call would revert this call as a whole, so
+        /// a lapse could never be recorded.
+        fn can_pull(&self, token: &Erc20Ref, subscriber: AccountId, amount: Balance) -> bool {
+            let this = self.env().account_id();
+            let allowance = token.allowance(subscriber, this).max(
+                token
+                    .recurring_allowance(subscriber, this)
+                    .map(|grant| grant.amount_per_period - grant.spent_in_period)
+                    .unwrap_or(0),
+            );
+            token.balance_of(subscriber) >= amount
+                && (allowance >= amount || token.is_approved_for_all(subscriber, this))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(caller);
+        }
+
+        #[ink::test]
+        fn register_plan_works() {
+            let mut subscriptions = Subscriptions::new();
+            let token = AccountId::from([0x9; 32]);
+            assert_eq!(subscriptions.register_plan(token, 100, 10, 5), Ok(0));
+            assert_eq!(
+                subscriptions.plan(0),
+                Some(Plan {
+                    merchant: AccountId::from([0x1; 32]),
+                    token,
+                    amount: 100,
+                    period: 10,
+                    grace_period: 5,
+                })
+            );
+            assert_eq!(
+                subscriptions.register_plan(token, 100, 10, 10),
+                Err(Error::InvalidPlan)
+            );
+            assert_eq!(
+                subscriptions.register_plan(token, 0, 10, 5),
+                Err(Error::InvalidPlan)
+            );
+        }
+
+        #[ink::test]
+        fn subscribe_works() {
+            let mut subscriptions = Subscriptions::new();
+            assert_eq!(subscriptions.subscribe(0), Err(Error::PlanNotFound));
+            subscriptions
+                .register_plan(AccountId::from([0x9; 32]), 100, 10, 5)
+                .unwrap();
+            set_caller(AccountId::from([0x2; 32]));
+            assert_eq!(subscriptions.subscribe(0), Ok(0));
+            let subscription = subscriptions.subscription(0).unwrap();
+            assert_eq!(subscription.subscriber, AccountId::from([0x2; 32]));
+            assert_eq!(subscription.status, Status::Active);
+        }
+
+        #[ink::test]
+        fn cancel_works() {
+            let mut subscriptions = Subscriptions::new();
+            subscriptions
+                .register_plan(AccountId::from([0x9; 32]), 100, 10, 5)
+                .unwrap();
+            set_caller(AccountId::from([0x2; 32]));
+            subscriptions.subscribe(0).unwrap();
+            set_caller(AccountId::from([0x3; 32]));
+            assert_eq!(subscriptions.cancel(0), Err(Error::NotParty));
+            set_caller(AccountId::from([0x1; 32]));
+            assert_eq!(subscriptions.cancel(0), Ok(()));
+            assert_eq!(
+                subscriptions.subscription(0).map(|s| s.status),
+                Some(Status::Cancelled)
+            );
+            assert_eq!(subscriptions.cancel(0), Err(Error::NotActive));
+            assert_eq!(subscriptions.process_due(0), Err(Error::NotActive));
+        }
+
+        #[ink::test]
+        fn process_due_waits_for_due_date() {
+            let mut subscriptions = Subscriptions::new();
+            subscriptions
+                .register_plan(AccountId::from([0x9; 32]), 100, 10, 5)
+                .unwrap();
+            subscriptions.subscribe(0).unwrap();
+            let mut subscription = subscriptions.subscriptions.get(0).unwrap();
+            subscription.next_due = Timestamp::MAX;
+            subscriptions.subscriptions.insert(0, &subscription);
+            assert_eq!(subscriptions.process_due(0), Err(Error::NotDue));
+            assert_eq!(
+                subscriptions.process_due(1),
+                Err(Error::SubscriptionNotFound)
+            );
+        }
+    }
+}
@@ -0,0 +1,611 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Constant-product market between the course `Erc20` and any other PSP22
+/// token.
+///
+/// Liquidity providers deposit both tokens in the current ratio of the reserves
+/// and get shares of the pool in return. A swap pays one token in and takes the
+/// other out so that `reserve_token * reserve_other` does not shrink, after
+/// keeping 0.3% of the input in the pool as the fee for the providers.
+///
+/// Both tokens are pulled with `transfer_from`, so callers have to approve the
+/// pair first. The pair books what it actually received, so a token that
+/// charges a transfer fee, as the course `Erc20` can, gives the caller shares
+/// or output for the amount after the fee.
+///
+/// The pair is also the PSP22 token of its shares, so they can be moved or
+/// staked in the `farm` like any other token.
+#[ink::contract]
+mod swap_pair {
+    use erc20::Erc20Ref;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+
+    /// Share of the input kept as fee, in thousandths.
+    const FEE_PER_MILLE: Balance = 3;
+
+    #[ink(storage)]
+    pub struct SwapPair {
+        token: AccountId,
+        other: AccountId,
+        reserve_token: Balance,
+        reserve_other: Balance,
+        total_shares: Balance,
+        shares: Mapping<AccountId, Balance>,
+        share_allowances: Mapping<(AccountId, AccountId), Balance>,
+    }
+
+    /// Shares moved, minted (`from` is `None`) or burnt (`to` is `None`).
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LiquidityAdded {
+        #[ink(topic)]
+        provider: AccountId,
+        amount_token: Balance,
+        amount_other: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LiquidityRemoved {
+        #[ink(topic)]
+        provider: AccountId,
+        amount_token: Balance,
+        amount_other: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Swapped {
+        #[ink(topic)]
+        trader: AccountId,
+        #[ink(topic)]
+        token_in: AccountId,
+        amount_in: Balance,
+        amount_out: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// The token is neither side of the pair.
+        UnknownToken,
+        ZeroAmount,
+        InsufficientLiquidity,
+        InsufficientShares,
+        /// The result is below the minimum the caller accepts.
+        SlippageExceeded,
+        Overflow,
+        TokenError(erc20::Error),
+        OtherTokenError(PSP22Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::OtherTokenError(error)
+        }
+    }
+
+    impl SwapPair {
+        /// `token` is the course `Erc20`, `other` any PSP22 token.
+        #[ink(constructor)]
+        pub fn new(token: AccountId, other: AccountId) -> Self {
+            assert!(token != other, "a pair needs two different tokens");
+            Self {
+                token,
+                other,
+                reserve_token: 0,
+                reserve_other: 0,
+                total_shares: 0,
+                shares: Mapping::default(),
+                share_allowances: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn token(&self) -> AccountId {
+            self.token
+        }
+
+        #[ink(message)]
+        pub fn other(&self) -> AccountId {
+            self.other
+        }
+
+        /// Reserves of `token` and `other`, in that order.
+        #[ink(message)]
+        pub fn reserves(&self) -> (Balance, Balance) {
+            (self.reserve_token, self.reserve_other)
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        #[ink(message)]
+        pub fn shares_of(&self, provider: AccountId) -> Balance {
+            self.shares.get(provider).unwrap_or(0)
+        }
+
+        /// PSP22::total_supply
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> Balance {
+            self.total_shares
+        }
+
+        /// PSP22::balance_of
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.shares_of(owner)
+        }
+
+        /// PSP22::allowance
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.share_allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// PSP22::transfer
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.move_shares(from, to, value)
+        }
+
+        /// PSP22::transfer_from
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let spender = self.env().caller();
+            let allowance = self.allowance(from, spender);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+            self.share_allowances
+                .insert((from, spender), &(allowance - value));
+            self.move_shares(from, to, value)
+        }
+
+        /// PSP22::approve
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.share_allowances.insert((owner, spender), &value);
+            ink::codegen::EmitEvent::<SwapPair>::emit_event(
+                self.env(),
+                Approval {
+                    owner,
+                    spender,
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        /// Deposits `amount_token` and `amount_other` and returns the shares
+        /// minted for them. The first deposit sets the price; later ones get
+        /// shares for the smaller of the two sides in the current ratio, so
+        /// the excess of the other side is donated to the pool.
+        #[ink(message)]
+        pub fn add_liquidity(
+            &mut self,
+            amount_token: Balance,
+            amount_other: Balance,
+            min_shares: Balance,
+        ) -> Result<Balance> {
+            if amount_token == 0 || amount_other == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let provider = self.env().caller();
+            let amount_token = self.pull(self.token, provider, amount_token)?;
+            let amount_other = self.pull(self.other, provider, amount_other)?;
+            let shares = self.shares_for(amount_token, amount_other)?;
+            if shares < min_shares {
+                return Err(Error::SlippageExceeded);
+            }
+
+            self.reserve_token += amount_token;
+            self.reserve_other += amount_other;
+            self.total_shares += shares;
+            self.shares
+                .insert(provider, &(self.shares_of(provider) + shares));
+            ink::codegen::EmitEvent::<SwapPair>::emit_event(
+                self.env(),
+                Transfer {
+                    from: None,
+                    to: Some(provider),
+                    value: shares,
+                },
+            );
+            ink::codegen::EmitEvent::<SwapPair>::emit_event(
+                self.env(),
+                LiquidityAdded {
+                    provider,
+                    amount_token,
+                    amount_other,
+                    shares,
+                },
+            );
+            Ok(shares)
+        }
+
+        /// Burns `shares` of the caller and pays out their part of both
+        /// reserves, `token` first.
+        #[ink(message)]
+        pub fn remove_liquidity(
+            &mut self,
+            shares: Balance,
+            min_token: Balance,
+            min_other: Balance,
+        ) -> Result<(Balance, Balance)> {
+            let provider = self.env().caller();
+            let provider_shares = self.shares_of(provider);
+            if shares == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            if provider_shares < shares {
+                return Err(Error::InsufficientShares);
+            }
+            let amount_token = mul_div(shares, self.reserve_token, self.total_shares)?;
+            let amount_other = mul_div(shares, self.reserve_other, self.total_shares)?;
+            if amount_token < min_token || amount_other < min_other {
+                return Err(Error::SlippageExceeded);
+            }
+
+            self.shares.insert(provider, &(provider_shares - shares));
+            self.total_shares -= shares;
+            self.reserve_token -= amount_token;
+            self.reserve_other -= amount_other;
+            self.token_contract().transfer(provider, amount_token)?;
+            psp22::transfer(self.other, provider, amount_other)?;
+            ink::codegen::EmitEvent::<SwapPair>::emit_event(
+                self.env(),
+                Transfer {
+                    from: Some(provider),
+                    to: None,
+                    value: shares,
+                },
+            );
+            ink::codegen::EmitEvent::<SwapPair>::emit_event(
+                self.env(),
+                LiquidityRemoved {
+                    provider,
+                    amount_token,
+                    amount_other,
+                    shares,
+                },
+            );
+            Ok((amount_token, amount_other))
+        }
+
+        /// What `swap` would pay out for `amount_in` of `token_in` right now,
+        /// if all of it reaches the pool.
+        #[ink(message)]
+        pub fn quote(&self, token_in: AccountId, amount_in: Balance) -> Result<Balance> {
+            let (reserve_in, reserve_out) = self.reserves_for(token_in)?;
+            amount_out(amount_in, reserve_in, reserve_out)
+        }
+
+        /// Sells `amount_in` of `token_in` for the other token of the pair and
+        /// returns how much was bought. Fails if that is below `min_amount_out`.
+        #[ink(message)]
+        pub fn swap(
+            &mut self,
+            token_in: AccountId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+        ) -> Result<Balance> {
+            self.reserves_for(token_in)?;
+            if amount_in == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let trader = self.env().caller();
+            let amount_in = self.pull(token_in, trader, amount_in)?;
+            let amount_out = self.quote(token_in, amount_in)?;
+            if amount_out < min_amount_out {
+                return Err(Error::SlippageExceeded);
+            }
+
+            if token_in == self.token {
+                psp22::transfer(self.other, trader, amount_out)?;
+                self.reserve_token += amount_in;
+                self.reserve_other -= amount_out;
+            } else {
+                self.token_contract().transfer(trader, amount_out)?;
+                self.reserve_other += amount_in;
+                self.reserve_token -= amount_out;
+            }
+            ink::codegen::EmitEvent::<SwapPair>::emit_event(
+                self.env(),
+                Swapped {
+                    trader,
+                    token_in,
+                    amount_in,
+                    amount_out,
+                },
+            );
+            Ok(amount_out)
+        }
+
+        /// Shares minted for a deposit of `amount_token` and `amount_other`.
+        fn shares_for(&self, amount_token: Balance, amount_other: Balance) -> Result<Balance> {
+            let shares = if self.total_shares == 0 {
+                sqrt(
+                    amount_token
+                        .checked_mul(amount_other)
+                        .ok_or(Error::Overflow)?,
+                )
+            } else {
+                core::cmp::min(
+                    mul_div(amount_token, self.total_shares, self.reserve_token)?,
+                    mul_div(amount_other, self.total_shares, self.reserve_other)?,
+                )
+            };
+            if shares == 0 {
+                return Err(Error::InsufficientLiquidity);
+            }
+            Ok(shares)
+        }
+
+        /// Pulls `amount` of `token`, either side of the pair, from `from` and
+        /// returns how much the pair received.
+        fn pull(&mut self, token: AccountId, from: AccountId, amount: Balance) -> Result<Balance> {
+            let before = self.balance_of_token(token)?;
+            if token == self.token {
+                self.token_contract()
+                    .transfer_from(from, self.env().account_id(), amount)?;
+            } else {
+                psp22::transfer_from(token, from, self.env().account_id(), amount)?;
+            }
+            Ok(self.balance_of_token(token)?.saturating_sub(before))
+        }
+
+        fn balance_of_token(&self, token: AccountId) -> Result<Balance> {
+            let this = self.env().account_id();
+            if token == self.token {
+                Ok(self.token_contract().balance_of(this))
+            } else {
+                Ok(psp22::balance_of(token, this)?)
+            }
+        }
+
+        fn move_shares(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let from_shares = self.shares_of(from);
+            if from_shares < value {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+            self.shares.insert(from, &(from_shares - value));
+            self.shares.insert(to, &(self.shares_of(to) + value));
+            ink::codegen::EmitEvent::<SwapPair>::emit_event(
+                self.env(),
+                Transfer {
+                    from: Some(from),
+                    to: Some(to),
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        /// Reserves of `token_in` and of the token it is swapped for.
+        fn reserves_for(&self, token_in: AccountId) -> Result<(Balance, Balance)> {
+            if token_in == self.token {
+                Ok((self.reserve_token, self.reserve_other))
+            } else if token_in == self.other {
+                Ok((self.reserve_other, self.reserve_token))
+            } else {
+                Err(Error::UnknownToken)
+            }
+        }
+
+        fn token_contract(&self) -> Erc20Ref {
+            ink::env::call::FromAccountId::from_account_id(self.token)
+        }
+    }
+
+    /// Output of a swap of `amount_in` against the reserves after the fee:
+    /// `(x + in') * (y - out) = x * y` with `in' = in * 0.997`, rounded down.
+    fn amount_out(
+        amount_in: Balance,
+        reserve_in: Balance,
+        reserve_out: Balance,
+    ) -> Result<Balance> {
+        if amount_in == 0 {
+            return Err(Error::ZeroAmount);
+        }
+        if reserve_in == 0 || reserve_out == 0 {
+            return Err(Error::InsufficientLiquidity);
+        }
+        let amount_in_with_fee = amount_in
+            .checked_mul(1000 - FEE_PER_MILLE)
+            .ok_or(Error::Overflow)?;
+        let numerator = amount_in_with_fee
+            .checked_mul(reserve_out)
+            .ok_or(Error::Overflow)?;
+        let denominator = reserve_in
+            .checked_mul(1000)
+            .and_then(|reserve| reserve.checked_add(amount_in_with_fee))
+            .ok_or(Error::Overflow)?;
+        let amount_out = numerator / denominator;
+        if amount_out == 0 {
+            return Err(Error::InsufficientLiquidity);
+        }
+        Ok(amount_out)
+    }
+
+    /// `a * b / c`, rounded down.
+    fn mul_div(a: Balance, b: Balance, c: Balance) -> Result<Balance> {
+        Ok(a.checked_mul(b).ok_or(Error::Overflow)? / c)
+    }
+
+    /// Integer square root, rounded down.
+    fn sqrt(value: Balance) -> Balance {
+        if value < 2 {
+            return value;
+        }
+        let mut root = value;
+        let mut next = value / 2 + 1;
+        while next < root {
+            root = next;
+            next = (value / next + next) / 2;
+        }
+        root
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_works() {
+            let pair = SwapPair::new(AccountId::from([0x8; 32]), AccountId::from([0x9; 32]));
+            assert_eq!(pair.token(), AccountId::from([0x8; 32]));
+            assert_eq!(pair.other(), AccountId::from([0x9; 32]));
+            assert_eq!(pair.reserves(), (0, 0));
+            assert_eq!(pair.total_shares(), 0);
+        }
+
+        #[ink::test]
+        fn quote_needs_liquidity() {
+            let mut pair = SwapPair::new(AccountId::from([0x8; 32]), AccountId::from([0x9; 32]));
+            assert_eq!(
+                pair.quote(AccountId::from([0x8; 32]), 100),
+                Err(Error::InsufficientLiquidity)
+            );
+            assert_eq!(
+                pair.swap(AccountId::from([0x7; 32]), 100, 0),
+                Err(Error::UnknownToken)
+            );
+        }
+
+        #[ink::test]
+        fn quote_follows_reserves() {
+            let mut pair = SwapPair::new(AccountId::from([0x8; 32]), AccountId::from([0x9; 32]));
+            pair.reserve_token = 1_000;
+            pair.reserve_other = 2_000;
+            assert_eq!(pair.quote(AccountId::from([0x8; 32]), 100), Ok(181));
+            assert_eq!(pair.quote(AccountId::from([0x9; 32]), 100), Ok(47));
+        }
+
+        #[ink::test]
+        fn amount_out_keeps_product() {
+            let (reserve_in, reserve_out) = (1_000_000, 1_000_000);
+            let out = amount_out(10_000, reserve_in, reserve_out).unwrap();
+            assert_eq!(out, 9_871);
+            assert!((reserve_in + 10_000) * (reserve_out - out) >= reserve_in * reserve_out);
+            assert_eq!(
+                amount_out(0, reserve_in, reserve_out),
+                Err(Error::ZeroAmount)
+            );
+            assert_eq!(amount_out(1, 1_000, 1), Err(Error::InsufficientLiquidity));
+        }
+
+        #[ink::test]
+        fn sqrt_works() {
+            assert_eq!(sqrt(0), 0);
+            assert_eq!(sqrt(1), 1);
+            assert_eq!(sqrt(15), 3);
+            assert_eq!(sqrt(16), 4);
+            assert_eq!(sqrt(Balance::MAX), u64::MAX as Balance);
+        }
+
+        #[ink::test]
+        fn shares_are_psp22() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut pair = SwapPair::new(AccountId::from([0x8; 32]), AccountId::from([0x9; 32]));
+            pair.shares.insert(accounts.alice, &1_000);
+            pair.total_shares = 1_000;
+            assert_eq!(pair.total_supply(), 1_000);
+            assert_eq!(pair.transfer(accounts.bob, 400, Vec::new()), Ok(()));
+            assert_eq!(pair.balance_of(accounts.bob), 400);
+            assert_eq!(
+                pair.transfer(accounts.bob, 601, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            // A farm pulls staked shares with `transfer_from`.
+            assert_eq!(pair.approve(accounts.charlie, 100), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                pair.transfer_from(accounts.alice, accounts.charlie, 101, Vec::new()),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                pair.transfer_from(accounts.alice, accounts.charlie, 100, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(pair.allowance(accounts.alice, accounts.charlie), 0);
+            assert_eq!(pair.balance_of(accounts.alice), 500);
+            assert_eq!(pair.shares_of(accounts.charlie), 100);
+            assert_eq!(pair.total_supply(), 1_000);
+            assert_eq!(ink::env::test::recorded_events().count(), 3);
+        }
+
+        #[ink::test]
+        fn shares_follow_the_amounts_received() {
+            let mut pair = SwapPair::new(AccountId::from([0x8; 32]), AccountId::from([0x9; 32]));
+            assert_eq!(pair.shares_for(400, 100), Ok(200));
+            pair.reserve_token = 400;
+            pair.reserve_other = 100;
+            pair.total_shares = 200;
+            // 10% of the token went to the transfer fee, so the deposit only
+            // counts for 90 of the 100 sent.
+            assert_eq!(pair.shares_for(90, 25), Ok(45));
+            assert_eq!(pair.shares_for(0, 25), Err(Error::InsufficientLiquidity));
+        }
+
+        #[ink::test]
+        fn remove_liquidity_needs_shares() {
+            let mut pair = SwapPair::new(AccountId::from([0x8; 32]), AccountId::from([0x9; 32]));
+            assert_eq!(
+                pair.remove_liquidity(1, 0, 0),
+                Err(Error::InsufficientShares)
+            );
+            assert_eq!(pair.remove_liquidity(0, 0, 0), Err(Error::ZeroAmount));
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Measures the gas and storage deposit of the token's core messages on a live
+//! contracts node: transfers, allowances, minting and burning, and
+//! `issue_batch` against the same number of separate transfers. It writes them
+//! as a markdown table, so storage layout and batching decisions can be argued
+//! with numbers. Messages outside that set are not measured.
+//!
+//! Needs the same setup as the e2e tests and is only compiled with the `bench`
+//! feature:
+//!
+//! ```text
+//! cargo test --features bench --test bench -- --nocapture
+//! ```
+//!
+//! The report is printed and written to `target/bench-report.md`, or to
+//! `BENCH_REPORT` if set.
+
+#![cfg(feature = "bench")]
+
+mod common;
+
+use common::{call, deploy, dry_run, ALICE, BOB, CHARLIE, DAVE, EVE, FERDIE};
+
+/// Gas and storage deposit reported by a dry-run.
+struct Cost {
+    gas: String,
+    storage_deposit: String,
+}
+
+fn measure(contract: &str, message: &str, args: &[&str]) -> Cost {
+    measure_as(contract, message, args, "//Alice")
+}
+
+fn measure_as(contract: &str, message: &str, args: &[&str], suri: &str) -> Cost {
+    let output = dry_run(contract, message, args, suri);
+    let field = |name: &str| {
+        output
+            .lines()
+            .find(|line| line.contains(name))
+            .and_then(|line| line.split_once(name).map(|(_, rest)| rest))
+            .map(|value| {
+                value
+                    .trim_matches(|c: char| c == ':' || c.is_whitespace())
+                    .to_owned()
+            })
+            .unwrap_or_else(|| "n/a".into())
+    };
+    Cost {
+        gas: field("Gas Consumed"),
+        storage_deposit: field("Storage Deposit"),
+    }
+}
+
+#[test]
+fn bench_messages() {
+    let contract = deploy(1_000_000, "0xbe");
+    // Bob holds tokens already, the others have never been written to storage.
+    call(&contract, "transfer", &[BOB, "1"], "//Alice");
+    call(&contract, "approve", &[BOB, "100"], "//Alice");
+    call(
+        &contract,
+        "set_minter_allowance",
+        &[ALICE, "1000000"],
+        "//Alice",
+    );
+
+    let mut rows = vec![
+        (
+            "transfer to existing account",
+            measure(&contract, "transfer", &[BOB, "1"]),
+        ),
+        (
+            "transfer to fresh account",
+            measure(&contract, "transfer", &[CHARLIE, "1"]),
+        ),
+        (
+            "transfer_from to fresh account",
+            measure_as(&contract, "transfer_from", &[ALICE, CHARLIE, "1"], "//Bob"),
+        ),
+        ("approve", measure(&contract, "approve", &[BOB, "100"])),
+        ("burn", measure(&contract, "burn", &["1"])),
+        (
+            "issue to fresh account",
+            measure(&contract, "issue", &[CHARLIE, "1"]),
+        ),
+    ];
+
+    // N separate transfers to fresh accounts, then `issue_batch` minting to
+    // the same accounts in one call.
+    for (n, recipients) in [1usize, 2, 4]
+        .iter()
+        .map(|n| (*n, &[CHARLIE, DAVE, EVE, FERDIE][..*n]))
+    {
+        let costs: Vec<Cost> = recipients
+            .iter()
+            .map(|to| measure(&contract, "transfer", &[to, "1"]))
+            .collect();
+        let join =
+            |f: fn(&Cost) -> &String| costs.iter().map(f).cloned().collect::<Vec<_>>().join(" + ");
+        rows.push((
+            match n {
+                1 => "1 transfer to fresh accounts",
+                2 => "2 transfers to fresh accounts",
+                _ => "4 transfers to fresh accounts",
+            },
+            Cost {
+                gas: join(|cost| &cost.gas),
+                storage_deposit: join(|cost| &cost.storage_deposit),
+            },
+        ));
+        let batch = format!(
+            "[{}]",
+            recipients
+                .iter()
+                .map(|to| format!("({}, 1)", to))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        rows.push((
+            match n {
+                1 => "issue_batch to 1 fresh account",
+                2 => "issue_batch to 2 fresh accounts",
+                _ => "issue_batch to 4 fresh accounts",
+            },
+            measure(&contract, "issue_batch", &[&batch]),
+        ));
+    }
+
+    let mut report =
+        String::from("| operation | gas consumed | storage deposit |\n|---|---|---|\n");
+    for (operation, cost) in &rows {
+        report.push_str(&format!(
+            "| {} | {} | {} |\n",
+            operation, cost.gas, cost.storage_deposit
+        ));
+    }
+    println!("{}", report);
+    let path = std::env::var("BENCH_REPORT")
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/target/bench-report.md").into());
+    std::fs::write(&path, report).expect("Cannot write bench report");
+}
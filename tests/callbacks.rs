@@ -0,0 +1,132 @@
+//! Integration tests of the token's callbacks against the `mock_receiver`
+//! contract, which accepts, rejects or re-enters as it is told, so that
+//! `transfer_and_call`, flash loans and the reentrancy guard run against a
+//! real counterparty on a live contracts node.
+//!
+//! They need the same setup as the e2e tests and are compiled with the same
+//! `e2e-tests` feature:
+//!
+//! ```text
+//! cargo test --features e2e-tests --test callbacks -- --test-threads=1
+//! ```
+
+#![cfg(feature = "e2e-tests")]
+
+mod common;
+
+use common::{
+    assert_balance, call, deploy, deploy_mock_receiver, dry_run, query_in, query_number,
+    query_number_in, ALICE, MOCK_RECEIVER_DIR,
+};
+
+fn query_receiver(receiver: &str, message: &str) -> String {
+    query_in(MOCK_RECEIVER_DIR, receiver, message, &[])
+}
+
+fn query_receiver_number(receiver: &str, message: &str) -> u128 {
+    query_number_in(MOCK_RECEIVER_DIR, receiver, message, &[])
+}
+
+#[test]
+fn transfer_and_call_notifies_accepting_receiver() {
+    let token = deploy(1000, "0x21");
+    let receiver = deploy_mock_receiver("Accept", "0x21");
+    let events = call(
+        &token,
+        "transfer_and_call",
+        &[&receiver, "100", "0x"],
+        "//Alice",
+    );
+    assert!(events.contains("Transfer"), "no Transfer event: {}", events);
+    assert_balance(&token, ALICE, 900);
+    assert_balance(&token, &receiver, 100);
+    assert_eq!(query_receiver_number(&receiver, "received"), 100);
+}
+
+#[test]
+fn transfer_and_call_reverts_when_rejected() {
+    let token = deploy(1000, "0x22");
+    let receiver = deploy_mock_receiver("Reject", "0x22");
+    let output = dry_run(
+        &token,
+        "transfer_and_call",
+        &[&receiver, "100", "0x"],
+        "//Alice",
+    );
+    assert!(
+        output.contains("TransferRejected"),
+        "not rejected: {}",
+        output
+    );
+    assert_balance(&token, &receiver, 0);
+}
+
+#[test]
+fn receiver_cannot_reenter_from_token_callback() {
+    // The token calls `on_token_received` without allowing reentry, so the
+    // runtime traps the receiver's call back and the token sees a rejection.
+    let token = deploy(1000, "0x23");
+    let receiver = deploy_mock_receiver("Reenter", "0x23");
+    let output = dry_run(
+        &token,
+        "transfer_and_call",
+        &[&receiver, "100", "0x"],
+        "//Alice",
+    );
+    assert!(
+        output.contains("TransferRejected"),
+        "not rejected: {}",
+        output
+    );
+    assert_balance(&token, ALICE, 1000);
+}
+
+#[test]
+fn flash_loan_is_repaid_with_fee() {
+    let token = deploy(1000, "0x24");
+    let receiver = deploy_mock_receiver("Accept", "0x24");
+    call(&token, "set_flash_fee", &["100", "None"], "//Alice");
+    // The borrower pays the 1% fee out of its own balance.
+    call(&token, "transfer", &[&receiver, "10"], "//Alice");
+    let events = call(&token, "flash_loan", &[&receiver, "1000", "0x"], "//Alice");
+    assert!(
+        events.contains("FlashLoan"),
+        "no FlashLoan event: {}",
+        events
+    );
+    assert_balance(&token, &receiver, 0);
+    assert_eq!(query_number(&token, "total_supply", &[]), 990);
+    assert_eq!(query_receiver_number(&receiver, "loans"), 1);
+}
+
+#[test]
+fn flash_loan_reverts_when_rejected() {
+    let token = deploy(1000, "0x25");
+    let receiver = deploy_mock_receiver("Reject", "0x25");
+    let output = dry_run(&token, "flash_loan", &[&receiver, "1000", "0x"], "//Alice");
+    assert!(
+        output.contains("FlashLoanRejected"),
+        "not rejected: {}",
+        output
+    );
+    assert_eq!(query_number(&token, "total_supply", &[]), 1000);
+}
+
+#[test]
+fn reentrancy_guard_blocks_nested_flash_loan() {
+    // Flash loans allow reentry so the borrower can use the tokens, but the
+    // guard refuses a second loan taken out from within the first one.
+    let token = deploy(1000, "0x26");
+    let receiver = deploy_mock_receiver("Reenter", "0x26");
+    let events = call(&token, "flash_loan", &[&receiver, "1000", "0x"], "//Alice");
+    assert!(
+        events.contains("FlashLoan"),
+        "no FlashLoan event: {}",
+        events
+    );
+    assert_eq!(
+        query_receiver(&receiver, "last_reentry"),
+        "Ok(Some(Blocked))"
+    );
+    assert_eq!(query_number(&token, "total_supply", &[]), 1000);
+}
@@ -0,0 +1,256 @@
+//! Helpers shared by the test suites that run against a live contracts node
+//! through `cargo-contract`.
+
+#![allow(dead_code)]
+
+use std::process::Command;
+
+pub const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+pub const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+pub const CHARLIE: &str = "5FLSigC9HGRKVhB9FiEo4Y3koPsNmBmLJbpXg2mp1hXcS59Y";
+pub const DAVE: &str = "5DAAnrj7VHTznn2AWBemMuyBwZWs6FNFjdyVXUeYum3PTXFy";
+pub const EVE: &str = "5HGjWAeFDfFCWPsjFQdVV2Msvz2XtMktvgocEZcCj68kUMaw";
+pub const FERDIE: &str = "5CiPPseXPECbkjWCa6MnjNokrgYjMqmKndv2rSnekmSK2DjL";
+
+/// Directory of the `mock_receiver` contract that the callback tests deploy
+/// next to the token.
+pub const MOCK_RECEIVER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/mock_receiver");
+
+/// Directory of the `identity_registry` contract that the transfer rules tests
+/// deploy as the token's rules registry.
+pub const IDENTITY_REGISTRY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/identity_registry");
+
+/// Directory of the `treasury` contract, which spends tokens through the
+/// `psp22` client.
+pub const TREASURY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/treasury");
+
+/// Directory of the `escrow` contract, which holds tokens for an arbiter to
+/// release or refund.
+pub const ESCROW_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/escrow");
+
+/// Directory of the `multisig` contract, which manages its owners through
+/// calls to itself.
+pub const MULTISIG_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/multisig");
+
+/// Directory of the `token_factory` contract, which deploys tokens from the
+/// uploaded `Erc20` code.
+pub const TOKEN_FACTORY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/token_factory");
+
+pub fn node_url() -> String {
+    std::env::var("CONTRACTS_NODE_URL").unwrap_or_else(|_| "ws://127.0.0.1:9944".into())
+}
+
+/// Runs `cargo contract <args>` in the crate root and returns its stdout.
+pub fn cargo_contract(args: &[&str]) -> String {
+    cargo_contract_in(env!("CARGO_MANIFEST_DIR"), args)
+}
+
+/// Runs `cargo contract <args>` in `dir`, e.g. the directory of a helper
+/// contract of the workspace, and returns its stdout.
+pub fn cargo_contract_in(dir: &str, args: &[&str]) -> String {
+    let output = Command::new("cargo")
+        .arg("contract")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("cargo-contract is not installed");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(
+        output.status.success(),
+        "cargo contract {:?} failed:\n{}\n{}",
+        args,
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+}
+
+/// Deploys a fresh instance with `new(total_supply)` from Alice and returns
+/// its address. Every test gets its own instance through a unique salt.
+pub fn deploy(total_supply: u128, salt: &str) -> String {
+    instantiate(
+        env!("CARGO_MANIFEST_DIR"),
+        &[&total_supply.to_string()],
+        salt,
+    )
+}
+
+/// Deploys a fresh `mock_receiver` with `new(behavior)` from Alice and returns
+/// its address.
+pub fn deploy_mock_receiver(behavior: &str, salt: &str) -> String {
+    instantiate(MOCK_RECEIVER_DIR, &[behavior], salt)
+}
+
+/// Deploys a fresh `identity_registry` with `new(required_topics)` from Alice,
+/// who owns it, and returns its address.
+pub fn deploy_identity_registry(required_topics: &str, salt: &str) -> String {
+    instantiate(IDENTITY_REGISTRY_DIR, &[required_topics], salt)
+}
+
+/// Deploys a fresh `treasury` with `new()` from Alice, who is its owner and
+/// governor, and returns its address.
+pub fn deploy_treasury(salt: &str) -> String {
+    instantiate(TREASURY_DIR, &[], salt)
+}
+
+/// Deploys a fresh `escrow` of `token` with `new(token, arbiter)` from Alice
+/// and returns its address.
+pub fn deploy_escrow(token: &str, arbiter: &str, salt: &str) -> String {
+    instantiate(ESCROW_DIR, &[token, arbiter], salt)
+}
+
+/// Deploys a fresh `multisig` with `new(requirement, owners)` from Alice and
+/// returns its address. `owners` is a list such as `[<alice>, <bob>]`.
+pub fn deploy_multisig(requirement: u32, owners: &str, salt: &str) -> String {
+    instantiate(MULTISIG_DIR, &[&requirement.to_string(), owners], salt)
+}
+
+/// Deploys a fresh `token_factory` with `new(code_hash)` from Alice, who owns
+/// it, and returns its address.
+pub fn deploy_token_factory(code_hash: &str, salt: &str) -> String {
+    instantiate(TOKEN_FACTORY_DIR, &[code_hash], salt)
+}
+
+/// Hash of the token's code, read from the metadata `cargo contract build`
+/// writes. The code is on the node once any test deployed a token.
+pub fn code_hash() -> String {
+    cargo_contract(&["build"]);
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/target/ink/erc20.json");
+    let metadata = std::fs::read_to_string(path).expect("Cannot read the token's metadata");
+    metadata
+        .split("\"hash\": \"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("no code hash in the token's metadata")
+        .to_owned()
+}
+
+/// Builds the contract in `dir` and instantiates it with `new(args)`.
+fn instantiate(dir: &str, args: &[&str], salt: &str) -> String {
+    cargo_contract_in(dir, &["build"]);
+    let url = node_url();
+    let mut command = vec![
+        "instantiate",
+        "--constructor",
+        "new",
+        "--salt",
+        salt,
+        "--suri",
+        "//Alice",
+        "--url",
+        &url,
+        "--skip-confirm",
+        "--output-json",
+    ];
+    if !args.is_empty() {
+        command.push("--args");
+        command.extend(args);
+    }
+    let output = cargo_contract_in(dir, &command);
+    output
+        .split("\"contract\": \"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("no contract address in instantiate output")
+        .to_owned()
+}
+
+/// Submits a call of `message` signed by `suri` and returns the output,
+/// which lists the emitted events.
+pub fn call(contract: &str, message: &str, args: &[&str], suri: &str) -> String {
+    call_in(env!("CARGO_MANIFEST_DIR"), contract, message, args, suri)
+}
+
+/// `call` of a contract built from `dir`, whose metadata encodes the call.
+pub fn call_in(dir: &str, contract: &str, message: &str, args: &[&str], suri: &str) -> String {
+    let url = node_url();
+    let mut command = vec![
+        "call",
+        "--contract",
+        contract,
+        "--message",
+        message,
+        "--suri",
+        suri,
+        "--url",
+        &url,
+        "--skip-confirm",
+    ];
+    if !args.is_empty() {
+        command.push("--args");
+        command.extend(args);
+    }
+    cargo_contract_in(dir, &command)
+}
+
+/// Dry-runs `message` as `suri` without submitting it and returns the full
+/// output, including the gas and storage deposit it would cost.
+pub fn dry_run(contract: &str, message: &str, args: &[&str], suri: &str) -> String {
+    dry_run_in(env!("CARGO_MANIFEST_DIR"), contract, message, args, suri)
+}
+
+/// `dry_run` of a contract built from `dir`, whose metadata decodes the call.
+pub fn dry_run_in(dir: &str, contract: &str, message: &str, args: &[&str], suri: &str) -> String {
+    let url = node_url();
+    let mut command = vec![
+        "call",
+        "--contract",
+        contract,
+        "--message",
+        message,
+        "--suri",
+        suri,
+        "--url",
+        &url,
+        "--dry-run",
+    ];
+    if !args.is_empty() {
+        command.push("--args");
+        command.extend(args);
+    }
+    cargo_contract_in(dir, &command)
+}
+
+/// Dry-runs a read-only `message` and returns its decoded return value, e.g.
+/// `Ok(1000)`.
+pub fn query(contract: &str, message: &str, args: &[&str]) -> String {
+    query_in(env!("CARGO_MANIFEST_DIR"), contract, message, args)
+}
+
+/// `query` of a contract built from `dir`.
+pub fn query_in(dir: &str, contract: &str, message: &str, args: &[&str]) -> String {
+    let output = dry_run_in(dir, contract, message, args, "//Alice");
+    // The value is on the line labelled `Data`, below `Result` and `Reverted`.
+    output
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix("Data "))
+        .unwrap_or_else(|| panic!("no return value in output of {}:\n{}", message, output))
+        .trim()
+        .to_owned()
+}
+
+/// `query` of a message that returns a number.
+pub fn query_number(contract: &str, message: &str, args: &[&str]) -> u128 {
+    query_number_in(env!("CARGO_MANIFEST_DIR"), contract, message, args)
+}
+
+/// `query_number` of a contract built from `dir`.
+pub fn query_number_in(dir: &str, contract: &str, message: &str, args: &[&str]) -> u128 {
+    let value = query_in(dir, contract, message, args);
+    value
+        .strip_prefix("Ok(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .map(|number| number.replace('_', ""))
+        .and_then(|number| number.parse().ok())
+        .unwrap_or_else(|| panic!("{} did not return a number: {}", message, value))
+}
+
+/// Asserts that `account` holds exactly `expected` of the token `contract`.
+pub fn assert_balance(contract: &str, account: &str, expected: u128) {
+    assert_eq!(
+        query_number(contract, "balance_of", &[account]),
+        expected,
+        "balance of {}",
+        account
+    );
+}
@@ -0,0 +1,62 @@
+//! End-to-end tests that deploy the contract to a live contracts node and
+//! drive it through real extrinsics, so dispatch, codec and selector problems
+//! that unit tests can't see show up here.
+//!
+//! They need a running `substrate-contracts-node --dev` and `cargo-contract`
+//! on the `PATH`, and are only compiled with the `e2e-tests` feature:
+//!
+//! ```text
+//! cargo test --features e2e-tests --test e2e -- --test-threads=1
+//! ```
+//!
+//! The node defaults to `ws://127.0.0.1:9944`; set `CONTRACTS_NODE_URL` to use
+//! another one.
+
+#![cfg(feature = "e2e-tests")]
+
+mod common;
+
+use common::{assert_balance, call, deploy, query_number, ALICE, BOB, CHARLIE};
+
+#[test]
+fn constructor_works() {
+    let contract = deploy(1000, "0x01");
+    assert_eq!(query_number(&contract, "total_supply", &[]), 1000);
+    assert_balance(&contract, ALICE, 1000);
+    assert_balance(&contract, BOB, 0);
+}
+
+#[test]
+fn transfer_works() {
+    let contract = deploy(1000, "0x02");
+    let events = call(&contract, "transfer", &[BOB, "100"], "//Alice");
+    assert!(events.contains("Transfer"), "no Transfer event: {}", events);
+    assert_balance(&contract, ALICE, 900);
+    assert_balance(&contract, BOB, 100);
+}
+
+#[test]
+fn approve_and_transfer_from_work() {
+    let contract = deploy(1000, "0x03");
+    let events = call(&contract, "approve", &[BOB, "300"], "//Alice");
+    assert!(events.contains("Approval"), "no Approval event: {}", events);
+    assert_eq!(query_number(&contract, "allowance", &[ALICE, BOB]), 300);
+
+    let events = call(
+        &contract,
+        "transfer_from",
+        &[ALICE, CHARLIE, "200"],
+        "//Bob",
+    );
+    assert!(events.contains("Transfer"), "no Transfer event: {}", events);
+    assert_balance(&contract, ALICE, 800);
+    assert_balance(&contract, CHARLIE, 200);
+    assert_eq!(query_number(&contract, "allowance", &[ALICE, BOB]), 100);
+}
+
+#[test]
+fn deprecated_selectors_still_dispatch() {
+    let contract = deploy(1000, "0x04");
+    call(&contract, "transer", &[BOB, "10"], "//Alice");
+    assert_balance(&contract, BOB, 10);
+}
@@ -0,0 +1,63 @@
+//! Integration tests of the `escrow` contract holding real tokens, so that a
+//! deposit, release and refund are shown to move funds between the payer, the
+//! escrow and the payee.
+//!
+//! They need the same setup as the e2e tests and are compiled with the same
+//! `e2e-tests` feature:
+//!
+//! ```text
+//! cargo test --features e2e-tests --test escrow -- --test-threads=1
+//! ```
+
+#![cfg(feature = "e2e-tests")]
+
+mod common;
+
+use common::{
+    assert_balance, call, call_in, deploy, deploy_escrow, query_in, ALICE, BOB, CHARLIE, ESCROW_DIR,
+};
+
+/// Deploys a token and an escrow of it arbitrated by Bob, and has Alice
+/// deposit 300 for Charlie under order 1, refundable at once.
+fn funded_escrow(salt: &str) -> (String, String) {
+    let token = deploy(1000, salt);
+    let escrow = deploy_escrow(&token, BOB, salt);
+    call(&token, "approve", &[&escrow, "300"], "//Alice");
+    let events = call_in(
+        ESCROW_DIR,
+        &escrow,
+        "deposit",
+        &["1", CHARLIE, "300", "0"],
+        "//Alice",
+    );
+    assert!(
+        events.contains("Deposited"),
+        "no Deposited event: {}",
+        events
+    );
+    assert_balance(&token, ALICE, 700);
+    assert_balance(&token, &escrow, 300);
+    let deal = query_in(ESCROW_DIR, &escrow, "deal", &["1"]);
+    assert!(deal.contains("amount: 300"), "deal not booked: {}", deal);
+    (token, escrow)
+}
+
+#[test]
+fn release_pays_the_payee() {
+    let (token, escrow) = funded_escrow("0x51");
+    let events = call_in(ESCROW_DIR, &escrow, "release", &["1"], "//Bob");
+    assert!(events.contains("Released"), "no Released event: {}", events);
+    assert_balance(&token, &escrow, 0);
+    assert_balance(&token, CHARLIE, 300);
+    assert_balance(&token, ALICE, 700);
+}
+
+#[test]
+fn refund_pays_the_payer_back() {
+    let (token, escrow) = funded_escrow("0x52");
+    let events = call_in(ESCROW_DIR, &escrow, "refund", &["1"], "//Bob");
+    assert!(events.contains("Refunded"), "no Refunded event: {}", events);
+    assert_balance(&token, &escrow, 0);
+    assert_balance(&token, CHARLIE, 0);
+    assert_balance(&token, ALICE, 1000);
+}
@@ -0,0 +1,51 @@
+//! Integration tests of the `multisig` contract managing its owners through
+//! transactions it executes against itself, which only work on a live node
+//! where the call re-enters the wallet.
+//!
+//! They need the same setup as the e2e tests and are compiled with the same
+//! `e2e-tests` feature:
+//!
+//! ```text
+//! cargo test --features e2e-tests --test multisig -- --test-threads=1
+//! ```
+
+#![cfg(feature = "e2e-tests")]
+
+mod common;
+
+use common::{call_in, deploy_multisig, query_in, ALICE, BOB, CHARLIE, MULTISIG_DIR};
+
+/// Selector of `add_owner`.
+const ADD_OWNER: &str = "[0xad, 0x6d, 0x43, 0x58]";
+/// Charlie's public key, the SCALE encoding of his `AccountId`.
+const CHARLIE_ENCODED: &str = "0x90b5ab205c6974c9ea841be688864633dc9ca8a357843eeacf2314649965fe22";
+
+#[test]
+fn executed_transaction_adds_an_owner() {
+    let wallet = deploy_multisig(2, &format!("[{}, {}]", ALICE, BOB), "0x61");
+    let transaction = format!(
+        "Transaction {{ callee: {}, selector: {}, input: {}, transferred_value: 0, gas_limit: 0 }}",
+        wallet, ADD_OWNER, CHARLIE_ENCODED
+    );
+    call_in(
+        MULTISIG_DIR,
+        &wallet,
+        "submit_transaction",
+        &[&transaction],
+        "//Alice",
+    );
+    call_in(MULTISIG_DIR, &wallet, "confirm", &["0"], "//Bob");
+    let events = call_in(MULTISIG_DIR, &wallet, "execute", &["0"], "//Alice");
+    assert!(
+        events.contains("OwnerAddition"),
+        "no OwnerAddition event: {}",
+        events
+    );
+    let owners = query_in(MULTISIG_DIR, &wallet, "owners", &[]);
+    assert!(owners.contains(CHARLIE), "not an owner: {}", owners);
+    assert_eq!(query_in(MULTISIG_DIR, &wallet, "requirement", &[]), "Ok(2)");
+    assert_eq!(
+        query_in(MULTISIG_DIR, &wallet, "transaction", &["0"]),
+        "Ok(None)"
+    );
+}
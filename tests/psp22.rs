@@ -0,0 +1,74 @@
+//! Integration tests of the token's PSP22 messages against the `treasury`
+//! contract, which only knows tokens through the `psp22` client, so that the
+//! standard selectors are shown to move real funds.
+//!
+//! They need the same setup as the e2e tests and are compiled with the same
+//! `e2e-tests` feature:
+//!
+//! ```text
+//! cargo test --features e2e-tests --test psp22 -- --test-threads=1
+//! ```
+
+#![cfg(feature = "e2e-tests")]
+
+mod common;
+
+use common::{
+    assert_balance, call, call_in, deploy, deploy_treasury, dry_run, query_number_in, BOB,
+    TREASURY_DIR,
+};
+
+#[test]
+fn treasury_spend_moves_tokens() {
+    let token = deploy(1000, "0x41");
+    let treasury = deploy_treasury("0x41");
+    call(&token, "transfer", &[&treasury, "300"], "//Alice");
+    assert_balance(&token, &treasury, 300);
+    let asset = format!("Token({})", token);
+    call_in(
+        TREASURY_DIR,
+        &treasury,
+        "propose_spend",
+        &[&asset, BOB, "120"],
+        "//Alice",
+    );
+    let events = call_in(TREASURY_DIR, &treasury, "approve_spend", &["0"], "//Alice");
+    assert!(events.contains("Spend"), "no Spend event: {}", events);
+    assert_balance(&token, &treasury, 180);
+    assert_balance(&token, BOB, 120);
+}
+
+#[test]
+fn treasury_credits_accepted_tokens_only() {
+    let token = deploy(1000, "0x42");
+    let treasury = deploy_treasury("0x42");
+    let output = dry_run(
+        &token,
+        "transfer_and_call",
+        &[&treasury, "100", "0x"],
+        "//Alice",
+    );
+    assert!(
+        output.contains("TransferRejected"),
+        "not rejected: {}",
+        output
+    );
+    call_in(
+        TREASURY_DIR,
+        &treasury,
+        "set_token_accepted",
+        &[&token, "true"],
+        "//Alice",
+    );
+    call(
+        &token,
+        "transfer_and_call",
+        &[&treasury, "100", "0x"],
+        "//Alice",
+    );
+    let asset = format!("Token({})", token);
+    assert_eq!(
+        query_number_in(TREASURY_DIR, &treasury, "received", &[&asset]),
+        100
+    );
+}
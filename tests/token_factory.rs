@@ -0,0 +1,59 @@
+//! Integration tests of the `token_factory` contract deploying tokens from
+//! the uploaded code, so that `predict_address` is checked against where the
+//! contracts pallet really puts a token.
+//!
+//! They need the same setup as the e2e tests and are compiled with the same
+//! `e2e-tests` feature:
+//!
+//! ```text
+//! cargo test --features e2e-tests --test token_factory -- --test-threads=1
+//! ```
+
+#![cfg(feature = "e2e-tests")]
+
+mod common;
+
+use common::{
+    assert_balance, call_in, code_hash, deploy, deploy_token_factory, query_in, ALICE,
+    TOKEN_FACTORY_DIR,
+};
+
+#[test]
+fn deterministic_token_lands_at_predicted_address() {
+    // Deploying a token uploads the code the factory instantiates.
+    deploy(1, "0x71");
+    let factory = deploy_token_factory(&code_hash(), "0x71");
+    let salt = format!("0x{}", "07".repeat(32));
+    let params = format!(
+        "TokenParams {{ name: Some(\"Course Token\"), symbol: Some(\"CRS\"), total_supply: 1000, owner: {} }}",
+        ALICE
+    );
+    let predicted = query_in(
+        TOKEN_FACTORY_DIR,
+        &factory,
+        "predict_address",
+        &[&salt, &params],
+    );
+    let predicted = predicted
+        .strip_prefix("Ok(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or_else(|| panic!("no address predicted: {}", predicted))
+        .to_owned();
+    let events = call_in(
+        TOKEN_FACTORY_DIR,
+        &factory,
+        "create_deterministic",
+        &[&salt, &params],
+        "//Alice",
+    );
+    assert!(
+        events.contains("TokenCreated"),
+        "no TokenCreated event: {}",
+        events
+    );
+    assert_eq!(
+        query_in(TOKEN_FACTORY_DIR, &factory, "tokens_created", &["0"]),
+        format!("Ok([{}])", predicted)
+    );
+    assert_balance(&predicted, ALICE, 1000);
+}
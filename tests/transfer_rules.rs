@@ -0,0 +1,113 @@
+//! Integration tests of the rules registry against the `identity_registry`
+//! contract, so that every path moving tokens between two holders, not only
+//! `transfer`, is shown to ask it.
+//!
+//! They need the same setup as the e2e tests and are compiled with the same
+//! `e2e-tests` feature:
+//!
+//! ```text
+//! cargo test --features e2e-tests --test transfer_rules -- --test-threads=1
+//! ```
+
+#![cfg(feature = "e2e-tests")]
+
+mod common;
+
+use common::{
+    assert_balance, call, call_in, deploy, deploy_identity_registry, dry_run, ALICE, BOB, CHARLIE,
+    IDENTITY_REGISTRY_DIR,
+};
+
+/// The claim topic the registry requires.
+const KYC: &str = "1";
+
+/// Deploys a token and a registry requiring `KYC` that verified `verified`.
+/// The token doesn't ask the registry until `enforce` is called, so tests can
+/// set up what would not be allowed afterwards.
+fn setup(salt: &str, verified: &[&str]) -> (String, String) {
+    let token = deploy(1000, salt);
+    let registry = deploy_identity_registry(&format!("[{}]", KYC), salt);
+    call_in(
+        IDENTITY_REGISTRY_DIR,
+        &registry,
+        "set_registrar",
+        &[ALICE, "true"],
+        "//Alice",
+    );
+    for account in verified {
+        call_in(
+            IDENTITY_REGISTRY_DIR,
+            &registry,
+            "add_claim",
+            &[account, KYC, &u64::MAX.to_string()],
+            "//Alice",
+        );
+    }
+    (token, registry)
+}
+
+fn enforce(token: &str, registry: &str) {
+    let registry = format!("Some({})", registry);
+    call(token, "set_rules_registry", &[&registry], "//Alice");
+}
+
+fn assert_not_allowed(output: &str) {
+    assert!(
+        output.contains("TransferNotAllowed"),
+        "transfer was allowed: {}",
+        output
+    );
+}
+
+#[test]
+fn scheduled_transfer_is_checked_on_execution() {
+    let (token, registry) = setup("0x31", &[ALICE]);
+    call(&token, "schedule_transfer", &[BOB, "100", "0"], "//Alice");
+    enforce(&token, &registry);
+    assert_not_allowed(&dry_run(&token, "execute", &["0"], "//Alice"));
+    assert_not_allowed(&dry_run(
+        &token,
+        "schedule_transfer",
+        &[BOB, "100", "0"],
+        "//Alice",
+    ));
+    // Returning the tokens to their sender is still possible.
+    call(&token, "cancel", &["0"], "//Alice");
+    assert_balance(&token, ALICE, 1000);
+}
+
+#[test]
+fn inheritance_claim_is_checked() {
+    let (token, registry) = setup("0x32", &[ALICE]);
+    call(&token, "set_heir", &[BOB, "0"], "//Alice");
+    enforce(&token, &registry);
+    assert_not_allowed(&dry_run(&token, "claim_inheritance", &[ALICE], "//Bob"));
+    assert_balance(&token, BOB, 0);
+}
+
+#[test]
+fn sponsorship_is_checked() {
+    let (token, registry) = setup("0x33", &[ALICE]);
+    call(
+        &token,
+        "set_sponsorship",
+        &["Some(Sponsorship { starter_amount: 10, max_accounts_per_sponsor: 1 })"],
+        "//Alice",
+    );
+    enforce(&token, &registry);
+    assert_not_allowed(&dry_run(&token, "sponsor_account", &[CHARLIE], "//Alice"));
+    assert_balance(&token, CHARLIE, 0);
+}
+
+#[test]
+fn round_up_is_checked() {
+    // Bob may receive, but the charity the round-up goes to may not.
+    let (token, registry) = setup("0x34", &[ALICE, BOB]);
+    call(&token, "set_roundup", &[CHARLIE, "10"], "//Alice");
+    enforce(&token, &registry);
+    assert_not_allowed(&dry_run(&token, "transfer", &[BOB, "5"], "//Alice"));
+    // A transfer that needs no rounding up goes through.
+    call(&token, "transfer", &[BOB, "10"], "//Alice");
+    assert_balance(&token, BOB, 10);
+    assert_balance(&token, CHARLIE, 0);
+}
@@ -0,0 +1,300 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Deploys new `Erc20` tokens from code already uploaded to the chain.
+///
+/// The factory instantiates the code at `code_hash` with the parameters the
+/// caller picks and keeps the address of every token it deployed. Each
+/// deployment uses a fresh salt, since instantiating the same code with the
+/// same salt twice would give the same address. Whatever the caller pays with
+/// `create` endows the new token.
+///
+/// `create_deterministic` instead derives the salt from one the caller picks
+/// and the token's parameters, so the address is known before deployment:
+/// `predict_address` computes it the way the contracts pallet does, as the
+/// BLAKE2-256 hash of `(b"contract_addr_v1", factory, code_hash, input, salt)`
+/// SCALE encoded, where `input` is the encoded `new_token` call.
+#[ink::contract]
+mod token_factory {
+    use erc20::Erc20Ref;
+    use ink::env::hash::Blake2x256;
+    use ink::prelude::{string::String, vec::Vec};
+    use ink::storage::Mapping;
+    use scale::Encode;
+
+    /// How many tokens a page of `tokens_created` holds.
+    pub const TOKENS_PAGE_SIZE: u32 = 50;
+
+    /// What the new token is called, how much it starts with and who owns it.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TokenParams {
+        pub name: Option<String>,
+        pub symbol: Option<String>,
+        pub total_supply: Balance,
+        /// Owns the token and holds its whole initial supply.
+        pub owner: AccountId,
+    }
+
+    #[ink(storage)]
+    pub struct TokenFactory {
+        owner: AccountId,
+        /// The `Erc20` code new tokens run.
+        code_hash: Hash,
+        /// Every token deployed, oldest first, by index.
+        tokens: Mapping<u32, AccountId>,
+        token_count: u32,
+    }
+
+    #[ink(event)]
+    pub struct TokenCreated {
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        owner: AccountId,
+        creator: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct CodeHashUpdated {
+        code_hash: Hash,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        InstantiationFailed,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl TokenFactory {
+        /// The caller becomes the owner, who may change `code_hash` later.
+        #[ink(constructor)]
+        pub fn new(code_hash: Hash) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                code_hash,
+                tokens: Mapping::default(),
+                token_count: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn code_hash(&self) -> Hash {
+            self.code_hash
+        }
+
+        /// Makes tokens created from now on run the code at `code_hash`.
+        #[ink(message)]
+        pub fn set_code_hash(&mut self, code_hash: Hash) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            self.code_hash = code_hash;
+            ink::codegen::EmitEvent::<TokenFactory>::emit_event(
+                self.env(),
+                CodeHashUpdated { code_hash },
+            );
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn token_count(&self) -> u32 {
+            self.token_count
+        }
+
+        /// Page `page` (counting from 0) of the tokens deployed, oldest first.
+        #[ink(message)]
+        pub fn tokens_created(&self, page: u32) -> Vec<AccountId> {
+            let start = page.saturating_mul(TOKENS_PAGE_SIZE);
+            let end = start.saturating_add(TOKENS_PAGE_SIZE).min(self.token_count);
+            (start..end)
+                .filter_map(|index| self.tokens.get(index))
+                .collect()
+        }
+
+        /// Deploys a token with `params`, endowed with what the caller paid,
+        /// and returns its address.
+        #[ink(message, payable)]
+        pub fn create(&mut self, params: TokenParams) -> Result<AccountId> {
+            // The creator and the number of tokens so far are never repeated.
+            let salt = (self.env().caller(), self.token_count).encode();
+            self.instantiate(params, salt)
+        }
+
+        /// Deploys a token with `params` at `predict_address(salt, params)`,
+        /// endowed with what the caller paid. Fails if a token with the same
+        /// salt and parameters was deployed already.
+        #[ink(message, payable)]
+        pub fn create_deterministic(
+            &mut self,
+            salt: Hash,
+            params: TokenParams,
+        ) -> Result<AccountId> {
+            let salt = deterministic_salt(salt, &params);
+            self.instantiate(params, salt)
+        }
+
+        /// The address `create_deterministic(salt, params)` deploys to with
+        /// the current `code_hash`.
+        #[ink(message)]
+        pub fn predict_address(&self, salt: Hash, params: TokenParams) -> AccountId {
+            let salt = deterministic_salt(salt, &params);
+            let input = self.constructor_input(params);
+            let entropy = (
+                b"contract_addr_v1",
+                self.env().account_id(),
+                self.code_hash,
+                input,
+                salt,
+            )
+                .encode();
+            AccountId::from(self.env().hash_bytes::<Blake2x256>(&entropy))
+        }
+
+        /// The selector and arguments of the `new_token` call deploying a
+        /// token with `params`.
+        fn constructor_input(&self, params: TokenParams) -> Vec<u8> {
+            Erc20Ref::new_token(
+                params.name,
+                params.symbol,
+                params.total_supply,
+                params.owner,
+            )
+            .endowment(0)
+            .code_hash(self.code_hash)
+            .salt_bytes(Vec::new())
+            .params()
+            .exec_input()
+            .encode()
+        }
+
+        fn instantiate(&mut self, params: TokenParams, salt: Vec<u8>) -> Result<AccountId> {
+            let owner = params.owner;
+            let token = Erc20Ref::new_token(
+                params.name,
+                params.symbol,
+                params.total_supply,
+                params.owner,
+            )
+            .endowment(self.env().transferred_value())
+            .code_hash(self.code_hash)
+            .salt_bytes(salt)
+            .try_instantiate()
+            .map_err(|_| Error::InstantiationFailed)?
+            .map_err(|_| Error::InstantiationFailed)?;
+            let token = ink::ToAccountId::to_account_id(&token);
+            self.tokens.insert(self.token_count, &token);
+            self.token_count += 1;
+            ink::codegen::EmitEvent::<TokenFactory>::emit_event(
+                self.env(),
+                TokenCreated {
+                    token,
+                    owner,
+                    creator: self.env().caller(),
+                },
+            );
+            Ok(token)
+        }
+    }
+
+    /// The salt a deterministic deployment passes to the chain, covering the
+    /// parameters so different tokens never share an address.
+    fn deterministic_salt(salt: Hash, params: &TokenParams) -> Vec<u8> {
+        (salt, params).encode()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn factory() -> TokenFactory {
+            TokenFactory::new(Hash::from([0x1; 32]))
+        }
+
+        #[ink::test]
+        fn tokens_created_is_paged() {
+            let mut factory = factory();
+            for byte in 0..=TOKENS_PAGE_SIZE as u8 {
+                factory
+                    .tokens
+                    .insert(u32::from(byte), &AccountId::from([byte; 32]));
+            }
+            factory.token_count = TOKENS_PAGE_SIZE + 1;
+            assert_eq!(factory.token_count(), TOKENS_PAGE_SIZE + 1);
+            let first = factory.tokens_created(0);
+            assert_eq!(first.len(), TOKENS_PAGE_SIZE as usize);
+            assert_eq!(first[0], AccountId::from([0x0; 32]));
+            assert_eq!(
+                factory.tokens_created(1),
+                vec![AccountId::from([TOKENS_PAGE_SIZE as u8; 32])]
+            );
+            assert_eq!(factory.tokens_created(2), Vec::new());
+            assert_eq!(factory.tokens_created(u32::MAX), Vec::new());
+        }
+
+        fn params(total_supply: Balance) -> TokenParams {
+            TokenParams {
+                name: Some("Course Token".into()),
+                symbol: Some("CRS".into()),
+                total_supply,
+                owner: accounts().alice,
+            }
+        }
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        #[ink::test]
+        fn predict_address_depends_on_salt_params_and_code() {
+            let mut factory = factory();
+            let salt = Hash::from([0x7; 32]);
+            let address = factory.predict_address(salt, params(1000));
+            assert_eq!(factory.predict_address(salt, params(1000)), address);
+            assert_ne!(
+                factory.predict_address(Hash::from([0x8; 32]), params(1000)),
+                address
+            );
+            assert_ne!(factory.predict_address(salt, params(1001)), address);
+            factory.set_code_hash(Hash::from([0x2; 32])).unwrap();
+            assert_ne!(factory.predict_address(salt, params(1000)), address);
+        }
+
+        #[ink::test]
+        fn constructor_input_calls_new_token() {
+            let factory = factory();
+            let input = factory.constructor_input(params(1000));
+            assert_eq!(input[..4], [0x7d, 0x97, 0xec, 0x7b]);
+            let params = params(1000);
+            assert_eq!(
+                input[4..],
+                (
+                    params.name,
+                    params.symbol,
+                    params.total_supply,
+                    params.owner
+                )
+                    .encode()[..]
+            );
+        }
+
+        #[ink::test]
+        fn only_the_owner_sets_code_hash() {
+            let accounts = accounts();
+            let mut factory = factory();
+            assert_eq!(factory.set_code_hash(Hash::from([0x2; 32])), Ok(()));
+            assert_eq!(factory.code_hash(), Hash::from([0x2; 32]));
+            factory.owner = accounts.bob;
+            assert_eq!(
+                factory.set_code_hash(Hash::from([0x3; 32])),
+                Err(Error::NotOwner)
+            );
+        }
+    }
+}
@@ -0,0 +1,490 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Collects native funds and PSP22 tokens, e.g. transfer fees and crowdsale
+/// proceeds, and spends them only through proposals the governor approves.
+///
+/// The owner proposes spends; the governor, typically a multisig or timelock
+/// contract, approves or rejects them. The treasury accounts per asset for
+/// what it was sent through `deposit` or `transfer_and_call` and what it spent.
+/// Only tokens the owner accepted can be sent with `transfer_and_call`, as
+/// any contract can claim to be a token there. Funds sent with a plain
+/// transfer are spendable but not counted as received.
+///
+/// The owner can also set native funds aside. Reserving goes through the
+/// balances pallet via the chain extension in `chain_extensions::balances`,
+/// as the contracts pallet itself only lets contracts transfer. Reserved
+/// funds stay on the treasury's account but can't be spent, not even by the
+/// treasury, until they are unreserved.
+#[ink::contract(env = chain_extensions::balances::BalancesEnvironment)]
+mod treasury {
+    use chain_extensions::balances::BalancesError;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+
+    pub type ProposalId = u32;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Asset {
+        Native,
+        /// A PSP22 token contract.
+        Token(AccountId),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct SpendProposal {
+        pub asset: Asset,
+        pub to: AccountId,
+        pub amount: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct Treasury {
+        owner: AccountId,
+        governor: AccountId,
+        proposals: Mapping<ProposalId, SpendProposal>,
+        next_proposal_id: ProposalId,
+        received: Mapping<Asset, Balance>,
+        spent: Mapping<Asset, Balance>,
+        /// Token contracts whose `on_token_received` calls are credited.
+        accepted_tokens: Mapping<AccountId, ()>,
+    }
+
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        from: AccountId,
+        asset: Asset,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SpendProposed {
+        #[ink(topic)]
+        id: ProposalId,
+        asset: Asset,
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SpendRejected {
+        #[ink(topic)]
+        id: ProposalId,
+    }
+
+    #[ink(event)]
+    pub struct Spend {
+        #[ink(topic)]
+        id: ProposalId,
+        #[ink(topic)]
+        to: AccountId,
+        asset: Asset,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct GovernorChanged {
+        #[ink(topic)]
+        governor: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct TokenAcceptanceChanged {
+        #[ink(topic)]
+        token: AccountId,
+        accepted: bool,
+    }
+
+    #[ink(event)]
+    pub struct Reserved {
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Unreserved {
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotOwner,
+        NotGovernor,
+        ProposalNotFound,
+        BalancesError(BalancesError),
+        TokenError(PSP22Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<BalancesError> for Error {
+        fn from(error: BalancesError) -> Self {
+            Error::BalancesError(error)
+        }
+    }
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl Treasury {
+        /// The caller is owner and governor until it hands the governor role
+        /// to the governance contract with `set_governor`.
+        #[ink(constructor, payable)]
+        pub fn new() -> Self {
+            let caller = Self::env().caller();
+            Self {
+                owner: caller,
+                governor: caller,
+                proposals: Mapping::default(),
+                next_proposal_id: 0,
+                received: Mapping::default(),
+                spent: Mapping::default(),
+                accepted_tokens: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn governor(&self) -> AccountId {
+            self.governor
+        }
+
+        #[ink(message)]
+        pub fn set_governor(&mut self, governor: AccountId) -> Result<()> {
+            self.ensure_governor()?;
+            self.governor = governor;
+            self.env().emit_event(GovernorChanged { governor });
+            Ok(())
+        }
+
+        /// Takes native funds, counting them as received.
+        #[ink(message, payable)]
+        pub fn deposit(&mut self) {
+            let amount = self.env().transferred_value();
+            self.record_deposit(self.env().caller(), Asset::Native, amount);
+        }
+
+        /// How much of `asset` the treasury was sent through `deposit` or
+        /// `transfer_and_call`.
+        #[ink(message)]
+        pub fn received(&self, asset: Asset) -> Balance {
+            self.received.get(asset).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn is_token_accepted(&self, token: AccountId) -> bool {
+            self.accepted_tokens.contains(token)
+        }
+
+        /// Starts or stops taking deposits of `token` through
+        /// `transfer_and_call`.
+        #[ink(message)]
+        pub fn set_token_accepted(&mut self, token: AccountId, accepted: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if accepted {
+                self.accepted_tokens.insert(token, &());
+            } else {
+                self.accepted_tokens.remove(token);
+            }
+            self.env()
+                .emit_event(TokenAcceptanceChanged { token, accepted });
+            Ok(())
+        }
+
+        /// How much of `asset` approved spends paid out.
+        #[ink(message)]
+        pub fn spent(&self, asset: Asset) -> Balance {
+            self.spent.get(asset).unwrap_or(0)
+        }
+
+        /// Native funds the treasury can spend.
+        #[ink(message)]
+        pub fn free_balance(&self) -> Balance {
+            self.env().balance()
+        }
+
+        #[ink(message)]
+        pub fn reserved_balance(&self) -> Result<Balance> {
+            Ok(self.env().extension().reserved_balance()?)
+        }
+
+        /// Sets `amount` of the free balance aside.
+        #[ink(message)]
+        pub fn reserve(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.env().extension().reserve(amount)?;
+            self.env().emit_event(Reserved { amount });
+            Ok(())
+        }
+
+        /// Makes up to `amount` of the reserved balance spendable again and
+        /// returns how much was unreserved.
+        #[ink(message)]
+        pub fn unreserve(&mut self, amount: Balance) -> Result<Balance> {
+            self.ensure_owner()?;
+            let remaining = self.env().extension().unreserve(amount)?;
+            let amount = amount - remaining;
+            self.env().emit_event(Unreserved { amount });
+            Ok(amount)
+        }
+
+        /// Proposes to pay `amount` of `asset` to `to`. Nothing moves until the
+        /// governor approves.
+        #[ink(message)]
+        pub fn propose_spend(
+            &mut self,
+            asset: Asset,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<ProposalId> {
+            self.ensure_owner()?;
+            let id = self.next_proposal_id;
+            self.next_proposal_id += 1;
+            self.proposals
+                .insert(id, &SpendProposal { asset, to, amount });
+            self.env().emit_event(SpendProposed {
+                id,
+                asset,
+                to,
+                amount,
+            });
+            Ok(id)
+        }
+
+        #[ink(message)]
+        pub fn proposal(&self, id: ProposalId) -> Option<SpendProposal> {
+            self.proposals.get(id)
+        }
+
+        /// Pays out proposal `id`. Native funds come out of the free balance.
+        #[ink(message)]
+        pub fn approve_spend(&mut self, id: ProposalId) -> Result<()> {
+            self.ensure_governor()?;
+            let SpendProposal { asset, to, amount } =
+                self.proposals.take(id).ok_or(Error::ProposalNotFound)?;
+            match asset {
+                Asset::Native => self.env().extension().transfer(to, amount)?,
+                Asset::Token(token) => psp22::transfer(token, to, amount)?,
+            }
+            let spent = self.spent(asset) + amount;
+            self.spent.insert(asset, &spent);
+            self.env().emit_event(Spend {
+                id,
+                to,
+                asset,
+                amount,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn reject_spend(&mut self, id: ProposalId) -> Result<()> {
+            self.ensure_governor()?;
+            self.proposals.take(id).ok_or(Error::ProposalNotFound)?;
+            self.env().emit_event(SpendRejected { id });
+            Ok(())
+        }
+
+        fn record_deposit(&mut self, from: AccountId, asset: Asset, amount: Balance) {
+            let received = self.received(asset) + amount;
+            self.received.insert(asset, &received);
+            self.env().emit_event(Deposited {
+                from,
+                asset,
+                amount,
+            });
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        fn ensure_governor(&self) -> Result<()> {
+            if self.env().caller() != self.governor {
+                return Err(Error::NotGovernor);
+            }
+            Ok(())
+        }
+    }
+
+    impl psp22::TokenReceiver for Treasury {
+        /// Takes an accepted token, counting it as received from `from`, and
+        /// rejects any other caller.
+        #[ink(message)]
+        fn on_token_received(
+            &mut self,
+            _operator: AccountId,
+            from: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> bool {
+            let token = self.env().caller();
+            if !self.is_token_accepted(token) {
+                return false;
+            }
+            self.record_deposit(from, Asset::Token(token), value);
+            true
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use scale::{Decode, Encode};
+
+        /// Tracks a reserved balance and answers every call with `status`.
+        struct MockBalances {
+            func_id: u32,
+            reserved: Balance,
+            status: u32,
+        }
+
+        impl ink::env::test::ChainExtension for MockBalances {
+            fn func_id(&self) -> u32 {
+                self.func_id
+            }
+
+            fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+                // The off-chain engine hands over the input encoded once more.
+                let input = Vec::<u8>::decode(&mut &input[..]).unwrap();
+                let mut input = &input[..];
+                match self.func_id {
+                    0x1202 => {
+                        self.reserved += Balance::decode(&mut input).unwrap();
+                    }
+                    0x1203 => {
+                        let value = Balance::decode(&mut input).unwrap();
+                        let unreserved = value.min(self.reserved);
+                        self.reserved -= unreserved;
+                        (value - unreserved).encode_to(output);
+                    }
+                    _ => {}
+                }
+                self.status
+            }
+        }
+
+        fn register(func_id: u32, reserved: Balance, status: u32) {
+            ink::env::test::register_chain_extension(MockBalances {
+                func_id,
+                reserved,
+                status,
+            });
+        }
+
+        #[ink::test]
+        fn unreserve_returns_unreserved_amount() {
+            let mut treasury = Treasury::new();
+            register(0x1203, 30, 0);
+            assert_eq!(treasury.unreserve(50), Ok(30));
+        }
+
+        #[ink::test]
+        fn reserve_maps_errors() {
+            let mut treasury = Treasury::new();
+            register(0x1202, 0, 0);
+            assert_eq!(treasury.reserve(10), Ok(()));
+            register(0x1202, 0, 2);
+            assert_eq!(
+                treasury.reserve(10),
+                Err(Error::BalancesError(BalancesError::ExistentialDeposit))
+            );
+        }
+
+        #[ink::test]
+        fn only_owner_moves_funds() {
+            let mut treasury = Treasury::new();
+            treasury.owner = AccountId::from([0x2; 32]);
+            assert_eq!(treasury.reserve(10), Err(Error::NotOwner));
+            assert_eq!(treasury.unreserve(10), Err(Error::NotOwner));
+            assert_eq!(
+                treasury.propose_spend(Asset::Native, AccountId::from([0x3; 32]), 10),
+                Err(Error::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn approved_spend_is_paid() {
+            let mut treasury = Treasury::new();
+            let to = AccountId::from([0x3; 32]);
+            assert_eq!(treasury.propose_spend(Asset::Native, to, 10), Ok(0));
+            register(0x1201, 0, 0);
+            assert_eq!(treasury.approve_spend(0), Ok(()));
+            assert_eq!(treasury.spent(Asset::Native), 10);
+            assert_eq!(treasury.proposal(0), None);
+            assert_eq!(treasury.approve_spend(0), Err(Error::ProposalNotFound));
+        }
+
+        #[ink::test]
+        fn only_governor_decides_spends() {
+            let mut treasury = Treasury::new();
+            let to = AccountId::from([0x3; 32]);
+            treasury.propose_spend(Asset::Native, to, 10).unwrap();
+            treasury.governor = AccountId::from([0x2; 32]);
+            assert_eq!(treasury.approve_spend(0), Err(Error::NotGovernor));
+            assert_eq!(treasury.reject_spend(0), Err(Error::NotGovernor));
+            assert_eq!(treasury.set_governor(to), Err(Error::NotGovernor));
+            assert_eq!(treasury.spent(Asset::Native), 0);
+        }
+
+        #[ink::test]
+        fn rejected_spend_is_dropped() {
+            let mut treasury = Treasury::new();
+            let to = AccountId::from([0x3; 32]);
+            treasury.propose_spend(Asset::Native, to, 10).unwrap();
+            assert_eq!(treasury.reject_spend(0), Ok(()));
+            assert_eq!(treasury.proposal(0), None);
+        }
+
+        #[ink::test]
+        fn token_deposits_are_counted_per_token() {
+            use psp22::TokenReceiver;
+
+            let mut treasury = Treasury::new();
+            let from = AccountId::from([0x3; 32]);
+            let token = ink::env::caller::<ink::env::DefaultEnvironment>();
+            assert_eq!(treasury.set_token_accepted(token, true), Ok(()));
+            assert!(treasury.on_token_received(from, from, 25, Vec::new()));
+            assert_eq!(treasury.received(Asset::Token(token)), 25);
+            assert_eq!(treasury.received(Asset::Native), 0);
+        }
+
+        #[ink::test]
+        fn unaccepted_tokens_are_rejected() {
+            use psp22::TokenReceiver;
+
+            let mut treasury = Treasury::new();
+            let from = AccountId::from([0x3; 32]);
+            let token = ink::env::caller::<ink::env::DefaultEnvironment>();
+            assert!(!treasury.on_token_received(from, from, 25, Vec::new()));
+            treasury.set_token_accepted(token, true).unwrap();
+            treasury.set_token_accepted(token, false).unwrap();
+            assert!(!treasury.on_token_received(from, from, 25, Vec::new()));
+            assert_eq!(treasury.received(Asset::Token(token)), 0);
+            treasury.owner = from;
+            assert_eq!(
+                treasury.set_token_accepted(token, true),
+                Err(Error::NotOwner)
+            );
+        }
+    }
+}
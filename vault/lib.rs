@@ -0,0 +1,564 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// A vault in the style of ERC-4626: holders of an underlying PSP22 token
+/// deposit it for shares, themselves a PSP22 token, and redeem the shares for
+/// their part of whatever the vault holds by then.
+///
+/// `deposit` and `mint` pull the underlying with `transfer_from`, so callers
+/// have to approve the vault first. `withdraw` and `redeem` can burn someone
+/// else's shares within the share allowance they gave the caller.
+///
+/// Conversions round in favour of the vault: a caller never gets more shares
+/// or assets than they paid for. The vault also counts `VIRTUAL_SHARES` shares
+/// and one asset that nobody owns, so that the first depositor can't inflate
+/// the share price by sending tokens to the vault directly and have the next
+/// deposit round down to nothing.
+#[ink::contract]
+mod vault {
+    use ink::prelude::{format, vec::Vec};
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+
+    /// Shares counted on top of the supply. The more there are, the more a
+    /// donation has to be to round a deposit down by one asset.
+    pub const VIRTUAL_SHARES: Balance = 1_000;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Rounding {
+        Down,
+        Up,
+    }
+
+    #[ink(storage)]
+    pub struct Vault {
+        asset: AccountId,
+        total_supply: Balance,
+        balances: Mapping<AccountId, Balance>,
+        allowances: Mapping<(AccountId, AccountId), Balance>,
+    }
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        owner: AccountId,
+        assets: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        owner: AccountId,
+        assets: Balance,
+        shares: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        ZeroAmount,
+        /// The deposit is worth less than one share.
+        ZeroShares,
+        InsufficientBalance,
+        InsufficientAllowance,
+        Overflow,
+        UnderlyingError(PSP22Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::UnderlyingError(error)
+        }
+    }
+
+    impl From<Error> for PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::InsufficientBalance => PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance => PSP22Error::InsufficientAllowance,
+                error => PSP22Error::Custom(format!("{:?}", error)),
+            }
+        }
+    }
+
+    impl Vault {
+        #[ink(constructor)]
+        pub fn new(asset: AccountId) -> Self {
+            Self {
+                asset,
+                total_supply: 0,
+                balances: Mapping::default(),
+                allowances: Mapping::default(),
+            }
+        }
+
+        /// PSP22::total_supply
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        /// PSP22::balance_of
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or(0)
+        }
+
+        /// PSP22::allowance
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// PSP22::transfer
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let from = self.env().caller();
+            Ok(self.transfer_helper(from, to, value)?)
+        }
+
+        /// PSP22::transfer_from
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            self.spend_allowance(from, self.env().caller(), value)?;
+            Ok(self.transfer_helper(from, to, value)?)
+        }
+
+        /// PSP22::approve
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            ink::codegen::EmitEvent::<Vault>::emit_event(
+                self.env(),
+                Approval {
+                    owner,
+                    spender,
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        /// The underlying token.
+        #[ink(message)]
+        pub fn asset(&self) -> AccountId {
+            self.asset
+        }
+
+        /// How much of the underlying the vault manages.
+        #[ink(message)]
+        pub fn total_assets(&self) -> Result<Balance> {
+            Ok(psp22::balance_of(self.asset, self.env().account_id())?)
+        }
+
+        /// Shares `assets` are worth now, rounded down.
+        #[ink(message)]
+        pub fn convert_to_shares(&self, assets: Balance) -> Result<Balance> {
+            self.to_shares(assets, Rounding::Down)
+        }
+
+        /// Assets `shares` are worth now, rounded down.
+        #[ink(message)]
+        pub fn convert_to_assets(&self, shares: Balance) -> Result<Balance> {
+            self.to_assets(shares, Rounding::Down)
+        }
+
+        /// Shares `deposit` would mint for `assets`.
+        #[ink(message)]
+        pub fn preview_deposit(&self, assets: Balance) -> Result<Balance> {
+            self.to_shares(assets, Rounding::Down)
+        }
+
+        /// Assets `mint` would take for `shares`.
+        #[ink(message)]
+        pub fn preview_mint(&self, shares: Balance) -> Result<Balance> {
+            self.to_assets(shares, Rounding::Up)
+        }
+
+        /// Shares `withdraw` would burn for `assets`.
+        #[ink(message)]
+        pub fn preview_withdraw(&self, assets: Balance) -> Result<Balance> {
+            self.to_shares(assets, Rounding::Up)
+        }
+
+        /// Assets `redeem` would pay for `shares`.
+        #[ink(message)]
+        pub fn preview_redeem(&self, shares: Balance) -> Result<Balance> {
+            self.to_assets(shares, Rounding::Down)
+        }
+
+        /// Takes `assets` from the caller and mints the shares they are worth
+        /// to `receiver`. Returns the shares minted.
+        #[ink(message)]
+        pub fn deposit(&mut self, assets: Balance, receiver: AccountId) -> Result<Balance> {
+            if assets == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let shares = self.preview_deposit(assets)?;
+            if shares == 0 {
+                return Err(Error::ZeroShares);
+            }
+            self.deposit_helper(receiver, assets, shares)?;
+            Ok(shares)
+        }
+
+        /// Mints `shares` to `receiver` for the assets they are worth, taken
+        /// from the caller. Returns the assets taken.
+        #[ink(message)]
+        pub fn mint(&mut self, shares: Balance, receiver: AccountId) -> Result<Balance> {
+            if shares == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let assets = self.preview_mint(shares)?;
+            self.deposit_helper(receiver, assets, shares)?;
+            Ok(assets)
+        }
+
+        /// Burns the shares `assets` are worth from `owner` and sends `assets`
+        /// to `receiver`. Returns the shares burnt.
+        #[ink(message)]
+        pub fn withdraw(
+            &mut self,
+            assets: Balance,
+            receiver: AccountId,
+            owner: AccountId,
+        ) -> Result<Balance> {
+            if assets == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let shares = self.preview_withdraw(assets)?;
+            self.withdraw_helper(receiver, owner, assets, shares)?;
+            Ok(shares)
+        }
+
+        /// Burns `shares` from `owner` and sends the assets they are worth to
+        /// `receiver`. Returns the assets sent.
+        #[ink(message)]
+        pub fn redeem(
+            &mut self,
+            shares: Balance,
+            receiver: AccountId,
+            owner: AccountId,
+        ) -> Result<Balance> {
+            if shares == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let caller = self.env().caller();
+            if caller != owner && self.allowance(owner, caller) < shares {
+                return Err(Error::InsufficientAllowance);
+            }
+            let assets = self.preview_redeem(shares)?;
+            self.withdraw_helper(receiver, owner, assets, shares)?;
+            Ok(assets)
+        }
+
+        fn to_shares(&self, assets: Balance, rounding: Rounding) -> Result<Balance> {
+            to_shares(assets, self.total_assets()?, self.total_supply, rounding)
+        }
+
+        fn to_assets(&self, shares: Balance, rounding: Rounding) -> Result<Balance> {
+            to_assets(shares, self.total_assets()?, self.total_supply, rounding)
+        }
+
+        fn deposit_helper(
+            &mut self,
+            receiver: AccountId,
+            assets: Balance,
+            shares: Balance,
+        ) -> Result<()> {
+            let sender = self.env().caller();
+            psp22::transfer_from(self.asset, sender, self.env().account_id(), assets)?;
+            self.mint_shares(receiver, shares)?;
+            ink::codegen::EmitEvent::<Vault>::emit_event(
+                self.env(),
+                Deposit {
+                    sender,
+                    owner: receiver,
+                    assets,
+                    shares,
+                },
+            );
+            self.after_deposit(assets);
+            Ok(())
+        }
+
+        fn withdraw_helper(
+            &mut self,
+            receiver: AccountId,
+            owner: AccountId,
+            assets: Balance,
+            shares: Balance,
+        ) -> Result<()> {
+            let sender = self.env().caller();
+            if sender != owner {
+                self.spend_allowance(owner, sender, shares)?;
+            }
+            self.burn_shares(owner, shares)?;
+            self.before_withdraw(assets);
+            psp22::transfer(self.asset, receiver, assets)?;
+            ink::codegen::EmitEvent::<Vault>::emit_event(
+                self.env(),
+                Withdraw {
+                    sender,
+                    receiver,
+                    owner,
+                    assets,
+                    shares,
+                },
+            );
+            Ok(())
+        }
+
+        /// Where a strategy would put newly deposited assets to work. Assets
+        /// it moves elsewhere would have to be added to `total_assets`.
+        fn after_deposit(&mut self, _assets: Balance) {}
+
+        /// Where a strategy would bring `assets` back into the vault before
+        /// they are paid out.
+        fn before_withdraw(&mut self, _assets: Balance) {}
+
+        fn spend_allowance(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let allowance = self.allowance(owner, spender);
+            if allowance < value {
+                return Err(Error::InsufficientAllowance);
+            }
+            self.allowances
+                .insert((owner, spender), &(allowance - value));
+            Ok(())
+        }
+
+        fn transfer_helper(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.burn(from, value)?;
+            self.credit(to, value)?;
+            ink::codegen::EmitEvent::<Vault>::emit_event(
+                self.env(),
+                Transfer {
+                    from: Some(from),
+                    to: Some(to),
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        fn mint_shares(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.credit(to, value)?;
+            ink::codegen::EmitEvent::<Vault>::emit_event(
+                self.env(),
+                Transfer {
+                    from: None,
+                    to: Some(to),
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        fn burn_shares(&mut self, from: AccountId, value: Balance) -> Result<()> {
+            self.burn(from, value)?;
+            ink::codegen::EmitEvent::<Vault>::emit_event(
+                self.env(),
+                Transfer {
+                    from: Some(from),
+                    to: None,
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        fn credit(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            let balance = self
+                .balance_of(to)
+                .checked_add(value)
+                .ok_or(Error::Overflow)?;
+            self.balances.insert(to, &balance);
+            self.total_supply += value;
+            Ok(())
+        }
+
+        fn burn(&mut self, from: AccountId, value: Balance) -> Result<()> {
+            let balance = self.balance_of(from);
+            if balance < value {
+                return Err(Error::InsufficientBalance);
+            }
+            self.balances.insert(from, &(balance - value));
+            self.total_supply -= value;
+            Ok(())
+        }
+    }
+
+    /// Shares `assets` are worth in a vault holding `total_assets` with
+    /// `total_supply` shares, counting the virtual shares and asset.
+    fn to_shares(
+        assets: Balance,
+        total_assets: Balance,
+        total_supply: Balance,
+        rounding: Rounding,
+    ) -> Result<Balance> {
+        mul_div(
+            assets,
+            total_supply
+                .checked_add(VIRTUAL_SHARES)
+                .ok_or(Error::Overflow)?,
+            total_assets.checked_add(1).ok_or(Error::Overflow)?,
+            rounding,
+        )
+    }
+
+    /// Assets `shares` are worth in a vault holding `total_assets` with
+    /// `total_supply` shares, counting the virtual shares and asset.
+    fn to_assets(
+        shares: Balance,
+        total_assets: Balance,
+        total_supply: Balance,
+        rounding: Rounding,
+    ) -> Result<Balance> {
+        mul_div(
+            shares,
+            total_assets.checked_add(1).ok_or(Error::Overflow)?,
+            total_supply
+                .checked_add(VIRTUAL_SHARES)
+                .ok_or(Error::Overflow)?,
+            rounding,
+        )
+    }
+
+    fn mul_div(a: Balance, b: Balance, d: Balance, rounding: Rounding) -> Result<Balance> {
+        let product = a.checked_mul(b).ok_or(Error::Overflow)?;
+        let quotient = product / d;
+        if rounding == Rounding::Up && product % d != 0 {
+            return Ok(quotient + 1);
+        }
+        Ok(quotient)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn vault() -> Vault {
+            Vault::new(AccountId::from([0x9; 32]))
+        }
+
+        #[ink::test]
+        fn conversions_round_in_favour_of_the_vault() {
+            // 2 assets backing 1000 shares: 1 asset is worth 666.67 shares.
+            assert_eq!(to_shares(1, 2, 1000, Rounding::Down), Ok(666));
+            assert_eq!(to_shares(1, 2, 1000, Rounding::Up), Ok(667));
+            assert_eq!(to_assets(1000, 2, 1000, Rounding::Down), Ok(1));
+            assert_eq!(to_assets(1000, 2, 1000, Rounding::Up), Ok(2));
+            // An empty vault mints the virtual shares' worth per asset.
+            assert_eq!(to_shares(5, 0, 0, Rounding::Down), Ok(5 * VIRTUAL_SHARES));
+            assert_eq!(
+                to_shares(Balance::MAX, 0, 0, Rounding::Down),
+                Err(Error::Overflow)
+            );
+        }
+
+        #[ink::test]
+        fn donations_do_not_pay_off() {
+            // The attacker deposits 1 asset and then sends 10 000 straight to
+            // the vault to inflate the share price.
+            let attacker_shares = to_shares(1, 0, 0, Rounding::Down).unwrap();
+            assert_eq!(attacker_shares, 1000);
+            let victim_shares = to_shares(10_000, 10_001, 1000, Rounding::Down).unwrap();
+            assert_eq!(victim_shares, 1999);
+            // The victim loses to rounding, the attacker half the donation.
+            assert_eq!(
+                to_assets(victim_shares, 20_001, 2999, Rounding::Down),
+                Ok(9998)
+            );
+            assert_eq!(
+                to_assets(attacker_shares, 20_001, 2999, Rounding::Down),
+                Ok(5001)
+            );
+        }
+
+        #[ink::test]
+        fn shares_are_transferable() {
+            let accounts = accounts();
+            let mut vault = vault();
+            vault.credit(accounts.alice, 100).unwrap();
+            assert_eq!(vault.transfer(accounts.bob, 60, Vec::new()), Ok(()));
+            assert_eq!(vault.balance_of(accounts.alice), 40);
+            assert_eq!(vault.balance_of(accounts.bob), 60);
+            assert_eq!(vault.total_supply(), 100);
+            assert_eq!(
+                vault.transfer(accounts.bob, 41, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn redeeming_for_others_needs_allowance() {
+            let accounts = accounts();
+            let mut vault = vault();
+            vault.credit(accounts.bob, 100).unwrap();
+            assert_eq!(
+                vault.redeem(0, accounts.alice, accounts.bob),
+                Err(Error::ZeroAmount)
+            );
+            assert_eq!(
+                vault.redeem(50, accounts.alice, accounts.bob),
+                Err(Error::InsufficientAllowance)
+            );
+            assert_eq!(vault.deposit(0, accounts.alice), Err(Error::ZeroAmount));
+        }
+    }
+}
@@ -0,0 +1,352 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Vote-escrow for the course `Erc20`: the longer tokens are locked, the more
+/// voting power they give.
+///
+/// A lock of `amount` until `end` gives `amount * (end - now) / MAX_LOCK_TIME`
+/// voting power, so it starts at up to `amount` for a four year lock and
+/// decays linearly to zero at `end`. Lock ends are rounded down to whole weeks
+/// so that `total_voting_power` only has to apply expiries week by week:
+/// between them the total is `(sum of amount * end - now * sum of amount) /
+/// MAX_LOCK_TIME` over the locks still running.
+#[ink::contract]
+mod ve_token {
+    use erc20::Erc20Ref;
+    use ink::storage::Mapping;
+
+    pub const WEEK: Timestamp = 7 * 24 * 60 * 60 * 1000;
+    pub const MAX_LOCK_TIME: Timestamp = 4 * 52 * WEEK;
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Lock {
+        pub amount: Balance,
+        pub end: Timestamp,
+    }
+
+    /// Sums over the locks still running, for the total voting power.
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Totals {
+        pub amount: Balance,
+        /// Sum of `amount * end`.
+        pub weighted_end: Balance,
+        /// Expiries up to here have been taken out.
+        pub checkpoint: Timestamp,
+    }
+
+    #[ink(storage)]
+    pub struct VeToken {
+        token: AccountId,
+        locks: Mapping<AccountId, Lock>,
+        totals: Totals,
+        /// Amount whose locks end at a week boundary.
+        expiries: Mapping<Timestamp, Balance>,
+    }
+
+    #[ink(event)]
+    pub struct Locked {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        end: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        ZeroAmount,
+        LockExists,
+        NoLock,
+        LockExpired,
+        LockNotExpired,
+        /// The unlock time is not after the current end, or too far out.
+        InvalidUnlockTime,
+        Overflow,
+        TokenError(erc20::Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<erc20::Error> for Error {
+        fn from(error: erc20::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl VeToken {
+        #[ink(constructor)]
+        pub fn new(token: AccountId) -> Self {
+            Self {
+                token,
+                locks: Mapping::default(),
+                totals: Totals {
+                    checkpoint: Self::env().block_timestamp(),
+                    ..Totals::default()
+                },
+                expiries: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn lock_of(&self, account: AccountId) -> Option<Lock> {
+            self.locks.get(account)
+        }
+
+        #[ink(message)]
+        pub fn voting_power_of(&self, account: AccountId) -> Balance {
+            let now = self.env().block_timestamp();
+            match self.locks.get(account) {
+                Some(lock) if lock.end > now => {
+                    lock.amount * Balance::from(lock.end - now) / Balance::from(MAX_LOCK_TIME)
+                }
+                _ => 0,
+            }
+        }
+
+        /// Sum of the voting power of all accounts, up to rounding.
+        #[ink(message)]
+        pub fn total_voting_power(&self) -> Balance {
+            let now = self.env().block_timestamp();
+            let totals = self.totals_at(now);
+            (totals.weighted_end - Balance::from(now) * totals.amount)
+                / Balance::from(MAX_LOCK_TIME)
+        }
+
+        /// Locks `amount` tokens of the caller, who must have approved this
+        /// contract for them, until `unlock_time` rounded down to a week.
+        #[ink(message)]
+        pub fn create_lock(&mut self, amount: Balance, unlock_time: Timestamp) -> Result<()> {
+            let account = self.env().caller();
+            if self.locks.contains(account) {
+                return Err(Error::LockExists);
+            }
+            let lock = Lock {
+                amount: 0,
+                end: self.valid_end(unlock_time, 0)?,
+            };
+            self.pull(account, amount)?;
+            self.add_lock(account, lock, amount)
+        }
+
+        /// Adds `amount` tokens to the caller's running lock.
+        #[ink(message)]
+        pub fn increase_amount(&mut self, amount: Balance) -> Result<()> {
+            let account = self.env().caller();
+            let lock = self.running_lock(account)?;
+            self.pull(account, amount)?;
+            self.remove_lock(&lock);
+            self.add_lock(account, lock, amount)
+        }
+
+        /// Moves the end of the caller's running lock out to `unlock_time`
+        /// rounded down to a week.
+        #[ink(message)]
+        pub fn increase_unlock_time(&mut self, unlock_time: Timestamp) -> Result<()> {
+            let account = self.env().caller();
+            let lock = self.running_lock(account)?;
+            let end = self.valid_end(unlock_time, lock.end)?;
+            self.remove_lock(&lock);
+            self.add_lock(account, Lock { end, ..lock }, 0)
+        }
+
+        /// Returns the tokens of the caller's expired lock.
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> Result<()> {
+            let account = self.env().caller();
+            let lock = self.lock_of(account).ok_or(Error::NoLock)?;
+            if lock.end > self.env().block_timestamp() {
+                return Err(Error::LockNotExpired);
+            }
+            self.locks.remove(account);
+            let mut token: Erc20Ref = ink::env::call::FromAccountId::from_account_id(self.token);
+            token.transfer(account, lock.amount)?;
+            ink::codegen::EmitEvent::<VeToken>::emit_event(
+                self.env(),
+                Withdrawn {
+                    account,
+                    amount: lock.amount,
+                },
+            );
+            Ok(())
+        }
+
+        fn running_lock(&self, account: AccountId) -> Result<Lock> {
+            let lock = self.lock_of(account).ok_or(Error::NoLock)?;
+            if lock.end <= self.env().block_timestamp() {
+                return Err(Error::LockExpired);
+            }
+            Ok(lock)
+        }
+
+        /// `unlock_time` rounded down to a week, if that is after `current_end`
+        /// and now and within `MAX_LOCK_TIME` from now.
+        fn valid_end(&self, unlock_time: Timestamp, current_end: Timestamp) -> Result<Timestamp> {
+            let now = self.env().block_timestamp();
+            let end = unlock_time / WEEK * WEEK;
+            if end <= now || end <= current_end || end > now + MAX_LOCK_TIME {
+                return Err(Error::InvalidUnlockTime);
+            }
+            Ok(end)
+        }
+
+        fn pull(&mut self, account: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            let mut token: Erc20Ref = ink::env::call::FromAccountId::from_account_id(self.token);
+            token.transfer_from(account, self.env().account_id(), amount)?;
+            Ok(())
+        }
+
+        /// Totals with the expiries up to `now` taken out.
+        fn totals_at(&self, now: Timestamp) -> Totals {
+            let mut totals = self.totals.clone();
+            let mut week = totals.checkpoint / WEEK * WEEK + WEEK;
+            while week <= now {
+                if let Some(amount) = self.expiries.get(week) {
+                    totals.amount -= amount;
+                    totals.weighted_end -= amount * Balance::from(week);
+                }
+                week += WEEK;
+            }
+            totals.checkpoint = now;
+            totals
+        }
+
+        /// Takes a running lock out of the totals.
+        fn remove_lock(&mut self, lock: &Lock) {
+            self.totals = self.totals_at(self.env().block_timestamp());
+            self.totals.amount -= lock.amount;
+            self.totals.weighted_end -= lock.amount * Balance::from(lock.end);
+            let expiring = self.expiries.get(lock.end).unwrap_or(0);
+            self.expiries.insert(lock.end, &(expiring - lock.amount));
+        }
+
+        /// Stores `lock` with `amount` added and counts it in the totals.
+        fn add_lock(&mut self, account: AccountId, lock: Lock, amount: Balance) -> Result<()> {
+            let lock = Lock {
+                amount: lock.amount.checked_add(amount).ok_or(Error::Overflow)?,
+                ..lock
+            };
+            let mut totals = self.totals_at(self.env().block_timestamp());
+            totals.amount = totals
+                .amount
+                .checked_add(lock.amount)
+                .ok_or(Error::Overflow)?;
+            totals.weighted_end = lock
+                .amount
+                .checked_mul(Balance::from(lock.end))
+                .and_then(|weighted| totals.weighted_end.checked_add(weighted))
+                .ok_or(Error::Overflow)?;
+            self.totals = totals;
+            let expiring = self.expiries.get(lock.end).unwrap_or(0);
+            self.expiries.insert(lock.end, &(expiring + lock.amount));
+            self.locks.insert(account, &lock.clone());
+            ink::codegen::EmitEvent::<VeToken>::emit_event(
+                self.env(),
+                Locked {
+                    account,
+                    amount: lock.amount,
+                    end: lock.end,
+                },
+            );
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn set_block_timestamp(timestamp: Timestamp) {
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+        }
+
+        fn lock(ve: &mut VeToken, account: AccountId, amount: Balance, end: Timestamp) {
+            let lock = Lock {
+                amount: 0,
+                end: ve.valid_end(end, 0).unwrap(),
+            };
+            ve.add_lock(account, lock, amount).unwrap();
+        }
+
+        #[ink::test]
+        fn voting_power_decays_linearly() {
+            set_block_timestamp(0);
+            let mut ve = VeToken::new(AccountId::from([0x9; 32]));
+            let alice = AccountId::from([0x1; 32]);
+            lock(&mut ve, alice, 1_000, MAX_LOCK_TIME);
+            assert_eq!(ve.voting_power_of(alice), 1_000);
+            set_block_timestamp(MAX_LOCK_TIME / 4);
+            assert_eq!(ve.voting_power_of(alice), 750);
+            set_block_timestamp(MAX_LOCK_TIME);
+            assert_eq!(ve.voting_power_of(alice), 0);
+        }
+
+        #[ink::test]
+        fn total_follows_expiries() {
+            set_block_timestamp(0);
+            let mut ve = VeToken::new(AccountId::from([0x9; 32]));
+            let alice = AccountId::from([0x1; 32]);
+            let bob = AccountId::from([0x2; 32]);
+            lock(&mut ve, alice, 1_000, MAX_LOCK_TIME);
+            lock(&mut ve, bob, 1_000, MAX_LOCK_TIME / 2);
+            assert_eq!(ve.total_voting_power(), 1_500);
+            set_block_timestamp(MAX_LOCK_TIME / 2);
+            assert_eq!(ve.total_voting_power(), 500);
+            set_block_timestamp(MAX_LOCK_TIME / 2 + WEEK);
+            assert_eq!(
+                ve.total_voting_power(),
+                ve.voting_power_of(alice) + ve.voting_power_of(bob)
+            );
+        }
+
+        #[ink::test]
+        fn increase_unlock_time_moves_expiry() {
+            set_block_timestamp(0);
+            let mut ve = VeToken::new(AccountId::from([0x9; 32]));
+            let alice = AccountId::from([0x1; 32]);
+            lock(&mut ve, alice, 1_000, MAX_LOCK_TIME / 2);
+            assert_eq!(
+                ve.increase_unlock_time(MAX_LOCK_TIME / 4),
+                Err(Error::InvalidUnlockTime)
+            );
+            assert_eq!(
+                ve.increase_unlock_time(MAX_LOCK_TIME + WEEK),
+                Err(Error::InvalidUnlockTime)
+            );
+            assert_eq!(ve.increase_unlock_time(MAX_LOCK_TIME), Ok(()));
+            assert_eq!(ve.voting_power_of(alice), 1_000);
+            assert_eq!(ve.total_voting_power(), 1_000);
+            set_block_timestamp(MAX_LOCK_TIME / 2 + WEEK);
+            assert_eq!(ve.total_voting_power(), ve.voting_power_of(alice));
+        }
+
+        #[ink::test]
+        fn lock_rules_are_checked() {
+            set_block_timestamp(0);
+            let mut ve = VeToken::new(AccountId::from([0x9; 32]));
+            let alice = AccountId::from([0x1; 32]);
+            assert_eq!(ve.increase_amount(1), Err(Error::NoLock));
+            assert_eq!(ve.create_lock(1, WEEK - 1), Err(Error::InvalidUnlockTime));
+            lock(&mut ve, alice, 1_000, WEEK);
+            assert_eq!(ve.create_lock(1, 2 * WEEK), Err(Error::LockExists));
+            assert_eq!(ve.withdraw(), Err(Error::LockNotExpired));
+            set_block_timestamp(WEEK);
+            assert_eq!(ve.increase_amount(1), Err(Error::LockExpired));
+        }
+    }
+}
@@ -0,0 +1,383 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Releases any PSP22 token it holds to a single beneficiary on a linear
+/// schedule. Nothing vests before the cliff; everything has vested at
+/// `start + duration`.
+///
+/// The grantor, who instantiated the wallet, can revoke the grant of a token,
+/// taking back what has not vested yet while the beneficiary keeps what has.
+/// If the beneficiary loses its key the grantor can move the grant to a new
+/// account. What has vested by then of each tracked token is released to the
+/// previous beneficiary first, so only the unvested remainder moves. Tokens
+/// are tracked once released, revoked or passed to `track_token`, which the
+/// beneficiary should do for every token it is granted.
+#[ink::contract]
+mod vesting_wallet {
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+
+    #[ink(storage)]
+    pub struct VestingWallet {
+        grantor: AccountId,
+        beneficiary: AccountId,
+        start: Timestamp,
+        cliff: Timestamp,
+        duration: Timestamp,
+        released: Mapping<AccountId, Balance>,
+        /// What had vested of a token when its grant was revoked.
+        revoked: Mapping<AccountId, Balance>,
+        /// Tokens settled with the beneficiary before a reassignment.
+        tokens: Vec<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct TokenReleased {
+        #[ink(topic)]
+        token: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct GrantRevoked {
+        #[ink(topic)]
+        token: AccountId,
+        vested: Balance,
+        refunded: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BeneficiaryReassigned {
+        #[ink(topic)]
+        previous_beneficiary: AccountId,
+        #[ink(topic)]
+        new_beneficiary: AccountId,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        NotGrantor,
+        NothingToRelease,
+        AlreadyRevoked,
+        /// Everything has vested, so there is nothing to revoke.
+        FullyVested,
+        TokenError(PSP22Error),
+        /// Only the grantor and the beneficiary may track tokens.
+        NotParty,
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl VestingWallet {
+        /// `cliff_duration` and `duration` are both counted from `start`.
+        #[ink(constructor)]
+        pub fn new(
+            beneficiary: AccountId,
+            start: Timestamp,
+            cliff_duration: Timestamp,
+            duration: Timestamp,
+        ) -> Self {
+            assert!(cliff_duration <= duration, "cliff exceeds duration");
+            assert!(start.checked_add(duration).is_some(), "schedule overflows");
+            Self {
+                grantor: Self::env().caller(),
+                beneficiary,
+                start,
+                cliff: start
+                    .checked_add(cliff_duration)
+                    .expect("schedule overflows"),
+                duration,
+                released: Mapping::default(),
+                revoked: Mapping::default(),
+                tokens: Vec::new(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn grantor(&self) -> AccountId {
+            self.grantor
+        }
+
+        #[ink(message)]
+        pub fn beneficiary(&self) -> AccountId {
+            self.beneficiary
+        }
+
+        #[ink(message)]
+        pub fn start(&self) -> Timestamp {
+            self.start
+        }
+
+        #[ink(message)]
+        pub fn cliff(&self) -> Timestamp {
+            self.cliff
+        }
+
+        #[ink(message)]
+        pub fn duration(&self) -> Timestamp {
+            self.duration
+        }
+
+        #[ink(message)]
+        pub fn released(&self, token: AccountId) -> Balance {
+            self.released.get(token).unwrap_or(0)
+        }
+
+        /// Amount of `token` that has vested but not yet been released.
+        #[ink(message)]
+        pub fn releasable(&self, token: AccountId) -> Result<Balance> {
+            let balance = psp22::balance_of(token, self.env().account_id())?;
+            Ok(self.releasable_amount(token, balance, self.env().block_timestamp()))
+        }
+
+        #[ink(message)]
+        pub fn tokens(&self) -> Vec<AccountId> {
+            self.tokens.clone()
+        }
+
+        /// Has the vested part of `token` released to the beneficiary before
+        /// any reassignment.
+        #[ink(message)]
+        pub fn track_token(&mut self, token: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            if caller != self.grantor && caller != self.beneficiary {
+                return Err(Error::NotParty);
+            }
+            self.track(token);
+            Ok(())
+        }
+
+        /// What had vested of `token` when its grant was revoked, if it was.
+        #[ink(message)]
+        pub fn revoked(&self, token: AccountId) -> Option<Balance> {
+            self.revoked.get(token)
+        }
+
+        /// Sends everything releasable of `token` to the beneficiary. Anyone may call it.
+        #[ink(message)]
+        pub fn release(&mut self, token: AccountId) -> Result<()> {
+            if self.release_vested(token)? == 0 {
+                return Err(Error::NothingToRelease);
+            }
+            Ok(())
+        }
+
+        /// Ends the grant of `token`: what has vested stays releasable to the
+        /// beneficiary, the rest goes back to the grantor.
+        #[ink(message)]
+        pub fn revoke(&mut self, token: AccountId) -> Result<()> {
+            self.ensure_grantor()?;
+            if self.revoked.contains(token) {
+                return Err(Error::AlreadyRevoked);
+            }
+            let balance = psp22::balance_of(token, self.env().account_id())?;
+            let total_allocation = balance + self.released(token);
+            let vested = self.vested_amount(total_allocation, self.env().block_timestamp());
+            if vested == total_allocation {
+                return Err(Error::FullyVested);
+            }
+            let refunded = total_allocation - vested;
+            self.revoked.insert(token, &vested);
+            self.track(token);
+            psp22::transfer(token, self.grantor, refunded)?;
+            self.env().emit_event(GrantRevoked {
+                token,
+                vested,
+                refunded,
+            });
+            Ok(())
+        }
+
+        /// Moves the grant to `new_beneficiary`, e.g. after the beneficiary
+        /// lost its key. What has vested of the tracked tokens is released to
+        /// the previous beneficiary first.
+        #[ink(message)]
+        pub fn reassign_beneficiary(&mut self, new_beneficiary: AccountId) -> Result<()> {
+            self.ensure_grantor()?;
+            for token in self.tokens.clone() {
+                self.release_vested(token)?;
+            }
+            let previous_beneficiary = self.beneficiary;
+            self.beneficiary = new_beneficiary;
+            self.env().emit_event(BeneficiaryReassigned {
+                previous_beneficiary,
+                new_beneficiary,
+            });
+            Ok(())
+        }
+
+        /// Sends what is releasable of `token` to the beneficiary, if
+        /// anything, and returns the amount.
+        fn release_vested(&mut self, token: AccountId) -> Result<Balance> {
+            self.track(token);
+            let amount = self.releasable(token)?;
+            if amount == 0 {
+                return Ok(0);
+            }
+            self.released
+                .insert(token, &(self.released(token) + amount));
+            psp22::transfer(token, self.beneficiary, amount)?;
+            self.env().emit_event(TokenReleased { token, amount });
+            Ok(amount)
+        }
+
+        fn track(&mut self, token: AccountId) {
+            if !self.tokens.contains(&token) {
+                self.tokens.push(token);
+            }
+        }
+
+        fn ensure_grantor(&self) -> Result<()> {
+            if self.env().caller() != self.grantor {
+                return Err(Error::NotGrantor);
+            }
+            Ok(())
+        }
+
+        /// Releasable amount of `token` while the wallet holds `balance` of it.
+        /// A revoked grant vests no further.
+        fn releasable_amount(
+            &self,
+            token: AccountId,
+            balance: Balance,
+            timestamp: Timestamp,
+        ) -> Balance {
+            let released = self.released(token);
+            let vested = match self.revoked(token) {
+                Some(vested) => vested,
+                None => self.vested_amount(balance + released, timestamp),
+            };
+            vested.saturating_sub(released)
+        }
+
+        fn vested_amount(&self, total_allocation: Balance, timestamp: Timestamp) -> Balance {
+            if timestamp < self.cliff {
+                0
+            } else if timestamp >= self.start + self.duration {
+                total_allocation
+            } else {
+                let elapsed = (timestamp - self.start) as Balance;
+                total_allocation.saturating_mul(elapsed) / self.duration as Balance
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_works() {
+            let beneficiary = AccountId::from([0x2; 32]);
+            let wallet = VestingWallet::new(beneficiary, 1000, 100, 400);
+            assert_eq!(wallet.beneficiary(), beneficiary);
+            assert_eq!(wallet.start(), 1000);
+            assert_eq!(wallet.cliff(), 1100);
+            assert_eq!(wallet.duration(), 400);
+            assert_eq!(wallet.released(AccountId::from([0x9; 32])), 0);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "cliff exceeds duration")]
+        fn new_fails_for_cliff_after_end() {
+            VestingWallet::new(AccountId::from([0x2; 32]), 1000, 500, 400);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "schedule overflows")]
+        fn new_fails_for_schedule_overflow() {
+            VestingWallet::new(AccountId::from([0x2; 32]), Timestamp::MAX - 100, 200, 200);
+        }
+
+        #[ink::test]
+        fn nothing_vests_before_cliff() {
+            let wallet = VestingWallet::new(AccountId::from([0x2; 32]), 1000, 100, 400);
+            assert_eq!(wallet.vested_amount(1000, 0), 0);
+            assert_eq!(wallet.vested_amount(1000, 1099), 0);
+        }
+
+        #[ink::test]
+        fn vests_linearly_after_cliff() {
+            let wallet = VestingWallet::new(AccountId::from([0x2; 32]), 1000, 100, 400);
+            assert_eq!(wallet.vested_amount(1000, 1100), 250);
+            assert_eq!(wallet.vested_amount(1000, 1200), 500);
+            assert_eq!(wallet.vested_amount(1000, 1399), 997);
+        }
+
+        #[ink::test]
+        fn everything_vests_at_end() {
+            let wallet = VestingWallet::new(AccountId::from([0x2; 32]), 1000, 100, 400);
+            assert_eq!(wallet.vested_amount(1000, 1400), 1000);
+            assert_eq!(wallet.vested_amount(1000, Timestamp::MAX), 1000);
+        }
+
+        #[ink::test]
+        fn vests_from_start_without_cliff() {
+            let wallet = VestingWallet::new(AccountId::from([0x2; 32]), 1000, 0, 400);
+            assert_eq!(wallet.vested_amount(1000, 999), 0);
+            assert_eq!(wallet.vested_amount(1000, 1000), 0);
+            assert_eq!(wallet.vested_amount(1000, 1001), 2);
+        }
+
+        #[ink::test]
+        fn cliff_at_end_vests_at_once() {
+            let wallet = VestingWallet::new(AccountId::from([0x2; 32]), 1000, 400, 400);
+            assert_eq!(wallet.vested_amount(1000, 1399), 0);
+            assert_eq!(wallet.vested_amount(1000, 1400), 1000);
+        }
+
+        #[ink::test]
+        fn revoked_grant_stops_vesting() {
+            let token = AccountId::from([0x9; 32]);
+            let mut wallet = VestingWallet::new(AccountId::from([0x2; 32]), 1000, 100, 400);
+            assert_eq!(wallet.releasable_amount(token, 1000, 1099), 0);
+            assert_eq!(wallet.releasable_amount(token, 1000, 1100), 250);
+            wallet.revoked.insert(token, &500);
+            wallet.released.insert(token, &200);
+            assert_eq!(wallet.releasable_amount(token, 300, 1200), 300);
+            assert_eq!(wallet.releasable_amount(token, 300, 1400), 300);
+            assert_eq!(wallet.releasable_amount(token, 300, Timestamp::MAX), 300);
+            assert_eq!(wallet.revoke(token), Err(Error::AlreadyRevoked));
+        }
+
+        #[ink::test]
+        fn only_grantor_revokes_and_reassigns() {
+            let token = AccountId::from([0x9; 32]);
+            let new_beneficiary = AccountId::from([0x3; 32]);
+            let mut wallet = VestingWallet::new(AccountId::from([0x2; 32]), 1000, 100, 400);
+            assert_eq!(wallet.grantor(), AccountId::from([0x1; 32]));
+            assert_eq!(wallet.reassign_beneficiary(new_beneficiary), Ok(()));
+            assert_eq!(wallet.beneficiary(), new_beneficiary);
+            wallet.grantor = AccountId::from([0x4; 32]);
+            assert_eq!(wallet.revoke(token), Err(Error::NotGrantor));
+            assert_eq!(
+                wallet.reassign_beneficiary(AccountId::from([0x2; 32])),
+                Err(Error::NotGrantor)
+            );
+            assert_eq!(wallet.beneficiary(), new_beneficiary);
+        }
+
+        #[ink::test]
+        fn only_parties_track_tokens() {
+            let token = AccountId::from([0x9; 32]);
+            let beneficiary = AccountId::from([0x2; 32]);
+            let mut wallet = VestingWallet::new(beneficiary, 1000, 100, 400);
+            assert_eq!(wallet.track_token(token), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(beneficiary);
+            assert_eq!(wallet.track_token(token), Ok(()));
+            assert_eq!(wallet.tokens(), vec![token]);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(AccountId::from([0x3; 32]));
+            assert_eq!(
+                wallet.track_token(AccountId::from([0x8; 32])),
+                Err(Error::NotParty)
+            );
+            assert_eq!(wallet.tokens(), vec![token]);
+        }
+    }
+}
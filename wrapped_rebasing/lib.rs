@@ -0,0 +1,320 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Wraps a `RebasingToken` into a PSP22 token whose balances only change
+/// through transfers, for AMMs, lending markets and other integrations that
+/// can't handle balances moving on their own.
+///
+/// One wrapped token is one share of the rebasing token, so a rebase changes
+/// what a wrapped token unwraps to rather than how many a holder has. `wrap`
+/// pulls rebasing tokens with `transfer_from`, so callers have to approve the
+/// wrapper first.
+#[ink::contract]
+mod wrapped_rebasing {
+    use ink::prelude::{format, vec::Vec};
+    use ink::storage::Mapping;
+    use psp22::PSP22Error;
+    use rebasing_token::RebasingTokenRef;
+
+    #[ink(storage)]
+    pub struct WrappedRebasing {
+        underlying: AccountId,
+        total_supply: Balance,
+        balances: Mapping<AccountId, Balance>,
+        allowances: Mapping<(AccountId, AccountId), Balance>,
+    }
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        ZeroAmount,
+        InsufficientBalance,
+        InsufficientAllowance,
+        Overflow,
+        TokenError(rebasing_token::Error),
+        UnderlyingError(PSP22Error),
+    }
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl From<rebasing_token::Error> for Error {
+        fn from(error: rebasing_token::Error) -> Self {
+            Error::TokenError(error)
+        }
+    }
+
+    impl From<PSP22Error> for Error {
+        fn from(error: PSP22Error) -> Self {
+            Error::UnderlyingError(error)
+        }
+    }
+
+    impl From<Error> for PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::InsufficientBalance => PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance => PSP22Error::InsufficientAllowance,
+                error => PSP22Error::Custom(format!("{:?}", error)),
+            }
+        }
+    }
+
+    impl WrappedRebasing {
+        #[ink(constructor)]
+        pub fn new(underlying: AccountId) -> Self {
+            Self {
+                underlying,
+                total_supply: 0,
+                balances: Mapping::default(),
+                allowances: Mapping::default(),
+            }
+        }
+
+        /// PSP22::total_supply
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        /// PSP22::balance_of
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or(0)
+        }
+
+        /// PSP22::allowance
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// PSP22::transfer
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let from = self.env().caller();
+            Ok(self.transfer_helper(from, to, value)?)
+        }
+
+        /// PSP22::transfer_from
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let spender = self.env().caller();
+            let allowance = self.allowance(from, spender);
+            if allowance < value {
+                return Err(Error::InsufficientAllowance.into());
+            }
+            self.allowances
+                .insert((from, spender), &(allowance - value));
+            Ok(self.transfer_helper(from, to, value)?)
+        }
+
+        /// PSP22::approve
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            ink::codegen::EmitEvent::<WrappedRebasing>::emit_event(
+                self.env(),
+                Approval {
+                    owner,
+                    spender,
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn underlying(&self) -> AccountId {
+            self.underlying
+        }
+
+        /// What `wrapped` tokens unwrap to at the current share rate.
+        #[ink(message)]
+        pub fn wrapped_to_underlying(&self, wrapped: Balance) -> Balance {
+            self.underlying_contract().shares_to_balance(wrapped)
+        }
+
+        /// How many wrapped tokens `amount` rebasing tokens wrap to now.
+        #[ink(message)]
+        pub fn underlying_to_wrapped(&self, amount: Balance) -> Balance {
+            self.underlying_contract().balance_to_shares(amount)
+        }
+
+        /// Takes `amount` of the caller's rebasing tokens and mints the shares
+        /// they are worth as wrapped tokens. Returns the amount minted.
+        #[ink(message)]
+        pub fn wrap(&mut self, amount: Balance) -> Result<Balance> {
+            let caller = self.env().caller();
+            let this = self.env().account_id();
+            let mut underlying = self.underlying_contract();
+            let shares_before = underlying.shares_of(this);
+            underlying.transfer_from(caller, this, amount, Vec::new())?;
+            // Count the shares that arrived, so rounding in the rebasing token
+            // can't mint more than the wrapper holds.
+            let wrapped = underlying.shares_of(this) - shares_before;
+            if wrapped == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            self.mint(caller, wrapped)?;
+            ink::codegen::EmitEvent::<WrappedRebasing>::emit_event(
+                self.env(),
+                Transfer {
+                    from: None,
+                    to: Some(caller),
+                    value: wrapped,
+                },
+            );
+            Ok(wrapped)
+        }
+
+        /// Burns `wrapped` of the caller's wrapped tokens and sends the shares
+        /// back as rebasing tokens. Returns what they were worth.
+        #[ink(message)]
+        pub fn unwrap(&mut self, wrapped: Balance) -> Result<Balance> {
+            let caller = self.env().caller();
+            if wrapped == 0 {
+                return Err(Error::ZeroAmount);
+            }
+            self.burn(caller, wrapped)?;
+            ink::codegen::EmitEvent::<WrappedRebasing>::emit_event(
+                self.env(),
+                Transfer {
+                    from: Some(caller),
+                    to: None,
+                    value: wrapped,
+                },
+            );
+            let mut underlying = self.underlying_contract();
+            underlying.transfer_shares(caller, wrapped)?;
+            Ok(underlying.shares_to_balance(wrapped))
+        }
+
+        fn transfer_helper(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.burn(from, value)?;
+            self.mint(to, value)?;
+            ink::codegen::EmitEvent::<WrappedRebasing>::emit_event(
+                self.env(),
+                Transfer {
+                    from: Some(from),
+                    to: Some(to),
+                    value,
+                },
+            );
+            Ok(())
+        }
+
+        fn mint(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            let balance = self
+                .balance_of(to)
+                .checked_add(value)
+                .ok_or(Error::Overflow)?;
+            self.balances.insert(to, &balance);
+            self.total_supply += value;
+            Ok(())
+        }
+
+        fn burn(&mut self, from: AccountId, value: Balance) -> Result<()> {
+            let balance = self.balance_of(from);
+            if balance < value {
+                return Err(Error::InsufficientBalance);
+            }
+            self.balances.insert(from, &(balance - value));
+            self.total_supply -= value;
+            Ok(())
+        }
+
+        fn underlying_contract(&self) -> RebasingTokenRef {
+            ink::env::call::FromAccountId::from_account_id(self.underlying)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        }
+
+        fn wrapped() -> WrappedRebasing {
+            WrappedRebasing::new(AccountId::from([0x9; 32]))
+        }
+
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = accounts();
+            let mut wrapped = wrapped();
+            wrapped.mint(accounts.alice, 100).unwrap();
+            assert_eq!(wrapped.transfer(accounts.bob, 60, Vec::new()), Ok(()));
+            assert_eq!(wrapped.balance_of(accounts.alice), 40);
+            assert_eq!(wrapped.balance_of(accounts.bob), 60);
+            assert_eq!(wrapped.total_supply(), 100);
+            assert_eq!(
+                wrapped.transfer(accounts.bob, 41, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_spends_allowance() {
+            let accounts = accounts();
+            let mut wrapped = wrapped();
+            wrapped.mint(accounts.alice, 100).unwrap();
+            assert_eq!(wrapped.approve(accounts.alice, 50), Ok(()));
+            assert_eq!(
+                wrapped.transfer_from(accounts.alice, accounts.bob, 51, Vec::new()),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                wrapped.transfer_from(accounts.alice, accounts.bob, 50, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(wrapped.allowance(accounts.alice, accounts.alice), 0);
+            assert_eq!(wrapped.balance_of(accounts.bob), 50);
+        }
+
+        #[ink::test]
+        fn unwrapping_nothing_fails() {
+            let mut wrapped = wrapped();
+            assert_eq!(wrapped.unwrap(0), Err(Error::ZeroAmount));
+            assert_eq!(wrapped.unwrap(1), Err(Error::InsufficientBalance));
+        }
+    }
+}